@@ -3,30 +3,420 @@
 #include <stdint.h>
 #include <stdlib.h>
 
+/**
+ * Broad category of the last error, for callers that branch on failures.
+ */
+typedef enum RBErrorCode {
+  /**
+   * No error
+   */
+  RBErrorCode_None = 0,
+  /**
+   * An error without a more specific category
+   */
+  RBErrorCode_Unknown = 1,
+  /**
+   * A null pointer, zero size or otherwise invalid argument
+   */
+  RBErrorCode_InvalidArgs = 2,
+  /**
+   * The SVG could not be parsed
+   */
+  RBErrorCode_Parse = 3,
+  /**
+   * The computed scale factors were zero, NaN or infinite
+   */
+  RBErrorCode_InvalidScale = 4,
+  /**
+   * An output buffer could not be allocated
+   */
+  RBErrorCode_Alloc = 5,
+  /**
+   * A panic was caught at the FFI boundary
+   */
+  RBErrorCode_Panic = 6,
+  /**
+   * The operation ran past its time limit
+   */
+  RBErrorCode_Timeout = 7,
+  /**
+   * The caller cancelled the operation, e.g. from a callback
+   */
+  RBErrorCode_Cancelled = 8,
+  /**
+   * The operation conflicts with one that is still running
+   */
+  RBErrorCode_Busy = 9,
+} RBErrorCode;
+
+/**
+ * Layout of raw pixel buffers, caller-supplied or in an `RBImage`.
+ *
+ * Unless a function says otherwise, caller-supplied pixels use straight
+ * (non-premultiplied) alpha, the convention of most image decoders, while
+ * `RBImage` pixels are premultiplied.
+ */
+typedef enum RBPixelFormat {
+  /**
+   * 4 bytes per pixel: R, G, B, A
+   */
+  RBPixelFormat_Rgba8 = 0,
+  /**
+   * 4 bytes per pixel: B, G, R, A
+   */
+  RBPixelFormat_Bgra8 = 1,
+  /**
+   * 3 bytes per pixel: R, G, B (opaque)
+   */
+  RBPixelFormat_Rgb8 = 2,
+  /**
+   * 1 byte per pixel: a single gray or channel value (opaque)
+   */
+  RBPixelFormat_Gray8 = 3,
+  /**
+   * 16 bytes per pixel: R, G, B, A as native-endian `f32` in [0, 1]
+   */
+  RBPixelFormat_Rgba32F = 4,
+} RBPixelFormat;
+
+/**
+ * Blend modes accepted by `rb_image_blend()`.
+ *
+ * The first four are Porter-Duff operators; the rest are the separable
+ * blend modes from the W3C Compositing spec, composited source-over.
+ */
+typedef enum RBBlendMode {
+  /**
+   * Source over destination
+   */
+  RBBlendMode_SrcOver = 0,
+  /**
+   * Source where destination is opaque
+   */
+  RBBlendMode_SrcIn = 1,
+  /**
+   * Source where destination is transparent
+   */
+  RBBlendMode_SrcOut = 2,
+  /**
+   * Destination over source
+   */
+  RBBlendMode_DstOver = 3,
+  /**
+   * Multiply color channels
+   */
+  RBBlendMode_Multiply = 4,
+  /**
+   * Inverse of multiplying the inverses
+   */
+  RBBlendMode_Screen = 5,
+  /**
+   * Multiply or screen, depending on the destination
+   */
+  RBBlendMode_Overlay = 6,
+  /**
+   * Darker of source and destination
+   */
+  RBBlendMode_Darken = 7,
+  /**
+   * Lighter of source and destination
+   */
+  RBBlendMode_Lighten = 8,
+} RBBlendMode;
+
+/**
+ * Compression applied to TIFF output.
+ */
+typedef enum RBTiffCompression {
+  /**
+   * Uncompressed strips
+   */
+  RBTiffCompression_None = 0,
+  /**
+   * LZW with horizontal prediction
+   */
+  RBTiffCompression_Lzw = 1,
+  /**
+   * Deflate (zlib) with horizontal prediction
+   */
+  RBTiffCompression_Deflate = 2,
+} RBTiffCompression;
+
+/**
+ * How `rb_render_svg_to_rgba_with_id_list()` interprets its id list.
+ */
+typedef enum RBVisibilityMode {
+  /**
+   * Render only the listed elements (and their descendants)
+   */
+  RBVisibilityMode_Whitelist = 0,
+  /**
+   * Render everything except the listed elements
+   */
+  RBVisibilityMode_Blacklist = 1,
+} RBVisibilityMode;
+
+/**
+ * What `rb_image_threshold()` compares against its threshold.
+ *
+ * The color modes look at the image composited over white, so transparent
+ * areas count as white.
+ */
+typedef enum RBThresholdMode {
+  /**
+   * Black where luminance is below the threshold
+   */
+  RBThresholdMode_Luminance = 0,
+  /**
+   * Black where alpha is at or above the threshold
+   */
+  RBThresholdMode_Alpha = 1,
+  /**
+   * Black where every one of R, G and B is below the threshold
+   */
+  RBThresholdMode_AnyChannel = 2,
+} RBThresholdMode;
+
+/**
+ * Edge operators for `rb_image_edge_detect()`.
+ */
+typedef enum RBEdgeMode {
+  /**
+   * 3×3 Sobel gradient magnitude
+   */
+  RBEdgeMode_Sobel = 0,
+  /**
+   * 3×3 Prewitt gradient magnitude
+   */
+  RBEdgeMode_Prewitt = 1,
+  /**
+   * 5×5 Laplacian of Gaussian (σ ≈ 1.4), absolute response
+   */
+  RBEdgeMode_LaplacianOfGaussian = 2,
+} RBEdgeMode;
+
+/**
+ * Winding rule deciding which parts of a self-intersecting shape are inside.
+ */
+typedef enum RBFillRule {
+  /**
+   * Inside wherever the outline winds around a point a non-zero number of times
+   */
+  RBFillRule_NonZero = 0,
+  /**
+   * Inside wherever a ray from a point crosses the outline an odd number of times
+   */
+  RBFillRule_EvenOdd = 1,
+} RBFillRule;
+
+/**
+ * Color sub-pixel layout of the LCD panel `rb_render_svg_to_rgba_subpixel()`
+ * targets.
+ */
+typedef enum RBSubpixelOrder {
+  /**
+   * Horizontal stripes, red on the left
+   */
+  RBSubpixelOrder_Rgb = 0,
+  /**
+   * Horizontal stripes, blue on the left
+   */
+  RBSubpixelOrder_Bgr = 1,
+  /**
+   * Vertical stripes, red on top
+   */
+  RBSubpixelOrder_Vrgb = 2,
+  /**
+   * Vertical stripes, blue on top
+   */
+  RBSubpixelOrder_Vbgr = 3,
+} RBSubpixelOrder;
+
+/**
+ * How `rb_render_svg_exact()` fits the SVG into the output, named after the
+ * SVG `preserveAspectRatio` values.
+ *
+ * `XMin`/`XMid`/`XMax` align the left edges, centers or right edges and
+ * `YMin`/`YMid`/`YMax` the top edges, centers or bottom edges. The plain
+ * modes scale uniformly until the whole SVG fits (`meet`), leaving
+ * transparent bands on the other axis; the `Slice` modes scale uniformly
+ * until the output is covered, cropping the overflow.
+ */
+typedef enum RBScaleMode {
+  /**
+   * Stretch each axis independently to fill the output, like `rb_render_svg_to_rgba()`
+   */
+  RBScaleMode_None = 0,
+  /**
+   * `xMinYMin meet`
+   */
+  RBScaleMode_XMinYMin = 1,
+  /**
+   * `xMidYMin meet`
+   */
+  RBScaleMode_XMidYMin = 2,
+  /**
+   * `xMaxYMin meet`
+   */
+  RBScaleMode_XMaxYMin = 3,
+  /**
+   * `xMinYMid meet`
+   */
+  RBScaleMode_XMinYMid = 4,
+  /**
+   * `xMidYMid meet`, the SVG default
+   */
+  RBScaleMode_XMidYMid = 5,
+  /**
+   * `xMaxYMid meet`
+   */
+  RBScaleMode_XMaxYMid = 6,
+  /**
+   * `xMinYMax meet`
+   */
+  RBScaleMode_XMinYMax = 7,
+  /**
+   * `xMidYMax meet`
+   */
+  RBScaleMode_XMidYMax = 8,
+  /**
+   * `xMaxYMax meet`
+   */
+  RBScaleMode_XMaxYMax = 9,
+  /**
+   * `xMinYMin slice`
+   */
+  RBScaleMode_XMinYMinSlice = 10,
+  /**
+   * `xMidYMin slice`
+   */
+  RBScaleMode_XMidYMinSlice = 11,
+  /**
+   * `xMaxYMin slice`
+   */
+  RBScaleMode_XMaxYMinSlice = 12,
+  /**
+   * `xMinYMid slice`
+   */
+  RBScaleMode_XMinYMidSlice = 13,
+  /**
+   * `xMidYMid slice`
+   */
+  RBScaleMode_XMidYMidSlice = 14,
+  /**
+   * `xMaxYMid slice`
+   */
+  RBScaleMode_XMaxYMidSlice = 15,
+  /**
+   * `xMinYMax slice`
+   */
+  RBScaleMode_XMinYMaxSlice = 16,
+  /**
+   * `xMidYMax slice`
+   */
+  RBScaleMode_XMidYMaxSlice = 17,
+  /**
+   * `xMaxYMax slice`
+   */
+  RBScaleMode_XMaxYMaxSlice = 18,
+} RBScaleMode;
+
+/**
+ * Checksum algorithm for `rb_image_compute_checksum()`.
+ */
+typedef enum RBChecksumAlgorithm {
+  /**
+   * CRC-32 (IEEE 802.3, as in zlib, PNG and `crc32` tools), in the low 32 bits
+   */
+  RBChecksumAlgorithm_Crc32 = 0,
+  /**
+   * 64-bit FNV-1a
+   */
+  RBChecksumAlgorithm_Fnv1a = 1,
+  /**
+   * XXH64 with seed 0; the fastest of the three
+   */
+  RBChecksumAlgorithm_Xxh64 = 2,
+} RBChecksumAlgorithm;
+
+/**
+ * Per-caller error state for the `rb_*_ctx()` functions. Opaque to C callers.
+ *
+ * The thread-local error functions assume one logical caller per OS thread,
+ * which doesn't hold for async runtimes that move a task between threads
+ * between the render call and the error lookup. A context carries the
+ * error with it instead. One context must not be used by two calls at the
+ * same time; give each task its own.
+ */
+typedef struct RBContext RBContext;
+
+/**
+ * Rendering options. Opaque to C callers.
+ *
+ * Create with `rb_options_new()`, configure with the `rb_options_set_*`
+ * functions and release with `rb_options_free()`.
+ */
+typedef struct RBOptions RBOptions;
+
+/**
+ * A parsed SVG document. Opaque to C callers.
+ */
+typedef struct RBTree RBTree;
+
+/**
+ * Callback type invoked with the panic message and the caller's userdata.
+ */
+typedef void (*RBPanicCallback)(const char *msg, void *userdata);
+
+/**
+ * Callback type invoked when a background font preload finishes, with the
+ * number of faces loaded (0 if cancelled) and the caller's userdata.
+ */
+typedef void (*RBFontsLoadedCallback)(uintptr_t face_count, void *userdata);
+
 /**
  * C-compatible structure representing a rendered image.
  * This structure is used to return RGBA pixel data from the rendering functions.
  *
  * # Fields
- * * `ptr` - Pointer to the RGBA pixel data (owned by the library)
+ * * `ptr` - Pointer to the pixel data, laid out as `format` says (owned by the library)
  * * `len` - Total number of bytes in the pixel data
  * * `width` - Width of the image in pixels
  * * `height` - Height of the image in pixels
+ * * `format` - Layout of each pixel
  *
  * # Memory Layout
- * The pixel data is stored as RGBA bytes in row-major order:
+ * Unless `format` says otherwise, the pixel data is stored as RGBA bytes in
+ * row-major order:
  * - Each pixel is 4 bytes (R, G, B, A)
  * - Total size = width * height * 4 bytes
  * - Row 0: [R0G0B0A0, R1G1B1A1, ..., R(width-1)G(width-1)B(width-1)A(width-1)]
  * - Row 1: [R0G0B0A0, R1G1B1A1, ..., R(width-1)G(width-1)B(width-1)A(width-1)]
  * - etc.
  *
+ * Rows are always tightly packed, with no padding between them: the stride
+ * is `width * bytes per pixel` (see `rb_image_stride()`) and
+ * `len == height * stride` for every image this library returns. tiny-skia
+ * pixmaps have no native stride of their own to preserve; their rows are
+ * packed the same way, and the buffer is handed over as is.
+ *
+ * Color channels are sRGB-encoded and premultiplied by alpha, exactly as
+ * resvg renders them; use `rb_image_srgb_to_linear()` for linear-light
+ * pipelines.
+ *
+ * Images in other formats, such as the `RBPixelFormat::Gray8` planes from
+ * `rb_image_split_channels()` or `RBPixelFormat::Rgb8` renders, have
+ * `len == width * height * bytes per pixel`. They are freed the same way
+ * but are only accepted where a function says so; the `rb_image_*`
+ * functions otherwise expect RGBA and reject other formats as invalid
+ * arguments.
+ *
  * # Safety
  * The caller must call `rb_free_image()` to free the memory when done.
  */
 typedef struct RBImage {
   /**
-   * Pointer to the RGBA pixel data
+   * Pointer to the pixel data, laid out as `format` says
    */
   uint8_t *ptr;
   /**
@@ -41,8 +431,43 @@ typedef struct RBImage {
    * Height of the image in pixels
    */
   uint32_t height;
+  /**
+   * Layout of each pixel, as an `RBPixelFormat` value
+   */
+  uint32_t format;
 } RBImage;
 
+/**
+ * Callback receiving one finished row of an incremental render; return
+ * `false` to cancel the rest of the render.
+ */
+typedef bool (*RBRowCallback)(uint32_t row, const uint8_t *data, uintptr_t stride, void *userdata);
+
+/**
+ * C-compatible width/height pair used by the batch rendering functions.
+ */
+typedef struct RBSize {
+  /**
+   * Width in pixels
+   */
+  uint32_t width;
+  /**
+   * Height in pixels
+   */
+  uint32_t height;
+} RBSize;
+
+/**
+ * Callback type invoked as batch items finish, with the number finished so
+ * far, the total and the caller's userdata.
+ */
+typedef void (*RBProgressCallback)(uintptr_t done, uintptr_t total, void *userdata);
+
+/**
+ * Callback receiving one construct found by `rb_feature_report()`.
+ */
+typedef void (*RBFeatureCallback)(const char *what, const char *id, uint32_t line, void *userdata);
+
 /**
  * Gets a pointer to the last error message for the current thread.
  *
@@ -56,6 +481,30 @@ typedef struct RBImage {
  */
 const char *rb_last_error(void);
 
+/**
+ * Gets the category of the last error for the current thread.
+ *
+ * # Returns
+ * * The error code matching the message from `rb_last_error_copy()`
+ * * `RBErrorCode::None` if the last call succeeded
+ */
+enum RBErrorCode rb_last_error_code(void);
+
+/**
+ * Gets a short description of an error code, e.g. for log messages.
+ *
+ * # Arguments
+ * * `code` - An `RBErrorCode` value, as an integer
+ *
+ * # Returns
+ * * A static null-terminated string describing the code (never null)
+ * * `"unrecognized error code"` for values outside `RBErrorCode`
+ *
+ * # Safety
+ * The returned string is static and must not be freed.
+ */
+const char *rb_error_code_string(int32_t code);
+
 /**
  * Copies the last error message into a caller-provided buffer.
  * This is a safer alternative to `rb_last_error()` as it avoids lifetime issues.
@@ -75,53 +524,3453 @@ const char *rb_last_error(void);
 uintptr_t rb_last_error_copy(char *buf, uintptr_t len);
 
 /**
- * Renders an SVG file to RGBA pixel data.
+ * Copies the warnings raised by the last call into a caller-provided buffer.
  *
- * This is the main function for converting SVG content to raster images.
- * The SVG is scaled to fit the requested dimensions while maintaining aspect ratio.
+ * Warnings describe content that was skipped or adjusted while the call
+ * still succeeded (for example an embedded image over the size limit).
+ * Multiple warnings are separated by `"; "`.
  *
  * # Arguments
- * * `svg_ptr` - Pointer to the SVG data (must not be null)
- * * `svg_len` - Length of the SVG data in bytes
- * * `width` - Desired output width in pixels (must be > 0)
- * * `height` - Desired output height in pixels (must be > 0)
+ * * `buf` - Pointer to the destination buffer (must not be null)
+ * * `len` - Size of the destination buffer in bytes
  *
  * # Returns
- * * `RBImage` struct containing the rendered pixel data
- * * If an error occurs, returns an image with null pointer and zero dimensions
+ * * Number of bytes written to the buffer (excluding null terminator)
+ * * 0 if there were no warnings or if buffer is null/empty
  *
  * # Safety
- * The caller must ensure `svg_ptr` points to valid SVG data for `svg_len` bytes.
- * The returned image must be freed with `rb_free_image()` when no longer needed.
- *
- * # Error Handling
- * Errors are stored in thread-local storage and can be retrieved with:
- * - `rb_last_error()` - Get pointer to error message
- * - `rb_last_error_copy()` - Copy error message to buffer
+ * The caller must ensure `buf` points to a valid buffer of at least `len` bytes.
  */
-struct RBImage rb_render_svg_to_rgba(const uint8_t *svg_ptr,
-                                     uintptr_t svg_len,
-                                     uint32_t width,
-                                     uint32_t height);
+uintptr_t rb_last_warning_copy(char *buf, uintptr_t len);
 
 /**
- * Frees memory allocated for an RBImage.
+ * Installs a process-wide callback that is notified whenever a panic occurs
+ * inside the library.
  *
- * This function must be called to free the memory allocated by `rb_render_svg_to_rgba()`.
- * Failing to call this function will result in a memory leak.
+ * The panic is still caught at the FFI boundary and reported through
+ * `rb_last_error()`; the callback is purely for logging/telemetry. Panics
+ * outside the library's calls don't reach it.
+ *
+ * The first callback installs a panic hook that wraps the one already in
+ * place, which keeps running for every panic. Passing a null callback puts
+ * that previous hook back, so call this from the same place as any other
+ * code that replaces the panic hook.
  *
  * # Arguments
- * * `img` - The RBImage structure to free
+ * * `cb` - Callback receiving the panic message (valid only for the duration of the call)
+ * * `userdata` - Opaque pointer forwarded to the callback unchanged
  *
  * # Safety
- * This function is safe to call multiple times on the same image (idempotent).
- * After calling this function, the image structure should not be used again.
- *
- * # Example
- * ```c
- * RBImage img = rb_render_svg_to_rgba(svg_data, svg_len, 100, 100);
- * // Use the image...
- * rb_free_image(img);
- * ```
+ * The callback may be invoked from any thread that calls into the library,
+ * so it and `userdata` must be thread-safe. The callback must not unwind.
  */
-void rb_free_image(struct RBImage img);
+void rb_set_panic_handler(RBPanicCallback cb, void *userdata);
+
+/**
+ * Allocates a new options object with every setting at its default.
+ *
+ * # Returns
+ * * A pointer that must be released with `rb_options_free()`
+ */
+struct RBOptions *rb_options_new(void);
+
+/**
+ * Frees an options object created by `rb_options_new()`.
+ *
+ * # Arguments
+ * * `opts` - The options to free (null is ignored)
+ *
+ * # Safety
+ * `opts` must come from `rb_options_new()` and must not be used afterwards.
+ */
+void rb_options_free(struct RBOptions *opts);
+
+/**
+ * Allocates a deep copy of an options object.
+ *
+ * Useful for a "base options" pattern: configure once, clone per job,
+ * adjust per-job settings, render, then free the clone.
+ *
+ * # Arguments
+ * * `src` - The options to copy (must not be null)
+ *
+ * # Returns
+ * * A new options object that must be released with `rb_options_free()`
+ * * Null if `src` is null (error is set)
+ *
+ * # Safety
+ * `src` must be a valid pointer from `rb_options_new()`.
+ */
+struct RBOptions *rb_options_clone(const struct RBOptions *src);
+
+/**
+ * Overwrites every setting in `dst` with the settings from `src`.
+ *
+ * # Arguments
+ * * `src` - The options to copy from (must not be null)
+ * * `dst` - An existing options object to copy into (must not be null)
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if either pointer is null (error is set)
+ *
+ * # Safety
+ * Both pointers must be valid pointers from `rb_options_new()`.
+ */
+bool rb_options_copy_to(const struct RBOptions *src, struct RBOptions *dst);
+
+/**
+ * Installs process-wide default options.
+ *
+ * Every function that parses or renders an SVG without being given
+ * options, or that is given null `opts`, uses a copy of these instead of
+ * the built-in defaults, so settings such as the background, DPI, fonts or
+ * size limits can be configured once at startup. That includes
+ * `rb_tree_parse()` with null `opts`, the batch, PNG, TIFF and compositing
+ * functions, and queries such as `rb_profile_svg()`. The only exceptions
+ * are `rb_render_svg_to_rgba_error_image()`, whose placard must look the
+ * same whatever the defaults are, and `rb_image_apply_svg_filter()`, which
+ * filters an existing image rather than rendering a document. Options objects from `rb_options_new()` are
+ * unaffected.
+ *
+ * The `output_format` of the defaults applies only to functions that hand
+ * back the render unchanged (`rb_render_svg_to_rgba()`, `_with_options()`,
+ * `_ctx()`, `_with_matrix_out()`, `_max_dim()` and `rb_render_svg_region()`);
+ * the others post-process the pixels and always return premultiplied RGBA.
+ * `opts` is copied, so it may be changed or freed afterwards without
+ * affecting the defaults.
+ *
+ * The defaults are guarded by a read-write lock: this may be called from
+ * any thread, renders that already started keep the defaults they began
+ * with, and renders that start afterwards see the new ones.
+ *
+ * # Arguments
+ * * `opts` - Options to use as the defaults, or null to restore the
+ *   built-in defaults
+ *
+ * # Safety
+ * `opts` must be null or a valid pointer from `rb_options_new()`.
+ */
+void rb_set_default_options(const struct RBOptions *opts);
+
+/**
+ * Copies the current process-wide default options into `out`.
+ *
+ * # Arguments
+ * * `out` - An existing options object to overwrite (must not be null);
+ *   receives the built-in defaults if none were installed
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if `out` is null (error is set)
+ *
+ * # Safety
+ * `out` must be null or a valid pointer from `rb_options_new()`.
+ */
+bool rb_get_default_options(struct RBOptions *out);
+
+/**
+ * Sets the size assumed for SVGs that declare neither a size nor a viewBox.
+ *
+ * Without `width`, `height` or `viewBox` on the root element an SVG has no
+ * intrinsic size, and usvg falls back to the bounding box of its content,
+ * which turns a lone small shape into a 1×1 "document". Such SVGs are
+ * instead treated as if the root declared `width` and `height` of the
+ * assumed size (100×100 by default), and a warning naming the size is
+ * recorded for `rb_last_warning_copy()`. SVGs that declare any of the
+ * three attributes are unaffected.
+ *
+ * # Arguments
+ * * `opts` - The options to modify (must not be null)
+ * * `width` - Assumed width in user units (must be finite and > 0)
+ * * `height` - Assumed height in user units (must be finite and > 0)
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if `opts` is null or a value is invalid (error is set)
+ *
+ * # Safety
+ * `opts` must be a valid pointer from `rb_options_new()`.
+ */
+bool rb_options_set_assumed_size(struct RBOptions *opts, float width, float height);
+
+/**
+ * Overrides the SVG's intrinsic size used when fitting it to the output.
+ *
+ * Normally the SVG's declared size (`tree.size()`) is stretched onto the
+ * requested output dimensions. With an override the declared size is
+ * replaced, e.g. treating every file as 512×512 no matter what it says.
+ *
+ * The override only changes the mapping onto the output: the document is
+ * still laid out in its own coordinate system (its viewBox still maps onto
+ * its declared size), so content is scaled by `output / override` rather than
+ * `output / declared`. A 100×100 SVG overridden to 200×200 and rendered at
+ * 200×200 therefore occupies the top-left quarter.
+ *
+ * # Arguments
+ * * `opts` - The options to modify (must not be null)
+ * * `width` - Intrinsic width in user units (<= 0 uses the parsed width)
+ * * `height` - Intrinsic height in user units (<= 0 uses the parsed height)
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if `opts` is null or a value isn't finite (error is set)
+ *
+ * # Safety
+ * `opts` must be a valid pointer from `rb_options_new()`.
+ */
+bool rb_options_set_size_override(struct RBOptions *opts, float width, float height);
+
+/**
+ * Controls how `<image>` elements with `data:` URIs are handled.
+ *
+ * Embedded images are decoded by default. For untrusted input they can be
+ * skipped entirely or limited by payload size; a skipped image renders as
+ * empty and adds a warning (see `rb_last_warning_copy()`). The limit
+ * applies to the decoded (base64-decoded) payload, i.e. the compressed
+ * image file, and also covers images inside nested SVG images.
+ *
+ * # Arguments
+ * * `opts` - The options to modify (must not be null)
+ * * `allow` - `false` skips every data-URI image
+ * * `max_bytes` - Largest accepted payload in bytes (0 = unlimited)
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if `opts` is null (error is set)
+ *
+ * # Safety
+ * `opts` must be a valid pointer from `rb_options_new()`.
+ */
+bool rb_options_set_embedded_images(struct RBOptions *opts, bool allow, uintptr_t max_bytes);
+
+/**
+ * Makes rendering fail when the SVG uses features that resvg cannot render
+ * faithfully, instead of silently producing a degraded image.
+ *
+ * Detection inspects the SVG document before parsing and currently covers:
+ * - Animation elements (`<animate>`, `<animateMotion>`, `<animateTransform>`,
+ *   `<set>`): only the static document is rendered
+ * - `<foreignObject>` (HTML content is not rendered)
+ * - SVG fonts (`<font>`, `<font-face>`, `<glyph>`, `<missing-glyph>`)
+ * - `<script>`
+ * - Filter inputs `BackgroundImage`, `BackgroundAlpha`, `FillPaint` and
+ *   `StrokePaint`, and the `enable-background` attribute
+ *
+ * Other filters are supported and do not trigger strict mode. The error
+ * message lists every unsupported construct found, with its line number.
+ *
+ * # Arguments
+ * * `opts` - The options to modify (must not be null)
+ * * `enabled` - `true` to reject SVGs with unsupported features
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if `opts` is null (error is set)
+ *
+ * # Safety
+ * `opts` must be a valid pointer from `rb_options_new()`.
+ */
+bool rb_options_set_strict_features(struct RBOptions *opts, bool enabled);
+
+/**
+ * Forces rendering to be clipped to the root `viewBox`.
+ *
+ * Content outside the viewBox can show up when the output's aspect ratio
+ * differs from the viewBox's (the letterbox area of `preserveAspectRatio`
+ * "meet"). With this enabled those pixels are always transparent, giving
+ * predictable icon bounds regardless of `overflow`. SVGs without a
+ * `viewBox` are unaffected.
+ *
+ * # Arguments
+ * * `opts` - The options to modify (must not be null)
+ * * `enabled` - `true` to clip to the viewBox
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if `opts` is null (error is set)
+ *
+ * # Safety
+ * `opts` must be a valid pointer from `rb_options_new()`.
+ */
+bool rb_options_set_clip_to_viewbox(struct RBOptions *opts, bool enabled);
+
+/**
+ * Enables or disables anti-aliasing of text, independently of shapes.
+ *
+ * Small anti-aliased text can look fuzzy on low-DPI screens. With text
+ * anti-aliasing off, glyph outlines are filled with hard pixel edges while
+ * every other shape stays smooth. This sets the default for the SVG
+ * `text-rendering` property (off = `optimizeSpeed`), so text elements that
+ * set `text-rendering` themselves keep their own choice. resvg does no
+ * hinting, so glyph stems aren't snapped to the pixel grid either way.
+ *
+ * # Arguments
+ * * `opts` - The options to modify (must not be null)
+ * * `enabled` - `false` to render text aliased (the default is `true`)
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if `opts` is null (error is set)
+ *
+ * # Safety
+ * `opts` must be a valid pointer from `rb_options_new()`.
+ */
+bool rb_options_set_text_antialias(struct RBOptions *opts, bool enabled);
+
+/**
+ * Renders every shape with crisp, non-anti-aliased edges.
+ *
+ * resvg already honors `shape-rendering: crispEdges` (and `optimizeSpeed`)
+ * on individual elements. Enabling this treats every shape as if it
+ * declared `crispEdges`, overriding `shape-rendering` attributes in the
+ * document, so 1px axis-aligned lines in schematics and CAD drawings come
+ * out as solid pixel rows instead of two half-covered ones. Like any
+ * injected CSS (see `rb_options_set_style_sheet()`), it yields to the
+ * document's own `<style>` rules and inline `style` attributes. Text is
+ * controlled separately by `rb_options_set_text_antialias()`.
+ *
+ * # Arguments
+ * * `opts` - The options to modify (must not be null)
+ * * `enabled` - `true` to render all shapes aliased (the default is `false`)
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if `opts` is null (error is set)
+ *
+ * # Safety
+ * `opts` must be a valid pointer from `rb_options_new()`.
+ */
+bool rb_options_set_crisp_edges(struct RBOptions *opts, bool enabled);
+
+/**
+ * Sets the seed for randomized rendering steps.
+ *
+ * This is currently a no-op. Every rendering step is deterministic:
+ * error-diffusion dithering, supersampling and the filters produce
+ * byte-identical output for identical input, and no step reads the seed,
+ * so changing it changes nothing. It is only stored and copied with the
+ * options.
+ *
+ * # Arguments
+ * * `opts` - The options to modify (must not be null)
+ * * `seed` - The seed (the default is 0)
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if `opts` is null (error is set)
+ *
+ * # Safety
+ * `opts` must be a valid pointer from `rb_options_new()`.
+ */
+bool rb_options_set_seed(struct RBOptions *opts, uint64_t seed);
+
+/**
+ * Sets the fill rule used by shapes and clip paths that don't declare one.
+ *
+ * Some CAD exports are drawn expecting even-odd filling but omit
+ * `fill-rule`, so their holes render filled under the SVG default of
+ * `nonzero`. This changes the document-wide default of both `fill-rule` and
+ * `clip-rule`, as if the root `<svg>` had declared them. Elements, groups
+ * and document `<style>` rules that set either property keep their own
+ * value; only a declaration on an `<svg>` element itself is replaced.
+ *
+ * # Arguments
+ * * `opts` - The options to modify (must not be null)
+ * * `rule` - The default rule, as an `RBFillRule` value (the default is
+ *   `RBFillRule::NonZero`)
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if `opts` is null or `rule` is unknown (error is set)
+ *
+ * # Safety
+ * `opts` must be a valid pointer from `rb_options_new()`.
+ */
+bool rb_options_set_default_clip_rule(struct RBOptions *opts, uint32_t rule);
+
+/**
+ * Clamps the output size of renders with `opts`, keeping its aspect ratio.
+ *
+ * The requested `width × height` is scaled by one factor until it lies
+ * within the limits, so the proportions the caller asked for survive:
+ * with a 400 × 400 maximum, a 1000 × 500 request renders at 400 × 200. If a
+ * minimum and a maximum can't both be met at that aspect ratio, the maximum
+ * wins. Sides are rounded to whole pixels. Region renders, whose output
+ * size is the region's, ignore the limits.
+ *
+ * # Arguments
+ * * `opts` - The options to modify (must not be null)
+ * * `min_width` - Smallest output width in pixels (0 = no minimum)
+ * * `min_height` - Smallest output height in pixels (0 = no minimum)
+ * * `max_width` - Largest output width in pixels (0 = no maximum)
+ * * `max_height` - Largest output height in pixels (0 = no maximum)
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if `opts` is null or a maximum is below its minimum (error is set)
+ *
+ * # Safety
+ * `opts` must be a valid pointer from `rb_options_new()`.
+ */
+bool rb_options_set_size_limits(struct RBOptions *opts,
+                                uint32_t min_width,
+                                uint32_t min_height,
+                                uint32_t max_width,
+                                uint32_t max_height);
+
+/**
+ * Selects the pixel layout of images rendered with `opts`.
+ *
+ * `RBPixelFormat::Rgba8` (the default) returns premultiplied RGBA.
+ * `RBPixelFormat::Rgb8` returns tightly packed 3-byte RGB for targets
+ * without alpha: every pixel is composited over the background color and the
+ * alpha channel dropped, so `len == width * height * 3`. The returned
+ * image's `format` field records the layout. This applies to the render
+ * functions that take options and return an `RBImage`.
+ *
+ * # Arguments
+ * * `opts` - The options to modify (must not be null)
+ * * `format` - The numeric value of `RBPixelFormat::Rgba8` or
+ *   `RBPixelFormat::Rgb8`
+ * * `bg_r`, `bg_g`, `bg_b` - RGB that transparency is flattened onto
+ *   (ignored for formats with alpha)
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if `opts` is null or `format` is unknown or unsupported (error is set)
+ *
+ * # Safety
+ * `opts` must be a valid pointer from `rb_options_new()`.
+ */
+bool rb_options_set_output_format(struct RBOptions *opts,
+                                  uint32_t format,
+                                  uint8_t bg_r,
+                                  uint8_t bg_g,
+                                  uint8_t bg_b);
+
+/**
+ * Enables or disables an opaque checkerboard behind everything rendered with `opts`.
+ *
+ * After rendering, the image is composited over a two-color checkerboard,
+ * the classic way preview UIs show transparency. The result is fully
+ * opaque. The top-left square is anchored where the SVG's origin lands in
+ * the output, so region renders line up with full renders.
+ *
+ * # Arguments
+ * * `opts` - The options to modify (must not be null)
+ * * `enabled` - `true` to render over the checkerboard
+ * * `square_size` - Edge length of one square in pixels (must be > 0 when enabled)
+ * * `color1_r`, `color1_g`, `color1_b` - RGB of the top-left square
+ * * `color2_r`, `color2_g`, `color2_b` - RGB of the squares next to it
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if `opts` is null or `square_size` is 0 while enabling (error is set)
+ *
+ * # Safety
+ * `opts` must be a valid pointer from `rb_options_new()`.
+ */
+bool rb_options_set_checkerboard(struct RBOptions *opts,
+                                 bool enabled,
+                                 uint32_t square_size,
+                                 uint8_t color1_r,
+                                 uint8_t color1_g,
+                                 uint8_t color1_b,
+                                 uint8_t color2_r,
+                                 uint8_t color2_g,
+                                 uint8_t color2_b);
+
+/**
+ * Sets the directory that relative resource references resolve against.
+ *
+ * SVGs rendered from memory have no location of their own, so by default a
+ * relative `<image href="logo.png">` can't be loaded and is skipped. With a
+ * base path, such references resolve as though the SVG bytes had been read
+ * from a file in that directory. Only local paths are supported; `http:`
+ * and other URLs are never fetched. Absolute paths resolve regardless.
+ *
+ * # Arguments
+ * * `opts` - The options to modify (must not be null)
+ * * `path` - Null-terminated directory path, or null to remove the base path
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if `opts` is null or `path` is not valid UTF-8 (error is set)
+ *
+ * # Safety
+ * `opts` must be a valid pointer from `rb_options_new()`; `path` must be null
+ * or a valid null-terminated string.
+ */
+bool rb_options_set_base_path(struct RBOptions *opts, const char *path);
+
+/**
+ * Sets a CSS style sheet that is applied to every SVG parsed with `opts`.
+ *
+ * The rules are applied as if from a `<style>` element placed before the
+ * document's own styles, so rules in the SVG itself override them on equal
+ * specificity. This is the hook for templating: one SVG, many looks, e.g.
+ * `"#value { fill: red }"`.
+ *
+ * # Arguments
+ * * `opts` - The options to modify (must not be null)
+ * * `css` - Null-terminated CSS text, or null to remove the style sheet
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if `opts` is null or `css` is not valid UTF-8 (error is set)
+ *
+ * # Safety
+ * `opts` must be a valid pointer from `rb_options_new()`; `css` must be null
+ * or a valid null-terminated string.
+ */
+bool rb_options_set_style_sheet(struct RBOptions *opts, const char *css);
+
+/**
+ * Sets the user's languages, as BCP-47 tags.
+ *
+ * This maps to usvg's `Options::languages`, which is the only language hint
+ * usvg 0.44 exposes: it decides which branch of a `<switch>` is rendered
+ * through the `systemLanguage` attribute. usvg has no option for the shaping
+ * language or the default text direction (direction comes from the Unicode
+ * bidi algorithm), so this does not change glyph selection for ambiguous
+ * codepoints; pick the font with `font-family` for that.
+ *
+ * # Arguments
+ * * `opts` - The options to modify (must not be null)
+ * * `languages` - Null-terminated, comma-separated list of tags in order of
+ *   preference (e.g. `"ja, en-US"`), or null to restore the default (`"en"`)
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if `opts` is null or `languages` is not valid UTF-8 (error is set)
+ *
+ * # Safety
+ * `opts` must be a valid pointer from `rb_options_new()`; `languages` must be
+ * null or a valid null-terminated string.
+ */
+bool rb_options_set_languages(struct RBOptions *opts, const char *languages);
+
+/**
+ * Loads the system's fonts into the options' font database.
+ *
+ * Text only renders with fonts from the options' database, which starts
+ * empty. This scans the platform's font locations (fontconfig paths on
+ * Linux, the system font folders on macOS and Windows) via fontdb's
+ * `Database::load_system_fonts()`, replacing any database the options had.
+ * The scan can take from milliseconds to seconds depending on the system,
+ * so call it once and clone the options (`rb_options_clone()` shares the
+ * loaded database).
+ *
+ * # Arguments
+ * * `opts` - The options to modify (must not be null)
+ *
+ * # Returns
+ * * The number of font faces loaded; 0 means none were found
+ * * 0 if `opts` is null (error is set)
+ *
+ * # Safety
+ * `opts` must be a valid pointer from `rb_options_new()`.
+ */
+uint32_t rb_options_set_font_db_from_system(struct RBOptions *opts);
+
+/**
+ * Returns the number of font faces in the options' font database.
+ *
+ * Options without their own database report the shared one loaded by
+ * `rb_preload_fonts_async()`, since that is what their renders use.
+ *
+ * # Arguments
+ * * `opts` - The options to inspect (must not be null)
+ *
+ * # Returns
+ * * The number of font faces available to text rendering
+ * * 0 if no fonts are loaded, or if `opts` is null (error is set)
+ *
+ * # Safety
+ * `opts` must be a valid pointer from `rb_options_new()`.
+ */
+uintptr_t rb_options_get_font_count(const struct RBOptions *opts);
+
+/**
+ * Lists the font families available to text rendering with `opts`.
+ *
+ * Compare the result with `rb_query_svg_used_fonts()` to see why text falls
+ * back to another font. Families are unique and sorted; a face with
+ * localized family names contributes each of them.
+ *
+ * # Arguments
+ * * `opts` - The options to inspect (must not be null)
+ * * `out_families` - Receives the array of family names (must not be null)
+ * * `out_count` - Receives the number of names (must not be null)
+ *
+ * # Returns
+ * * `true` on success (an empty list if no fonts are loaded); free the
+ *   array with `rb_free_string_array()`
+ * * `false` on error (error is set, outputs are null/0)
+ *
+ * # Safety
+ * `opts` must be a valid pointer from `rb_options_new()`; both output
+ * pointers must be valid for writes.
+ */
+bool rb_options_list_fonts(const struct RBOptions *opts,
+                           char ***out_families,
+                           uintptr_t *out_count);
+
+/**
+ * Loads the system fonts into a process-wide database on a background thread.
+ *
+ * Once the load completes, every render whose options have no font database
+ * of their own (including the plain functions and null options) uses it.
+ * Renders that race the load behave as if it hadn't started: they see no
+ * fonts, so text is skipped (parsing and everything else is unaffected). A
+ * render parses with whichever database was current when it started, so a
+ * completing load never changes a render in progress. Options with their
+ * own database, e.g. from `rb_options_set_font_db_from_system()`, are never
+ * affected.
+ *
+ * Calling this after a load completed rescans and replaces the database,
+ * e.g. after fonts were installed.
+ *
+ * # Arguments
+ * * `done_cb` - Called once, on the loading thread, when the load finishes
+ *   or is cancelled (null = no notification)
+ * * `userdata` - Opaque pointer forwarded to the callback unchanged
+ *
+ * # Returns
+ * * `true` if the load was started
+ * * `false` if a load is already running (error is set, with
+ *   `RBErrorCode::Busy`)
+ *
+ * # Safety
+ * The callback runs on a thread the library spawns, so it and `userdata`
+ * must be thread-safe. The callback must not unwind.
+ */
+bool rb_preload_fonts_async(RBFontsLoadedCallback done_cb, void *userdata);
+
+/**
+ * Cancels a font preload started by `rb_preload_fonts_async()`.
+ *
+ * The directory scan itself can't be interrupted, so the loading thread
+ * runs to completion, but its result is discarded and the callback receives
+ * 0. A database from an earlier completed load stays in place. A new
+ * preload can be started once the cancelled one's callback has fired.
+ *
+ * # Returns
+ * * `true` if a running load was cancelled
+ * * `false` if no load was running
+ */
+bool rb_cancel_font_preload(void);
+
+/**
+ * Substitutes one font family for another in every render.
+ *
+ * Text asking for `from` (matched case-insensitively, as CSS does) is set
+ * in `to` instead, e.g. mapping a brand font that isn't installed to
+ * `"Arial"`. `to` may also be a generic family (`serif`, `sans-serif`,
+ * `cursive`, `fantasy`, `monospace`). If no font named `to` is available
+ * either, `from` itself and then the rest of the element's `font-family`
+ * list are tried as usual. Aliases are not chained. Adding an alias for a
+ * family that already has one replaces it; renders that already started
+ * keep the aliases they began with.
+ *
+ * # Arguments
+ * * `from` - Null-terminated family name as written in SVGs (must not be null)
+ * * `to` - Null-terminated replacement family, or null to remove the alias
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` on invalid arguments (error is set)
+ *
+ * # Safety
+ * `from` and `to` must be null or valid null-terminated strings.
+ */
+bool rb_add_font_alias(const char *from, const char *to);
+
+/**
+ * Sets the number of threads used for parallel rendering.
+ *
+ * The library uses its own thread pool rather than rayon's global one.
+ * The pool is created by the first parallel render (e.g. `rb_render_svg_to_rgba_batch()`),
+ * so this must be called before then; later calls have no effect.
+ *
+ * # Arguments
+ * * `n` - Number of worker threads (0 = one per CPU core)
+ *
+ * # Returns
+ * * `true` if the setting will take effect
+ * * `false` if the pool has already been created (error is set)
+ */
+bool rb_set_thread_count(uintptr_t n);
+
+/**
+ * Renders an SVG file to RGBA pixel data.
+ *
+ * This is the main function for converting SVG content to raster images.
+ * The SVG is scaled to fit the requested dimensions while maintaining aspect ratio.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `width` - Desired output width in pixels (must be > 0)
+ * * `height` - Desired output height in pixels (must be > 0)
+ *
+ * # Returns
+ * * `RBImage` struct containing the rendered pixel data
+ * * If an error occurs, returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * The caller must ensure `svg_ptr` points to valid SVG data for `svg_len` bytes.
+ * The returned image must be freed with `rb_free_image()` when no longer needed.
+ *
+ * # Error Handling
+ * Errors are stored in thread-local storage and can be retrieved with:
+ * - `rb_last_error()` - Get pointer to error message
+ * - `rb_last_error_copy()` - Copy error message to buffer
+ */
+struct RBImage rb_render_svg_to_rgba(const uint8_t *svg_ptr,
+                                     uintptr_t svg_len,
+                                     uint32_t width,
+                                     uint32_t height);
+
+/**
+ * Renders an SVG file to RGBA pixel data using the given options.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `width` - Desired output width in pixels (must be > 0)
+ * * `height` - Desired output height in pixels (must be > 0)
+ * * `opts` - Options from `rb_options_new()` (null = the defaults from
+ *   `rb_set_default_options()`)
+ *
+ * # Returns
+ * * Same as `rb_render_svg_to_rgba()`
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba()`; `opts` must be null or a
+ * valid pointer from `rb_options_new()`.
+ */
+struct RBImage rb_render_svg_to_rgba_with_options(const uint8_t *svg_ptr,
+                                                  uintptr_t svg_len,
+                                                  uint32_t width,
+                                                  uint32_t height,
+                                                  const struct RBOptions *opts);
+
+/**
+ * Allocates a new context with no error recorded.
+ *
+ * # Returns
+ * * A pointer that must be released with `rb_context_free()`
+ */
+struct RBContext *rb_context_new(void);
+
+/**
+ * Frees a context created by `rb_context_new()`.
+ *
+ * # Arguments
+ * * `ctx` - The context to free (null is ignored)
+ *
+ * # Safety
+ * `ctx` must come from `rb_context_new()` and must not be used afterwards.
+ */
+void rb_context_free(struct RBContext *ctx);
+
+/**
+ * Gets the error message of the last failed call made with a context.
+ *
+ * # Arguments
+ * * `ctx` - The context (null returns null)
+ *
+ * # Returns
+ * * A pointer to a null-terminated C string containing the error message
+ * * `std::ptr::null()` if the last call with `ctx` succeeded
+ *
+ * # Safety
+ * `ctx` must be null or a valid pointer from `rb_context_new()`. The
+ * returned pointer is owned by the context and stays valid until the next
+ * call that uses `ctx`, on any thread.
+ */
+const char *rb_context_last_error(const struct RBContext *ctx);
+
+/**
+ * Gets the category of the last error recorded in a context.
+ *
+ * # Arguments
+ * * `ctx` - The context (null returns `RBErrorCode::InvalidArgs`)
+ *
+ * # Returns
+ * * The error code matching `rb_context_last_error()`
+ * * `RBErrorCode::None` if the last call with `ctx` succeeded
+ *
+ * # Safety
+ * `ctx` must be null or a valid pointer from `rb_context_new()`.
+ */
+enum RBErrorCode rb_context_last_error_code(const struct RBContext *ctx);
+
+/**
+ * Gets the warnings raised by the last call made with a context.
+ *
+ * # Arguments
+ * * `ctx` - The context (null returns null)
+ *
+ * # Returns
+ * * A pointer to a null-terminated C string with the warnings, separated
+ *   by `"; "` like `rb_last_warning()`
+ * * `std::ptr::null()` if the last call with `ctx` raised none
+ *
+ * # Safety
+ * `ctx` must be null or a valid pointer from `rb_context_new()`. The
+ * returned pointer is owned by the context and stays valid until the next
+ * call that uses `ctx`, on any thread.
+ */
+const char *rb_context_last_warning(const struct RBContext *ctx);
+
+/**
+ * Renders an SVG like `rb_render_svg_to_rgba()`, reporting errors through
+ * a context instead of thread-local storage.
+ *
+ * The outcome and any warnings are recorded only in `ctx` (see
+ * `rb_context_last_error()` and `rb_context_last_warning()`); the
+ * thread-local error and warning state is left alone, so the call may
+ * resume on any thread before checking them. The one exception is a null
+ * `ctx`: there is nowhere else to report it, so the call fails with an
+ * "invalid args" thread-local error.
+ *
+ * # Arguments
+ * * `ctx` - Context from `rb_context_new()` (must not be null)
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `width` - Desired output width in pixels (must be > 0)
+ * * `height` - Desired output height in pixels (must be > 0)
+ *
+ * # Returns
+ * * Same as `rb_render_svg_to_rgba()`
+ *
+ * # Safety
+ * `ctx` must be a valid pointer from `rb_context_new()` that no other call
+ * is using; `svg_ptr` must be valid for `svg_len` bytes.
+ */
+struct RBImage rb_render_svg_to_rgba_ctx(struct RBContext *ctx,
+                                         const uint8_t *svg_ptr,
+                                         uintptr_t svg_len,
+                                         uint32_t width,
+                                         uint32_t height);
+
+/**
+ * Renders an SVG file like `rb_render_svg_to_rgba()`, giving up after a
+ * wall-clock time limit.
+ *
+ * The render runs on a worker thread with its own copy of the SVG. If it
+ * finishes in time its image is returned; otherwise the call fails with
+ * `RBErrorCode::Timeout` and the worker is abandoned. Threads can't be
+ * killed safely, so an abandoned worker keeps its CPU time, its copy of the
+ * SVG and its pixmap until the render finishes on its own, then frees them
+ * and exits. To bound that cost, at most 4 workers may be running at once,
+ * counting both those still awaited and those abandoned; while that many
+ * are, further calls fail immediately with `RBErrorCode::Timeout` instead of
+ * starting another. Warnings raised by the worker are not reported.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `width` - Desired output width in pixels (must be > 0)
+ * * `height` - Desired output height in pixels (must be > 0)
+ * * `timeout_ms` - Time limit in milliseconds (must be > 0)
+ *
+ * # Returns
+ * * `RBImage` struct containing the rendered pixel data
+ * * If an error occurs or the time limit passes, returns an image with null
+ *   pointer and zero dimensions
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba()`.
+ */
+struct RBImage rb_render_svg_timeout(const uint8_t *svg_ptr,
+                                     uintptr_t svg_len,
+                                     uint32_t width,
+                                     uint32_t height,
+                                     uint32_t timeout_ms);
+
+/**
+ * Renders an SVG file like `rb_render_svg_to_rgba_with_options()` and reports
+ * the exact transform that was applied.
+ *
+ * The matrix maps SVG user space (the root coordinate system, after the
+ * viewBox is applied) to output pixels, including every fit/size option.
+ * It is written as six floats `[a, b, c, d, e, f]` in SVG `matrix()` order:
+ *
+ * ```text
+ * x_px = a * x + c * y + e
+ * y_px = b * x + d * y + f
+ * ```
+ *
+ * Invert it to map output pixel coordinates back into SVG space.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `width` - Desired output width in pixels (must be > 0)
+ * * `height` - Desired output height in pixels (must be > 0)
+ * * `opts` - Options from `rb_options_new()` (null = the defaults from
+ *   `rb_set_default_options()`)
+ * * `out_matrix` - Receives 6 floats; written only on success (must not be null)
+ *
+ * # Returns
+ * * Same as `rb_render_svg_to_rgba()`
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba_with_options()`; `out_matrix`
+ * must be valid for writing 6 floats.
+ */
+struct RBImage rb_render_svg_to_rgba_with_matrix_out(const uint8_t *svg_ptr,
+                                                     uintptr_t svg_len,
+                                                     uint32_t width,
+                                                     uint32_t height,
+                                                     const struct RBOptions *opts,
+                                                     float *out_matrix);
+
+/**
+ * Renders an SVG file with an explicit transform given as a CSS `matrix()`.
+ *
+ * `css_matrix` is a single `matrix(a, b, c, d, e, f)` function, with commas
+ * and/or spaces between the numbers, as written in CSS or SVG `transform`
+ * values. It maps SVG user space (after the viewBox is applied) to output
+ * pixels exactly as `rb_render_svg_to_rgba_with_matrix_out()` reports, and
+ * replaces the usual stretch-to-fit; content mapped outside the
+ * `width × height` canvas is cut off.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `width` - Output width in pixels (must be > 0)
+ * * `height` - Output height in pixels (must be > 0)
+ * * `css_matrix` - Null-terminated `matrix(...)` string (must not be null)
+ *
+ * # Returns
+ * * `RBImage` struct containing the rendered pixel data
+ * * If an error occurs (including a string that isn't exactly one
+ *   `matrix()` with finite values), returns an image with null pointer and
+ *   zero dimensions
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba()`; `css_matrix` must be a
+ * valid null-terminated string.
+ */
+struct RBImage rb_render_svg_to_rgba_with_css_transform(const uint8_t *svg_ptr,
+                                                        uintptr_t svg_len,
+                                                        uint32_t width,
+                                                        uint32_t height,
+                                                        const char *css_matrix);
+
+/**
+ * Renders an SVG file to RGBA pixel data with a fixed seed for any
+ * randomized rendering steps.
+ *
+ * Renders with the default options (see `rb_set_default_options()`) and
+ * their seed replaced by `seed`, as `rb_options_set_seed()` would set it.
+ * The seed is currently a no-op (rendering is fully deterministic), so this
+ * behaves exactly like `rb_render_svg_to_rgba()` whatever the seed.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `width` - Desired output width in pixels (must be > 0)
+ * * `height` - Desired output height in pixels (must be > 0)
+ * * `seed` - Seed for randomized rendering steps (currently unused)
+ *
+ * # Returns
+ * * Same as `rb_render_svg_to_rgba()`
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba()`.
+ */
+struct RBImage rb_render_svg_to_rgba_with_seed(const uint8_t *svg_ptr,
+                                               uintptr_t svg_len,
+                                               uint32_t width,
+                                               uint32_t height,
+                                               uint64_t seed);
+
+/**
+ * Renders an SVG file at its declared (natural) size.
+ *
+ * The output size is the SVG's declared size converted from CSS pixels
+ * (96 per inch) to the requested DPI and rounded up:
+ * `width = ceil(svg_width * dpi / 96)`, and likewise for the height.
+ * Use `dpi = 96` for a 1:1 render.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `dpi` - Target resolution in dots per inch (must be > 0)
+ *
+ * # Returns
+ * * `RBImage` whose `width`/`height` are the computed dimensions
+ * * If an error occurs, returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba()`.
+ */
+struct RBImage rb_render_svg_to_rgba_at_natural_size(const uint8_t *svg_ptr,
+                                                     uintptr_t svg_len,
+                                                     float dpi);
+
+/**
+ * Renders an SVG for a HiDPI display, sized in logical points.
+ *
+ * The output is `round(logical_w * dpr) × round(logical_h * dpr)` physical
+ * pixels, the backing size UI frameworks expect for an icon of
+ * `logical_w × logical_h` points at that device pixel ratio; the SVG is
+ * stretched to fill it like in `rb_render_svg_to_rgba()`. The returned
+ * `width`/`height` are the physical size; the logical size is written to
+ * the out-params and is also remembered for the image until it is freed,
+ * so `rb_image_logical_width()` and `rb_image_logical_height()` can
+ * recover it later.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `logical_w` - Width in logical points (must be > 0)
+ * * `logical_h` - Height in logical points (must be > 0)
+ * * `dpr` - Device pixel ratio, e.g. 2.0 for a Retina display (must be
+ *   finite and > 0)
+ * * `out_logical_w` - Receives the logical width on success (null = not needed)
+ * * `out_logical_h` - Receives the logical height on success (null = not needed)
+ *
+ * # Returns
+ * * `RBImage` whose `width`/`height` are the physical dimensions
+ * * If an error occurs (including a physical side that rounds to 0),
+ *   returns an image with null pointer and zero dimensions; the out-params
+ *   are set to 0
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba()`; `out_logical_w` and
+ * `out_logical_h` must each be null or valid for a write.
+ */
+struct RBImage rb_render_svg_logical(const uint8_t *svg_ptr,
+                                     uintptr_t svg_len,
+                                     uint32_t logical_w,
+                                     uint32_t logical_h,
+                                     float dpr,
+                                     uint32_t *out_logical_w,
+                                     uint32_t *out_logical_h);
+
+/**
+ * Renders an SVG for a HiDPI ("Retina") display.
+ *
+ * The output is `round(logical_w * scale_factor) × round(logical_h *
+ * scale_factor)` physical pixels, the way macOS and iOS size assets for a
+ * backing scale factor; see `rb_render_svg_logical()`. The returned
+ * `width`/`height` are the physical size; the logical size is remembered
+ * for the image, so read it back with `rb_image_logical_width()` and
+ * `rb_image_logical_height()`.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `logical_w` - Width in logical points (must be > 0)
+ * * `logical_h` - Height in logical points (must be > 0)
+ * * `scale_factor` - Physical pixels per point: 2.0 for classic Retina,
+ *   1.5 and 3.0 are also common (must be finite and > 0)
+ *
+ * # Returns
+ * * Same as `rb_render_svg_logical()`
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba()`.
+ */
+struct RBImage rb_render_svg_to_rgba_retina(const uint8_t *svg_ptr,
+                                            uintptr_t svg_len,
+                                            uint32_t logical_w,
+                                            uint32_t logical_h,
+                                            float scale_factor);
+
+/**
+ * Gets the logical width of an image rendered for a HiDPI display.
+ *
+ * For images from `rb_render_svg_logical()` or
+ * `rb_render_svg_to_rgba_retina()` this is the `logical_w` that was passed
+ * in. Any other image, including a copy or conversion of one of those, has
+ * no logical size and reports its physical `width`.
+ *
+ * # Arguments
+ * * `img` - The image (null returns 0)
+ *
+ * # Returns
+ * * The width in logical points
+ * * 0 if `img` is null (error is set)
+ *
+ * # Safety
+ * `img` must be null or point to a valid `RBImage`.
+ */
+uint32_t rb_image_logical_width(const struct RBImage *img);
+
+/**
+ * Gets the logical height of an image rendered for a HiDPI display.
+ *
+ * The height counterpart of `rb_image_logical_width()`.
+ *
+ * # Arguments
+ * * `img` - The image (null returns 0)
+ *
+ * # Returns
+ * * The height in logical points
+ * * 0 if `img` is null (error is set)
+ *
+ * # Safety
+ * `img` must be null or point to a valid `RBImage`.
+ */
+uint32_t rb_image_logical_height(const struct RBImage *img);
+
+/**
+ * Renders an SVG file scaled uniformly so its longer side is `max_dim` pixels.
+ *
+ * `scale = max_dim / max(svg_width, svg_height)`, and the output is
+ * `ceil(svg_width * scale) × ceil(svg_height * scale)`, so the image always
+ * fits within `max_dim × max_dim` with the SVG's aspect ratio preserved.
+ * Renders use the default options (see `rb_set_default_options()`), so
+ * their size limits from `rb_options_set_size_limits()` still apply and can
+ * shrink the output further, again keeping the aspect ratio.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `max_dim` - Maximum output width/height in pixels (must be > 0)
+ *
+ * # Returns
+ * * `RBImage` whose `width`/`height` are the computed dimensions
+ * * If an error occurs, returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba()`.
+ */
+struct RBImage rb_render_svg_to_rgba_max_dim(const uint8_t *svg_ptr,
+                                             uintptr_t svg_len,
+                                             uint32_t max_dim);
+
+/**
+ * Renders an SVG file so its longest side is `max_side` pixels.
+ *
+ * Landscape SVGs come out `max_side` wide, portrait ones `max_side` tall,
+ * and the other side follows the aspect ratio; see
+ * `rb_render_svg_to_rgba_max_dim()`, which this calls. A maximum size set
+ * with `rb_options_set_size_limits()` on the default options caps
+ * `max_side`.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `max_side` - Length of the longer output side in pixels (must be > 0)
+ *
+ * # Returns
+ * * `RBImage` whose `width`/`height` are the computed dimensions
+ * * If an error occurs (including an output too large to allocate), returns
+ *   an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba()`.
+ */
+struct RBImage rb_render_svg_longest(const uint8_t *svg_ptr, uintptr_t svg_len, uint32_t max_side);
+
+/**
+ * Renders an SVG with explicit `preserveAspectRatio` control.
+ *
+ * The result is what the SVG would look like embedded as
+ * `<svg width="{width}" height="{height}" preserveAspectRatio="{fit}">`:
+ * its `viewBox` (or, without one, its `width × height` box) is placed in
+ * the output according to `fit`, replacing the document's own
+ * `preserveAspectRatio`. A small declared size doesn't matter; only the
+ * `viewBox` proportions do. Content outside the `viewBox` can show in the
+ * transparent bands of the meet modes, as in a browser.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `width` - Output width in pixels (must be > 0)
+ * * `height` - Output height in pixels (must be > 0)
+ * * `fit` - How the SVG is scaled and aligned in the output, as an
+ *   `RBScaleMode` value
+ *
+ * # Returns
+ * * `RBImage` of exactly `width × height` pixels
+ * * If an error occurs, returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba()`.
+ */
+struct RBImage rb_render_svg_exact(const uint8_t *svg_ptr,
+                                   uintptr_t svg_len,
+                                   uint32_t width,
+                                   uint32_t height,
+                                   uint32_t fit);
+
+/**
+ * Renders an SVG with only some of its elements shown, selected by `id`.
+ *
+ * This enables layer-style exports (hiding guides, labels, annotations)
+ * from a single source file. The filter is applied as an injected style
+ * sheet (see `rb_options_set_style_sheet()`): blacklisted elements get
+ * `display: none`; in whitelist mode every element gets
+ * `visibility: hidden` except the listed ones and their descendants. The
+ * SVG's own `style` attributes, and its style rules using `#id` selectors,
+ * take precedence over the filter.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `width` - Desired output width in pixels (must be > 0)
+ * * `height` - Desired output height in pixels (must be > 0)
+ * * `ids` - Array of `id_count` null-terminated element ids (may be null
+ *   when `id_count` is 0); ids must not contain `"` or `\`
+ * * `id_count` - Number of ids
+ * * `mode` - Whether the ids are the only elements shown or the ones hidden,
+ *   as an `RBVisibilityMode` value
+ *
+ * # Returns
+ * * `RBImage` struct containing the rendered pixel data
+ * * If an error occurs, returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * `svg_ptr` must be valid for `svg_len` bytes and `ids` for `id_count`
+ * valid null-terminated strings. The returned image must be freed with
+ * `rb_free_image()`.
+ */
+struct RBImage rb_render_svg_to_rgba_with_id_list(const uint8_t *svg_ptr,
+                                                  uintptr_t svg_len,
+                                                  uint32_t width,
+                                                  uint32_t height,
+                                                  const char *const *ids,
+                                                  uintptr_t id_count,
+                                                  uint32_t mode);
+
+/**
+ * Renders one page (artboard) of a multi-page SVG, or one symbol or `<use>`
+ * instance of an icon sheet.
+ *
+ * Multi-artboard documents usually keep each page in its own top-level
+ * `<g id="...">`. Only the element with id `page_id` and its descendants
+ * are drawn, using the same filter as `rb_render_svg_to_rgba_with_id_list()`
+ * in whitelist mode, and the page's bounding box (including stroke) is
+ * scaled uniformly to fit the output and centered in it, so pages of any
+ * size or position export the same way. Other pages are not drawn, even
+ * where they overlap this one; the output outside the page is transparent.
+ *
+ * Icon libraries define `<symbol>`s and draw them with `<use>`:
+ * * A `<symbol>` id renders the symbol whether or not the document uses
+ *   it, instantiated as by `<use width="100%" height="100%">`: its own
+ *   `viewBox` and `preserveAspectRatio` fit it into the output
+ *   (letterboxing by default), and a symbol without a `viewBox` is drawn
+ *   at 1 user unit per pixel from the top-left corner.
+ * * A `<use>` id renders that one instance, with its own position, size
+ *   and overrides, fitted like a page; other instances of the same symbol
+ *   are not drawn. Transforms and inherited properties of the `<use>`'s
+ *   ancestors do not apply.
+ *
+ * In both cases styles and definitions anywhere in the document apply.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `page_id` - Null-terminated `id` of the page, `<symbol>` or `<use>`
+ *   element (must not be null; must not contain `"` or `\`)
+ * * `width` - Output width in pixels (must be > 0)
+ * * `height` - Output height in pixels (must be > 0)
+ *
+ * # Returns
+ * * `RBImage` of exactly `width × height` pixels
+ * * If an error occurs (including an unknown id or an empty page), returns
+ *   an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba()`; `page_id` must be a
+ * valid null-terminated string.
+ */
+struct RBImage rb_render_page(const uint8_t *svg_ptr,
+                              uintptr_t svg_len,
+                              const char *page_id,
+                              uint32_t width,
+                              uint32_t height);
+
+/**
+ * Renders a placeholder image that shows an error message.
+ *
+ * The image has a light red grid background and a red border, with the
+ * message in red text wrapped to the image width. Text needs a
+ * `sans-serif` font in the font database; without one only the grid and
+ * border are drawn, which still marks the image as broken.
+ *
+ * # Arguments
+ * * `error_msg` - Null-terminated message to show, or null to use the
+ *   current thread's last error (as from `rb_last_error_copy()`)
+ * * `width` - Output width in pixels (must be > 0)
+ * * `height` - Output height in pixels (must be > 0)
+ *
+ * # Returns
+ * * The placeholder image
+ * * If an error occurs, returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * `error_msg` must be null or a valid null-terminated string. The returned
+ * image must be freed with `rb_free_image()`.
+ *
+ * # Example
+ * ```c
+ * RBImage img = rb_render_svg_to_rgba(svg_data, svg_len, 100, 100);
+ * if (!img.ptr) img = rb_render_svg_to_rgba_error_image(NULL, 100, 100);
+ * ```
+ */
+struct RBImage rb_render_svg_to_rgba_error_image(const char *error_msg,
+                                                 uint32_t width,
+                                                 uint32_t height);
+
+/**
+ * Renders an SVG file and maps the result onto a fixed color palette.
+ *
+ * Each pixel becomes the palette entry nearest to it. Distance is Euclidean
+ * over straight RGBA rather than RGB alone: transparent pixels have straight
+ * RGB 0,0,0, so an RGB-only distance would map them to opaque black (or any
+ * dark entry) instead of the palette's alpha-0 entry that GIF's transparent
+ * index expects. For opaque pixels and an opaque palette the alpha term is
+ * zero and this is exactly RGB distance. With `dither`, Floyd–Steinberg
+ * error diffusion trades banding for noise in gradients.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `width` - Desired output width in pixels (must be > 0)
+ * * `height` - Desired output height in pixels (must be > 0)
+ * * `palette` - `palette_size` straight RGBA quadruples (must not be null)
+ * * `palette_size` - Number of palette entries (must be > 0)
+ * * `dither` - `true` for Floyd–Steinberg error diffusion
+ *
+ * # Returns
+ * * `RBImage` containing only (premultiplied) palette colors
+ * * If an error occurs, returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba()`; `palette` must be valid
+ * for `palette_size * 4` bytes.
+ */
+struct RBImage rb_render_svg_to_rgba_palette(const uint8_t *svg_ptr,
+                                             uintptr_t svg_len,
+                                             uint32_t width,
+                                             uint32_t height,
+                                             const uint8_t *palette,
+                                             uintptr_t palette_size,
+                                             bool dither);
+
+/**
+ * Renders an SVG to floating-point coverage, for pipelines that need more
+ * than 8 bits of anti-aliasing precision.
+ *
+ * The SVG is rendered at 4× the requested size in each direction and every
+ * 4×4 block of samples is averaged, giving 16 coverage samples per output
+ * pixel. The result is an `RBPixelFormat::Rgba32F` image: four
+ * native-endian `f32` per pixel (premultiplied R, G, B, A, each in
+ * [0, 1]) and `len == width * height * 16`. `ptr` comes from the system
+ * allocator and is aligned for `float` on supported platforms. The
+ * supersampled render needs 16× the memory of a normal one.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `width` - Desired output width in pixels (must be > 0)
+ * * `height` - Desired output height in pixels (must be > 0)
+ *
+ * # Returns
+ * * `RBImage` containing the coverage values
+ * * If an error occurs, returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba()`.
+ */
+struct RBImage rb_render_svg_coverage(const uint8_t *svg_ptr,
+                                      uintptr_t svg_len,
+                                      uint32_t width,
+                                      uint32_t height);
+
+/**
+ * Renders an SVG with LCD sub-pixel anti-aliasing.
+ *
+ * The SVG is rendered at 3× resolution along the stripe direction (width
+ * for `Rgb`/`Bgr`, height for `Vrgb`/`Vbgr`), every sample is smoothed with
+ * FreeType's default 5-tap LCD filter (weights 8, 77, 86, 77, 8 / 256) to
+ * limit color fringes, and each output channel takes the sample under its
+ * own sub-pixel. Edges therefore get three times the positioning precision
+ * across the stripes, which mostly benefits small text but applies to
+ * every shape.
+ *
+ * The output is premultiplied RGBA whose channels carry separate coverage;
+ * alpha is the largest of the three. It only looks right on a panel with
+ * the given layout, at 1:1 scale, composited over an opaque background;
+ * most accurate when the SVG itself is opaque. The supersampled render
+ * needs 3× the memory of a normal one.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `width` - Desired output width in pixels (must be > 0)
+ * * `height` - Desired output height in pixels (must be > 0)
+ * * `subpixel_order` - The panel's sub-pixel layout, as an `RBSubpixelOrder`
+ *   value
+ *
+ * # Returns
+ * * `RBImage` struct containing the rendered pixel data
+ * * If an error occurs, returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba()`.
+ */
+struct RBImage rb_render_svg_to_rgba_subpixel(const uint8_t *svg_ptr,
+                                              uintptr_t svg_len,
+                                              uint32_t width,
+                                              uint32_t height,
+                                              uint32_t subpixel_order);
+
+/**
+ * Frees memory allocated for an RBImage.
+ *
+ * This function must be called to free the memory allocated by `rb_render_svg_to_rgba()`.
+ * Failing to call this function will result in a memory leak.
+ *
+ * # Arguments
+ * * `img` - The RBImage structure to free
+ *
+ * # Safety
+ * This function is safe to call multiple times on the same image (idempotent).
+ * After calling this function, the image structure should not be used again.
+ *
+ * # Example
+ * ```c
+ * RBImage img = rb_render_svg_to_rgba(svg_data, svg_len, 100, 100);
+ * // Use the image...
+ * rb_free_image(img);
+ * ```
+ */
+void rb_free_image(struct RBImage img);
+
+/**
+ * Gets the number of bytes between the starts of consecutive rows.
+ *
+ * Rows are tightly packed, so this is always `width * bytes per pixel`
+ * (`width * 4` for RGBA); it is provided for APIs such as GPU uploads
+ * that take an explicit row pitch.
+ *
+ * # Arguments
+ * * `img` - The image (null returns 0)
+ *
+ * # Returns
+ * * The row stride in bytes
+ * * 0 for a null pointer or an empty image
+ *
+ * # Safety
+ * `img` must be null or point to a valid `RBImage`.
+ */
+uintptr_t rb_image_stride(const struct RBImage *img);
+
+/**
+ * Renders one rectangle of an SVG as it would appear in a full render.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `width` - Width of the full (virtual) output in pixels (must be > 0)
+ * * `height` - Height of the full (virtual) output in pixels (must be > 0)
+ * * `x`, `y`, `w`, `h` - The region to render, in output pixels; must be
+ *   non-empty and lie within `width × height`
+ * * `opts` - Options from `rb_options_new()` (null = the defaults from
+ *   `rb_set_default_options()`)
+ *
+ * # Returns
+ * * A `w × h` image
+ * * If an error occurs, returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba_with_options()`.
+ */
+struct RBImage rb_render_svg_region(const uint8_t *svg_ptr,
+                                    uintptr_t svg_len,
+                                    uint32_t width,
+                                    uint32_t height,
+                                    uint32_t x,
+                                    uint32_t y,
+                                    uint32_t w,
+                                    uint32_t h,
+                                    const struct RBOptions *opts);
+
+/**
+ * Renders the area around one element, as a browser screenshot of that
+ * element would show it.
+ *
+ * The element's bounding box (including stroke) is scaled uniformly to fit
+ * the `out_w × out_h` output minus a margin of `padding` pixels on every
+ * side, and centered in it. When the box's aspect ratio differs from the
+ * output's, the spare room on two sides grows beyond the padding. Margins
+ * show whatever surrounds the box in the document: the whole document is
+ * rendered, so backgrounds and siblings that overlap the margins appear
+ * too, while everything outside is cut off.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `element_id` - Null-terminated `id` of the element (must not be null)
+ * * `padding` - Margin around the bounding box in output pixels (must be
+ *   finite and less than half of each output dimension; negative values
+ *   crop into the box)
+ * * `out_w` - Output width in pixels (must be > 0)
+ * * `out_h` - Output height in pixels (must be > 0)
+ *
+ * # Returns
+ * * An `out_w × out_h` image
+ * * If an error occurs (including an unknown id or an empty bounding box),
+ *   returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba()`; `element_id` must be a
+ * valid null-terminated string.
+ */
+struct RBImage rb_render_svg_to_rgba_clipped_to_element(const uint8_t *svg_ptr,
+                                                        uintptr_t svg_len,
+                                                        const char *element_id,
+                                                        float padding,
+                                                        uint32_t out_w,
+                                                        uint32_t out_h);
+
+/**
+ * Re-renders a dirty rectangle of a previously rendered image in place.
+ *
+ * Meant for interactive viewers where only a small area changes between
+ * frames: pass the updated SVG and the area that changed, and only that
+ * area is rasterized and written into `img`. The region's pixels are
+ * replaced (not blended), since the re-render already contains everything
+ * that is visible there.
+ *
+ * The caller guarantees that nothing outside the dirty rectangle changed
+ * since `img` was rendered from the same SVG, size and options; the
+ * library does not verify this.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the updated SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `opts` - The options `img` was rendered with (null = the defaults from
+ *   `rb_set_default_options()`)
+ * * `img` - The cached image to update (must not be null)
+ * * `x`, `y`, `w`, `h` - The dirty rectangle in pixels, clipped to the image
+ *
+ * # Returns
+ * * `true` on success (a rectangle entirely outside the image is a no-op)
+ * * `false` on error; `img` is left untouched (error is set)
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba_with_options()`; `img` must be
+ * a valid RGBA image from this library.
+ */
+bool rb_render_svg_dirty_rect(const uint8_t *svg_ptr,
+                              uintptr_t svg_len,
+                              const struct RBOptions *opts,
+                              struct RBImage *img,
+                              uint32_t x,
+                              uint32_t y,
+                              uint32_t w,
+                              uint32_t h);
+
+/**
+ * Renders an SVG straight into a memory-mapped file.
+ *
+ * For outputs too large to hold in RAM. The file at `path` is created (or
+ * truncated), sized to `width * height * 4` bytes and mapped; the image is
+ * then rendered in full-width bands of rows directly into the mapping, so
+ * resident memory stays bounded by the OS page cache rather than by the
+ * image size. The SVG is parsed once.
+ *
+ * As with `rb_render_svg_to_rgba_with_options()`, the requested size is
+ * first clamped to the options' size limits (see
+ * `rb_options_set_size_limits()`); `width` and `height` in the file layout
+ * below mean the clamped size.
+ *
+ * File layout: no header, rows top to bottom, each row `width * 4` bytes of
+ * premultiplied RGBA (the same bytes `rb_render_svg_to_rgba()` returns).
+ *
+ * Supported wherever the `memmap2` crate is (Unix and Windows). Band
+ * boundaries follow the REGION RENDERING caveats above.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `width` - Output width in pixels (must be > 0)
+ * * `height` - Output height in pixels (must be > 0)
+ * * `path` - Null-terminated, UTF-8 path of the output file
+ * * `opts` - Rendering options, or null for the defaults from
+ *   `rb_set_default_options()`
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` on error, including when the file size would overflow the
+ *   platform's file offset or address space (error is set)
+ *
+ * # Safety
+ * `svg_ptr` must be valid for `svg_len` bytes, `path` must be a valid
+ * null-terminated string and `opts` must be null or a valid pointer from
+ * `rb_options_new()`. The file must not be modified by anyone else while
+ * rendering.
+ */
+bool rb_render_svg_to_mmap(const uint8_t *svg_ptr,
+                           uintptr_t svg_len,
+                           uint32_t width,
+                           uint32_t height,
+                           const char *path,
+                           const struct RBOptions *opts);
+
+/**
+ * Renders an SVG progressively, handing each row to a callback as soon as
+ * its band is done.
+ *
+ * The image is rendered in full-width bands of 32 rows, top to bottom, and
+ * after each band its rows are passed to `row_cb` in order. Each call gets
+ * the row index, the row's `width * 4` bytes of premultiplied RGBA (valid
+ * only during the call) and the stride, which equals the row length. If the
+ * callback returns `false`, no further rows are rendered or delivered, the
+ * error is set to "cancelled" (`RBErrorCode::Cancelled`) and the function
+ * returns `false`. Band boundaries follow the REGION RENDERING caveats
+ * above. The SVG is parsed once.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `width` - Output width in pixels (must be > 0)
+ * * `height` - Output height in pixels (must be > 0)
+ * * `row_cb` - Called once per row, on the calling thread (must not be null)
+ * * `userdata` - Opaque pointer forwarded to the callback unchanged
+ *
+ * # Returns
+ * * `true` if every row was rendered and delivered
+ * * `false` on error or cancellation (error is set)
+ *
+ * # Safety
+ * `svg_ptr` must be valid for `svg_len` bytes. The callback must not unwind
+ * and must not keep `data` past its return.
+ */
+bool rb_render_svg_to_rgba_incremental(const uint8_t *svg_ptr,
+                                       uintptr_t svg_len,
+                                       uint32_t width,
+                                       uint32_t height,
+                                       RBRowCallback row_cb,
+                                       void *userdata);
+
+/**
+ * Parses an SVG once for repeated rendering.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `opts` - Options to parse and render with, or null for the defaults from
+ *   `rb_set_default_options()`; they are copied, so `opts` may be freed afterwards
+ *
+ * # Returns
+ * * A tree that must be released with `rb_tree_free()`
+ * * Null if parsing failed (error is set)
+ *
+ * # Safety
+ * `svg_ptr` must be valid for `svg_len` bytes; `opts` must be null or a
+ * valid pointer from `rb_options_new()`.
+ */
+struct RBTree *rb_tree_parse(const uint8_t *svg_ptr,
+                             uintptr_t svg_len,
+                             const struct RBOptions *opts);
+
+/**
+ * Frees a tree created by `rb_tree_parse()` or `rb_tree_clone()`.
+ *
+ * # Arguments
+ * * `tree` - The tree to free (null is ignored)
+ *
+ * # Safety
+ * `tree` must come from this library and must not be used afterwards.
+ */
+void rb_tree_free(struct RBTree *tree);
+
+/**
+ * Copies a parsed tree without re-parsing it.
+ *
+ * # Arguments
+ * * `tree` - The tree to copy (must not be null)
+ *
+ * # Returns
+ * * A new tree that must be released with `rb_tree_free()`
+ * * Null if `tree` is null (error is set)
+ *
+ * # Safety
+ * `tree` must be a valid pointer from `rb_tree_parse()` or `rb_tree_clone()`.
+ */
+struct RBTree *rb_tree_clone(const struct RBTree *tree);
+
+/**
+ * Renders a parsed tree to RGBA pixel data.
+ *
+ * Several threads may render the same tree at once; see the section notes
+ * above.
+ *
+ * # Arguments
+ * * `tree` - The tree to render (must not be null)
+ * * `width` - Desired output width in pixels (must be > 0)
+ * * `height` - Desired output height in pixels (must be > 0)
+ *
+ * # Returns
+ * * `RBImage` struct containing the rendered pixel data
+ * * If an error occurs, returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * `tree` must be a valid pointer from `rb_tree_parse()` or `rb_tree_clone()`.
+ * The returned image must be freed with `rb_free_image()`.
+ */
+struct RBImage rb_tree_render(const struct RBTree *tree, uint32_t width, uint32_t height);
+
+/**
+ * Renders a parsed tree with an additional style sheet.
+ *
+ * The style sheet is applied after the one from the tree's options (so it
+ * wins on equal specificity) and only for this render. This re-parses the
+ * tree's source; a null or empty `css` renders the stored tree directly.
+ *
+ * # Arguments
+ * * `tree` - The tree to render (must not be null)
+ * * `css` - Null-terminated CSS text, or null for none
+ * * `width` - Desired output width in pixels (must be > 0)
+ * * `height` - Desired output height in pixels (must be > 0)
+ *
+ * # Returns
+ * * `RBImage` struct containing the rendered pixel data
+ * * If an error occurs, returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * `tree` must be a valid pointer from `rb_tree_parse()` or `rb_tree_clone()`;
+ * `css` must be null or a valid null-terminated string. The returned image
+ * must be freed with `rb_free_image()`.
+ */
+struct RBImage rb_tree_render_with_style_sheet(const struct RBTree *tree,
+                                               const char *css,
+                                               uint32_t width,
+                                               uint32_t height);
+
+/**
+ * Sets the fill color of the element with the given `id`.
+ *
+ * The color applies to the element and is inherited by its children, like
+ * a presentation attribute; a shape with `fill="none"` gets the new fill.
+ * The SVG's own `style` attributes and `#id` rules in its style sheets take
+ * precedence. Setting a color again for the same element replaces it.
+ *
+ * # Arguments
+ * * `tree` - The tree to modify (must not be null)
+ * * `id` - Null-terminated element id; must not contain `"` or `\`
+ * * `r`, `g`, `b`, `a` - The color (straight alpha)
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if the id doesn't name a rendered shape, group or text
+ *   element, or on any other error (error is set, the tree is unchanged)
+ *
+ * # Safety
+ * `tree` must be a valid pointer from `rb_tree_parse()` or `rb_tree_clone()`
+ * and `id` a valid null-terminated string.
+ */
+bool rb_tree_set_fill(struct RBTree *tree,
+                      const char *id,
+                      uint8_t r,
+                      uint8_t g,
+                      uint8_t b,
+                      uint8_t a);
+
+/**
+ * Sets the stroke color of the element with the given `id`.
+ *
+ * Works like `rb_tree_set_fill()`. Only the color changes; an element
+ * without a stroke gets one with the SVG default width of 1.
+ *
+ * # Arguments
+ * * `tree` - The tree to modify (must not be null)
+ * * `id` - Null-terminated element id; must not contain `"` or `\`
+ * * `r`, `g`, `b`, `a` - The color (straight alpha)
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` on error (error is set, the tree is unchanged)
+ *
+ * # Safety
+ * Same requirements as `rb_tree_set_fill()`.
+ */
+bool rb_tree_set_stroke(struct RBTree *tree,
+                        const char *id,
+                        uint8_t r,
+                        uint8_t g,
+                        uint8_t b,
+                        uint8_t a);
+
+/**
+ * Renders one SVG at several sizes in parallel.
+ *
+ * The SVG is parsed once and each size is rendered on the library's thread
+ * pool (see `rb_set_thread_count()`).
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `sizes` - Array of `count` output sizes (each dimension must be > 0)
+ * * `count` - Number of entries in `sizes` and `out_images`
+ * * `out_images` - Caller-provided array of `count` images to fill
+ *
+ * # Returns
+ * * `true` on success; `out_images[i]` holds the render for `sizes[i]`
+ * * `false` on failure; no images are written (error is set)
+ *
+ * # Safety
+ * `sizes` and `out_images` must each point to `count` valid elements.
+ * Every returned image must be freed with `rb_free_image()`.
+ */
+bool rb_render_svg_to_rgba_batch(const uint8_t *svg_ptr,
+                                 uintptr_t svg_len,
+                                 const struct RBSize *sizes,
+                                 uintptr_t count,
+                                 struct RBImage *out_images);
+
+/**
+ * Renders one SVG at several sizes in parallel like
+ * `rb_render_svg_to_rgba_batch()`, reporting progress as sizes finish.
+ *
+ * `progress_cb` is called once per size that finishes rendering, in
+ * completion order, with `done` counting up from 1 to `count`. It runs on
+ * the thread pool's worker threads, not the calling thread, but calls are
+ * serialized: no two overlap, so a callback that only touches its own
+ * state needs no locking, though anything it shares with other threads
+ * (such as a UI) does. The render waits while the callback runs, so keep
+ * it short—post the update to your UI thread rather than drawing in it.
+ * If a size fails, sizes already in flight may still be reported before the
+ * call returns `false`.
+ *
+ * # Arguments
+ * * Same as `rb_render_svg_to_rgba_batch()`, plus:
+ * * `progress_cb` - Callback receiving the progress (null = none)
+ * * `userdata` - Opaque pointer forwarded to the callback unchanged
+ *
+ * # Returns
+ * * Same as `rb_render_svg_to_rgba_batch()`
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba_batch()`; `userdata` must
+ * be usable from other threads.
+ */
+bool rb_render_svg_to_rgba_batch_with_progress(const uint8_t *svg_ptr,
+                                               uintptr_t svg_len,
+                                               const struct RBSize *sizes,
+                                               uintptr_t count,
+                                               struct RBImage *out_images,
+                                               RBProgressCallback progress_cb,
+                                               void *userdata);
+
+/**
+ * Renders one SVG at several square sizes and writes each as a PNG file.
+ *
+ * The SVG is parsed once; sizes are then rendered and written one at a
+ * time, so only one image is in memory at once. Size `n` is written to
+ * `{dir}/{name_prefix}-{n}.png`, replacing an existing file. A size that
+ * fails to render or write doesn't stop the others: its error is recorded,
+ * prefixed with the file name, and the remaining sizes are still written.
+ * When several fail, their errors are joined with `"; "`.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `sizes` - Array of `count` edge lengths in pixels (each must be > 0)
+ * * `count` - Number of entries in `sizes`
+ * * `dir` - Null-terminated, UTF-8 path of an existing directory
+ * * `name_prefix` - Null-terminated, UTF-8 file name prefix, e.g. `"icon"`
+ *
+ * # Returns
+ * * The number of files written; less than `count` if any size failed
+ *   (error is set)
+ * * -1 on invalid arguments or if the SVG fails to parse (error is set)
+ *
+ * # Safety
+ * `svg_ptr` must be valid for `svg_len` bytes, `sizes` must point to
+ * `count` valid elements, and `dir` and `name_prefix` must be valid
+ * null-terminated strings.
+ */
+int32_t rb_render_svg_to_png_set(const uint8_t *svg_ptr,
+                                 uintptr_t svg_len,
+                                 const uint32_t *sizes,
+                                 uintptr_t count,
+                                 const char *dir,
+                                 const char *name_prefix);
+
+/**
+ * Writes one PNG per size like `rb_render_svg_to_png_set()`, reporting
+ * progress as files finish.
+ *
+ * Sizes are processed in order on the calling thread, and `progress_cb`
+ * is called after each one, whether it was written or failed, with `done`
+ * counting up from 1 to `count`.
+ *
+ * # Arguments
+ * * Same as `rb_render_svg_to_png_set()`, plus:
+ * * `progress_cb` - Callback receiving the progress (null = none)
+ * * `userdata` - Opaque pointer forwarded to the callback unchanged
+ *
+ * # Returns
+ * * Same as `rb_render_svg_to_png_set()`
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_png_set()`.
+ */
+int32_t rb_render_svg_to_png_set_with_progress(const uint8_t *svg_ptr,
+                                               uintptr_t svg_len,
+                                               const uint32_t *sizes,
+                                               uintptr_t count,
+                                               const char *dir,
+                                               const char *name_prefix,
+                                               RBProgressCallback progress_cb,
+                                               void *userdata);
+
+/**
+ * Renders an SVG over a raster background image.
+ *
+ * The background is scaled to `width × height` with bilinear filtering, the
+ * SVG is rendered into its own transparent layer, and the layer is composited
+ * over the background (Porter-Duff source-over). This is the common "SVG
+ * overlay on a photo" case.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `bg_ptr` - Background pixels, tightly packed, straight alpha (must not be null)
+ * * `bg_len` - Length of the background buffer in bytes
+ * * `bg_format` - Layout of the background pixels, as an `RBPixelFormat`
+ *   value
+ * * `bg_w` - Background width in pixels (must be > 0)
+ * * `bg_h` - Background height in pixels (must be > 0)
+ * * `width` - Desired output width in pixels (must be > 0)
+ * * `height` - Desired output height in pixels (must be > 0)
+ *
+ * # Returns
+ * * The composited image
+ * * If an error occurs, returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba()`; `bg_ptr` must be valid for
+ * `bg_len` bytes.
+ */
+struct RBImage rb_render_svg_to_rgba_with_background_image(const uint8_t *svg_ptr,
+                                                           uintptr_t svg_len,
+                                                           const uint8_t *bg_ptr,
+                                                           uintptr_t bg_len,
+                                                           uint32_t bg_format,
+                                                           uint32_t bg_w,
+                                                           uint32_t bg_h,
+                                                           uint32_t width,
+                                                           uint32_t height);
+
+/**
+ * Renders an SVG with a drop shadow behind it.
+ *
+ * The SVG is rendered at `width × height`, and its alpha channel, offset by
+ * `(shadow_dx, shadow_dy)` and blurred with `rb_image_gaussian_blur()`'s
+ * kernel, is filled with the shadow color and composited underneath it
+ * (source-over). The output grows on each side as far as the shadow
+ * reaches, i.e. the offset plus the blur radius `ceil(3 * shadow_blur)`,
+ * so nothing is clipped; the SVG's own pixels start at
+ * `(max(0, ceil(r - dx)), max(0, ceil(r - dy)))` for a radius `r`.
+ * Fractional offsets are resampled bilinearly.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `width` - Width of the SVG itself in pixels (must be > 0)
+ * * `height` - Height of the SVG itself in pixels (must be > 0)
+ * * `shadow_dx` - Horizontal shadow offset in pixels; positive is right
+ * * `shadow_dy` - Vertical shadow offset in pixels; positive is down
+ * * `shadow_blur` - Blur standard deviation in pixels (must be >= 0;
+ *   0 gives a hard shadow)
+ * * `shadow_r`, `shadow_g`, `shadow_b` - Shadow color
+ * * `shadow_a` - Shadow opacity (straight alpha); scales the silhouette's alpha
+ *
+ * # Returns
+ * * The image containing the SVG and its shadow
+ * * If an error occurs, returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba()`.
+ */
+struct RBImage rb_render_svg_to_rgba_with_shadow(const uint8_t *svg_ptr,
+                                                 uintptr_t svg_len,
+                                                 uint32_t width,
+                                                 uint32_t height,
+                                                 float shadow_dx,
+                                                 float shadow_dy,
+                                                 float shadow_blur,
+                                                 uint8_t shadow_r,
+                                                 uint8_t shadow_g,
+                                                 uint8_t shadow_b,
+                                                 uint8_t shadow_a);
+
+/**
+ * Renders an SVG and stamps a PNG watermark onto it.
+ *
+ * The watermark is drawn at its own pixel size with its top-left corner at
+ * `(wm_x, wm_y)` (offsets may be negative; it is clipped to the output),
+ * using source-over compositing with its alpha scaled by `wm_opacity`.
+ * Everything happens on pixels; the SVG is not modified. To watermark with
+ * an SVG, render it first and encode it as PNG.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `width` - Desired output width in pixels (must be > 0)
+ * * `height` - Desired output height in pixels (must be > 0)
+ * * `wm_ptr` - Pointer to the watermark, a PNG file in memory (must not be null)
+ * * `wm_len` - Length of the watermark data in bytes
+ * * `wm_x` - Horizontal position of the watermark in output pixels
+ * * `wm_y` - Vertical position of the watermark in output pixels
+ * * `wm_opacity` - Watermark opacity, clamped to 0.0..=1.0
+ *
+ * # Returns
+ * * The composited image
+ * * If an error occurs, returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba()`; `wm_ptr` must be valid for
+ * `wm_len` bytes.
+ */
+struct RBImage rb_render_svg_to_rgba_with_watermark(const uint8_t *svg_ptr,
+                                                    uintptr_t svg_len,
+                                                    uint32_t width,
+                                                    uint32_t height,
+                                                    const uint8_t *wm_ptr,
+                                                    uintptr_t wm_len,
+                                                    int32_t wm_x,
+                                                    int32_t wm_y,
+                                                    float wm_opacity);
+
+/**
+ * Renders an SVG through a second SVG used as a cookie cutter.
+ *
+ * Both documents are rendered at `width × height`, each stretched to fill
+ * it. The clip render's colors are ignored; its alpha scales the main
+ * render's pixels, so the main SVG shows where the clip SVG is opaque,
+ * partially where it is translucent (including its anti-aliased edges), and
+ * nowhere else. Neither source is modified.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the main SVG data (must not be null)
+ * * `svg_len` - Length of the main SVG data in bytes
+ * * `clip_svg_ptr` - Pointer to the clip SVG data (must not be null)
+ * * `clip_svg_len` - Length of the clip SVG data in bytes
+ * * `width` - Desired output width in pixels (must be > 0)
+ * * `height` - Desired output height in pixels (must be > 0)
+ *
+ * # Returns
+ * * `RBImage` containing the masked render
+ * * If an error occurs in either SVG, returns an image with null pointer
+ *   and zero dimensions
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba()`, for both SVGs.
+ */
+struct RBImage rb_render_svg_to_rgba_with_clip_path(const uint8_t *svg_ptr,
+                                                    uintptr_t svg_len,
+                                                    const uint8_t *clip_svg_ptr,
+                                                    uintptr_t clip_svg_len,
+                                                    uint32_t width,
+                                                    uint32_t height);
+
+/**
+ * Blends `src` into `dst` in place.
+ *
+ * `src` is placed with its top-left corner at `(src_x, src_y)` in `dst`
+ * (offsets may be negative) and clipped to `dst`. Only pixels covered by
+ * `src` are touched, including for `SrcIn` and `SrcOut`.
+ *
+ * # Arguments
+ * * `dst` - The destination image, modified in place (must not be null)
+ * * `src` - The image to blend in (must not be null)
+ * * `mode` - The blend mode, as an `RBBlendMode` value
+ * * `src_x` - Horizontal offset of `src` in `dst`
+ * * `src_y` - Vertical offset of `src` in `dst`
+ *
+ * # Returns
+ * * true on success
+ * * false if an error occurred (`dst` is left unchanged)
+ *
+ * # Safety
+ * `dst` and `src` must point to `RBImage`s returned by this library. They
+ * may be the same image.
+ */
+bool rb_image_blend(struct RBImage *dst,
+                    const struct RBImage *src,
+                    uint32_t mode,
+                    int32_t src_x,
+                    int32_t src_y);
+
+/**
+ * Creates an opaque two-color checkerboard, the usual backdrop for showing
+ * transparency.
+ *
+ * Blend a render over it with `rb_image_blend()` and `RBBlendMode::SrcOver`
+ * to display it the way image editors do. Photoshop's default grid uses
+ * white (255, 255, 255) and light gray (204, 204, 204) with 8 pixel
+ * squares. The square at the top-left corner gets the first color. Renders
+ * can get the same backing directly with `rb_options_set_checkerboard()`.
+ *
+ * # Arguments
+ * * `width` - Image width in pixels (must be > 0)
+ * * `height` - Image height in pixels (must be > 0)
+ * * `cell_size` - Edge length of one square in pixels (must be > 0)
+ * * `color1_r`, `color1_g`, `color1_b` - RGB of the top-left square
+ * * `color2_r`, `color2_g`, `color2_b` - RGB of the squares next to it
+ *
+ * # Returns
+ * * An opaque RGBA image
+ * * On error, an image with null pointer and zero dimensions (error is set)
+ *
+ * # Safety
+ * The returned image must be freed with `rb_free_image()`.
+ */
+struct RBImage rb_create_checkerboard_image(uint32_t width,
+                                            uint32_t height,
+                                            uint32_t cell_size,
+                                            uint8_t color1_r,
+                                            uint8_t color1_g,
+                                            uint8_t color1_b,
+                                            uint8_t color2_r,
+                                            uint8_t color2_g,
+                                            uint8_t color2_b);
+
+/**
+ * Frees a buffer returned by one of the encoded-output functions.
+ *
+ * # Arguments
+ * * `ptr` - The buffer (null is ignored)
+ * * `len` - The length reported when the buffer was returned
+ *
+ * # Safety
+ * `ptr`/`len` must come from this library and must not be used afterwards.
+ */
+void rb_free_buffer(uint8_t *ptr, uintptr_t len);
+
+/**
+ * Renders an SVG file to an RGBA TIFF in memory.
+ *
+ * The TIFF stores 8-bit RGBA with straight (unassociated) alpha, so
+ * transparency is preserved for archival use.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `width` - Desired output width in pixels (must be > 0)
+ * * `height` - Desired output height in pixels (must be > 0)
+ * * `compression` - Strip compression to apply, as an `RBTiffCompression`
+ *   value
+ * * `out_len` - Receives the length of the returned buffer (must not be null)
+ *
+ * # Returns
+ * * Pointer to the TIFF bytes, to be freed with `rb_free_buffer()`
+ * * Null on error (error is set, `out_len` is set to 0)
+ *
+ * # Safety
+ * The caller must ensure `svg_ptr` points to valid SVG data for `svg_len`
+ * bytes and that `out_len` is valid for a write.
+ */
+uint8_t *rb_render_svg_to_tiff(const uint8_t *svg_ptr,
+                               uintptr_t svg_len,
+                               uint32_t width,
+                               uint32_t height,
+                               uint32_t compression,
+                               uintptr_t *out_len);
+
+/**
+ * Writes an image to a binary PPM (P6) file.
+ *
+ * PPM has no alpha channel: pixels are written as if composited over black.
+ *
+ * # Arguments
+ * * `img` - The image to write (must not be null)
+ * * `path` - Null-terminated, UTF-8 file path; an existing file is replaced
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` on error (error is set)
+ *
+ * # Safety
+ * `img` must point to an `RBImage` returned by this library; `path` must be
+ * a valid null-terminated string.
+ */
+bool rb_image_write_ppm(const struct RBImage *img, const char *path);
+
+/**
+ * Writes an image to a binary PGM (P5) grayscale file.
+ *
+ * Gray levels are Rec. 601 luma of the image composited over black.
+ *
+ * # Arguments
+ * * `img` - The image to write (must not be null)
+ * * `path` - Null-terminated, UTF-8 file path; an existing file is replaced
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` on error (error is set)
+ *
+ * # Safety
+ * `img` must point to an `RBImage` returned by this library; `path` must be
+ * a valid null-terminated string.
+ */
+bool rb_image_write_pgm(const struct RBImage *img, const char *path);
+
+/**
+ * Renders an SVG file to a PNG in memory.
+ *
+ * PNG defines straight alpha, so the premultiplied render is
+ * un-premultiplied before encoding; a 50% transparent red pixel is stored
+ * as `(255, 0, 0, 128)`.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `width` - Desired output width in pixels (must be > 0)
+ * * `height` - Desired output height in pixels (must be > 0)
+ * * `out_len` - Receives the length of the returned buffer (must not be null)
+ *
+ * # Returns
+ * * Pointer to the PNG bytes, to be freed with `rb_free_buffer()`
+ * * Null on error (error is set, `out_len` is set to 0)
+ *
+ * # Safety
+ * The caller must ensure `svg_ptr` points to valid SVG data for `svg_len`
+ * bytes and that `out_len` is valid for a write.
+ */
+uint8_t *rb_render_svg_to_png(const uint8_t *svg_ptr,
+                              uintptr_t svg_len,
+                              uint32_t width,
+                              uint32_t height,
+                              uintptr_t *out_len);
+
+/**
+ * Renders an SVG and writes it as a color-managed PNG.
+ *
+ * The SVG is rendered in sRGB, as SVG colors are defined. With
+ * `icc_profile_path` null, pixels are written unchanged and an sRGB
+ * profile (Little CMS's built-in IEC 61966-2.1 equivalent) is embedded in
+ * the PNG's `iCCP` chunk. With a profile, the RGB channels are converted
+ * from sRGB to that profile's color space with the perceptual rendering
+ * intent and the profile itself is embedded, so color-managed viewers and
+ * print pipelines reproduce the original colors. Only RGB output profiles
+ * can be used, since PNG stores RGB or gray. Alpha is straight, as in
+ * `rb_render_svg_to_png()`, and is not converted.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `width` - Desired output width in pixels (must be > 0)
+ * * `height` - Desired output height in pixels (must be > 0)
+ * * `icc_profile_path` - Null-terminated, UTF-8 path of an ICC profile, or
+ *   null for sRGB
+ * * `output_path` - Null-terminated, UTF-8 path of the PNG to write; an
+ *   existing file is replaced
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` on error, e.g. an unreadable or non-RGB profile (error is set)
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba()`; both paths must be null
+ * or valid null-terminated strings.
+ */
+bool rb_render_svg_to_png_with_icc(const uint8_t *svg_ptr,
+                                   uintptr_t svg_len,
+                                   uint32_t width,
+                                   uint32_t height,
+                                   const char *icc_profile_path,
+                                   const char *output_path);
+
+/**
+ * Encodes an existing image as PNG, e.g. after editing its pixels.
+ *
+ * The image's `format` field decides the PNG layout: RGBA and BGRA images
+ * (premultiplied, as this library produces them) are un-premultiplied into
+ * 8-bit RGBA, `RBPixelFormat::Rgb8` becomes 8-bit RGB,
+ * `RBPixelFormat::Gray8` 8-bit grayscale and `RBPixelFormat::Rgba32F`
+ * 16-bit RGBA. Caller-built images must set `format` to an `RBPixelFormat`
+ * value (anything else is an "invalid args" error) and a matching `len`.
+ *
+ * # Arguments
+ * * `img` - The image to encode (must not be null)
+ * * `out_len` - Receives the length of the returned buffer (must not be null)
+ *
+ * # Returns
+ * * Pointer to the PNG bytes, to be freed with `rb_free_buffer()`
+ * * Null on error (error is set, `out_len` is set to 0)
+ *
+ * # Safety
+ * `img` must point to an `RBImage` whose `ptr` is valid for `len` bytes,
+ * and `out_len` must be valid for a write.
+ */
+uint8_t *rb_image_to_png(const struct RBImage *img, uintptr_t *out_len);
+
+/**
+ * Renders an SVG and traces its silhouette back into an SVG path.
+ *
+ * The pipeline is: render at `width × height`, mark pixels whose alpha is
+ * above `threshold` as inside, extract the boundaries of that mask with
+ * marching squares (samples at pixel centers), simplify each boundary with
+ * Ramer–Douglas–Peucker at a tolerance of 0.5 pixels, and write the result
+ * as one `<path fill-rule="evenodd">` so holes stay holes. Colors are
+ * discarded; the output is the shape only, as cutters and routers expect.
+ *
+ * The result is a standalone SVG of `width × height` user units, one unit
+ * per output pixel, with no path if nothing is above the threshold. Like
+ * any raster trace it is only as precise as the render: render larger for
+ * finer outlines.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `width` - Render width in pixels (must be > 0)
+ * * `height` - Render height in pixels (must be > 0)
+ * * `threshold` - Pixels with alpha at or below this count as outside
+ * * `out_svg` - Receives the outline SVG, to be freed with `rb_free_buffer()`
+ * * `out_svg_len` - Receives the length of the outline SVG in bytes
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` on error (error is set, outputs are null/0)
+ *
+ * # Safety
+ * `svg_ptr` must be valid for `svg_len` bytes and both output pointers must
+ * be valid for writes.
+ */
+bool rb_render_svg_to_svg_outline(const uint8_t *svg_ptr,
+                                  uintptr_t svg_len,
+                                  uint32_t width,
+                                  uint32_t height,
+                                  uint8_t threshold,
+                                  uint8_t **out_svg,
+                                  uintptr_t *out_svg_len);
+
+/**
+ * Computes a stable content hash of an image, suitable as a cache key.
+ *
+ * The hash is 64-bit FNV-1a over the width and height (little-endian `u32`s)
+ * followed by the pixel bytes. It is not cryptographic, but it is identical
+ * across runs, platforms and library builds for identical images.
+ *
+ * # Arguments
+ * * `img` - The image to hash (must not be null)
+ *
+ * # Returns
+ * * The hash value
+ * * 0 if `img` is null (error is set)
+ *
+ * # Safety
+ * `img` must point to an `RBImage` returned by this library (or one whose
+ * `ptr` is valid for `len` bytes).
+ */
+uint64_t rb_image_hash(const struct RBImage *img);
+
+/**
+ * Computes a checksum of an image's pixel buffer.
+ *
+ * Only the `len` bytes at `ptr` are hashed, so the checksum matches what
+ * external tools compute over the same raw buffer; unlike `rb_image_hash()`
+ * the dimensions are not included. Identical renders produce identical
+ * checksums on every run and platform. None of the algorithms are
+ * cryptographic.
+ *
+ * # Arguments
+ * * `img` - The image to checksum (must not be null)
+ * * `algorithm` - The checksum algorithm, as an `RBChecksumAlgorithm` value
+ * * `out` - Receives the checksum (must not be null)
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if `img` or `out` is null (error is set)
+ *
+ * # Safety
+ * `img` must point to an `RBImage` whose `ptr` is valid for `len` bytes,
+ * and `out` must be valid for a write.
+ */
+bool rb_image_compute_checksum(const struct RBImage *img, uint32_t algorithm, uint64_t *out);
+
+/**
+ * Computes a 64-bit perceptual hash (pHash) of an image.
+ *
+ * Unlike `rb_image_hash()`, similar-looking images get similar hashes:
+ * compare two with the Hamming distance `popcount(a ^ b)`. Distances up to
+ * about 10 usually mean the same picture with small changes (a slight
+ * recolor, resampling, compression); unrelated images average around 32.
+ *
+ * The image is composited over white, converted to Rec. 601 luma and
+ * box-averaged down to 32×32. A 2D DCT-II is taken, and the 8×8 lowest
+ * frequencies after the DC term (rows and columns 1–8) are compared
+ * against their median: bit `8 * v + u` (counting from the least
+ * significant bit) is set where coefficient `(u + 1, v + 1)` is above it.
+ * The hash ignores the image's size and aspect ratio.
+ *
+ * # Arguments
+ * * `img` - The image to hash (must not be null)
+ *
+ * # Returns
+ * * The perceptual hash
+ * * 0 if `img` is null or invalid (error is set)
+ *
+ * # Safety
+ * `img` must point to an `RBImage` returned by this library (or one whose
+ * `ptr` is valid for `len` bytes).
+ */
+uint64_t rb_image_phash(const struct RBImage *img);
+
+/**
+ * Counts the distinct RGBA colors in an image.
+ *
+ * Colors are compared as stored, i.e. premultiplied. The scan stops as soon
+ * as more than `cap` colors have been seen, so asking "does this fit in a
+ * 256-entry palette?" stays cheap on photographic content.
+ *
+ * # Arguments
+ * * `img` - The image to scan (must not be null)
+ * * `cap` - Maximum number of colors to count
+ *
+ * # Returns
+ * * The number of distinct colors, at most `cap`
+ * * `cap + 1` if the image has more than `cap` colors
+ * * 0 if `img` is null (error is set)
+ *
+ * # Safety
+ * `img` must point to an `RBImage` returned by this library (or one whose
+ * `ptr` is valid for `len` bytes).
+ */
+uint32_t rb_image_color_count(const struct RBImage *img, uint32_t cap);
+
+/**
+ * Converts an image to a palette of at most `max_colors` entries plus one
+ * palette index per pixel, using median-cut quantization.
+ *
+ * Palette entries are RGBA quadruples in the image's own alpha convention
+ * (premultiplied for images from this library; call
+ * `rb_image_unpremultiply()` first for a straight palette). Images with at
+ * most `max_colors` distinct colors are reproduced exactly.
+ *
+ * There is no dithering: every pixel takes the average color of its
+ * median-cut box. For a dithered result, render with
+ * `rb_render_svg_to_rgba_palette(..., dither = true)` using the palette from
+ * a first pass, then quantize that image again; its colors all come from
+ * the palette, so the second pass maps them exactly.
+ *
+ * To size the buffers, call once with `out_palette` and `out_indices` null:
+ * only `*out_palette_len` is written. Alternatively, pass a palette buffer
+ * of `max_colors * 4` bytes, which is always enough. `out_indices` needs
+ * `width * height` bytes.
+ *
+ * # Arguments
+ * * `img` - The image to quantize (must not be null)
+ * * `max_colors` - Palette size limit, 1 to 256
+ * * `out_palette` - Receives the palette, 4 bytes per entry (null = size query)
+ * * `out_palette_len` - Receives the palette length in bytes (must not be null)
+ * * `out_indices` - Receives one palette index per pixel, rows top to bottom
+ *   (null = size query)
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` on invalid arguments or an invalid image (error is set)
+ *
+ * # Safety
+ * `img` must point to an `RBImage` returned by this library; `out_palette`
+ * must be null or valid for the number of bytes a size query reports;
+ * `out_indices` must be null or valid for `width * height` bytes.
+ */
+bool rb_quantize(const struct RBImage *img,
+                 uint32_t max_colors,
+                 uint8_t *out_palette,
+                 uintptr_t *out_palette_len,
+                 uint8_t *out_indices);
+
+/**
+ * Like `rb_quantize()`, but returns the palette indices as an image.
+ *
+ * The palette and indices are exactly `rb_quantize()`'s. `out_indexed`
+ * receives an `RBPixelFormat::Gray8` image of the source's dimensions whose
+ * bytes are palette indices, ready to hand to a GIF or indexed-PNG encoder.
+ *
+ * # Arguments
+ * * `src` - The image to quantize (must not be null)
+ * * `max_colors` - Palette size limit, 1 to 256
+ * * `out_palette` - Receives the palette, 4 bytes per entry; must hold
+ *   `max_colors * 4` bytes (must not be null)
+ * * `out_palette_len` - Receives the palette length in bytes, as from
+ *   `rb_quantize()` (must not be null)
+ * * `out_indexed` - Receives the index image (must not be null)
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` on invalid arguments or an invalid image (error is set, the
+ *   outputs are left unchanged)
+ *
+ * # Safety
+ * `src` must point to an `RBImage` returned by this library; the output
+ * pointers must be valid for writes. The index image must be freed with
+ * `rb_free_image()`.
+ */
+bool rb_image_quantize_to_n_colors(const struct RBImage *src,
+                                   uint32_t max_colors,
+                                   uint8_t *out_palette,
+                                   uintptr_t *out_palette_len,
+                                   struct RBImage *out_indexed);
+
+/**
+ * Applies one SVG `<filter>` to the image's pixels.
+ *
+ * The filter is applied by resvg's own filter engine, to an `<image>` the
+ * size of `img`, so filter regions and `objectBoundingBox` units refer to
+ * the whole image. Only the `<filter>` element itself is used: primitives
+ * that reference other parts of `filter_svg` (e.g. `feImage` pointing at an
+ * element) will not resolve.
+ *
+ * # Arguments
+ * * `img` - The source image (must not be null)
+ * * `filter_svg` - An SVG document containing the filter definition
+ * * `filter_svg_len` - Length of `filter_svg` in bytes
+ * * `filter_id` - Null-terminated `id` of the `<filter>` to apply
+ *
+ * # Returns
+ * * A new image of the same dimensions
+ * * If an error occurs, returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * All pointers must be valid for their stated lengths. The returned image
+ * must be freed with `rb_free_image()`.
+ */
+struct RBImage rb_image_apply_svg_filter(const struct RBImage *img,
+                                         const uint8_t *filter_svg,
+                                         uintptr_t filter_svg_len,
+                                         const char *filter_id);
+
+/**
+ * Applies a Gaussian blur with standard deviation `sigma` pixels.
+ *
+ * The blur runs as two separable 1D passes (horizontal, then vertical) with
+ * a kernel radius of `ceil(3 * sigma)`. Pixels outside the image count as
+ * transparent, so edges fade out the way a drop shadow should. Blurring is
+ * done on the premultiplied data, which keeps colors from bleeding out of
+ * transparent areas.
+ *
+ * # Arguments
+ * * `src` - The source image (must not be null)
+ * * `sigma` - Standard deviation in pixels (must be finite and >= 0);
+ *   0 returns an exact copy
+ *
+ * # Returns
+ * * A new image of the same dimensions
+ * * If an error occurs, returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * `src` must point to an `RBImage` returned by this library. The returned
+ * image must be freed with `rb_free_image()`.
+ */
+struct RBImage rb_image_gaussian_blur(const struct RBImage *src, float sigma);
+
+/**
+ * Sharpens an image with an unsharp mask.
+ *
+ * The image is blurred with `rb_image_gaussian_blur()`'s kernel, and every
+ * color channel moves away from its blurred value by `amount` times the
+ * difference: `out = orig + amount * (orig - blurred)`. Channels that
+ * differ from the blur by less than `threshold` are left alone, so flat
+ * areas and faint noise aren't amplified. The comparison uses straight
+ * (unpremultiplied) colors, weighting neighbours by their alpha, so
+ * transparent surroundings and the image border don't create halos. Alpha
+ * is not changed.
+ *
+ * # Arguments
+ * * `src` - The source image (must not be null)
+ * * `sigma` - Blur standard deviation in pixels (must be finite and >= 0);
+ *   typical values are 0.5–2
+ * * `amount` - Strength (must be finite and >= 0); 1.0 doubles the local
+ *   contrast, 0 or a `sigma` of 0 returns an exact copy
+ * * `threshold` - Smallest per-channel difference (0–255) that is sharpened
+ *
+ * # Returns
+ * * A new image of the same dimensions
+ * * If an error occurs, returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * `src` must point to an `RBImage` returned by this library. The returned
+ * image must be freed with `rb_free_image()`.
+ */
+struct RBImage rb_image_unsharp_mask(const struct RBImage *src,
+                                     float sigma,
+                                     float amount,
+                                     uint8_t threshold);
+
+/**
+ * Resizes an image with nearest-neighbor sampling.
+ *
+ * Every output pixel copies exactly one source pixel, chosen with integer
+ * index math, so edges stay hard and no new colors are introduced. Integer
+ * scale factors give pixel-perfect magnification, which suits pixel art and
+ * debugging views.
+ *
+ * # Arguments
+ * * `src` - The source image (must not be null)
+ * * `new_w` - Output width in pixels (must be > 0)
+ * * `new_h` - Output height in pixels (must be > 0)
+ *
+ * # Returns
+ * * A new `new_w × new_h` image
+ * * If an error occurs, returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * `src` must point to an `RBImage` returned by this library. The returned
+ * image must be freed with `rb_free_image()`.
+ */
+struct RBImage rb_image_resize_nearest(const struct RBImage *src, uint32_t new_w, uint32_t new_h);
+
+/**
+ * Resamples an image through an affine transform.
+ *
+ * The matrix `[a, b, c, d, e, f_val]` uses the layout
+ * `rb_render_svg_to_rgba_with_matrix_out()` writes, mapping source pixel
+ * coordinates to output coordinates as `x' = a*x + c*y + e`,
+ * `y' = b*x + d*y + f_val`, so rotations, skews, flips and scales all work;
+ * a perspective projection is not affine and can't be expressed. Each
+ * output pixel is mapped back through the inverse transform and sampled
+ * bilinearly, which leaves no holes. Output pixels that map outside the
+ * source get the background color; along the source's edges the two are
+ * blended by coverage.
+ *
+ * # Arguments
+ * * `src` - The source image (must not be null)
+ * * `a`, `b`, `c`, `d`, `e`, `f_val` - The transform; must be finite and
+ *   invertible
+ * * `out_w` - Output width in pixels (must be > 0)
+ * * `out_h` - Output height in pixels (must be > 0)
+ * * `bg_r`, `bg_g`, `bg_b`, `bg_a` - Color outside the source (straight alpha)
+ *
+ * # Returns
+ * * A new `out_w × out_h` image
+ * * If an error occurs, returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * `src` must point to an `RBImage` returned by this library. The returned
+ * image must be freed with `rb_free_image()`.
+ */
+struct RBImage rb_image_affine_transform(const struct RBImage *src,
+                                         float a,
+                                         float b,
+                                         float c,
+                                         float d,
+                                         float e,
+                                         float f_val,
+                                         uint32_t out_w,
+                                         uint32_t out_h,
+                                         uint8_t bg_r,
+                                         uint8_t bg_g,
+                                         uint8_t bg_b,
+                                         uint8_t bg_a);
+
+/**
+ * Converts a height map into a tangent-space normal map.
+ *
+ * Heights are the bytes of an `RBPixelFormat::Gray8` plane (as from
+ * `rb_image_split_channels()`) or the red channel of an RGBA image, with 0
+ * the lowest and 255 the highest point. Gradients come from a 3×3 Sobel
+ * operator with edge pixels repeated at the borders. Each normal
+ * `normalize(-dh/dx * strength, dh/dy * strength, 1)` is packed as
+ * `[nx*127+128, ny*127+128, nz*127+128, 255]`, with green pointing up the
+ * image (the OpenGL convention; invert green for DirectX).
+ *
+ * # Arguments
+ * * `height_map` - The height map (must not be null)
+ * * `strength` - Gradient multiplier; larger values give steeper normals
+ *   (must be finite, 0 gives a flat map)
+ *
+ * # Returns
+ * * A new opaque RGBA image of the same dimensions
+ * * If an error occurs, returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * `height_map` must point to an `RBImage` returned by this library. The
+ * returned image must be freed with `rb_free_image()`.
+ */
+struct RBImage rb_image_pack_normals(const struct RBImage *height_map, float strength);
+
+/**
+ * Finds edges in an image, e.g. to get SVG shape outlines for tracing.
+ *
+ * The operator runs on two grayscale planes, luminance (Rec. 601 weights,
+ * composited over black) and alpha, and each pixel keeps the stronger of
+ * the two responses, so a shape's outline is found whether it stands out
+ * by color or only against transparency. Edge pixels are repeated past the
+ * image borders. Responses are scaled so the strongest edge in the image
+ * is 255; an image without edges comes out all 0.
+ *
+ * # Arguments
+ * * `src` - The source image (must not be null)
+ * * `mode` - The edge operator, as an `RBEdgeMode` value
+ *
+ * # Returns
+ * * A new opaque image of the same dimensions with the edge strength in
+ *   R, 0 in G and B, and 255 in A
+ * * If an error occurs, returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * `src` must point to an `RBImage` returned by this library. The returned
+ * image must be freed with `rb_free_image()`.
+ */
+struct RBImage rb_image_edge_detect(const struct RBImage *src, uint32_t mode);
+
+/**
+ * Reduces an image to two values: opaque black and "off".
+ *
+ * Useful for silhouettes, binary masks and stencil buffers. Luminance uses
+ * the Rec. 601 weights; `threshold == 128` with
+ * `RBThresholdMode::Luminance` is the usual "ink vs. paper" split, while
+ * `RBThresholdMode::Alpha` gives the shape's silhouette regardless of
+ * color.
+ *
+ * # Arguments
+ * * `src` - The source image (must not be null)
+ * * `threshold` - Comparison value, see `RBThresholdMode`
+ * * `mode` - What to compare, as an `RBThresholdMode` value
+ * * `white_background` - `true` to make "off" pixels opaque white instead
+ *   of transparent
+ *
+ * # Returns
+ * * A new image of the same dimensions
+ * * If an error occurs, returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * `src` must point to an `RBImage` returned by this library. The returned
+ * image must be freed with `rb_free_image()`.
+ */
+struct RBImage rb_image_threshold(const struct RBImage *src,
+                                  uint8_t threshold,
+                                  uint32_t mode,
+                                  bool white_background);
+
+/**
+ * Draws an image as ASCII art, for eyeballing renders in a terminal.
+ *
+ * The image is composited over black and split into `cols` columns of
+ * cells; rows are chosen so the picture keeps its aspect ratio with
+ * character cells about twice as tall as wide. Each cell's average
+ * brightness picks one of ` .:;+xX$&#@`, darkest first.
+ *
+ * # Arguments
+ * * `img` - The image to draw (must not be null)
+ * * `cols` - Number of characters per line (must be > 0; capped at the
+ *   image width)
+ *
+ * # Returns
+ * * A null-terminated string with `\n` after every line, to be freed with
+ *   `rb_free_cstring()`
+ * * Null if an error occurred (error is set)
+ *
+ * # Safety
+ * `img` must point to an `RBImage` returned by this library.
+ */
+char *rb_image_to_ascii_art(const struct RBImage *img, uint32_t cols);
+
+/**
+ * Formats an image's first pixels as hex, for inspecting pixel data while
+ * debugging.
+ *
+ * Each pixel is written as its stored bytes in lowercase hex, e.g.
+ * `ff000080` for premultiplied RGBA (R, G, B, A); other formats write their
+ * own bytes per pixel (`rrggbb` for RGB, `gg` for gray). Pixels are
+ * separated by spaces, 16 to a line, in row-major order, and every line
+ * ends with `\n`. Lines don't follow image rows.
+ *
+ * # Arguments
+ * * `img` - The image to dump (must not be null)
+ * * `max_pixels` - Number of pixels to dump, capped at the image size
+ *   (0 = all)
+ *
+ * # Returns
+ * * A null-terminated string, to be freed with `rb_free_cstring()`
+ * * Null on error (error is set)
+ *
+ * # Safety
+ * `img` must point to an `RBImage` returned by this library (or one whose
+ * `format` and `len` match its pixels).
+ */
+char *rb_image_to_hex_dump(const struct RBImage *img, uint32_t max_pixels);
+
+/**
+ * Converts an image's color channels from sRGB encoding to linear light, in place.
+ *
+ * Each pixel is un-premultiplied, the sRGB transfer function is inverted
+ * per channel (IEC 61966-2-1), and the result is premultiplied again, so
+ * the output is still premultiplied RGBA, just linear. Alpha is unchanged.
+ * Note that 8 bits are too few for linear light: dark tones lose precision,
+ * so convert back only when necessary.
+ *
+ * # Arguments
+ * * `img` - The image to convert (must not be null)
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if `img` is null or invalid (error is set)
+ *
+ * # Safety
+ * `img` must point to an `RBImage` returned by this library.
+ */
+bool rb_image_srgb_to_linear(struct RBImage *img);
+
+/**
+ * Converts an image's color channels from linear light to sRGB encoding, in place.
+ *
+ * The inverse of `rb_image_srgb_to_linear()`.
+ *
+ * # Arguments
+ * * `img` - The image to convert (must not be null)
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if `img` is null or invalid (error is set)
+ *
+ * # Safety
+ * `img` must point to an `RBImage` returned by this library.
+ */
+bool rb_image_linear_to_srgb(struct RBImage *img);
+
+/**
+ * Converts an image from premultiplied to straight alpha, in place.
+ *
+ * Images from this library are premultiplied; use this before handing
+ * pixels to code that expects straight alpha (most encoders and image
+ * editors). Color information in nearly transparent pixels is coarse, so
+ * a round trip through `rb_image_premultiply()` is not lossless for them.
+ * Other `rb_image_*` functions expect premultiplied input.
+ *
+ * # Arguments
+ * * `img` - The image to convert (must not be null)
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if `img` is null or invalid (error is set)
+ *
+ * # Safety
+ * `img` must point to an `RBImage` returned by this library.
+ */
+bool rb_image_unpremultiply(struct RBImage *img);
+
+/**
+ * Converts an image from straight to premultiplied alpha, in place.
+ *
+ * The inverse of `rb_image_unpremultiply()`.
+ *
+ * # Arguments
+ * * `img` - The image to convert (must not be null)
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if `img` is null or invalid (error is set)
+ *
+ * # Safety
+ * `img` must point to an `RBImage` returned by this library.
+ */
+bool rb_image_premultiply(struct RBImage *img);
+
+/**
+ * Splits an image into one single-channel plane per channel.
+ *
+ * Each plane is an `RBPixelFormat::Gray8` image with one byte per pixel
+ * (`len == width * height`), rows top to bottom. Bytes are copied as stored, so the color planes of a
+ * premultiplied image are premultiplied too; call
+ * `rb_image_unpremultiply()` first to get straight color values.
+ * `rb_image_merge_channels()` recombines planes into an RGBA image.
+ *
+ * # Arguments
+ * * `src` - The image to split (must not be null)
+ * * `out_r` - Receives the red plane (null = skip)
+ * * `out_g` - Receives the green plane (null = skip)
+ * * `out_b` - Receives the blue plane (null = skip)
+ * * `out_a` - Receives the alpha plane (null = skip)
+ *
+ * # Returns
+ * * `true` on success; free every requested plane with `rb_free_image()`
+ * * `false` if `src` is null or invalid (error is set, requested planes are empty)
+ *
+ * # Safety
+ * `src` must point to an `RBImage` returned by this library; each non-null
+ * output pointer must be valid for writes.
+ */
+bool rb_image_split_channels(const struct RBImage *src,
+                             struct RBImage *out_r,
+                             struct RBImage *out_g,
+                             struct RBImage *out_b,
+                             struct RBImage *out_a);
+
+/**
+ * Combines up to four single-channel planes into one RGBA image.
+ *
+ * The inverse of `rb_image_split_channels()`. Bytes are combined as given:
+ * planes split from a premultiplied image recombine into the same image,
+ * while straight color planes give a straight image that should go through
+ * `rb_image_premultiply()` before other `rb_image_*` functions use it. Each
+ * plane contributes its first `width * height` bytes, rows top to bottom.
+ *
+ * # Arguments
+ * * `r` - Red plane (null = all 0)
+ * * `g` - Green plane (null = all 0)
+ * * `b` - Blue plane (null = all 0)
+ * * `a` - Alpha plane (null = all 255)
+ * * `width` - Output width in pixels (must be > 0)
+ * * `height` - Output height in pixels (must be > 0)
+ *
+ * # Returns
+ * * A new `width × height` RGBA image
+ * * If an error occurs (zero size, or a plane shorter than `width * height`),
+ *   returns an image with null pointer and zero dimensions
+ *
+ * # Safety
+ * Each non-null input must point to an `RBImage` whose `ptr` is valid for
+ * `len` bytes. The returned image must be freed with `rb_free_image()`.
+ */
+struct RBImage rb_image_merge_channels(const struct RBImage *r,
+                                       const struct RBImage *g,
+                                       const struct RBImage *b,
+                                       const struct RBImage *a,
+                                       uint32_t width,
+                                       uint32_t height);
+
+/**
+ * Converts an image to another pixel format.
+ *
+ * Every pair of `RBPixelFormat`s is supported, converting to the same format
+ * copies the image, and RGBA and BGRA stay premultiplied:
+ * - `Rgba8` ↔ `Bgra8` swaps red and blue
+ * - To `Rgb8` or `Gray8` composites the image over the opaque background,
+ *   exactly like `rb_options_set_output_format()`; gray uses the Rec. 601
+ *   luma `(299 R + 587 G + 114 B) / 1000`
+ * - From `Rgb8` or `Gray8` adds opaque alpha (gray is copied to R, G and B)
+ * - To `Rgba32F` maps each byte `v` to `v / 255.0`; from it, values are
+ *   clamped to [0, 1], scaled by 255 and rounded, so 8-bit values survive
+ *   a round trip unchanged
+ *
+ * # Arguments
+ * * `src` - The image to convert; its `format` and `len` must match (must
+ *   not be null; not modified)
+ * * `target_format` - The numeric value of the `RBPixelFormat` to return
+ * * `bg_r`, `bg_g`, `bg_b` - RGB that transparency is flattened onto; only
+ *   used when converting to `Rgb8` or `Gray8`
+ *
+ * # Returns
+ * * A new image in `target_format` with the same dimensions
+ * * If an error occurs (including an unknown `target_format`), returns an
+ *   image with null pointer and zero dimensions
+ *
+ * # Safety
+ * `src` must point to an `RBImage` whose `ptr` is valid for `len` bytes.
+ * The returned image must be freed with `rb_free_image()`.
+ */
+struct RBImage rb_image_convert(const struct RBImage *src,
+                                uint32_t target_format,
+                                uint8_t bg_r,
+                                uint8_t bg_g,
+                                uint8_t bg_b);
+
+/**
+ * Converts an image into a caller-provided buffer laid out for `glTexImage2D()`.
+ *
+ * Supported `gl_format` values, with the channels written per pixel:
+ * - `GL_RGBA` (`0x1908`): R, G, B, A
+ * - `GL_BGRA` (`0x80E1`): B, G, R, A
+ * - `GL_RGB` (`0x1907`): R, G, B
+ * - `GL_BGR` (`0x80E0`): B, G, R
+ * - `GL_ALPHA` (`0x1906`): A
+ *
+ * Supported `gl_type` values, for every format:
+ * - `GL_UNSIGNED_BYTE` (`0x1401`): one byte per channel, unchanged
+ * - `GL_UNSIGNED_SHORT` (`0x1403`): native-endian `u16`, `v * 257`
+ * - `GL_FLOAT` (`0x1406`): native-endian `f32`, `v / 255.0`
+ *
+ * Colors stay premultiplied, matching the usual
+ * `glBlendFunc(GL_ONE, GL_ONE_MINUS_SRC_ALPHA)`; `GL_RGB` and `GL_BGR`
+ * drop alpha, which leaves the image composited over black. Rows are
+ * tightly packed top to bottom, so the first row lands at texture
+ * coordinate t = 0. Rows of 3-channel byte data are only 4-byte aligned
+ * when the width is a multiple of 4; upload other widths with
+ * `glPixelStorei(GL_UNPACK_ALIGNMENT, 1)`.
+ *
+ * # Arguments
+ * * `img` - The image to convert, in `Rgba8` format (must not be null; not modified)
+ * * `gl_format` - OpenGL pixel format enum value, from the list above
+ * * `gl_type` - OpenGL pixel type enum value, from the list above
+ * * `out_ptr` - Destination buffer (must not be null)
+ * * `out_len` - Size of `out_ptr` in bytes; at least
+ *   `width * height * channels * bytes per channel`
+ *
+ * # Returns
+ * * `true` on success; exactly the required number of bytes were written
+ * * `false` on an unsupported combination, a short buffer or an invalid
+ *   image (error is set, `out_ptr` is untouched)
+ *
+ * # Safety
+ * `img` must point to an `RBImage` whose `ptr` is valid for `len` bytes,
+ * and `out_ptr` must be valid for writes of `out_len` bytes.
+ */
+bool rb_image_to_opengl_texture_data(const struct RBImage *img,
+                                     uint32_t gl_format,
+                                     uint32_t gl_type,
+                                     uint8_t *out_ptr,
+                                     uintptr_t out_len);
+
+/**
+ * Crops an image to the bounding box of its non-transparent pixels.
+ *
+ * # Arguments
+ * * `img` - The image to crop (must not be null; not modified)
+ * * `alpha_threshold` - Pixels with alpha at or below this count as empty
+ *   (0 keeps every pixel that isn't fully transparent)
+ *
+ * # Returns
+ * * A new image covering exactly the pixels with alpha above the threshold
+ * * If the image has no such pixels, or on any other error, an image with
+ *   null pointer and zero dimensions (error is set)
+ *
+ * # Safety
+ * `img` must point to an `RBImage` returned by this library. The returned
+ * image must be freed with `rb_free_image()`.
+ */
+struct RBImage rb_autocrop(const struct RBImage *img, uint8_t alpha_threshold);
+
+/**
+ * Like `rb_autocrop()`, but trims only the chosen edges.
+ *
+ * Edges that aren't trimmed stay where they are, so e.g. trimming only the
+ * top and bottom keeps the content's horizontal position and the full width.
+ *
+ * # Arguments
+ * * `img` - The image to crop (must not be null; not modified)
+ * * `alpha_threshold` - Pixels with alpha at or below this count as empty
+ * * `top` / `bottom` / `left` / `right` - Which edges to trim
+ *
+ * # Returns
+ * * A new image with the selected transparent borders removed
+ * * If the image has no pixels above the threshold, or on any other error,
+ *   an image with null pointer and zero dimensions (error is set)
+ *
+ * # Safety
+ * `img` must point to an `RBImage` returned by this library. The returned
+ * image must be freed with `rb_free_image()`.
+ */
+struct RBImage rb_autocrop_sides(const struct RBImage *img,
+                                 uint8_t alpha_threshold,
+                                 bool top,
+                                 bool bottom,
+                                 bool left,
+                                 bool right);
+
+/**
+ * Computes the Structural Similarity Index (SSIM) of two images.
+ *
+ * SSIM follows Wang et al. (2004): local statistics use an 11×11 Gaussian
+ * window with σ = 1.5, and the constants are `K1 = 0.01`, `K2 = 0.03` for
+ * 8-bit data. The index is computed separately for R, G, B and A (as
+ * stored, i.e. premultiplied) and the four mean scores are averaged. Near
+ * the borders the window is restricted to the image.
+ *
+ * 1.0 means identical; scores below about 0.95 usually mean visible
+ * differences.
+ *
+ * # Arguments
+ * * `a` - The first image (must not be null)
+ * * `b` - The second image, same dimensions as `a` (must not be null)
+ * * `out_score` - Receives the score (must not be null)
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if the images are invalid or differ in size (error is set)
+ *
+ * # Safety
+ * `a` and `b` must point to `RBImage`s returned by this library;
+ * `out_score` must be valid for a write.
+ */
+bool rb_image_compare_ssim(const struct RBImage *a, const struct RBImage *b, double *out_score);
+
+/**
+ * Lists the unique font families referenced by an SVG.
+ *
+ * Use this to check that required fonts are available (and load missing
+ * ones) before rendering. Families come from `font-family` attributes,
+ * inline `style` attributes and `<style>` sheets, in document order;
+ * generic families are reported as `serif`, `sans-serif`, `monospace`,
+ * `cursive` or `fantasy`. The `font` shorthand is not inspected.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `out_families` - Receives a null-terminated array of `out_count` strings
+ * * `out_count` - Receives the number of families
+ *
+ * # Returns
+ * * `true` on success (the array may be empty)
+ * * `false` on error (error is set, outputs are null/0)
+ *
+ * # Safety
+ * The caller must ensure `svg_ptr` points to valid SVG data for `svg_len`
+ * bytes and that both output pointers are valid for writes. Free the array
+ * with `rb_free_string_array()`.
+ */
+bool rb_query_svg_used_fonts(const uint8_t *svg_ptr,
+                             uintptr_t svg_len,
+                             char ***out_families,
+                             uintptr_t *out_count);
+
+/**
+ * Counts the nodes of an SVG's render tree by type, as a cheap complexity
+ * estimate before rendering.
+ *
+ * The SVG is parsed exactly as for rendering, and the counts describe the
+ * resulting usvg tree rather than the source markup: basic shapes and
+ * `<line>`/`<polygon>` etc. count as paths, `<use>` references count once
+ * per instance (each adding a group), hidden and empty elements are
+ * already dropped (as is text when no loaded font can render it), and
+ * usvg may add groups of its own (e.g. for filters). Content of clip
+ * paths, masks and patterns is not counted.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `out_total` - Receives the number of nodes of all types (null = skip)
+ * * `out_paths` - Receives the number of paths (null = skip)
+ * * `out_texts` - Receives the number of text nodes (null = skip)
+ * * `out_images` - Receives the number of raster and nested-SVG images (null = skip)
+ * * `out_groups` - Receives the number of groups, excluding the root (null = skip)
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` on error (error is set, outputs are untouched)
+ *
+ * # Safety
+ * `svg_ptr` must be valid for `svg_len` bytes; each non-null output pointer
+ * must be valid for a write.
+ */
+bool rb_query_svg_element_count(const uint8_t *svg_ptr,
+                                uintptr_t svg_len,
+                                uintptr_t *out_total,
+                                uintptr_t *out_paths,
+                                uintptr_t *out_texts,
+                                uintptr_t *out_images,
+                                uintptr_t *out_groups);
+
+/**
+ * Lists the distinct element names used in an SVG's source markup.
+ *
+ * Unlike `rb_query_svg_element_count()` this looks at the document as
+ * written, before usvg resolves it, so it reports e.g. `circle`, `use` or
+ * `feTurbulence` by name. Names are local names without namespace
+ * prefixes, unique and sorted; elements from foreign namespaces (such as
+ * Inkscape's `sodipodi:namedview`) are included by local name.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `out_types` - Receives a null-terminated array of `out_count` strings
+ * * `out_count` - Receives the number of names
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` on error (error is set, outputs are null/0)
+ *
+ * # Safety
+ * `svg_ptr` must be valid for `svg_len` bytes and both output pointers
+ * valid for writes. Free the array with `rb_free_string_array()`.
+ */
+bool rb_query_svg_node_types(const uint8_t *svg_ptr,
+                             uintptr_t svg_len,
+                             char ***out_types,
+                             uintptr_t *out_count);
+
+/**
+ * Lists the constructs in an SVG that resvg cannot render faithfully.
+ *
+ * This reports the same things `rb_options_set_strict_features()` rejects,
+ * one callback per occurrence in document order, so a compatibility report
+ * can point at each one. Detection is best-effort: it inspects the SVG
+ * document for known-problematic elements and attributes rather than
+ * rendering it, and other filters, blend modes and so on are supported and
+ * not reported. A return of 0 doesn't promise pixel-identical output to a
+ * browser.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `out_cb` - Called with what was found (e.g. `<foreignObject>` or
+ *   `in="BackgroundImage"`), the carrying element's `id` (empty if none)
+ *   and its 1-based line number; the strings are only valid during the
+ *   call. May be null to just count.
+ * * `userdata` - Opaque pointer forwarded to the callback unchanged
+ *
+ * # Returns
+ * * The number of constructs found (0 if none)
+ * * -1 on error (error is set)
+ *
+ * # Safety
+ * The caller must ensure `svg_ptr` points to valid SVG data for `svg_len`
+ * bytes. The callback must not unwind.
+ */
+int32_t rb_feature_report(const uint8_t *svg_ptr,
+                          uintptr_t svg_len,
+                          RBFeatureCallback out_cb,
+                          void *userdata);
+
+/**
+ * Reports whether an SVG needs fonts to render.
+ *
+ * Text nodes are dropped from the parsed tree when no font can be found for
+ * them, so the check works on the XML document instead: the SVG contains
+ * text if any `<text>` element (including its `<tspan>`/`<textPath>`
+ * children) has non-whitespace character data. Text inside `<defs>` or
+ * otherwise unrendered parts counts too, so the answer errs on the side of
+ * loading fonts.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ *
+ * # Returns
+ * * 1 if the SVG contains text
+ * * 0 if it is pure shapes/images
+ * * -1 on error (error is set)
+ *
+ * # Safety
+ * The caller must ensure `svg_ptr` points to valid SVG data for `svg_len` bytes.
+ */
+int32_t rb_svg_has_text(const uint8_t *svg_ptr, uintptr_t svg_len);
+
+/**
+ * Estimates the relative cost of rendering an SVG, without rendering it.
+ *
+ * The SVG is parsed exactly as for rendering, then its tree is weighed:
+ * each path counts 1, each text or image node 4, each clip path or mask 2
+ * and each filter primitive 10. The score is
+ * `width * height * (1 + total weight) / 1000`, saturating at `u64::MAX`.
+ * Scores are comparable between SVGs but have no unit; filters dominate
+ * because they run per pixel over their whole region.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `width` - Planned output width in pixels (must be > 0)
+ * * `height` - Planned output height in pixels (must be > 0)
+ * * `out_cost` - Receives the score (must not be null)
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` on error (error is set)
+ *
+ * # Safety
+ * `svg_ptr` must be valid for `svg_len` bytes and `out_cost` valid for a write.
+ */
+bool rb_estimate_cost(const uint8_t *svg_ptr,
+                      uintptr_t svg_len,
+                      uint32_t width,
+                      uint32_t height,
+                      uint64_t *out_cost);
+
+/**
+ * Renders an SVG node by node and reports where the time goes.
+ *
+ * Meant for finding the one element (usually a filter) that makes an SVG
+ * slow. Each top-level node is rendered on its own, at its final position
+ * in a `width × height` output, and timed. A root that holds nothing but a
+ * single plain group (no filter, mask, clip path or opacity) is looked
+ * through, so documents wrapped in one `<g>` still report their parts.
+ *
+ * The timings are approximate: nodes are rendered onto an empty canvas
+ * rather than over each other, so blending costs differ from a real render,
+ * and timer resolution limits accuracy for cheap nodes. Profiling costs
+ * about one extra full render plus a canvas clear per node, which is why
+ * it is a separate call rather than something normal renders do.
+ *
+ * The result is a JSON object:
+ * `{"total_ms": 12.5, "nodes": [{"index": 3, "id": "glow", "kind": "group", "ms": 11.9}, ...]}`.
+ * `nodes` is sorted slowest first; `index` is the node's position among
+ * its siblings, `id` is `""` for nodes without one (an anonymous wrapper
+ * group, as usvg creates for filtered elements, reports its only child's
+ * id) and `kind` is one of
+ * `group`, `path`, `image` or `text`.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `width` - Output width in pixels (must be > 0)
+ * * `height` - Output height in pixels (must be > 0)
+ * * `top_n` - Number of slowest nodes to report (0 = all)
+ * * `opts` - Options from `rb_options_new()` (null = the defaults from
+ *   `rb_set_default_options()`)
+ *
+ * # Returns
+ * * A null-terminated JSON string; free it with `rb_free_cstring()`
+ * * Null on error (error is set)
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba_with_options()`.
+ */
+char *rb_profile_svg(const uint8_t *svg_ptr,
+                     uintptr_t svg_len,
+                     uint32_t width,
+                     uint32_t height,
+                     uintptr_t top_n,
+                     const struct RBOptions *opts);
+
+/**
+ * Times repeated renders of one SVG, for profiling the library in the
+ * caller's own environment.
+ *
+ * Each iteration parses and renders the SVG as `rb_render_svg_to_rgba()`
+ * does, timed with a monotonic clock, and the image is dropped right
+ * away, so memory use stays at one render. Every render is also hashed and
+ * compared with the first; output that differs between iterations fails
+ * the call, which catches nondeterminism regressions.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes
+ * * `iterations` - Number of renders (must be > 0)
+ * * `width` - Output width in pixels (must be > 0)
+ * * `height` - Output height in pixels (must be > 0)
+ * * `out_min_us` - Receives the fastest render time in microseconds (must not be null)
+ * * `out_max_us` - Receives the slowest render time in microseconds (must not be null)
+ * * `out_avg_us` - Receives the mean render time in microseconds (must not be null)
+ *
+ * # Returns
+ * * `true` on success; the outputs are written
+ * * `false` on error or if two renders differ (error is set, outputs are
+ *   left unchanged)
+ *
+ * # Safety
+ * Same requirements as `rb_render_svg_to_rgba()`; the output pointers must
+ * be valid for writes.
+ */
+bool rb_render_svg_stress_test(const uint8_t *svg_ptr,
+                               uintptr_t svg_len,
+                               uint32_t iterations,
+                               uint32_t width,
+                               uint32_t height,
+                               uint64_t *out_min_us,
+                               uint64_t *out_max_us,
+                               uint64_t *out_avg_us);
+
+/**
+ * Produces a copy of an SVG with its `@font-face` fonts inlined.
+ *
+ * Every `url(...)` inside an `@font-face` rule of a `<style>` element that
+ * names a file inside `font_dir` is replaced with a base64 `data:` URI, so
+ * the SVG no longer depends on the font files. The MIME type follows the
+ * file extension (`font/woff2`, `font/woff`, `font/ttf`, `font/otf`).
+ * URLs that aren't local files, or files outside `font_dir`, are left as
+ * they are; missing files are reported through `rb_last_warning_copy()`.
+ *
+ * Note that resvg itself ignores `@font-face`; this is for making SVGs
+ * self-contained for archival or for other renderers.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes (must be UTF-8)
+ * * `font_dir` - Null-terminated directory that relative URLs resolve against
+ * * `out_svg` - Receives the new SVG, to be freed with `rb_free_buffer()`
+ * * `out_len` - Receives the length of the new SVG in bytes
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` on error (error is set, outputs are null/0)
+ *
+ * # Safety
+ * `svg_ptr` must be valid for `svg_len` bytes, `font_dir` must be a valid
+ * null-terminated string and both output pointers must be valid for writes.
+ */
+bool rb_svg_embed_fonts(const uint8_t *svg_ptr,
+                        uintptr_t svg_len,
+                        const char *font_dir,
+                        uint8_t **out_svg,
+                        uintptr_t *out_len);
+
+/**
+ * Produces a copy of an SVG with its local raster images inlined.
+ *
+ * The `href`/`xlink:href` of every `<image>` and `<feImage>` element that
+ * names a file inside `base_dir` is replaced with a base64 `data:` URI.
+ * The MIME type follows the file extension (PNG, JPEG, GIF, WebP and SVG);
+ * data URIs, remote URLs and element references are left as they are, and
+ * missing or unrecognized files are reported through
+ * `rb_last_warning_copy()`.
+ *
+ * # Arguments
+ * * `svg_ptr` - Pointer to the SVG data (must not be null)
+ * * `svg_len` - Length of the SVG data in bytes (must be UTF-8)
+ * * `base_dir` - Null-terminated directory that relative hrefs resolve against
+ * * `out_svg` - Receives the new SVG, to be freed with `rb_free_buffer()`
+ * * `out_len` - Receives the length of the new SVG in bytes
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` on error (error is set, outputs are null/0)
+ *
+ * # Safety
+ * `svg_ptr` must be valid for `svg_len` bytes, `base_dir` must be a valid
+ * null-terminated string and both output pointers must be valid for writes.
+ */
+bool rb_svg_inline_images(const uint8_t *svg_ptr,
+                          uintptr_t svg_len,
+                          const char *base_dir,
+                          uint8_t **out_svg,
+                          uintptr_t *out_len);
+
+/**
+ * Parses an SVG/CSS color string into RGBA components.
+ *
+ * Accepts everything SVG accepts for a color value, including:
+ * - `#rgb`, `#rrggbb`, `#rrggbbaa`
+ * - `rgb(r, g, b)`, `rgba(r, g, b, a)`
+ * - Named colors from the SVG color table (e.g. `cornflowerblue`)
+ *
+ * # Arguments
+ * * `color_str` - Null-terminated color string (must not be null)
+ * * `out_r`, `out_g`, `out_b`, `out_a` - Destinations for the components (must not be null)
+ *
+ * # Returns
+ * * `true` on success
+ * * `false` if the string isn't a valid color (error is set, outputs untouched)
+ *
+ * # Safety
+ * `color_str` must be a valid null-terminated string and every output
+ * pointer must be valid for a single byte write.
+ */
+bool rb_parse_color(const char *color_str,
+                    uint8_t *out_r,
+                    uint8_t *out_g,
+                    uint8_t *out_b,
+                    uint8_t *out_a);
+
+/**
+ * Frees a string array returned by this library.
+ *
+ * # Arguments
+ * * `array` - The array (null is ignored)
+ * * `count` - The count reported alongside the array
+ *
+ * # Safety
+ * `array`/`count` must come from this library and must not be used afterwards.
+ */
+void rb_free_string_array(char **array, uintptr_t count);
+
+/**
+ * Frees a string returned by this library.
+ *
+ * # Arguments
+ * * `s` - The string (null is ignored)
+ *
+ * # Safety
+ * `s` must come from this library (e.g. `rb_image_to_ascii_art()`) and must
+ * not be used afterwards.
+ */
+void rb_free_cstring(char *s);
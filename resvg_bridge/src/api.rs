@@ -0,0 +1,288 @@
+//! # Safe Rust API
+//!
+//! An idiomatic wrapper for Rust callers, so they can skip the raw pointer
+//! handling of the C interface.
+//!
+//! The FFI functions and this module share one code path: both validate
+//! their inputs and then call the same parse and render helpers in the crate
+//! root, so `render_svg()` produces exactly the pixels
+//! `rb_render_svg_to_rgba_with_options()` would for the same settings.
+//! Every `rb_options_set_*()` setting has a `RenderOptions` builder except
+//! the output format: `render_svg()` always returns premultiplied RGBA.
+//!
+//! ```no_run
+//! use resvg_bridge::api::{render_svg, RenderOptions};
+//!
+//! let svg = std::fs::read("icon.svg").unwrap();
+//! let image = render_svg(&svg, &RenderOptions::new(64, 64).clip_to_viewbox(true)).unwrap();
+//! assert_eq!(image.data.len(), 64 * 64 * 4);
+//! ```
+
+use std::{fmt, path::PathBuf, sync::Arc};
+
+use crate::{render_svg_bytes, Checkerboard, RBErrorCode, RBOptions};
+
+pub use crate::RBErrorCode as ErrorCode;
+pub use crate::RBFillRule as FillRule;
+
+/// A rendering error.
+#[derive(Clone, Debug)]
+pub struct Error {
+    code: ErrorCode,
+    message: String,
+}
+
+impl Error {
+    /// The error category, as `rb_last_error_code()` would report it.
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+
+    /// The error message, as `rb_last_error_copy()` would report it.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error { code: RBErrorCode::classify(&message), message }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A rendered image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// Premultiplied RGBA pixels, `width * height * 4` bytes, rows top to bottom
+    pub data: Vec<u8>,
+}
+
+/// Output size and rendering settings for `render_svg()`.
+///
+/// Every setting starts at the same default as a fresh `rb_options_new()`.
+#[derive(Clone, Default)]
+pub struct RenderOptions {
+    /// Output width in pixels (must be > 0)
+    pub width: u32,
+    /// Output height in pixels (must be > 0)
+    pub height: u32,
+    options: RBOptions,
+}
+
+impl RenderOptions {
+    /// Creates options for a `width × height` output with default settings.
+    pub fn new(width: u32, height: u32) -> Self {
+        RenderOptions { width, height, options: RBOptions::default() }
+    }
+
+    /// Overrides the intrinsic size used for the fit calculation.
+    ///
+    /// See `rb_options_set_size_override()`. `None`, non-positive and
+    /// non-finite values keep the parsed size for that dimension.
+    pub fn size_override(mut self, width: Option<f32>, height: Option<f32>) -> Self {
+        let valid = |v: Option<f32>| v.filter(|v| v.is_finite() && *v > 0.0);
+        self.options.override_width = valid(width);
+        self.options.override_height = valid(height);
+        self
+    }
+
+    /// Controls `data:` URI images. See `rb_options_set_embedded_images()`.
+    pub fn embedded_images(mut self, allow: bool, max_bytes: usize) -> Self {
+        self.options.skip_data_images = !allow;
+        self.options.max_data_image_bytes = max_bytes;
+        self
+    }
+
+    /// Rejects SVGs with features resvg can't render faithfully.
+    /// See `rb_options_set_strict_features()`.
+    pub fn strict_features(mut self, enabled: bool) -> Self {
+        self.options.strict_features = enabled;
+        self
+    }
+
+    /// Clips rendering to the root `viewBox`. See `rb_options_set_clip_to_viewbox()`.
+    pub fn clip_to_viewbox(mut self, enabled: bool) -> Self {
+        self.options.clip_to_viewbox = enabled;
+        self
+    }
+
+    /// Applies extra CSS while parsing. See `rb_options_set_style_sheet()`.
+    pub fn style_sheet(mut self, css: Option<String>) -> Self {
+        self.options.style_sheet = css;
+        self
+    }
+
+    /// Sets the user's languages for `systemLanguage`.
+    /// See `rb_options_set_languages()`.
+    pub fn languages<I, S>(mut self, languages: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.options.languages = Some(languages.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Sets the size of SVGs that declare neither a size nor a viewBox.
+    ///
+    /// See `rb_options_set_assumed_size()`. Non-positive and non-finite
+    /// values keep the default of 100×100.
+    pub fn assumed_size(mut self, width: f32, height: f32) -> Self {
+        let valid = |v: f32| v.is_finite() && v > 0.0;
+        if valid(width) && valid(height) {
+            self.options.assumed_size = Some((width, height));
+        }
+        self
+    }
+
+    /// Resolves relative `href`s against a directory. See `rb_options_set_base_path()`.
+    pub fn base_path(mut self, path: Option<PathBuf>) -> Self {
+        self.options.base_path = path;
+        self
+    }
+
+    /// Renders over an opaque checkerboard of `square_size`-pixel squares,
+    /// starting with `color1` at the origin.
+    ///
+    /// See `rb_options_set_checkerboard()`. A `square_size` of 0 leaves the
+    /// checkerboard off.
+    pub fn checkerboard(mut self, square_size: u32, color1: [u8; 3], color2: [u8; 3]) -> Self {
+        self.options.checkerboard =
+            (square_size > 0).then_some(Checkerboard { square_size, colors: [color1, color2] });
+        self
+    }
+
+    /// Controls text anti-aliasing. See `rb_options_set_text_antialias()`.
+    pub fn text_antialias(mut self, enabled: bool) -> Self {
+        self.options.aliased_text = !enabled;
+        self
+    }
+
+    /// Renders every shape without anti-aliasing. See `rb_options_set_crisp_edges()`.
+    pub fn crisp_edges(mut self, enabled: bool) -> Self {
+        self.options.crisp_edges = enabled;
+        self
+    }
+
+    /// Sets the fill and clip rule for elements that don't declare one.
+    /// See `rb_options_set_default_clip_rule()`.
+    pub fn default_clip_rule(mut self, rule: FillRule) -> Self {
+        self.options.default_fill_rule = rule;
+        self
+    }
+
+    /// Keeps the output size within limits, scaling it uniformly.
+    ///
+    /// See `rb_options_set_size_limits()`; 0 means no limit. A maximum below
+    /// its minimum is raised to the minimum.
+    pub fn size_limits(mut self, min_width: u32, min_height: u32, max_width: u32, max_height: u32) -> Self {
+        let max = |max: u32, min: u32| if max == 0 { 0 } else { max.max(min) };
+        self.options.min_width = min_width;
+        self.options.min_height = min_height;
+        self.options.max_width = max(max_width, min_width);
+        self.options.max_height = max(max_height, min_height);
+        self
+    }
+
+    /// Sets the seed for randomized rendering steps. See `rb_options_set_seed()`.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.options.seed = seed;
+        self
+    }
+
+    /// Sets the fonts available to text rendering, e.g. a database loaded
+    /// with `load_system_fonts()` as `rb_options_set_font_db_from_system()`
+    /// does. `None` uses the fonts shared by `rb_preload_fonts_async()`.
+    pub fn font_db(mut self, db: Option<Arc<usvg::fontdb::Database>>) -> Self {
+        self.options.fontdb = db;
+        self
+    }
+}
+
+/// Renders an SVG document, stretched to the size in `opts`.
+///
+/// # Arguments
+/// * `data` - The SVG (or gzip-compressed SVGZ) document
+/// * `opts` - Output size and rendering settings
+///
+/// # Returns
+/// * The rendered image
+/// * An error with the same code and message the FFI would report
+pub fn render_svg(data: &[u8], opts: &RenderOptions) -> Result<Image, Error> {
+    let pixmap = render_svg_bytes(data, opts.width, opts.height, &opts.options)?;
+    Ok(Image { width: pixmap.width(), height: pixmap.height(), data: pixmap.take() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SVG: &[u8] = br##"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10" viewBox="0 0 10 10"><rect x="-5" width="20" height="10" fill="#0a8" opacity="0.5"/></svg>"##;
+
+    #[test]
+    fn render_svg_matches_ffi() {
+        let opts = RenderOptions::new(40, 20).clip_to_viewbox(true);
+        let image = render_svg(SVG, &opts).unwrap();
+
+        let ffi = unsafe { crate::rb_render_svg_to_rgba_with_options(SVG.as_ptr(), SVG.len(), 40, 20, &opts.options) };
+        let ffi_bytes = unsafe { std::slice::from_raw_parts(ffi.ptr, ffi.len) }.to_vec();
+        crate::rb_free_image(ffi);
+
+        assert_eq!((image.width, image.height), (40, 20));
+        assert_eq!(image.data, ffi_bytes);
+    }
+
+    #[test]
+    fn errors_carry_the_ffi_code() {
+        let err = render_svg(b"<svg", &RenderOptions::new(10, 10)).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::Parse);
+        assert!(err.message().starts_with("parse error"));
+
+        let err = render_svg(SVG, &RenderOptions::new(0, 10)).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidArgs);
+    }
+
+    #[test]
+    fn builders_set_the_matching_options() {
+        let opts = RenderOptions::new(1, 1)
+            .size_override(Some(32.0), Some(f32::NAN))
+            .embedded_images(false, 42)
+            .strict_features(true)
+            .languages(["de", "en"]);
+        assert_eq!((opts.options.override_width, opts.options.override_height), (Some(32.0), None));
+        assert!(opts.options.skip_data_images && opts.options.strict_features);
+        assert_eq!(opts.options.max_data_image_bytes, 42);
+        assert_eq!(opts.options.languages, Some(vec!["de".to_string(), "en".to_string()]));
+
+        let opts = RenderOptions::new(1, 1)
+            .assumed_size(0.0, 50.0)
+            .base_path(Some("assets".into()))
+            .checkerboard(4, [255; 3], [204; 3])
+            .text_antialias(false)
+            .crisp_edges(true)
+            .default_clip_rule(FillRule::EvenOdd)
+            .size_limits(16, 16, 8, 0)
+            .seed(7)
+            .font_db(Some(Arc::new(usvg::fontdb::Database::new())));
+        assert_eq!(opts.options.assumed_size, None);
+        assert_eq!(opts.options.base_path.as_deref(), Some("assets".as_ref()));
+        assert!(opts.options.checkerboard.is_some_and(|c| c.square_size == 4 && c.colors == [[255; 3], [204; 3]]));
+        assert!(opts.options.aliased_text && opts.options.crisp_edges);
+        assert!(opts.options.default_fill_rule == FillRule::EvenOdd);
+        assert_eq!(opts.options.clamp_size(4, 4), (16, 16));
+        assert_eq!(opts.options.seed, 7);
+        assert!(opts.options.fontdb.is_some());
+    }
+}
@@ -8,8 +8,22 @@
 //! - Memory-safe FFI with proper resource management
 //! - High-quality SVG rendering with scaling support
 //! - C-compatible data structures for easy integration
+//! - A safe Rust API in [`api`] sharing the same rendering code
 
-use std::{cell::RefCell, os::raw::c_char, slice};
+pub mod api;
+
+use std::{
+    cell::{Cell, RefCell},
+    ffi::{CStr, CString},
+    os::raw::{c_char, c_void},
+    panic::{self, AssertUnwindSafe},
+    slice,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, OnceLock, RwLock,
+    },
+};
+use rayon::prelude::*;
 use resvg::tiny_skia::{Pixmap, Transform};
 use usvg::{self, Tree};
 
@@ -24,7 +38,86 @@ use usvg::{self, Tree};
 // Thread-local storage for the last error message.
 // Each thread maintains its own error state to prevent race conditions.
 thread_local! {
-    static LAST_ERR: RefCell<Option<String>> = RefCell::new(None);
+    static LAST_ERR: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+// Thread-local storage for non-fatal warnings raised by the last call,
+// e.g. content that was skipped. Multiple warnings are joined with "; ".
+thread_local! {
+    static LAST_WARN: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+// Thread-local storage for the category of the last error.
+thread_local! {
+    static LAST_ERR_CODE: Cell<RBErrorCode> = const { Cell::new(RBErrorCode::None) };
+}
+
+/// Broad category of the last error, for callers that branch on failures.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RBErrorCode {
+    /// No error
+    None = 0,
+    /// An error without a more specific category
+    Unknown = 1,
+    /// A null pointer, zero size or otherwise invalid argument
+    InvalidArgs = 2,
+    /// The SVG could not be parsed
+    Parse = 3,
+    /// The computed scale factors were zero, NaN or infinite
+    InvalidScale = 4,
+    /// An output buffer could not be allocated
+    Alloc = 5,
+    /// A panic was caught at the FFI boundary
+    Panic = 6,
+    /// The operation ran past its time limit
+    Timeout = 7,
+    /// The caller cancelled the operation, e.g. from a callback
+    Cancelled = 8,
+    /// The operation conflicts with one that is still running
+    Busy = 9,
+}
+
+impl RBErrorCode {
+    /// Categorizes an error message by its prefix, so internal helpers can
+    /// keep returning plain `String` errors.
+    fn classify(msg: &str) -> Self {
+        if msg.starts_with("invalid args") {
+            RBErrorCode::InvalidArgs
+        } else if msg.starts_with("parse error") {
+            RBErrorCode::Parse
+        } else if msg.starts_with("invalid scale") {
+            RBErrorCode::InvalidScale
+        } else if msg.starts_with("alloc") {
+            RBErrorCode::Alloc
+        } else if msg.starts_with("panic") {
+            RBErrorCode::Panic
+        } else if msg.starts_with("timeout") {
+            RBErrorCode::Timeout
+        } else if msg.starts_with("cancelled") {
+            RBErrorCode::Cancelled
+        } else if msg.starts_with("busy") {
+            RBErrorCode::Busy
+        } else {
+            RBErrorCode::Unknown
+        }
+    }
+
+    /// Short human-readable description of the category.
+    fn description(self) -> &'static CStr {
+        match self {
+            RBErrorCode::None => c"no error",
+            RBErrorCode::Unknown => c"unknown error",
+            RBErrorCode::InvalidArgs => c"invalid arguments",
+            RBErrorCode::Parse => c"SVG parse error",
+            RBErrorCode::InvalidScale => c"invalid scale factor",
+            RBErrorCode::Alloc => c"allocation failed",
+            RBErrorCode::Panic => c"internal panic",
+            RBErrorCode::Timeout => c"timed out",
+            RBErrorCode::Cancelled => c"cancelled",
+            RBErrorCode::Busy => c"already in progress",
+        }
+    }
 }
 
 /// Sets the current thread's error message.
@@ -33,9 +126,35 @@ thread_local! {
 /// # Arguments
 /// * `msg` - The error message to store
 fn set_err(msg: String) {
+    LAST_ERR_CODE.with(|c| c.set(RBErrorCode::classify(&msg)));
     LAST_ERR.with(|e| *e.borrow_mut() = Some(msg));
 }
 
+/// Clears the current thread's error and warning messages.
+/// Every exported function that can fail calls this first.
+fn clear_err() {
+    LAST_ERR.with(|e| *e.borrow_mut() = None);
+    LAST_ERR_CODE.with(|c| c.set(RBErrorCode::None));
+    LAST_WARN.with(|w| *w.borrow_mut() = None);
+}
+
+/// Records a warning for the current call without failing it.
+///
+/// # Arguments
+/// * `msg` - The warning message to append
+fn add_warning(msg: String) {
+    LAST_WARN.with(|w| {
+        let mut w = w.borrow_mut();
+        match w.as_mut() {
+            Some(existing) => {
+                existing.push_str("; ");
+                existing.push_str(&msg);
+            }
+            None => *w = Some(msg),
+        }
+    });
+}
+
 /// Gets a pointer to the last error message for the current thread.
 /// 
 /// # Returns
@@ -47,12 +166,58 @@ fn set_err(msg: String) {
 /// The caller should not free this pointer - it's managed by the thread-local storage.
 #[no_mangle]
 pub extern "C" fn rb_last_error() -> *const c_char {
-    LAST_ERR.with(|e| {
-        if let Some(s) = e.borrow().as_ref() {
-            s.as_ptr() as *const c_char
-        } else {
-            std::ptr::null()
-        }
+    ffi_guard(std::ptr::null(), || {
+        LAST_ERR.with(|e| {
+            if let Some(s) = e.borrow().as_ref() {
+                s.as_ptr() as *const c_char
+            } else {
+                std::ptr::null()
+            }
+        })
+    })
+}
+
+/// Gets the category of the last error for the current thread.
+///
+/// # Returns
+/// * The error code matching the message from `rb_last_error_copy()`
+/// * `RBErrorCode::None` if the last call succeeded
+#[no_mangle]
+pub extern "C" fn rb_last_error_code() -> RBErrorCode {
+    ffi_guard(RBErrorCode::Panic, || LAST_ERR_CODE.with(Cell::get))
+}
+
+/// Gets a short description of an error code, e.g. for log messages.
+///
+/// # Arguments
+/// * `code` - An `RBErrorCode` value, as an integer
+///
+/// # Returns
+/// * A static null-terminated string describing the code (never null)
+/// * `"unrecognized error code"` for values outside `RBErrorCode`
+///
+/// # Safety
+/// The returned string is static and must not be freed.
+#[no_mangle]
+pub extern "C" fn rb_error_code_string(code: i32) -> *const c_char {
+    const CODES: [RBErrorCode; 10] = [
+        RBErrorCode::None,
+        RBErrorCode::Unknown,
+        RBErrorCode::InvalidArgs,
+        RBErrorCode::Parse,
+        RBErrorCode::InvalidScale,
+        RBErrorCode::Alloc,
+        RBErrorCode::Panic,
+        RBErrorCode::Timeout,
+        RBErrorCode::Cancelled,
+        RBErrorCode::Busy,
+    ];
+    ffi_guard(c"unrecognized error code".as_ptr(), || {
+        CODES
+            .iter()
+            .find(|&&c| c as i32 == code)
+            .map_or(c"unrecognized error code", |c| c.description())
+            .as_ptr()
     })
 }
 
@@ -71,47 +236,226 @@ pub extern "C" fn rb_last_error() -> *const c_char {
 /// The caller must ensure `buf` points to a valid buffer of at least `len` bytes.
 /// The buffer will be null-terminated if there's space.
 #[no_mangle]
-pub extern "C" fn rb_last_error_copy(buf: *mut c_char, len: usize) -> usize {
+pub unsafe extern "C" fn rb_last_error_copy(buf: *mut c_char, len: usize) -> usize {
     if buf.is_null() || len == 0 { return 0; }
-    LAST_ERR.with(|e| {
-        if let Some(s) = e.borrow().as_ref() {
-            let bytes = s.as_bytes();
-            let n = bytes.len().min(len - 1);
-            unsafe {
-                std::ptr::copy_nonoverlapping(bytes.as_ptr(), buf as *mut u8, n);
-                *buf.add(n) = 0; // Null terminate
+    ffi_guard(0, || {
+        LAST_ERR.with(|e| {
+            if let Some(s) = e.borrow().as_ref() {
+                let bytes = s.as_bytes();
+                let n = bytes.len().min(len - 1);
+                unsafe {
+                    std::ptr::copy_nonoverlapping(bytes.as_ptr(), buf as *mut u8, n);
+                    *buf.add(n) = 0; // Null terminate
+                }
+                n
+            } else { 0 }
+        })
+    })
+}
+
+/// Copies the warnings raised by the last call into a caller-provided buffer.
+///
+/// Warnings describe content that was skipped or adjusted while the call
+/// still succeeded (for example an embedded image over the size limit).
+/// Multiple warnings are separated by `"; "`.
+///
+/// # Arguments
+/// * `buf` - Pointer to the destination buffer (must not be null)
+/// * `len` - Size of the destination buffer in bytes
+///
+/// # Returns
+/// * Number of bytes written to the buffer (excluding null terminator)
+/// * 0 if there were no warnings or if buffer is null/empty
+///
+/// # Safety
+/// The caller must ensure `buf` points to a valid buffer of at least `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rb_last_warning_copy(buf: *mut c_char, len: usize) -> usize {
+    if buf.is_null() || len == 0 { return 0; }
+    ffi_guard(0, || {
+        LAST_WARN.with(|w| {
+            if let Some(s) = w.borrow().as_ref() {
+                let bytes = s.as_bytes();
+                let n = bytes.len().min(len - 1);
+                unsafe {
+                    std::ptr::copy_nonoverlapping(bytes.as_ptr(), buf as *mut u8, n);
+                    *buf.add(n) = 0; // Null terminate
+                }
+                n
+            } else { 0 }
+        })
+    })
+}
+
+// ============================================================================
+// PANIC HANDLING
+// ============================================================================
+//
+// Unwinding out of an `extern "C"` function is undefined behavior, so every
+// exported function runs its body through `ffi_guard()`. A panic is caught at
+// the boundary, recorded as the thread's last error, and the function returns
+// its usual failure value instead of taking the host process down.
+
+/// Callback type invoked with the panic message and the caller's userdata.
+pub type RBPanicCallback = Option<extern "C" fn(msg: *const c_char, userdata: *mut c_void)>;
+
+/// A panic hook, as installed with `std::panic::set_hook()`.
+type PanicHook = dyn Fn(&panic::PanicHookInfo<'_>) + Sync + Send;
+
+/// The callback set with `rb_set_panic_handler()` and its userdata.
+static PANIC_CALLBACK: RwLock<Option<(extern "C" fn(*const c_char, *mut c_void), usize)>> = RwLock::new(None);
+
+/// The hook that was installed before ours, while ours is installed.
+static HOST_PANIC_HOOK: std::sync::Mutex<Option<Arc<PanicHook>>> = std::sync::Mutex::new(None);
+
+// Thread-local count of the library calls running on this thread, so the
+// panic hook can tell the library's panics from the host's.
+thread_local! {
+    static BRIDGE_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Extracts a human-readable message from a panic payload.
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        (*s).to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Runs `f` as library code, catching any panic it raises.
+///
+/// Panics inside `f` are reported to the `rb_set_panic_handler()` callback;
+/// every place that catches the library's panics goes through here.
+fn catch_panic<T>(f: impl FnOnce() -> T) -> std::thread::Result<T> {
+    BRIDGE_DEPTH.with(|d| d.set(d.get() + 1));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    BRIDGE_DEPTH.with(|d| d.set(d.get() - 1));
+    result
+}
+
+/// Runs `f`, converting any panic into an error state.
+///
+/// # Arguments
+/// * `fallback` - Value returned if `f` panics
+/// * `f` - The function body to run
+fn ffi_guard<T>(fallback: T, f: impl FnOnce() -> T) -> T {
+    match catch_panic(f) {
+        Ok(v) => v,
+        Err(payload) => {
+            set_err(format!("panic: {}", panic_message(&*payload)));
+            fallback
+        }
+    }
+}
+
+/// Installs a process-wide callback that is notified whenever a panic occurs
+/// inside the library.
+///
+/// The panic is still caught at the FFI boundary and reported through
+/// `rb_last_error()`; the callback is purely for logging/telemetry. Panics
+/// outside the library's calls don't reach it.
+///
+/// The first callback installs a panic hook that wraps the one already in
+/// place, which keeps running for every panic. Passing a null callback puts
+/// that previous hook back, so call this from the same place as any other
+/// code that replaces the panic hook.
+///
+/// # Arguments
+/// * `cb` - Callback receiving the panic message (valid only for the duration of the call)
+/// * `userdata` - Opaque pointer forwarded to the callback unchanged
+///
+/// # Safety
+/// The callback may be invoked from any thread that calls into the library,
+/// so it and `userdata` must be thread-safe. The callback must not unwind.
+#[no_mangle]
+pub extern "C" fn rb_set_panic_handler(cb: RBPanicCallback, userdata: *mut c_void) {
+    ffi_guard((), || {
+        let mut host = HOST_PANIC_HOOK.lock().unwrap_or_else(|e| e.into_inner());
+        // Raw pointers aren't Send; the caller is responsible for thread-safety
+        *PANIC_CALLBACK.write().unwrap_or_else(|e| e.into_inner()) = cb.map(|cb| (cb, userdata as usize));
+
+        match (cb, host.take()) {
+            (Some(_), None) => {
+                let previous: Arc<PanicHook> = Arc::from(panic::take_hook());
+                *host = Some(previous.clone());
+                panic::set_hook(Box::new(move |info| {
+                    previous(info);
+                    report_panic(info);
+                }));
             }
-            n
-        } else { 0 }
+            (Some(_), Some(previous)) => *host = Some(previous),
+            (None, Some(previous)) => panic::set_hook(Box::new(move |info| previous(info))),
+            (None, None) => {}
+        }
     })
 }
 
+/// Passes a panic raised inside the library to the `rb_set_panic_handler()`
+/// callback.
+fn report_panic(info: &panic::PanicHookInfo<'_>) {
+    if BRIDGE_DEPTH.with(Cell::get) == 0 {
+        return;
+    }
+    // A poisoned lock still holds a usable callback
+    let Some((cb, userdata)) = *PANIC_CALLBACK.read().unwrap_or_else(|e| e.into_inner()) else {
+        return;
+    };
+    let msg = match info.location() {
+        Some(loc) => format!("{} at {}:{}", panic_message(info.payload()), loc.file(), loc.line()),
+        None => panic_message(info.payload()),
+    };
+    let msg = CString::new(msg.replace('\0', " ")).unwrap_or_default();
+    cb(msg.as_ptr(), userdata as *mut c_void);
+}
+
 // ============================================================================
 // DATA STRUCTURES
 // ============================================================================
 
 /// C-compatible structure representing a rendered image.
 /// This structure is used to return RGBA pixel data from the rendering functions.
-/// 
+///
 /// # Fields
-/// * `ptr` - Pointer to the RGBA pixel data (owned by the library)
+/// * `ptr` - Pointer to the pixel data, laid out as `format` says (owned by the library)
 /// * `len` - Total number of bytes in the pixel data
 /// * `width` - Width of the image in pixels
 /// * `height` - Height of the image in pixels
-/// 
+/// * `format` - Layout of each pixel
+///
 /// # Memory Layout
-/// The pixel data is stored as RGBA bytes in row-major order:
+/// Unless `format` says otherwise, the pixel data is stored as RGBA bytes in
+/// row-major order:
 /// - Each pixel is 4 bytes (R, G, B, A)
 /// - Total size = width * height * 4 bytes
 /// - Row 0: [R0G0B0A0, R1G1B1A1, ..., R(width-1)G(width-1)B(width-1)A(width-1)]
 /// - Row 1: [R0G0B0A0, R1G1B1A1, ..., R(width-1)G(width-1)B(width-1)A(width-1)]
 /// - etc.
-/// 
+///
+/// Rows are always tightly packed, with no padding between them: the stride
+/// is `width * bytes per pixel` (see `rb_image_stride()`) and
+/// `len == height * stride` for every image this library returns. tiny-skia
+/// pixmaps have no native stride of their own to preserve; their rows are
+/// packed the same way, and the buffer is handed over as is.
+///
+/// Color channels are sRGB-encoded and premultiplied by alpha, exactly as
+/// resvg renders them; use `rb_image_srgb_to_linear()` for linear-light
+/// pipelines.
+///
+/// Images in other formats, such as the `RBPixelFormat::Gray8` planes from
+/// `rb_image_split_channels()` or `RBPixelFormat::Rgb8` renders, have
+/// `len == width * height * bytes per pixel`. They are freed the same way
+/// but are only accepted where a function says so; the `rb_image_*`
+/// functions otherwise expect RGBA and reject other formats as invalid
+/// arguments.
+///
 /// # Safety
 /// The caller must call `rb_free_image()` to free the memory when done.
 #[repr(C)]
 pub struct RBImage {
-    /// Pointer to the RGBA pixel data
+    /// Pointer to the pixel data, laid out as `format` says
     pub ptr: *mut u8,
     /// Total number of bytes in the pixel data
     pub len: usize,
@@ -119,122 +463,10452 @@ pub struct RBImage {
     pub width: u32,
     /// Height of the image in pixels
     pub height: u32,
+    /// Layout of each pixel, as an `RBPixelFormat` value
+    pub format: u32,
+}
+
+impl RBImage {
+    /// The null image returned from every failure path.
+    fn empty() -> Self {
+        RBImage { ptr: std::ptr::null_mut(), len: 0, width: 0, height: 0, format: RBPixelFormat::Rgba8 as u32 }
+    }
+
+    /// Hands ownership of `data` to the caller as an `RBImage`.
+    ///
+    /// The buffer is shrunk to its exact length so `rb_free_image()` can
+    /// rebuild it with `capacity == len`.
+    fn from_vec(data: Vec<u8>, width: u32, height: u32) -> Self {
+        // Move the data to the heap and forget it so it doesn't get dropped
+        let mut data = data.into_boxed_slice();
+        let out = RBImage { ptr: data.as_mut_ptr(), len: data.len(), width, height, format: RBPixelFormat::Rgba8 as u32 };
+        std::mem::forget(data); // Prevent automatic deallocation
+        out
+    }
+
+    /// Hands ownership of a rendered pixmap to the caller.
+    fn from_pixmap(pixmap: Pixmap) -> Self {
+        let (width, height) = (pixmap.width(), pixmap.height());
+        // `RBImage` promises tightly packed rows; tiny-skia stores them that way
+        debug_assert_eq!(pixmap.data().len(), width as usize * height as usize * 4);
+        Self::from_vec(pixmap.take(), width, height)
+    }
+
+    /// Hands ownership of a rendered pixmap to the caller, converted to the
+    /// output format selected in `opts`.
+    fn from_render(pixmap: Pixmap, opts: &RBOptions) -> Self {
+        match opts.output_format {
+            RBPixelFormat::Rgb8 => {
+                let (width, height) = (pixmap.width(), pixmap.height());
+                let bg = opts.flatten_background;
+                let data = pixmap
+                    .data()
+                    .chunks_exact(4)
+                    .flat_map(|px| {
+                        let inv = 255 - px[3] as u32;
+                        // Premultiplied source-over onto the opaque background
+                        std::array::from_fn::<u8, 3, _>(|c| {
+                            (px[c] as u32 + (bg[c] as u32 * inv + 127) / 255).min(255) as u8
+                        })
+                    })
+                    .collect();
+                Self::from_vec(data, width, height).with_format(RBPixelFormat::Rgb8)
+            }
+            _ => Self::from_pixmap(pixmap),
+        }
+    }
+
+    /// The pixel layout named by `format`.
+    ///
+    /// Images built by C callers can hold any value there, so an unknown
+    /// one is an "invalid args" error rather than undefined behavior.
+    fn pixel_format(&self) -> Result<RBPixelFormat, String> {
+        RBPixelFormat::from_raw(self.format).ok_or_else(|| format!("invalid args: unknown pixel format {}", self.format))
+    }
+
+    /// Tags the image's pixel layout.
+    fn with_format(mut self, format: RBPixelFormat) -> Self {
+        self.format = format as u32;
+        self
+    }
+}
+
+/// C-compatible width/height pair used by the batch rendering functions.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RBSize {
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+}
+
+/// Layout of raw pixel buffers, caller-supplied or in an `RBImage`.
+///
+/// Unless a function says otherwise, caller-supplied pixels use straight
+/// (non-premultiplied) alpha, the convention of most image decoders, while
+/// `RBImage` pixels are premultiplied.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RBPixelFormat {
+    /// 4 bytes per pixel: R, G, B, A
+    #[default]
+    Rgba8 = 0,
+    /// 4 bytes per pixel: B, G, R, A
+    Bgra8 = 1,
+    /// 3 bytes per pixel: R, G, B (opaque)
+    Rgb8 = 2,
+    /// 1 byte per pixel: a single gray or channel value (opaque)
+    Gray8 = 3,
+    /// 16 bytes per pixel: R, G, B, A as native-endian `f32` in [0, 1]
+    Rgba32F = 4,
 }
 
+impl RBPixelFormat {
+    /// The format with the given numeric value, as passed in from C.
+    ///
+    /// Functions taking a format from callers accept a `u32` and convert it
+    /// here, since an out-of-range value in the enum itself would be undefined
+    /// behavior.
+    fn from_raw(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(RBPixelFormat::Rgba8),
+            1 => Some(RBPixelFormat::Bgra8),
+            2 => Some(RBPixelFormat::Rgb8),
+            3 => Some(RBPixelFormat::Gray8),
+            4 => Some(RBPixelFormat::Rgba32F),
+            _ => None,
+        }
+    }
+
+    /// Number of bytes used by one pixel.
+    fn bytes_per_pixel(self) -> usize {
+        match self {
+            RBPixelFormat::Rgba8 | RBPixelFormat::Bgra8 => 4,
+            RBPixelFormat::Rgb8 => 3,
+            RBPixelFormat::Gray8 => 1,
+            RBPixelFormat::Rgba32F => 16,
+        }
+    }
+}
 
 // ============================================================================
-// RENDERING FUNCTIONS
+// OPTIONS
 // ============================================================================
+//
+// `RBOptions` is an opaque, heap-allocated bag of rendering settings used by
+// the `*_with_options` functions. Every setting starts at a default that
+// matches the plain functions, so a fresh options object changes nothing.
 
-/// Renders an SVG file to RGBA pixel data.
-/// 
-/// This is the main function for converting SVG content to raster images.
-/// The SVG is scaled to fit the requested dimensions while maintaining aspect ratio.
-/// 
+/// Rendering options. Opaque to C callers.
+///
+/// Create with `rb_options_new()`, configure with the `rb_options_set_*`
+/// functions and release with `rb_options_free()`.
+#[derive(Clone, Default)]
+pub struct RBOptions {
+    /// Intrinsic width to use for the fit calculation instead of the parsed one
+    override_width: Option<f32>,
+    /// Intrinsic height to use for the fit calculation instead of the parsed one
+    override_height: Option<f32>,
+    /// Skip `data:` URI images entirely
+    skip_data_images: bool,
+    /// Largest decoded `data:` URI payload to accept, in bytes (0 = unlimited)
+    max_data_image_bytes: usize,
+    /// Fail parsing when the SVG uses features resvg can't render faithfully
+    strict_features: bool,
+    /// Clip rendering to the root element's viewBox
+    clip_to_viewbox: bool,
+    /// Extra CSS applied to the document while parsing
+    style_sheet: Option<String>,
+    /// User languages for `systemLanguage` (None = usvg's default, `en`)
+    languages: Option<Vec<String>>,
+    /// Fonts available to text rendering (None = the shared preloaded fonts, if any)
+    fontdb: Option<Arc<usvg::fontdb::Database>>,
+    /// Opaque checkerboard composited under the rendered image
+    checkerboard: Option<Checkerboard>,
+    /// Render text without anti-aliasing (shapes are unaffected)
+    aliased_text: bool,
+    /// Render every shape without anti-aliasing, overriding `shape-rendering`
+    crisp_edges: bool,
+    /// Fill and clip rule for elements that don't declare one
+    default_fill_rule: RBFillRule,
+    /// Smallest output width (0 = no minimum)
+    min_width: u32,
+    /// Smallest output height (0 = no minimum)
+    min_height: u32,
+    /// Largest output width (0 = no maximum)
+    max_width: u32,
+    /// Largest output height (0 = no maximum)
+    max_height: u32,
+    /// Size of SVGs without width, height and viewBox (None = 100×100)
+    assumed_size: Option<(f32, f32)>,
+    /// Directory relative `href`s resolve against (None = relative hrefs fail)
+    base_path: Option<std::path::PathBuf>,
+    /// Pixel layout of returned images
+    output_format: RBPixelFormat,
+    /// RGB that transparency is flattened onto for formats without alpha
+    flatten_background: [u8; 3],
+    /// Seed for randomized rendering steps. No step is randomized yet, so
+    /// nothing reads it; the allow keeps the stored value from warning
+    /// until one is
+    #[allow(dead_code)]
+    seed: u64,
+}
+
+impl RBOptions {
+    /// Scales a requested output size uniformly into the configured limits.
+    fn clamp_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let limit = |v: u32| if v == 0 { f64::INFINITY } else { v as f64 };
+        let (w, h) = (width as f64, height as f64);
+        let grow = (self.min_width as f64 / w).max(self.min_height as f64 / h).max(1.0);
+        let shrink = (limit(self.max_width) / w).min(limit(self.max_height) / h);
+        let s = grow.min(shrink);
+        if s == 1.0 {
+            return (width, height);
+        }
+        // Rounding must never push a side past its maximum
+        let side = |v: f64, max: u32| {
+            let v = (v * s).round().clamp(1.0, u32::MAX as f64) as u32;
+            if max == 0 { v } else { v.min(max) }
+        };
+        (side(w, self.max_width), side(h, self.max_height))
+    }
+}
+
+/// An opaque two-color checkerboard used as a transparency backing.
+#[derive(Clone, Copy)]
+struct Checkerboard {
+    /// Edge length of one square in pixels (> 0)
+    square_size: u32,
+    /// RGB of the square at the origin and of its neighbours
+    colors: [[u8; 3]; 2],
+}
+
+impl Checkerboard {
+    /// Composites `canvas` over the checkerboard, leaving it fully opaque.
+    ///
+    /// `origin` is the canvas pixel where the top-left square starts, so partial
+    /// renders of the same image line up with the full one.
+    fn fill_behind(&self, canvas: &mut tiny_skia::PixmapMut, origin: (f32, f32)) {
+        let width = canvas.width() as usize;
+        let size = self.square_size as i64;
+        let (ox, oy) = (origin.0.round() as i64, origin.1.round() as i64);
+        for (i, px) in canvas.data_mut().chunks_exact_mut(4).enumerate() {
+            let (x, y) = ((i % width) as i64 - ox, (i / width) as i64 - oy);
+            let color = self.colors[((x.div_euclid(size) + y.div_euclid(size)) & 1) as usize];
+            let inv = 255 - px[3] as u32;
+            for c in 0..3 {
+                // Premultiplied source-over: src + dst * (1 - src alpha)
+                px[c] = (px[c] as u32 + (color[c] as u32 * inv + 127) / 255).min(255) as u8;
+            }
+            px[3] = 255;
+        }
+    }
+}
+
+/// Allocates a new options object with every setting at its default.
+///
+/// # Returns
+/// * A pointer that must be released with `rb_options_free()`
+#[no_mangle]
+pub extern "C" fn rb_options_new() -> *mut RBOptions {
+    ffi_guard(std::ptr::null_mut(), || Box::into_raw(Box::default()))
+}
+
+/// Frees an options object created by `rb_options_new()`.
+///
 /// # Arguments
-/// * `svg_ptr` - Pointer to the SVG data (must not be null)
-/// * `svg_len` - Length of the SVG data in bytes
-/// * `width` - Desired output width in pixels (must be > 0)
-/// * `height` - Desired output height in pixels (must be > 0)
-/// 
+/// * `opts` - The options to free (null is ignored)
+///
+/// # Safety
+/// `opts` must come from `rb_options_new()` and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rb_options_free(opts: *mut RBOptions) {
+    ffi_guard((), || {
+        if !opts.is_null() {
+            drop(unsafe { Box::from_raw(opts) });
+        }
+    })
+}
+
+/// Allocates a deep copy of an options object.
+///
+/// Useful for a "base options" pattern: configure once, clone per job,
+/// adjust per-job settings, render, then free the clone.
+///
+/// # Arguments
+/// * `src` - The options to copy (must not be null)
+///
 /// # Returns
-/// * `RBImage` struct containing the rendered pixel data
-/// * If an error occurs, returns an image with null pointer and zero dimensions
-/// 
+/// * A new options object that must be released with `rb_options_free()`
+/// * Null if `src` is null (error is set)
+///
 /// # Safety
-/// The caller must ensure `svg_ptr` points to valid SVG data for `svg_len` bytes.
-/// The returned image must be freed with `rb_free_image()` when no longer needed.
-/// 
-/// # Error Handling
-/// Errors are stored in thread-local storage and can be retrieved with:
-/// - `rb_last_error()` - Get pointer to error message
-/// - `rb_last_error_copy()` - Copy error message to buffer
+/// `src` must be a valid pointer from `rb_options_new()`.
 #[no_mangle]
-pub extern "C" fn rb_render_svg_to_rgba(
-    svg_ptr: *const u8,
-    svg_len: usize,
-    width: u32,
-    height: u32,
-) -> RBImage {
-    // Clear any previous error for this thread
-    LAST_ERR.with(|e| *e.borrow_mut() = None);
+pub unsafe extern "C" fn rb_options_clone(src: *const RBOptions) -> *mut RBOptions {
+    ffi_guard(std::ptr::null_mut(), || {
+        clear_err();
+        match unsafe { src.as_ref() } {
+            Some(src) => Box::into_raw(Box::new(src.clone())),
+            None => {
+                set_err("invalid args".into());
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
 
-    // Validate input parameters
-    if svg_ptr.is_null() || svg_len == 0 || width == 0 || height == 0 {
-        set_err("invalid args".into());
-        return RBImage { ptr: std::ptr::null_mut(), len: 0, width: 0, height: 0 };
-    }
+/// Overwrites every setting in `dst` with the settings from `src`.
+///
+/// # Arguments
+/// * `src` - The options to copy from (must not be null)
+/// * `dst` - An existing options object to copy into (must not be null)
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if either pointer is null (error is set)
+///
+/// # Safety
+/// Both pointers must be valid pointers from `rb_options_new()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_options_copy_to(src: *const RBOptions, dst: *mut RBOptions) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+        // Copying onto itself is a no-op (and must not alias `&`/`&mut`)
+        if std::ptr::eq(src, dst) && !src.is_null() {
+            return true;
+        }
+        match unsafe { (src.as_ref(), dst.as_mut()) } {
+            (Some(src), Some(dst)) => {
+                dst.clone_from(src);
+                true
+            }
+            _ => {
+                set_err("invalid args".into());
+                false
+            }
+        }
+    })
+}
 
-    // Convert raw pointer to byte slice
-    let svg_bytes = unsafe { slice::from_raw_parts(svg_ptr, svg_len) };
+/// Process-wide options used by renders that are given no options; `None`
+/// means the built-in defaults.
+static DEFAULT_OPTIONS: RwLock<Option<RBOptions>> = RwLock::new(None);
 
-    // Parse SVG content into a tree structure
-    let opt = usvg::Options::default();
-    let tree = match Tree::from_data(svg_bytes, &opt) {
-        Ok(t) => t,
-        Err(e) => {
-            set_err(format!("parse error: {e}"));
-            return RBImage { ptr: std::ptr::null_mut(), len: 0, width: 0, height: 0 };
-        }
-    };
+/// The options for a render called without any: the ones installed with
+/// `rb_set_default_options()`, else the built-in defaults.
+fn default_options() -> RBOptions {
+    DEFAULT_OPTIONS.read().unwrap_or_else(|e| e.into_inner()).clone().unwrap_or_default()
+}
 
-    // Allocate target buffer for the rendered image
-    let mut pixmap = match Pixmap::new(width, height) {
-        Some(p) => p,
-        None => {
-            set_err("alloc pixmap failed".into());
-            return RBImage { ptr: std::ptr::null_mut(), len: 0, width: 0, height: 0 };
+/// Installs process-wide default options.
+///
+/// Every function that parses or renders an SVG without being given
+/// options, or that is given null `opts`, uses a copy of these instead of
+/// the built-in defaults, so settings such as the background, DPI, fonts or
+/// size limits can be configured once at startup. That includes
+/// `rb_tree_parse()` with null `opts`, the batch, PNG, TIFF and compositing
+/// functions, and queries such as `rb_profile_svg()`. The only exceptions
+/// are `rb_render_svg_to_rgba_error_image()`, whose placard must look the
+/// same whatever the defaults are, and `rb_image_apply_svg_filter()`, which
+/// filters an existing image rather than rendering a document. Options objects from `rb_options_new()` are
+/// unaffected.
+///
+/// The `output_format` of the defaults applies only to functions that hand
+/// back the render unchanged (`rb_render_svg_to_rgba()`, `_with_options()`,
+/// `_ctx()`, `_with_matrix_out()`, `_max_dim()` and `rb_render_svg_region()`);
+/// the others post-process the pixels and always return premultiplied RGBA.
+/// `opts` is copied, so it may be changed or freed afterwards without
+/// affecting the defaults.
+///
+/// The defaults are guarded by a read-write lock: this may be called from
+/// any thread, renders that already started keep the defaults they began
+/// with, and renders that start afterwards see the new ones.
+///
+/// # Arguments
+/// * `opts` - Options to use as the defaults, or null to restore the
+///   built-in defaults
+///
+/// # Safety
+/// `opts` must be null or a valid pointer from `rb_options_new()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_set_default_options(opts: *const RBOptions) {
+    ffi_guard((), || {
+        clear_err();
+        let opts = unsafe { opts.as_ref() }.cloned();
+        *DEFAULT_OPTIONS.write().unwrap_or_else(|e| e.into_inner()) = opts;
+    })
+}
+
+/// Copies the current process-wide default options into `out`.
+///
+/// # Arguments
+/// * `out` - An existing options object to overwrite (must not be null);
+///   receives the built-in defaults if none were installed
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if `out` is null (error is set)
+///
+/// # Safety
+/// `out` must be null or a valid pointer from `rb_options_new()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_get_default_options(out: *mut RBOptions) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+
+        let Some(out) = (unsafe { out.as_mut() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        *out = default_options();
+        true
+    })
+}
+
+/// Sets the size assumed for SVGs that declare neither a size nor a viewBox.
+///
+/// Without `width`, `height` or `viewBox` on the root element an SVG has no
+/// intrinsic size, and usvg falls back to the bounding box of its content,
+/// which turns a lone small shape into a 1×1 "document". Such SVGs are
+/// instead treated as if the root declared `width` and `height` of the
+/// assumed size (100×100 by default), and a warning naming the size is
+/// recorded for `rb_last_warning_copy()`. SVGs that declare any of the
+/// three attributes are unaffected.
+///
+/// # Arguments
+/// * `opts` - The options to modify (must not be null)
+/// * `width` - Assumed width in user units (must be finite and > 0)
+/// * `height` - Assumed height in user units (must be finite and > 0)
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if `opts` is null or a value is invalid (error is set)
+///
+/// # Safety
+/// `opts` must be a valid pointer from `rb_options_new()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_options_set_assumed_size(opts: *mut RBOptions, width: f32, height: f32) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+        let Some(opts) = (unsafe { opts.as_mut() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        if !(width.is_finite() && width > 0.0 && height.is_finite() && height > 0.0) {
+            set_err("invalid args: assumed size must be finite and > 0".into());
+            return false;
         }
-    };
+        opts.assumed_size = Some((width, height));
+        true
+    })
+}
 
-    // Calculate scaling factors to fit SVG into requested dimensions
-    let size = tree.size();
-    let sx = width as f32 / size.width().max(1.0);
-    let sy = height as f32 / size.height().max(1.0);
-    let ts = Transform::from_scale(sx, sy);
+/// Overrides the SVG's intrinsic size used when fitting it to the output.
+///
+/// Normally the SVG's declared size (`tree.size()`) is stretched onto the
+/// requested output dimensions. With an override the declared size is
+/// replaced, e.g. treating every file as 512×512 no matter what it says.
+///
+/// The override only changes the mapping onto the output: the document is
+/// still laid out in its own coordinate system (its viewBox still maps onto
+/// its declared size), so content is scaled by `output / override` rather than
+/// `output / declared`. A 100×100 SVG overridden to 200×200 and rendered at
+/// 200×200 therefore occupies the top-left quarter.
+///
+/// # Arguments
+/// * `opts` - The options to modify (must not be null)
+/// * `width` - Intrinsic width in user units (<= 0 uses the parsed width)
+/// * `height` - Intrinsic height in user units (<= 0 uses the parsed height)
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if `opts` is null or a value isn't finite (error is set)
+///
+/// # Safety
+/// `opts` must be a valid pointer from `rb_options_new()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_options_set_size_override(opts: *mut RBOptions, width: f32, height: f32) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+        let Some(opts) = (unsafe { opts.as_mut() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        if !width.is_finite() || !height.is_finite() {
+            set_err("size override must be finite".into());
+            return false;
+        }
+        opts.override_width = (width > 0.0).then_some(width);
+        opts.override_height = (height > 0.0).then_some(height);
+        true
+    })
+}
 
-    // Render the SVG tree to the pixmap
-    resvg::render(&tree, ts, &mut pixmap.as_mut());
-
-    // Extract pixel data and prepare for FFI return
-    // We need to move the data to the heap and forget it so it doesn't get dropped
-    let mut data = pixmap.take();
-    let out = RBImage { 
-        ptr: data.as_mut_ptr(), 
-        len: data.len(), 
-        width, 
-        height 
-    };
-    std::mem::forget(data); // Prevent automatic deallocation
-    out
+/// Controls how `<image>` elements with `data:` URIs are handled.
+///
+/// Embedded images are decoded by default. For untrusted input they can be
+/// skipped entirely or limited by payload size; a skipped image renders as
+/// empty and adds a warning (see `rb_last_warning_copy()`). The limit
+/// applies to the decoded (base64-decoded) payload, i.e. the compressed
+/// image file, and also covers images inside nested SVG images.
+///
+/// # Arguments
+/// * `opts` - The options to modify (must not be null)
+/// * `allow` - `false` skips every data-URI image
+/// * `max_bytes` - Largest accepted payload in bytes (0 = unlimited)
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if `opts` is null (error is set)
+///
+/// # Safety
+/// `opts` must be a valid pointer from `rb_options_new()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_options_set_embedded_images(opts: *mut RBOptions, allow: bool, max_bytes: usize) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+        let Some(opts) = (unsafe { opts.as_mut() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        opts.skip_data_images = !allow;
+        opts.max_data_image_bytes = max_bytes;
+        true
+    })
 }
 
-/// Frees memory allocated for an RBImage.
-/// 
-/// This function must be called to free the memory allocated by `rb_render_svg_to_rgba()`.
-/// Failing to call this function will result in a memory leak.
-/// 
+/// Makes rendering fail when the SVG uses features that resvg cannot render
+/// faithfully, instead of silently producing a degraded image.
+///
+/// Detection inspects the SVG document before parsing and currently covers:
+/// - Animation elements (`<animate>`, `<animateMotion>`, `<animateTransform>`,
+///   `<set>`): only the static document is rendered
+/// - `<foreignObject>` (HTML content is not rendered)
+/// - SVG fonts (`<font>`, `<font-face>`, `<glyph>`, `<missing-glyph>`)
+/// - `<script>`
+/// - Filter inputs `BackgroundImage`, `BackgroundAlpha`, `FillPaint` and
+///   `StrokePaint`, and the `enable-background` attribute
+///
+/// Other filters are supported and do not trigger strict mode. The error
+/// message lists every unsupported construct found, with its line number.
+///
 /// # Arguments
-/// * `img` - The RBImage structure to free
-/// 
+/// * `opts` - The options to modify (must not be null)
+/// * `enabled` - `true` to reject SVGs with unsupported features
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if `opts` is null (error is set)
+///
 /// # Safety
-/// This function is safe to call multiple times on the same image (idempotent).
-/// After calling this function, the image structure should not be used again.
-/// 
-/// # Example
-/// ```c
-/// RBImage img = rb_render_svg_to_rgba(svg_data, svg_len, 100, 100);
-/// // Use the image...
-/// rb_free_image(img);
-/// ```
+/// `opts` must be a valid pointer from `rb_options_new()`.
 #[no_mangle]
-pub extern "C" fn rb_free_image(img: RBImage) {
-    // Only free if we have valid data
-    if !img.ptr.is_null() && img.len > 0 {
-        // Reconstruct the Vec to properly deallocate the memory
-        // This is safe because we know the memory was allocated by Vec::from_raw_parts
-        unsafe { 
-            drop(Vec::from_raw_parts(img.ptr, img.len, img.len)) 
+pub unsafe extern "C" fn rb_options_set_strict_features(opts: *mut RBOptions, enabled: bool) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+        let Some(opts) = (unsafe { opts.as_mut() }) else {
+            set_err("invalid args".into());
+            return false;
         };
+        opts.strict_features = enabled;
+        true
+    })
+}
+
+/// Forces rendering to be clipped to the root `viewBox`.
+///
+/// Content outside the viewBox can show up when the output's aspect ratio
+/// differs from the viewBox's (the letterbox area of `preserveAspectRatio`
+/// "meet"). With this enabled those pixels are always transparent, giving
+/// predictable icon bounds regardless of `overflow`. SVGs without a
+/// `viewBox` are unaffected.
+///
+/// # Arguments
+/// * `opts` - The options to modify (must not be null)
+/// * `enabled` - `true` to clip to the viewBox
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if `opts` is null (error is set)
+///
+/// # Safety
+/// `opts` must be a valid pointer from `rb_options_new()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_options_set_clip_to_viewbox(opts: *mut RBOptions, enabled: bool) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+        let Some(opts) = (unsafe { opts.as_mut() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        opts.clip_to_viewbox = enabled;
+        true
+    })
+}
+
+/// Enables or disables anti-aliasing of text, independently of shapes.
+///
+/// Small anti-aliased text can look fuzzy on low-DPI screens. With text
+/// anti-aliasing off, glyph outlines are filled with hard pixel edges while
+/// every other shape stays smooth. This sets the default for the SVG
+/// `text-rendering` property (off = `optimizeSpeed`), so text elements that
+/// set `text-rendering` themselves keep their own choice. resvg does no
+/// hinting, so glyph stems aren't snapped to the pixel grid either way.
+///
+/// # Arguments
+/// * `opts` - The options to modify (must not be null)
+/// * `enabled` - `false` to render text aliased (the default is `true`)
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if `opts` is null (error is set)
+///
+/// # Safety
+/// `opts` must be a valid pointer from `rb_options_new()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_options_set_text_antialias(opts: *mut RBOptions, enabled: bool) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+        let Some(opts) = (unsafe { opts.as_mut() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        opts.aliased_text = !enabled;
+        true
+    })
+}
+
+/// Renders every shape with crisp, non-anti-aliased edges.
+///
+/// resvg already honors `shape-rendering: crispEdges` (and `optimizeSpeed`)
+/// on individual elements. Enabling this treats every shape as if it
+/// declared `crispEdges`, overriding `shape-rendering` attributes in the
+/// document, so 1px axis-aligned lines in schematics and CAD drawings come
+/// out as solid pixel rows instead of two half-covered ones. Like any
+/// injected CSS (see `rb_options_set_style_sheet()`), it yields to the
+/// document's own `<style>` rules and inline `style` attributes. Text is
+/// controlled separately by `rb_options_set_text_antialias()`.
+///
+/// # Arguments
+/// * `opts` - The options to modify (must not be null)
+/// * `enabled` - `true` to render all shapes aliased (the default is `false`)
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if `opts` is null (error is set)
+///
+/// # Safety
+/// `opts` must be a valid pointer from `rb_options_new()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_options_set_crisp_edges(opts: *mut RBOptions, enabled: bool) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+        let Some(opts) = (unsafe { opts.as_mut() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        opts.crisp_edges = enabled;
+        true
+    })
+}
+
+/// Sets the seed for randomized rendering steps.
+///
+/// This is currently a no-op. Every rendering step is deterministic:
+/// error-diffusion dithering, supersampling and the filters produce
+/// byte-identical output for identical input, and no step reads the seed,
+/// so changing it changes nothing. It is only stored and copied with the
+/// options.
+///
+/// # Arguments
+/// * `opts` - The options to modify (must not be null)
+/// * `seed` - The seed (the default is 0)
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if `opts` is null (error is set)
+///
+/// # Safety
+/// `opts` must be a valid pointer from `rb_options_new()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_options_set_seed(opts: *mut RBOptions, seed: u64) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+        let Some(opts) = (unsafe { opts.as_mut() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        opts.seed = seed;
+        true
+    })
+}
+
+/// Winding rule deciding which parts of a self-intersecting shape are inside.
+#[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum RBFillRule {
+    /// Inside wherever the outline winds around a point a non-zero number of times
+    #[default]
+    NonZero = 0,
+    /// Inside wherever a ray from a point crosses the outline an odd number of times
+    EvenOdd = 1,
+}
+
+impl RBFillRule {
+    /// The rule with the given numeric value, as passed in from C.
+    fn from_raw(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(RBFillRule::NonZero),
+            1 => Some(RBFillRule::EvenOdd),
+            _ => None,
+        }
+    }
+}
+
+/// Sets the fill rule used by shapes and clip paths that don't declare one.
+///
+/// Some CAD exports are drawn expecting even-odd filling but omit
+/// `fill-rule`, so their holes render filled under the SVG default of
+/// `nonzero`. This changes the document-wide default of both `fill-rule` and
+/// `clip-rule`, as if the root `<svg>` had declared them. Elements, groups
+/// and document `<style>` rules that set either property keep their own
+/// value; only a declaration on an `<svg>` element itself is replaced.
+///
+/// # Arguments
+/// * `opts` - The options to modify (must not be null)
+/// * `rule` - The default rule, as an `RBFillRule` value (the default is
+///   `RBFillRule::NonZero`)
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if `opts` is null or `rule` is unknown (error is set)
+///
+/// # Safety
+/// `opts` must be a valid pointer from `rb_options_new()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_options_set_default_clip_rule(opts: *mut RBOptions, rule: u32) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+        let Some(opts) = (unsafe { opts.as_mut() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        let Some(rule) = RBFillRule::from_raw(rule) else {
+            set_err(format!("invalid args: unknown fill rule {rule}"));
+            return false;
+        };
+        opts.default_fill_rule = rule;
+        true
+    })
+}
+
+/// Clamps the output size of renders with `opts`, keeping its aspect ratio.
+///
+/// The requested `width × height` is scaled by one factor until it lies
+/// within the limits, so the proportions the caller asked for survive:
+/// with a 400 × 400 maximum, a 1000 × 500 request renders at 400 × 200. If a
+/// minimum and a maximum can't both be met at that aspect ratio, the maximum
+/// wins. Sides are rounded to whole pixels. Region renders, whose output
+/// size is the region's, ignore the limits.
+///
+/// # Arguments
+/// * `opts` - The options to modify (must not be null)
+/// * `min_width` - Smallest output width in pixels (0 = no minimum)
+/// * `min_height` - Smallest output height in pixels (0 = no minimum)
+/// * `max_width` - Largest output width in pixels (0 = no maximum)
+/// * `max_height` - Largest output height in pixels (0 = no maximum)
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if `opts` is null or a maximum is below its minimum (error is set)
+///
+/// # Safety
+/// `opts` must be a valid pointer from `rb_options_new()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_options_set_size_limits(
+    opts: *mut RBOptions,
+    min_width: u32,
+    min_height: u32,
+    max_width: u32,
+    max_height: u32,
+) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+        let Some(opts) = (unsafe { opts.as_mut() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        if (max_width != 0 && max_width < min_width) || (max_height != 0 && max_height < min_height) {
+            set_err("invalid args: maximum size below minimum size".into());
+            return false;
+        }
+        opts.min_width = min_width;
+        opts.min_height = min_height;
+        opts.max_width = max_width;
+        opts.max_height = max_height;
+        true
+    })
+}
+
+/// Selects the pixel layout of images rendered with `opts`.
+///
+/// `RBPixelFormat::Rgba8` (the default) returns premultiplied RGBA.
+/// `RBPixelFormat::Rgb8` returns tightly packed 3-byte RGB for targets
+/// without alpha: every pixel is composited over the background color and the
+/// alpha channel dropped, so `len == width * height * 3`. The returned
+/// image's `format` field records the layout. This applies to the render
+/// functions that take options and return an `RBImage`.
+///
+/// # Arguments
+/// * `opts` - The options to modify (must not be null)
+/// * `format` - The numeric value of `RBPixelFormat::Rgba8` or
+///   `RBPixelFormat::Rgb8`
+/// * `bg_r`, `bg_g`, `bg_b` - RGB that transparency is flattened onto
+///   (ignored for formats with alpha)
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if `opts` is null or `format` is unknown or unsupported (error is set)
+///
+/// # Safety
+/// `opts` must be a valid pointer from `rb_options_new()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_options_set_output_format(
+    opts: *mut RBOptions,
+    format: u32,
+    bg_r: u8,
+    bg_g: u8,
+    bg_b: u8,
+) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+        let Some(opts) = (unsafe { opts.as_mut() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        let format = match RBPixelFormat::from_raw(format) {
+            Some(f @ (RBPixelFormat::Rgba8 | RBPixelFormat::Rgb8)) => f,
+            Some(f) => {
+                set_err(format!("invalid args: unsupported output format {f:?}"));
+                return false;
+            }
+            None => {
+                set_err(format!("invalid args: unknown pixel format {format}"));
+                return false;
+            }
+        };
+        opts.output_format = format;
+        opts.flatten_background = [bg_r, bg_g, bg_b];
+        true
+    })
+}
+
+/// Enables or disables an opaque checkerboard behind everything rendered with `opts`.
+///
+/// After rendering, the image is composited over a two-color checkerboard,
+/// the classic way preview UIs show transparency. The result is fully
+/// opaque. The top-left square is anchored where the SVG's origin lands in
+/// the output, so region renders line up with full renders.
+///
+/// # Arguments
+/// * `opts` - The options to modify (must not be null)
+/// * `enabled` - `true` to render over the checkerboard
+/// * `square_size` - Edge length of one square in pixels (must be > 0 when enabled)
+/// * `color1_r`, `color1_g`, `color1_b` - RGB of the top-left square
+/// * `color2_r`, `color2_g`, `color2_b` - RGB of the squares next to it
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if `opts` is null or `square_size` is 0 while enabling (error is set)
+///
+/// # Safety
+/// `opts` must be a valid pointer from `rb_options_new()`.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn rb_options_set_checkerboard(
+    opts: *mut RBOptions,
+    enabled: bool,
+    square_size: u32,
+    color1_r: u8,
+    color1_g: u8,
+    color1_b: u8,
+    color2_r: u8,
+    color2_g: u8,
+    color2_b: u8,
+) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+        let Some(opts) = (unsafe { opts.as_mut() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        if !enabled {
+            opts.checkerboard = None;
+            return true;
+        }
+        if square_size == 0 {
+            set_err("invalid args: square size must be > 0".into());
+            return false;
+        }
+        let colors = [[color1_r, color1_g, color1_b], [color2_r, color2_g, color2_b]];
+        opts.checkerboard = Some(Checkerboard { square_size, colors });
+        true
+    })
+}
+
+/// Sets the directory that relative resource references resolve against.
+///
+/// SVGs rendered from memory have no location of their own, so by default a
+/// relative `<image href="logo.png">` can't be loaded and is skipped. With a
+/// base path, such references resolve as though the SVG bytes had been read
+/// from a file in that directory. Only local paths are supported; `http:`
+/// and other URLs are never fetched. Absolute paths resolve regardless.
+///
+/// # Arguments
+/// * `opts` - The options to modify (must not be null)
+/// * `path` - Null-terminated directory path, or null to remove the base path
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if `opts` is null or `path` is not valid UTF-8 (error is set)
+///
+/// # Safety
+/// `opts` must be a valid pointer from `rb_options_new()`; `path` must be null
+/// or a valid null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rb_options_set_base_path(opts: *mut RBOptions, path: *const c_char) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+        let Some(opts) = (unsafe { opts.as_mut() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        if path.is_null() {
+            opts.base_path = None;
+            return true;
+        }
+        match unsafe { CStr::from_ptr(path) }.to_str() {
+            Ok(path) => {
+                opts.base_path = Some(path.into());
+                true
+            }
+            Err(_) => {
+                set_err("base path is not valid UTF-8".into());
+                false
+            }
+        }
+    })
+}
+
+/// Sets a CSS style sheet that is applied to every SVG parsed with `opts`.
+///
+/// The rules are applied as if from a `<style>` element placed before the
+/// document's own styles, so rules in the SVG itself override them on equal
+/// specificity. This is the hook for templating: one SVG, many looks, e.g.
+/// `"#value { fill: red }"`.
+///
+/// # Arguments
+/// * `opts` - The options to modify (must not be null)
+/// * `css` - Null-terminated CSS text, or null to remove the style sheet
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if `opts` is null or `css` is not valid UTF-8 (error is set)
+///
+/// # Safety
+/// `opts` must be a valid pointer from `rb_options_new()`; `css` must be null
+/// or a valid null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rb_options_set_style_sheet(opts: *mut RBOptions, css: *const c_char) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+        let Some(opts) = (unsafe { opts.as_mut() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        if css.is_null() {
+            opts.style_sheet = None;
+            return true;
+        }
+        match unsafe { CStr::from_ptr(css) }.to_str() {
+            Ok(css) => {
+                opts.style_sheet = Some(css.to_owned());
+                true
+            }
+            Err(_) => {
+                set_err("style sheet is not valid UTF-8".into());
+                false
+            }
+        }
+    })
+}
+
+/// Sets the user's languages, as BCP-47 tags.
+///
+/// This maps to usvg's `Options::languages`, which is the only language hint
+/// usvg 0.44 exposes: it decides which branch of a `<switch>` is rendered
+/// through the `systemLanguage` attribute. usvg has no option for the shaping
+/// language or the default text direction (direction comes from the Unicode
+/// bidi algorithm), so this does not change glyph selection for ambiguous
+/// codepoints; pick the font with `font-family` for that.
+///
+/// # Arguments
+/// * `opts` - The options to modify (must not be null)
+/// * `languages` - Null-terminated, comma-separated list of tags in order of
+///   preference (e.g. `"ja, en-US"`), or null to restore the default (`"en"`)
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if `opts` is null or `languages` is not valid UTF-8 (error is set)
+///
+/// # Safety
+/// `opts` must be a valid pointer from `rb_options_new()`; `languages` must be
+/// null or a valid null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rb_options_set_languages(opts: *mut RBOptions, languages: *const c_char) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+        let Some(opts) = (unsafe { opts.as_mut() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        if languages.is_null() {
+            opts.languages = None;
+            return true;
+        }
+        match unsafe { CStr::from_ptr(languages) }.to_str() {
+            Ok(list) => {
+                let tags = list.split(',').map(str::trim).filter(|t| !t.is_empty()).map(String::from);
+                opts.languages = Some(tags.collect());
+                true
+            }
+            Err(_) => {
+                set_err("languages are not valid UTF-8".into());
+                false
+            }
+        }
+    })
+}
+
+/// Loads the system's fonts into the options' font database.
+///
+/// Text only renders with fonts from the options' database, which starts
+/// empty. This scans the platform's font locations (fontconfig paths on
+/// Linux, the system font folders on macOS and Windows) via fontdb's
+/// `Database::load_system_fonts()`, replacing any database the options had.
+/// The scan can take from milliseconds to seconds depending on the system,
+/// so call it once and clone the options (`rb_options_clone()` shares the
+/// loaded database).
+///
+/// # Arguments
+/// * `opts` - The options to modify (must not be null)
+///
+/// # Returns
+/// * The number of font faces loaded; 0 means none were found
+/// * 0 if `opts` is null (error is set)
+///
+/// # Safety
+/// `opts` must be a valid pointer from `rb_options_new()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_options_set_font_db_from_system(opts: *mut RBOptions) -> u32 {
+    ffi_guard(0, || {
+        clear_err();
+        let Some(opts) = (unsafe { opts.as_mut() }) else {
+            set_err("invalid args".into());
+            return 0;
+        };
+        let mut db = usvg::fontdb::Database::new();
+        db.load_system_fonts();
+        let count = db.len();
+        opts.fontdb = Some(Arc::new(db));
+        count.try_into().unwrap_or(u32::MAX)
+    })
+}
+
+/// Returns the number of font faces in the options' font database.
+///
+/// Options without their own database report the shared one loaded by
+/// `rb_preload_fonts_async()`, since that is what their renders use.
+///
+/// # Arguments
+/// * `opts` - The options to inspect (must not be null)
+///
+/// # Returns
+/// * The number of font faces available to text rendering
+/// * 0 if no fonts are loaded, or if `opts` is null (error is set)
+///
+/// # Safety
+/// `opts` must be a valid pointer from `rb_options_new()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_options_get_font_count(opts: *const RBOptions) -> usize {
+    ffi_guard(0, || {
+        clear_err();
+        let Some(opts) = (unsafe { opts.as_ref() }) else {
+            set_err("invalid args".into());
+            return 0;
+        };
+        effective_fontdb(opts).map_or(0, |db| db.len())
+    })
+}
+
+/// Lists the font families available to text rendering with `opts`.
+///
+/// Compare the result with `rb_query_svg_used_fonts()` to see why text falls
+/// back to another font. Families are unique and sorted; a face with
+/// localized family names contributes each of them.
+///
+/// # Arguments
+/// * `opts` - The options to inspect (must not be null)
+/// * `out_families` - Receives the array of family names (must not be null)
+/// * `out_count` - Receives the number of names (must not be null)
+///
+/// # Returns
+/// * `true` on success (an empty list if no fonts are loaded); free the
+///   array with `rb_free_string_array()`
+/// * `false` on error (error is set, outputs are null/0)
+///
+/// # Safety
+/// `opts` must be a valid pointer from `rb_options_new()`; both output
+/// pointers must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rb_options_list_fonts(
+    opts: *const RBOptions,
+    out_families: *mut *mut *mut c_char,
+    out_count: *mut usize,
+) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+
+        let (Some(opts), false, false) = (unsafe { opts.as_ref() }, out_families.is_null(), out_count.is_null()) else {
+            set_err("invalid args".into());
+            return false;
+        };
+
+        let families: std::collections::BTreeSet<String> = effective_fontdb(opts)
+            .iter()
+            .flat_map(|db| db.faces())
+            .flat_map(|face| face.families.iter().map(|(name, _)| name.clone()))
+            .collect();
+        unsafe { string_array_into_raw(families.into_iter().collect(), out_families, out_count) };
+        true
+    })
+}
+
+/// Callback type invoked when a background font preload finishes, with the
+/// number of faces loaded (0 if cancelled) and the caller's userdata.
+pub type RBFontsLoadedCallback = Option<extern "C" fn(face_count: usize, userdata: *mut c_void)>;
+
+/// System fonts loaded by `rb_preload_fonts_async()`, used by options without
+/// their own font database. Swapped whole, so readers never see a partial load.
+static SHARED_FONTDB: RwLock<Option<Arc<usvg::fontdb::Database>>> = RwLock::new(None);
+/// Set while a preload thread is scanning
+static FONT_PRELOAD_RUNNING: AtomicBool = AtomicBool::new(false);
+/// Bumped by every preload start and cancel; a load only publishes its result
+/// if no newer start or cancel happened while it was scanning
+static FONT_PRELOAD_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// The font database text rendering with `opts` uses: the options' own, else
+/// the shared preloaded one, else none.
+fn effective_fontdb(opts: &RBOptions) -> Option<Arc<usvg::fontdb::Database>> {
+    opts.fontdb
+        .clone()
+        .or_else(|| SHARED_FONTDB.read().unwrap_or_else(|e| e.into_inner()).clone())
+}
+
+/// Loads the system fonts into a process-wide database on a background thread.
+///
+/// Once the load completes, every render whose options have no font database
+/// of their own (including the plain functions and null options) uses it.
+/// Renders that race the load behave as if it hadn't started: they see no
+/// fonts, so text is skipped (parsing and everything else is unaffected). A
+/// render parses with whichever database was current when it started, so a
+/// completing load never changes a render in progress. Options with their
+/// own database, e.g. from `rb_options_set_font_db_from_system()`, are never
+/// affected.
+///
+/// Calling this after a load completed rescans and replaces the database,
+/// e.g. after fonts were installed.
+///
+/// # Arguments
+/// * `done_cb` - Called once, on the loading thread, when the load finishes
+///   or is cancelled (null = no notification)
+/// * `userdata` - Opaque pointer forwarded to the callback unchanged
+///
+/// # Returns
+/// * `true` if the load was started
+/// * `false` if a load is already running (error is set, with
+///   `RBErrorCode::Busy`)
+///
+/// # Safety
+/// The callback runs on a thread the library spawns, so it and `userdata`
+/// must be thread-safe. The callback must not unwind.
+#[no_mangle]
+pub extern "C" fn rb_preload_fonts_async(done_cb: RBFontsLoadedCallback, userdata: *mut c_void) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+        if FONT_PRELOAD_RUNNING.swap(true, Ordering::AcqRel) {
+            set_err("busy: a font preload is already running".into());
+            return false;
+        }
+        let generation = FONT_PRELOAD_GENERATION.fetch_add(1, Ordering::AcqRel) + 1;
+        // Raw pointers aren't Send; the caller is responsible for thread-safety
+        let userdata = userdata as usize;
+        let spawned = std::thread::Builder::new().name("rb-font-preload".into()).spawn(move || {
+            let count = catch_panic(|| {
+                let mut db = usvg::fontdb::Database::new();
+                db.load_system_fonts();
+                let mut shared = SHARED_FONTDB.write().unwrap_or_else(|e| e.into_inner());
+                // Checked under the lock so a cancel can't slip in between
+                if FONT_PRELOAD_GENERATION.load(Ordering::Acquire) != generation {
+                    return 0;
+                }
+                let count = db.len();
+                *shared = Some(Arc::new(db));
+                count
+            })
+            .unwrap_or(0);
+            FONT_PRELOAD_RUNNING.store(false, Ordering::Release);
+            if let Some(cb) = done_cb {
+                cb(count, userdata as *mut c_void);
+            }
+        });
+        if let Err(e) = spawned {
+            FONT_PRELOAD_RUNNING.store(false, Ordering::Release);
+            set_err(format!("failed to start font preload: {e}"));
+            return false;
+        }
+        true
+    })
+}
+
+/// Cancels a font preload started by `rb_preload_fonts_async()`.
+///
+/// The directory scan itself can't be interrupted, so the loading thread
+/// runs to completion, but its result is discarded and the callback receives
+/// 0. A database from an earlier completed load stays in place. A new
+/// preload can be started once the cancelled one's callback has fired.
+///
+/// # Returns
+/// * `true` if a running load was cancelled
+/// * `false` if no load was running
+#[no_mangle]
+pub extern "C" fn rb_cancel_font_preload() -> bool {
+    ffi_guard(false, || {
+        let _shared = SHARED_FONTDB.write().unwrap_or_else(|e| e.into_inner());
+        if !FONT_PRELOAD_RUNNING.load(Ordering::Acquire) {
+            return false;
+        }
+        FONT_PRELOAD_GENERATION.fetch_add(1, Ordering::AcqRel);
+        true
+    })
+}
+
+/// Process-wide `font-family` substitutions, keyed by lowercased family name.
+static FONT_ALIASES: RwLock<Option<std::collections::HashMap<String, String>>> = RwLock::new(None);
+
+/// Substitutes one font family for another in every render.
+///
+/// Text asking for `from` (matched case-insensitively, as CSS does) is set
+/// in `to` instead, e.g. mapping a brand font that isn't installed to
+/// `"Arial"`. `to` may also be a generic family (`serif`, `sans-serif`,
+/// `cursive`, `fantasy`, `monospace`). If no font named `to` is available
+/// either, `from` itself and then the rest of the element's `font-family`
+/// list are tried as usual. Aliases are not chained. Adding an alias for a
+/// family that already has one replaces it; renders that already started
+/// keep the aliases they began with.
+///
+/// # Arguments
+/// * `from` - Null-terminated family name as written in SVGs (must not be null)
+/// * `to` - Null-terminated replacement family, or null to remove the alias
+///
+/// # Returns
+/// * `true` on success
+/// * `false` on invalid arguments (error is set)
+///
+/// # Safety
+/// `from` and `to` must be null or valid null-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn rb_add_font_alias(from: *const c_char, to: *const c_char) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+
+        if from.is_null() {
+            set_err("invalid args".into());
+            return false;
+        }
+        let from = unsafe { CStr::from_ptr(from) }.to_string_lossy().trim().to_lowercase();
+        if from.is_empty() {
+            set_err("invalid args: empty family name".into());
+            return false;
+        }
+        let to = (!to.is_null()).then(|| unsafe { CStr::from_ptr(to) }.to_string_lossy().trim().to_string());
+
+        let mut aliases = FONT_ALIASES.write().unwrap_or_else(|e| e.into_inner());
+        let map = aliases.get_or_insert_with(std::collections::HashMap::new);
+        match to {
+            Some(to) if !to.is_empty() => map.insert(from, to),
+            _ => map.remove(&from),
+        };
+        true
+    })
+}
+
+/// Builds a font selector that applies `aliases` before querying the
+/// database the way usvg's default selector does.
+fn aliased_font_selector(aliases: std::collections::HashMap<String, String>) -> usvg::FontSelectionFn<'static> {
+    use usvg::fontdb::Family;
+
+    Box::new(move |font, fontdb| {
+        let generic = |name: &str| match name.to_ascii_lowercase().as_str() {
+            "serif" => Some(Family::Serif),
+            "sans-serif" => Some(Family::SansSerif),
+            "cursive" => Some(Family::Cursive),
+            "fantasy" => Some(Family::Fantasy),
+            "monospace" => Some(Family::Monospace),
+            _ => None,
+        };
+        let mut families = Vec::new();
+        for family in font.families() {
+            match family {
+                usvg::FontFamily::Serif => families.push(Family::Serif),
+                usvg::FontFamily::SansSerif => families.push(Family::SansSerif),
+                usvg::FontFamily::Cursive => families.push(Family::Cursive),
+                usvg::FontFamily::Fantasy => families.push(Family::Fantasy),
+                usvg::FontFamily::Monospace => families.push(Family::Monospace),
+                usvg::FontFamily::Named(name) => {
+                    if let Some(to) = aliases.get(&name.to_lowercase()) {
+                        families.push(generic(to).unwrap_or(Family::Name(to)));
+                    }
+                    families.push(Family::Name(name));
+                }
+            }
+        }
+        // usvg's default selector ends with serif as the last resort too
+        families.push(Family::Serif);
+
+        let stretch = match font.stretch() {
+            usvg::FontStretch::UltraCondensed => usvg::fontdb::Stretch::UltraCondensed,
+            usvg::FontStretch::ExtraCondensed => usvg::fontdb::Stretch::ExtraCondensed,
+            usvg::FontStretch::Condensed => usvg::fontdb::Stretch::Condensed,
+            usvg::FontStretch::SemiCondensed => usvg::fontdb::Stretch::SemiCondensed,
+            usvg::FontStretch::Normal => usvg::fontdb::Stretch::Normal,
+            usvg::FontStretch::SemiExpanded => usvg::fontdb::Stretch::SemiExpanded,
+            usvg::FontStretch::Expanded => usvg::fontdb::Stretch::Expanded,
+            usvg::FontStretch::ExtraExpanded => usvg::fontdb::Stretch::ExtraExpanded,
+            usvg::FontStretch::UltraExpanded => usvg::fontdb::Stretch::UltraExpanded,
+        };
+        let style = match font.style() {
+            usvg::FontStyle::Normal => usvg::fontdb::Style::Normal,
+            usvg::FontStyle::Italic => usvg::fontdb::Style::Italic,
+            usvg::FontStyle::Oblique => usvg::fontdb::Style::Oblique,
+        };
+        fontdb.query(&usvg::fontdb::Query {
+            families: &families,
+            weight: usvg::fontdb::Weight(font.weight()),
+            stretch,
+            style,
+        })
+    })
+}
+
+/// Builds the usvg parse options corresponding to `opts`.
+fn usvg_options(opts: &RBOptions) -> usvg::Options<'static> {
+    let mut opt = usvg::Options::default();
+
+    if opts.skip_data_images || opts.max_data_image_bytes > 0 {
+        let skip = opts.skip_data_images;
+        let max_bytes = opts.max_data_image_bytes;
+        let default_resolver = usvg::ImageHrefResolver::default_data_resolver();
+        opt.image_href_resolver.resolve_data = Box::new(move |mime, data, o| {
+            if skip {
+                add_warning(format!("skipped embedded {mime} image"));
+                None
+            } else if max_bytes > 0 && data.len() > max_bytes {
+                add_warning(format!(
+                    "skipped embedded {mime} image of {} bytes (limit {max_bytes})",
+                    data.len()
+                ));
+                None
+            } else {
+                default_resolver(mime, data, o)
+            }
+        });
+    }
+
+    opt.resources_dir = opts.base_path.clone();
+    opt.style_sheet = opts.style_sheet.clone();
+    if opts.default_fill_rule == RBFillRule::EvenOdd {
+        // Injected as the first rule so the caller's and the document's CSS win
+        let css = opt.style_sheet.take().unwrap_or_default();
+        opt.style_sheet = Some(format!("svg {{ fill-rule: evenodd; clip-rule: evenodd }}\n{css}"));
+    }
+    if opts.aliased_text {
+        opt.text_rendering = usvg::TextRendering::OptimizeSpeed;
+    }
+    if opts.crisp_edges {
+        // Appended last so it wins over the caller's CSS
+        opt.shape_rendering = usvg::ShapeRendering::CrispEdges;
+        let css = opt.style_sheet.take().unwrap_or_default();
+        opt.style_sheet = Some(format!("{css}\n* {{ shape-rendering: crispEdges }}"));
+    }
+    if let Some(languages) = &opts.languages {
+        opt.languages = languages.clone();
+    }
+    if let Some(fontdb) = effective_fontdb(opts) {
+        opt.fontdb = fontdb;
+    }
+    let aliases = FONT_ALIASES.read().unwrap_or_else(|e| e.into_inner()).clone();
+    if let Some(aliases) = aliases.filter(|a| !a.is_empty()) {
+        opt.font_resolver.select_font = aliased_font_selector(aliases);
+    }
+
+    opt
+}
+
+// ============================================================================
+// THREAD POOL
+// ============================================================================
+//
+// Parallel work runs on a dedicated rayon pool instead of the global one so
+// that host applications with their own pools can cap our thread usage.
+// The pool is built lazily on the first parallel render.
+
+/// Requested pool size; 0 means one thread per core.
+static THREAD_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// The library's rayon pool, built on first use.
+static THREAD_POOL: OnceLock<rayon::ThreadPool> = OnceLock::new();
+
+/// Returns the library's thread pool, building it on first use.
+fn thread_pool() -> &'static rayon::ThreadPool {
+    THREAD_POOL.get_or_init(|| {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(THREAD_COUNT.load(Ordering::Relaxed))
+            .thread_name(|i| format!("resvg-bridge-{i}"))
+            .build()
+            .expect("failed to build render thread pool")
+    })
+}
+
+/// Sets the number of threads used for parallel rendering.
+///
+/// The library uses its own thread pool rather than rayon's global one.
+/// The pool is created by the first parallel render (e.g. `rb_render_svg_to_rgba_batch()`),
+/// so this must be called before then; later calls have no effect.
+///
+/// # Arguments
+/// * `n` - Number of worker threads (0 = one per CPU core)
+///
+/// # Returns
+/// * `true` if the setting will take effect
+/// * `false` if the pool has already been created (error is set)
+#[no_mangle]
+pub extern "C" fn rb_set_thread_count(n: usize) -> bool {
+    ffi_guard(false, || {
+        if THREAD_POOL.get().is_some() {
+            set_err("thread pool already initialized".into());
+            return false;
+        }
+        THREAD_COUNT.store(n, Ordering::Relaxed);
+        true
+    })
+}
+
+// ============================================================================
+// RENDERING FUNCTIONS
+// ============================================================================
+//
+// Group opacity and isolation follow the SVG and Compositing specs, which is
+// also what browsers do, so there is deliberately no option to change them:
+//
+//   * `opacity` on a group applies to the group as a whole. Its children are
+//     composited together first and the result is faded once, so children
+//     that overlap don't show through each other (unlike `fill-opacity` on
+//     each child).
+//   * `mix-blend-mode` blends with everything already painted, up to the
+//     nearest isolated ancestor. A group is isolated when it has
+//     `isolation: isolate`, opacity below 1, a filter, a mask or a clip path.
+//   * The root element behaves like an isolated group over a transparent
+//     canvas, as in a browser with a transparent page background; use a
+//     background function to blend against a color instead.
+//
+// These are usvg's and resvg's own semantics, not settings of this library;
+// usvg has no options that alter them.
+
+/// A parsed SVG tree plus the render-time settings derived from its source.
+///
+/// Derefs to the underlying `Tree`.
+#[derive(Clone)]
+struct ParsedSvg {
+    tree: Tree,
+    /// Clip rectangle in tree-size units, when clipping was requested
+    clip: Option<tiny_skia::Rect>,
+    /// Opaque backing drawn behind the rendered pixels, when requested
+    backing: Option<Checkerboard>,
+}
+
+impl From<Tree> for ParsedSvg {
+    fn from(tree: Tree) -> Self {
+        ParsedSvg { tree, clip: None, backing: None }
+    }
+}
+
+impl std::ops::Deref for ParsedSvg {
+    type Target = Tree;
+
+    fn deref(&self) -> &Tree {
+        &self.tree
+    }
+}
+
+/// Parses raw SVG bytes into a tree.
+///
+/// # Arguments
+/// * `svg_bytes` - The SVG document
+/// * `opts` - Options affecting parsing (images, fonts, ...)
+fn parse_svg(svg_bytes: &[u8], opts: &RBOptions) -> Result<ParsedSvg, String> {
+    if opts.strict_features {
+        let found = find_unsupported_features(svg_bytes)?;
+        if !found.is_empty() {
+            let list: Vec<String> = found.iter().map(|f| f.to_string()).collect();
+            return Err(format!("unsupported features: {}", list.join(", ")));
+        }
+    }
+
+    let sized;
+    let (width, height) = opts.assumed_size.unwrap_or((100.0, 100.0));
+    let svg_bytes = match with_assumed_size(svg_bytes, width, height) {
+        Some(doc) => {
+            add_warning(format!("SVG has no width, height or viewBox; assuming {width}x{height}"));
+            sized = doc;
+            sized.as_bytes()
+        }
+        None => svg_bytes,
+    };
+
+    let opt = usvg_options(opts);
+    let tree = Tree::from_data(svg_bytes, &opt).map_err(|e| format!("parse error: {e}"))?;
+    let clip = if opts.clip_to_viewbox { viewbox_rect(svg_bytes, tree.size()) } else { None };
+    Ok(ParsedSvg { tree, clip, backing: opts.checkerboard })
+}
+
+/// Returns the document with `width` and `height` added to the root element
+/// if it declares none of `width`, `height` and `viewBox`, or `None` if the
+/// SVG has a size of its own (or can't be inspected; usvg reports that).
+fn with_assumed_size(svg_bytes: &[u8], width: f32, height: f32) -> Option<String> {
+    let decompressed;
+    let svg_bytes = if svg_bytes.starts_with(&[0x1f, 0x8b]) {
+        decompressed = usvg::decompress_svgz(svg_bytes).ok()?;
+        &decompressed[..]
+    } else {
+        svg_bytes
+    };
+    // Nearly every SVG has a viewBox; skip the XML parse for those
+    if svg_bytes.windows(7).any(|w| w == b"viewBox") {
+        return None;
+    }
+    let text = std::str::from_utf8(svg_bytes).ok()?;
+    let doc = usvg::roxmltree::Document::parse(text).ok()?;
+    let root = doc.root_element();
+    if ["width", "height", "viewBox"].iter().any(|a| root.has_attribute(*a)) {
+        return None;
+    }
+
+    // Insert right after the tag name
+    let start = root.range().start;
+    let name_end = text[start + 1..].find(|c: char| c.is_whitespace() || c == '>' || c == '/')? + start + 1;
+    let size = format!(r#" width="{width}" height="{height}""#);
+    Some(apply_edits(text, vec![(name_end..name_end, size)]))
+}
+
+/// Locates the root `viewBox` within the tree's size, following the root's
+/// `preserveAspectRatio` the same way usvg does.
+///
+/// Returns `None` if the SVG has no valid `viewBox`.
+fn viewbox_rect(svg_bytes: &[u8], size: usvg::Size) -> Option<tiny_skia::Rect> {
+    let (vb, aspect) = root_viewbox(svg_bytes)?;
+    let [x, y, w, h] = aspect_rect(aspect, vb.w, vb.h, size.width() as f64, size.height() as f64);
+    tiny_skia::Rect::from_xywh(x as f32, y as f32, w as f32, h as f32)
+}
+
+/// Reads the root element's `viewBox` and `preserveAspectRatio`.
+///
+/// Returns `None` if the SVG has no valid `viewBox`.
+fn root_viewbox(svg_bytes: &[u8]) -> Option<(svgtypes::ViewBox, svgtypes::AspectRatio)> {
+    use std::str::FromStr;
+
+    let decompressed;
+    let svg_bytes = if svg_bytes.starts_with(&[0x1f, 0x8b]) {
+        decompressed = usvg::decompress_svgz(svg_bytes).ok()?;
+        &decompressed[..]
+    } else {
+        svg_bytes
+    };
+    let text = std::str::from_utf8(svg_bytes).ok()?;
+    let doc = usvg::roxmltree::Document::parse(text).ok()?;
+    let root = doc.root_element();
+
+    let vb = svgtypes::ViewBox::from_str(root.attribute("viewBox")?).ok()?;
+    if !(vb.w > 0.0 && vb.h > 0.0) {
+        return None;
+    }
+    let aspect = root
+        .attribute("preserveAspectRatio")
+        .and_then(|v| svgtypes::AspectRatio::from_str(v).ok())
+        .unwrap_or_default();
+    Some((vb, aspect))
+}
+
+/// Places a `src_w × src_h` box in a `dst_w × dst_h` viewport following
+/// `preserveAspectRatio` rules, returning its `[x, y, width, height]`.
+fn aspect_rect(aspect: svgtypes::AspectRatio, src_w: f64, src_h: f64, dst_w: f64, dst_h: f64) -> [f64; 4] {
+    use svgtypes::Align;
+
+    let (w, h) = (dst_w, dst_h);
+    if aspect.align == Align::None {
+        return [0.0, 0.0, w, h];
+    }
+
+    let (sx, sy) = (w / src_w, h / src_h);
+    let s = if aspect.slice { sx.max(sy) } else { sx.min(sy) };
+    let (vw, vh) = (src_w * s, src_h * s);
+    let (x, y) = match aspect.align {
+        Align::None | Align::XMinYMin => (0.0, 0.0),
+        Align::XMidYMin => ((w - vw) / 2.0, 0.0),
+        Align::XMaxYMin => (w - vw, 0.0),
+        Align::XMinYMid => (0.0, (h - vh) / 2.0),
+        Align::XMidYMid => ((w - vw) / 2.0, (h - vh) / 2.0),
+        Align::XMaxYMid => (w - vw, (h - vh) / 2.0),
+        Align::XMinYMax => (0.0, h - vh),
+        Align::XMidYMax => ((w - vw) / 2.0, h - vh),
+        Align::XMaxYMax => (w - vw, h - vh),
+    };
+    [x, y, vw, vh]
+}
+
+/// Computes the transform that maps the tree onto a `width × height` output.
+///
+/// # Arguments
+/// * `tree` - The parsed SVG
+/// * `width` - Output width in pixels
+/// * `height` - Output height in pixels
+/// * `opts` - Rendering options
+fn fit_transform(tree: &Tree, width: u32, height: u32, opts: &RBOptions) -> Result<Transform, String> {
+    // Calculate scaling factors to fit SVG into requested dimensions
+    let size = tree.size();
+    let src_w = opts.override_width.unwrap_or(size.width());
+    let src_h = opts.override_height.unwrap_or(size.height());
+    let sx = width as f32 / src_w.max(1.0);
+    let sy = height as f32 / src_h.max(1.0);
+    checked_scale(sx, sy)
+}
+
+/// Builds a scale transform, rejecting factors tiny-skia can't render with.
+///
+/// Zero, subnormal, NaN or infinite factors would otherwise produce empty or
+/// garbage output without any error.
+fn checked_scale(sx: f32, sy: f32) -> Result<Transform, String> {
+    if !sx.is_normal() || !sy.is_normal() {
+        return Err(format!("invalid scale: {sx:e} x {sy:e}"));
+    }
+    Ok(Transform::from_scale(sx, sy))
+}
+
+/// Renders a parsed tree into a newly allocated pixmap, stretching it to fill
+/// the requested dimensions.
+///
+/// # Arguments
+/// * `tree` - The parsed SVG
+/// * `width` - Output width in pixels (must be > 0)
+/// * `height` - Output height in pixels (must be > 0)
+/// * `opts` - Rendering options
+fn render_tree(tree: &ParsedSvg, width: u32, height: u32, opts: &RBOptions) -> Result<Pixmap, String> {
+    let (width, height) = opts.clamp_size(width, height);
+    render_tree_with_transform(tree, width, height, fit_transform(tree, width, height, opts)?)
+}
+
+/// Renders a parsed tree into a newly allocated pixmap using an explicit transform.
+///
+/// # Arguments
+/// * `tree` - The parsed SVG
+/// * `width` - Output width in pixels (must be > 0)
+/// * `height` - Output height in pixels (must be > 0)
+/// * `ts` - Transform from SVG user space to output pixels
+fn render_tree_with_transform(tree: &ParsedSvg, width: u32, height: u32, ts: Transform) -> Result<Pixmap, String> {
+    // Allocate target buffer for the rendered image
+    let mut pixmap = Pixmap::new(width, height).ok_or("alloc pixmap failed")?;
+
+    render_into(tree, ts, &mut pixmap.as_mut())?;
+
+    Ok(pixmap)
+}
+
+/// Renders a parsed tree onto an existing (normally transparent) canvas.
+///
+/// # Arguments
+/// * `tree` - The parsed SVG
+/// * `ts` - Transform from SVG user space to canvas pixels
+/// * `canvas` - The pixels to draw onto
+fn render_into(tree: &ParsedSvg, ts: Transform, canvas: &mut tiny_skia::PixmapMut) -> Result<(), String> {
+    // Render the SVG tree to the pixmap
+    resvg::render(tree, ts, canvas);
+
+    if let Some(clip) = tree.clip {
+        let mut mask = tiny_skia::Mask::new(canvas.width(), canvas.height()).ok_or("alloc mask failed")?;
+        let path = tiny_skia::PathBuilder::from_rect(clip);
+        mask.fill_path(&path, tiny_skia::FillRule::Winding, true, ts);
+        canvas.apply_mask(&mask);
+    }
+
+    if let Some(backing) = tree.backing {
+        backing.fill_behind(canvas, (ts.tx, ts.ty));
+    }
+
+    Ok(())
+}
+
+/// Validates the raw FFI arguments shared by the render functions, then
+/// parses and renders the SVG.
+///
+/// # Safety
+/// `svg_ptr` must be null or valid for `svg_len` bytes.
+unsafe fn render_svg_raw(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    width: u32,
+    height: u32,
+    opts: &RBOptions,
+) -> Result<Pixmap, String> {
+    // Validate input parameters
+    if svg_ptr.is_null() || svg_len == 0 || width == 0 || height == 0 {
+        return Err("invalid args".into());
+    }
+
+    // Convert raw pointer to byte slice
+    let svg_bytes = unsafe { slice::from_raw_parts(svg_ptr, svg_len) };
+
+    render_svg_bytes(svg_bytes, width, height, opts)
+}
+
+/// Parses and renders an SVG. This is the core shared by the FFI
+/// functions and the safe [`api`].
+fn render_svg_bytes(svg_bytes: &[u8], width: u32, height: u32, opts: &RBOptions) -> Result<Pixmap, String> {
+    if svg_bytes.is_empty() || width == 0 || height == 0 {
+        return Err("invalid args".into());
+    }
+
+    let tree = parse_svg(svg_bytes, opts)?;
+    render_tree(&tree, width, height, opts)
+}
+
+/// Renders an SVG file to RGBA pixel data.
+/// 
+/// This is the main function for converting SVG content to raster images.
+/// The SVG is scaled to fit the requested dimensions while maintaining aspect ratio.
+/// 
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `width` - Desired output width in pixels (must be > 0)
+/// * `height` - Desired output height in pixels (must be > 0)
+/// 
+/// # Returns
+/// * `RBImage` struct containing the rendered pixel data
+/// * If an error occurs, returns an image with null pointer and zero dimensions
+/// 
+/// # Safety
+/// The caller must ensure `svg_ptr` points to valid SVG data for `svg_len` bytes.
+/// The returned image must be freed with `rb_free_image()` when no longer needed.
+/// 
+/// # Error Handling
+/// Errors are stored in thread-local storage and can be retrieved with:
+/// - `rb_last_error()` - Get pointer to error message
+/// - `rb_last_error_copy()` - Copy error message to buffer
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_rgba(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    width: u32,
+    height: u32,
+) -> RBImage {
+    rb_render_svg_to_rgba_with_options(svg_ptr, svg_len, width, height, std::ptr::null())
+}
+
+/// Renders an SVG file to RGBA pixel data using the given options.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `width` - Desired output width in pixels (must be > 0)
+/// * `height` - Desired output height in pixels (must be > 0)
+/// * `opts` - Options from `rb_options_new()` (null = the defaults from
+///   `rb_set_default_options()`)
+///
+/// # Returns
+/// * Same as `rb_render_svg_to_rgba()`
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba()`; `opts` must be null or a
+/// valid pointer from `rb_options_new()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_rgba_with_options(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    width: u32,
+    height: u32,
+    opts: *const RBOptions,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        // Clear any previous error for this thread
+        clear_err();
+
+        let default_opts;
+        let opts = match unsafe { opts.as_ref() } {
+            Some(opts) => opts,
+            None => {
+                default_opts = default_options();
+                &default_opts
+            }
+        };
+
+        match unsafe { render_svg_raw(svg_ptr, svg_len, width, height, opts) } {
+            Ok(pixmap) => RBImage::from_render(pixmap, opts),
+            Err(e) => {
+                set_err(e);
+                RBImage::empty()
+            }
+        }
+    })
+}
+
+/// Per-caller error state for the `rb_*_ctx()` functions. Opaque to C callers.
+///
+/// The thread-local error functions assume one logical caller per OS thread,
+/// which doesn't hold for async runtimes that move a task between threads
+/// between the render call and the error lookup. A context carries the
+/// error with it instead. One context must not be used by two calls at the
+/// same time; give each task its own.
+pub struct RBContext {
+    /// Message of the last failed call, null-terminated for C
+    last_error: Option<CString>,
+    /// Category of `last_error`
+    last_error_code: RBErrorCode,
+    /// Warnings raised by the last call, null-terminated for C
+    last_warning: Option<CString>,
+}
+
+impl Default for RBContext {
+    fn default() -> Self {
+        RBContext { last_error: None, last_error_code: RBErrorCode::None, last_warning: None }
+    }
+}
+
+impl RBContext {
+    /// Records the outcome of a call, panics included.
+    fn record<T>(&mut self, result: std::thread::Result<Result<T, String>>) -> Option<T> {
+        let result = result.unwrap_or_else(|payload| Err(format!("panic: {}", panic_message(&*payload))));
+        match result {
+            Ok(v) => {
+                self.last_error = None;
+                self.last_error_code = RBErrorCode::None;
+                Some(v)
+            }
+            Err(e) => {
+                self.last_error_code = RBErrorCode::classify(&e);
+                self.last_error = Some(CString::new(e.replace('\0', " ")).unwrap_or_default());
+                None
+            }
+        }
+    }
+}
+
+/// Allocates a new context with no error recorded.
+///
+/// # Returns
+/// * A pointer that must be released with `rb_context_free()`
+#[no_mangle]
+pub extern "C" fn rb_context_new() -> *mut RBContext {
+    ffi_guard(std::ptr::null_mut(), || Box::into_raw(Box::default()))
+}
+
+/// Frees a context created by `rb_context_new()`.
+///
+/// # Arguments
+/// * `ctx` - The context to free (null is ignored)
+///
+/// # Safety
+/// `ctx` must come from `rb_context_new()` and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rb_context_free(ctx: *mut RBContext) {
+    ffi_guard((), || {
+        if !ctx.is_null() {
+            drop(unsafe { Box::from_raw(ctx) });
+        }
+    })
+}
+
+/// Gets the error message of the last failed call made with a context.
+///
+/// # Arguments
+/// * `ctx` - The context (null returns null)
+///
+/// # Returns
+/// * A pointer to a null-terminated C string containing the error message
+/// * `std::ptr::null()` if the last call with `ctx` succeeded
+///
+/// # Safety
+/// `ctx` must be null or a valid pointer from `rb_context_new()`. The
+/// returned pointer is owned by the context and stays valid until the next
+/// call that uses `ctx`, on any thread.
+#[no_mangle]
+pub unsafe extern "C" fn rb_context_last_error(ctx: *const RBContext) -> *const c_char {
+    ffi_guard(std::ptr::null(), || {
+        unsafe { ctx.as_ref() }
+            .and_then(|ctx| ctx.last_error.as_ref())
+            .map_or(std::ptr::null(), |e| e.as_ptr())
+    })
+}
+
+/// Gets the category of the last error recorded in a context.
+///
+/// # Arguments
+/// * `ctx` - The context (null returns `RBErrorCode::InvalidArgs`)
+///
+/// # Returns
+/// * The error code matching `rb_context_last_error()`
+/// * `RBErrorCode::None` if the last call with `ctx` succeeded
+///
+/// # Safety
+/// `ctx` must be null or a valid pointer from `rb_context_new()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_context_last_error_code(ctx: *const RBContext) -> RBErrorCode {
+    ffi_guard(RBErrorCode::Panic, || {
+        unsafe { ctx.as_ref() }.map_or(RBErrorCode::InvalidArgs, |ctx| ctx.last_error_code)
+    })
+}
+
+/// Gets the warnings raised by the last call made with a context.
+///
+/// # Arguments
+/// * `ctx` - The context (null returns null)
+///
+/// # Returns
+/// * A pointer to a null-terminated C string with the warnings, separated
+///   by `"; "` like `rb_last_warning()`
+/// * `std::ptr::null()` if the last call with `ctx` raised none
+///
+/// # Safety
+/// `ctx` must be null or a valid pointer from `rb_context_new()`. The
+/// returned pointer is owned by the context and stays valid until the next
+/// call that uses `ctx`, on any thread.
+#[no_mangle]
+pub unsafe extern "C" fn rb_context_last_warning(ctx: *const RBContext) -> *const c_char {
+    ffi_guard(std::ptr::null(), || {
+        unsafe { ctx.as_ref() }
+            .and_then(|ctx| ctx.last_warning.as_ref())
+            .map_or(std::ptr::null(), |w| w.as_ptr())
+    })
+}
+
+/// Renders an SVG like `rb_render_svg_to_rgba()`, reporting errors through
+/// a context instead of thread-local storage.
+///
+/// The outcome and any warnings are recorded only in `ctx` (see
+/// `rb_context_last_error()` and `rb_context_last_warning()`); the
+/// thread-local error and warning state is left alone, so the call may
+/// resume on any thread before checking them. The one exception is a null
+/// `ctx`: there is nowhere else to report it, so the call fails with an
+/// "invalid args" thread-local error.
+///
+/// # Arguments
+/// * `ctx` - Context from `rb_context_new()` (must not be null)
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `width` - Desired output width in pixels (must be > 0)
+/// * `height` - Desired output height in pixels (must be > 0)
+///
+/// # Returns
+/// * Same as `rb_render_svg_to_rgba()`
+///
+/// # Safety
+/// `ctx` must be a valid pointer from `rb_context_new()` that no other call
+/// is using; `svg_ptr` must be valid for `svg_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_rgba_ctx(
+    ctx: *mut RBContext,
+    svg_ptr: *const u8,
+    svg_len: usize,
+    width: u32,
+    height: u32,
+) -> RBImage {
+    let Some(ctx) = (unsafe { ctx.as_mut() }) else {
+        clear_err();
+        set_err("invalid args: null context".into());
+        return RBImage::empty();
+    };
+    // Collect this call's warnings for the context and put the thread's back
+    let thread_warning = LAST_WARN.with(|w| w.take());
+    let opts = default_options();
+    let result = catch_panic(|| unsafe {
+        render_svg_raw(svg_ptr, svg_len, width, height, &opts).map(|pixmap| RBImage::from_render(pixmap, &opts))
+    });
+    let warning = LAST_WARN.with(|w| w.replace(thread_warning));
+    ctx.last_warning = warning.map(|w| CString::new(w.replace('\0', " ")).unwrap_or_default());
+    ctx.record(result).unwrap_or_else(RBImage::empty)
+}
+
+/// Most worker threads of timed renders allowed at once, so at most this
+/// many can be left behind by timeouts
+const MAX_RENDER_WORKERS: usize = 4;
+
+/// Worker threads of timed renders that haven't finished yet, whether their
+/// caller is still waiting or timed out
+static RENDER_WORKERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Renders an SVG file like `rb_render_svg_to_rgba()`, giving up after a
+/// wall-clock time limit.
+///
+/// The render runs on a worker thread with its own copy of the SVG. If it
+/// finishes in time its image is returned; otherwise the call fails with
+/// `RBErrorCode::Timeout` and the worker is abandoned. Threads can't be
+/// killed safely, so an abandoned worker keeps its CPU time, its copy of the
+/// SVG and its pixmap until the render finishes on its own, then frees them
+/// and exits. To bound that cost, at most 4 workers may be running at once,
+/// counting both those still awaited and those abandoned; while that many
+/// are, further calls fail immediately with `RBErrorCode::Timeout` instead of
+/// starting another. Warnings raised by the worker are not reported.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `width` - Desired output width in pixels (must be > 0)
+/// * `height` - Desired output height in pixels (must be > 0)
+/// * `timeout_ms` - Time limit in milliseconds (must be > 0)
+///
+/// # Returns
+/// * `RBImage` struct containing the rendered pixel data
+/// * If an error occurs or the time limit passes, returns an image with null
+///   pointer and zero dimensions
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_timeout(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    width: u32,
+    height: u32,
+    timeout_ms: u32,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        if svg_ptr.is_null() || svg_len == 0 || width == 0 || height == 0 || timeout_ms == 0 {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        }
+        // Reserve the worker's slot before spawning it, so concurrent calls
+        // can't all pass the check; the worker releases it when it exits
+        let reserved = RENDER_WORKERS
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| (n < MAX_RENDER_WORKERS).then_some(n + 1));
+        if reserved.is_err() {
+            set_err("timeout: too many timed renders are still running".into());
+            return RBImage::empty();
+        }
+
+        let svg_bytes = unsafe { slice::from_raw_parts(svg_ptr, svg_len) }.to_vec();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let spawned = std::thread::Builder::new().name("rb-timed-render".into()).spawn(move || {
+            let result = catch_panic(|| render_svg_bytes(&svg_bytes, width, height, &default_options()))
+                .unwrap_or_else(|payload| Err(format!("panic: {}", panic_message(&*payload))));
+            let _ = tx.send(result);
+            RENDER_WORKERS.fetch_sub(1, Ordering::AcqRel);
+        });
+        if let Err(e) = spawned {
+            RENDER_WORKERS.fetch_sub(1, Ordering::AcqRel);
+            set_err(format!("failed to start render thread: {e}"));
+            return RBImage::empty();
+        }
+
+        let result = match rx.recv_timeout(std::time::Duration::from_millis(timeout_ms as u64)) {
+            Ok(result) => result,
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => Err(format!("timeout: render exceeded {timeout_ms} ms")),
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => Err("render thread exited without a result".into()),
+        };
+
+        match result {
+            Ok(pixmap) => RBImage::from_pixmap(pixmap),
+            Err(e) => {
+                set_err(e);
+                RBImage::empty()
+            }
+        }
+    })
+}
+
+/// Renders an SVG file like `rb_render_svg_to_rgba_with_options()` and reports
+/// the exact transform that was applied.
+///
+/// The matrix maps SVG user space (the root coordinate system, after the
+/// viewBox is applied) to output pixels, including every fit/size option.
+/// It is written as six floats `[a, b, c, d, e, f]` in SVG `matrix()` order:
+///
+/// ```text
+/// x_px = a * x + c * y + e
+/// y_px = b * x + d * y + f
+/// ```
+///
+/// Invert it to map output pixel coordinates back into SVG space.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `width` - Desired output width in pixels (must be > 0)
+/// * `height` - Desired output height in pixels (must be > 0)
+/// * `opts` - Options from `rb_options_new()` (null = the defaults from
+///   `rb_set_default_options()`)
+/// * `out_matrix` - Receives 6 floats; written only on success (must not be null)
+///
+/// # Returns
+/// * Same as `rb_render_svg_to_rgba()`
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba_with_options()`; `out_matrix`
+/// must be valid for writing 6 floats.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_rgba_with_matrix_out(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    width: u32,
+    height: u32,
+    opts: *const RBOptions,
+    out_matrix: *mut f32,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        if svg_ptr.is_null() || svg_len == 0 || width == 0 || height == 0 || out_matrix.is_null() {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        }
+
+        let default_opts = default_options();
+        let opts = unsafe { opts.as_ref() }.unwrap_or(&default_opts);
+        let svg_bytes = unsafe { slice::from_raw_parts(svg_ptr, svg_len) };
+
+        let (width, height) = opts.clamp_size(width, height);
+        let result = parse_svg(svg_bytes, opts).and_then(|tree| {
+            let ts = fit_transform(&tree, width, height, opts)?;
+            render_tree_with_transform(&tree, width, height, ts).map(|p| (p, ts))
+        });
+
+        match result {
+            Ok((pixmap, ts)) => {
+                let matrix = [ts.sx, ts.ky, ts.kx, ts.sy, ts.tx, ts.ty];
+                unsafe { std::ptr::copy_nonoverlapping(matrix.as_ptr(), out_matrix, 6) };
+                RBImage::from_render(pixmap, opts)
+            }
+            Err(e) => {
+                set_err(e);
+                RBImage::empty()
+            }
+        }
+    })
+}
+
+/// Renders an SVG file with an explicit transform given as a CSS `matrix()`.
+///
+/// `css_matrix` is a single `matrix(a, b, c, d, e, f)` function, with commas
+/// and/or spaces between the numbers, as written in CSS or SVG `transform`
+/// values. It maps SVG user space (after the viewBox is applied) to output
+/// pixels exactly as `rb_render_svg_to_rgba_with_matrix_out()` reports, and
+/// replaces the usual stretch-to-fit; content mapped outside the
+/// `width × height` canvas is cut off.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `width` - Output width in pixels (must be > 0)
+/// * `height` - Output height in pixels (must be > 0)
+/// * `css_matrix` - Null-terminated `matrix(...)` string (must not be null)
+///
+/// # Returns
+/// * `RBImage` struct containing the rendered pixel data
+/// * If an error occurs (including a string that isn't exactly one
+///   `matrix()` with finite values), returns an image with null pointer and
+///   zero dimensions
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba()`; `css_matrix` must be a
+/// valid null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_rgba_with_css_transform(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    width: u32,
+    height: u32,
+    css_matrix: *const c_char,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        if svg_ptr.is_null() || svg_len == 0 || width == 0 || height == 0 || css_matrix.is_null() {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        }
+        let text = unsafe { CStr::from_ptr(css_matrix) }.to_string_lossy();
+        let Some(ts) = parse_css_matrix(&text) else {
+            set_err(format!("invalid args: not a matrix() function: {text:?}"));
+            return RBImage::empty();
+        };
+
+        let svg_bytes = unsafe { slice::from_raw_parts(svg_ptr, svg_len) };
+        match parse_svg(svg_bytes, &default_options()).and_then(|tree| render_tree_with_transform(&tree, width, height, ts)) {
+            Ok(pixmap) => RBImage::from_pixmap(pixmap),
+            Err(e) => {
+                set_err(e);
+                RBImage::empty()
+            }
+        }
+    })
+}
+
+/// Parses a lone `matrix(a, b, c, d, e, f)` with finite coefficients.
+fn parse_css_matrix(text: &str) -> Option<Transform> {
+    let mut tokens = svgtypes::TransformListParser::from(text.trim());
+    let Some(Ok(svgtypes::TransformListToken::Matrix { a, b, c, d, e, f })) = tokens.next() else {
+        return None;
+    };
+    let values = [a, b, c, d, e, f].map(|v| v as f32);
+    if tokens.next().is_some() || !values.iter().all(|v| v.is_finite()) {
+        return None;
+    }
+    let [a, b, c, d, e, f] = values;
+    Some(Transform::from_row(a, b, c, d, e, f))
+}
+
+/// Renders an SVG file to RGBA pixel data with a fixed seed for any
+/// randomized rendering steps.
+///
+/// Renders with the default options (see `rb_set_default_options()`) and
+/// their seed replaced by `seed`, as `rb_options_set_seed()` would set it.
+/// The seed is currently a no-op (rendering is fully deterministic), so this
+/// behaves exactly like `rb_render_svg_to_rgba()` whatever the seed.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `width` - Desired output width in pixels (must be > 0)
+/// * `height` - Desired output height in pixels (must be > 0)
+/// * `seed` - Seed for randomized rendering steps (currently unused)
+///
+/// # Returns
+/// * Same as `rb_render_svg_to_rgba()`
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_rgba_with_seed(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    width: u32,
+    height: u32,
+    seed: u64,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        let opts = RBOptions { seed, ..default_options() };
+        rb_render_svg_to_rgba_with_options(svg_ptr, svg_len, width, height, &opts)
+    })
+}
+
+/// Converts a computed floating-point output dimension into whole pixels,
+/// rounding up.
+fn output_dim(v: f32) -> Result<u32, String> {
+    let v = v.ceil();
+    if !v.is_finite() || v < 1.0 || v > u32::MAX as f32 {
+        return Err(format!("computed output size {v} is out of range"));
+    }
+    Ok(v as u32)
+}
+
+/// Renders an SVG file at its declared (natural) size.
+///
+/// The output size is the SVG's declared size converted from CSS pixels
+/// (96 per inch) to the requested DPI and rounded up:
+/// `width = ceil(svg_width * dpi / 96)`, and likewise for the height.
+/// Use `dpi = 96` for a 1:1 render.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `dpi` - Target resolution in dots per inch (must be > 0)
+///
+/// # Returns
+/// * `RBImage` whose `width`/`height` are the computed dimensions
+/// * If an error occurs, returns an image with null pointer and zero dimensions
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_rgba_at_natural_size(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    dpi: f32,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        if svg_ptr.is_null() || svg_len == 0 || !dpi.is_finite() || dpi <= 0.0 {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        }
+
+        let svg_bytes = unsafe { slice::from_raw_parts(svg_ptr, svg_len) };
+        let opts = default_options();
+        let result = parse_svg(svg_bytes, &opts).and_then(|tree| {
+            let size = tree.size();
+            let width = output_dim(size.width() * dpi / 96.0)?;
+            let height = output_dim(size.height() * dpi / 96.0)?;
+            render_tree(&tree, width, height, &opts)
+        });
+
+        match result {
+            Ok(pixmap) => RBImage::from_pixmap(pixmap),
+            Err(e) => {
+                set_err(e);
+                RBImage::empty()
+            }
+        }
+    })
+}
+
+/// Renders an SVG for a HiDPI display, sized in logical points.
+///
+/// The output is `round(logical_w * dpr) × round(logical_h * dpr)` physical
+/// pixels, the backing size UI frameworks expect for an icon of
+/// `logical_w × logical_h` points at that device pixel ratio; the SVG is
+/// stretched to fill it like in `rb_render_svg_to_rgba()`. The returned
+/// `width`/`height` are the physical size; the logical size is written to
+/// the out-params and is also remembered for the image until it is freed,
+/// so `rb_image_logical_width()` and `rb_image_logical_height()` can
+/// recover it later.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `logical_w` - Width in logical points (must be > 0)
+/// * `logical_h` - Height in logical points (must be > 0)
+/// * `dpr` - Device pixel ratio, e.g. 2.0 for a Retina display (must be
+///   finite and > 0)
+/// * `out_logical_w` - Receives the logical width on success (null = not needed)
+/// * `out_logical_h` - Receives the logical height on success (null = not needed)
+///
+/// # Returns
+/// * `RBImage` whose `width`/`height` are the physical dimensions
+/// * If an error occurs (including a physical side that rounds to 0),
+///   returns an image with null pointer and zero dimensions; the out-params
+///   are set to 0
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba()`; `out_logical_w` and
+/// `out_logical_h` must each be null or valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_logical(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    logical_w: u32,
+    logical_h: u32,
+    dpr: f32,
+    out_logical_w: *mut u32,
+    out_logical_h: *mut u32,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        let report = |w: u32, h: u32| unsafe {
+            if let Some(out) = out_logical_w.as_mut() {
+                *out = w;
+            }
+            if let Some(out) = out_logical_h.as_mut() {
+                *out = h;
+            }
+        };
+        report(0, 0);
+        if logical_w == 0 || logical_h == 0 || !dpr.is_finite() || dpr <= 0.0 {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        }
+
+        // Round in f64 so e.g. 10 points at 1.1 can't ceil up to 12 pixels
+        let physical = |v: u32| output_dim((v as f64 * dpr as f64).round() as f32);
+        let result = physical(logical_w)
+            .and_then(|w| Ok((w, physical(logical_h)?)))
+            .and_then(|(w, h)| unsafe { render_svg_raw(svg_ptr, svg_len, w, h, &default_options()) });
+
+        match result {
+            Ok(pixmap) => {
+                report(logical_w, logical_h);
+                let img = RBImage::from_pixmap(pixmap);
+                LOGICAL_SIZES
+                    .write()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .get_or_insert_with(std::collections::HashMap::new)
+                    .insert(img.ptr as usize, (logical_w, logical_h));
+                img
+            }
+            Err(e) => {
+                set_err(e);
+                RBImage::empty()
+            }
+        }
+    })
+}
+
+/// Renders an SVG for a HiDPI ("Retina") display.
+///
+/// The output is `round(logical_w * scale_factor) × round(logical_h *
+/// scale_factor)` physical pixels, the way macOS and iOS size assets for a
+/// backing scale factor; see `rb_render_svg_logical()`. The returned
+/// `width`/`height` are the physical size; the logical size is remembered
+/// for the image, so read it back with `rb_image_logical_width()` and
+/// `rb_image_logical_height()`.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `logical_w` - Width in logical points (must be > 0)
+/// * `logical_h` - Height in logical points (must be > 0)
+/// * `scale_factor` - Physical pixels per point: 2.0 for classic Retina,
+///   1.5 and 3.0 are also common (must be finite and > 0)
+///
+/// # Returns
+/// * Same as `rb_render_svg_logical()`
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_rgba_retina(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    logical_w: u32,
+    logical_h: u32,
+    scale_factor: f32,
+) -> RBImage {
+    unsafe {
+        rb_render_svg_logical(svg_ptr, svg_len, logical_w, logical_h, scale_factor, std::ptr::null_mut(), std::ptr::null_mut())
+    }
+}
+
+/// Logical sizes of the images returned by `rb_render_svg_logical()`, keyed
+/// by pixel pointer; `rb_free_image()` removes an image's entry.
+static LOGICAL_SIZES: RwLock<Option<std::collections::HashMap<usize, (u32, u32)>>> = RwLock::new(None);
+
+/// The remembered logical size of `img`, else its physical size.
+fn logical_size(img: &RBImage) -> (u32, u32) {
+    LOGICAL_SIZES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .and_then(|sizes| sizes.get(&(img.ptr as usize)).copied())
+        .unwrap_or((img.width, img.height))
+}
+
+/// Drops the remembered logical size of the image with pixel pointer `key`.
+fn forget_logical_size(key: usize) {
+    // Most images have no entry, so freeing them only takes the shared lock
+    let has_entry = LOGICAL_SIZES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .is_some_and(|sizes| sizes.contains_key(&key));
+    if !has_entry {
+        return;
+    }
+    if let Some(sizes) = LOGICAL_SIZES.write().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        sizes.remove(&key);
+    }
+}
+
+/// Gets the logical width of an image rendered for a HiDPI display.
+///
+/// For images from `rb_render_svg_logical()` or
+/// `rb_render_svg_to_rgba_retina()` this is the `logical_w` that was passed
+/// in. Any other image, including a copy or conversion of one of those, has
+/// no logical size and reports its physical `width`.
+///
+/// # Arguments
+/// * `img` - The image (null returns 0)
+///
+/// # Returns
+/// * The width in logical points
+/// * 0 if `img` is null (error is set)
+///
+/// # Safety
+/// `img` must be null or point to a valid `RBImage`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_logical_width(img: *const RBImage) -> u32 {
+    ffi_guard(0, || {
+        clear_err();
+
+        let Some(img) = (unsafe { img.as_ref() }) else {
+            set_err("invalid args".into());
+            return 0;
+        };
+        logical_size(img).0
+    })
+}
+
+/// Gets the logical height of an image rendered for a HiDPI display.
+///
+/// The height counterpart of `rb_image_logical_width()`.
+///
+/// # Arguments
+/// * `img` - The image (null returns 0)
+///
+/// # Returns
+/// * The height in logical points
+/// * 0 if `img` is null (error is set)
+///
+/// # Safety
+/// `img` must be null or point to a valid `RBImage`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_logical_height(img: *const RBImage) -> u32 {
+    ffi_guard(0, || {
+        clear_err();
+
+        let Some(img) = (unsafe { img.as_ref() }) else {
+            set_err("invalid args".into());
+            return 0;
+        };
+        logical_size(img).1
+    })
+}
+
+/// Renders an SVG file scaled uniformly so its longer side is `max_dim` pixels.
+///
+/// `scale = max_dim / max(svg_width, svg_height)`, and the output is
+/// `ceil(svg_width * scale) × ceil(svg_height * scale)`, so the image always
+/// fits within `max_dim × max_dim` with the SVG's aspect ratio preserved.
+/// Renders use the default options (see `rb_set_default_options()`), so
+/// their size limits from `rb_options_set_size_limits()` still apply and can
+/// shrink the output further, again keeping the aspect ratio.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `max_dim` - Maximum output width/height in pixels (must be > 0)
+///
+/// # Returns
+/// * `RBImage` whose `width`/`height` are the computed dimensions
+/// * If an error occurs, returns an image with null pointer and zero dimensions
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_rgba_max_dim(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    max_dim: u32,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        if svg_ptr.is_null() || svg_len == 0 || max_dim == 0 {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        }
+
+        let svg_bytes = unsafe { slice::from_raw_parts(svg_ptr, svg_len) };
+        let opts = default_options();
+        let result = parse_svg(svg_bytes, &opts).and_then(|tree| {
+            let size = tree.size();
+            let scale = max_dim as f32 / size.width().max(size.height());
+            // Rounding up must never push a side past the limit
+            let width = output_dim(size.width() * scale)?.min(max_dim);
+            let height = output_dim(size.height() * scale)?.min(max_dim);
+            render_tree(&tree, width, height, &opts)
+        });
+
+        match result {
+            Ok(pixmap) => RBImage::from_render(pixmap, &opts),
+            Err(e) => {
+                set_err(e);
+                RBImage::empty()
+            }
+        }
+    })
+}
+
+/// Renders an SVG file so its longest side is `max_side` pixels.
+///
+/// Landscape SVGs come out `max_side` wide, portrait ones `max_side` tall,
+/// and the other side follows the aspect ratio; see
+/// `rb_render_svg_to_rgba_max_dim()`, which this calls. A maximum size set
+/// with `rb_options_set_size_limits()` on the default options caps
+/// `max_side`.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `max_side` - Length of the longer output side in pixels (must be > 0)
+///
+/// # Returns
+/// * `RBImage` whose `width`/`height` are the computed dimensions
+/// * If an error occurs (including an output too large to allocate), returns
+///   an image with null pointer and zero dimensions
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_longest(svg_ptr: *const u8, svg_len: usize, max_side: u32) -> RBImage {
+    unsafe { rb_render_svg_to_rgba_max_dim(svg_ptr, svg_len, max_side) }
+}
+
+/// How `rb_render_svg_exact()` fits the SVG into the output, named after the
+/// SVG `preserveAspectRatio` values.
+///
+/// `XMin`/`XMid`/`XMax` align the left edges, centers or right edges and
+/// `YMin`/`YMid`/`YMax` the top edges, centers or bottom edges. The plain
+/// modes scale uniformly until the whole SVG fits (`meet`), leaving
+/// transparent bands on the other axis; the `Slice` modes scale uniformly
+/// until the output is covered, cropping the overflow.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RBScaleMode {
+    /// Stretch each axis independently to fill the output, like `rb_render_svg_to_rgba()`
+    None = 0,
+    /// `xMinYMin meet`
+    XMinYMin = 1,
+    /// `xMidYMin meet`
+    XMidYMin = 2,
+    /// `xMaxYMin meet`
+    XMaxYMin = 3,
+    /// `xMinYMid meet`
+    XMinYMid = 4,
+    /// `xMidYMid meet`, the SVG default
+    XMidYMid = 5,
+    /// `xMaxYMid meet`
+    XMaxYMid = 6,
+    /// `xMinYMax meet`
+    XMinYMax = 7,
+    /// `xMidYMax meet`
+    XMidYMax = 8,
+    /// `xMaxYMax meet`
+    XMaxYMax = 9,
+    /// `xMinYMin slice`
+    XMinYMinSlice = 10,
+    /// `xMidYMin slice`
+    XMidYMinSlice = 11,
+    /// `xMaxYMin slice`
+    XMaxYMinSlice = 12,
+    /// `xMinYMid slice`
+    XMinYMidSlice = 13,
+    /// `xMidYMid slice`
+    XMidYMidSlice = 14,
+    /// `xMaxYMid slice`
+    XMaxYMidSlice = 15,
+    /// `xMinYMax slice`
+    XMinYMaxSlice = 16,
+    /// `xMidYMax slice`
+    XMidYMaxSlice = 17,
+    /// `xMaxYMax slice`
+    XMaxYMaxSlice = 18,
+}
+
+impl RBScaleMode {
+    /// The mode with the given numeric value, as passed in from C.
+    fn from_raw(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(RBScaleMode::None),
+            1 => Some(RBScaleMode::XMinYMin),
+            2 => Some(RBScaleMode::XMidYMin),
+            3 => Some(RBScaleMode::XMaxYMin),
+            4 => Some(RBScaleMode::XMinYMid),
+            5 => Some(RBScaleMode::XMidYMid),
+            6 => Some(RBScaleMode::XMaxYMid),
+            7 => Some(RBScaleMode::XMinYMax),
+            8 => Some(RBScaleMode::XMidYMax),
+            9 => Some(RBScaleMode::XMaxYMax),
+            10 => Some(RBScaleMode::XMinYMinSlice),
+            11 => Some(RBScaleMode::XMidYMinSlice),
+            12 => Some(RBScaleMode::XMaxYMinSlice),
+            13 => Some(RBScaleMode::XMinYMidSlice),
+            14 => Some(RBScaleMode::XMidYMidSlice),
+            15 => Some(RBScaleMode::XMaxYMidSlice),
+            16 => Some(RBScaleMode::XMinYMaxSlice),
+            17 => Some(RBScaleMode::XMidYMaxSlice),
+            18 => Some(RBScaleMode::XMaxYMaxSlice),
+            _ => None,
+        }
+    }
+
+    /// The equivalent `preserveAspectRatio` value.
+    fn aspect_ratio(self) -> svgtypes::AspectRatio {
+        use svgtypes::Align;
+
+        let (align, slice) = match self {
+            RBScaleMode::None => (Align::None, false),
+            RBScaleMode::XMinYMin => (Align::XMinYMin, false),
+            RBScaleMode::XMidYMin => (Align::XMidYMin, false),
+            RBScaleMode::XMaxYMin => (Align::XMaxYMin, false),
+            RBScaleMode::XMinYMid => (Align::XMinYMid, false),
+            RBScaleMode::XMidYMid => (Align::XMidYMid, false),
+            RBScaleMode::XMaxYMid => (Align::XMaxYMid, false),
+            RBScaleMode::XMinYMax => (Align::XMinYMax, false),
+            RBScaleMode::XMidYMax => (Align::XMidYMax, false),
+            RBScaleMode::XMaxYMax => (Align::XMaxYMax, false),
+            RBScaleMode::XMinYMinSlice => (Align::XMinYMin, true),
+            RBScaleMode::XMidYMinSlice => (Align::XMidYMin, true),
+            RBScaleMode::XMaxYMinSlice => (Align::XMaxYMin, true),
+            RBScaleMode::XMinYMidSlice => (Align::XMinYMid, true),
+            RBScaleMode::XMidYMidSlice => (Align::XMidYMid, true),
+            RBScaleMode::XMaxYMidSlice => (Align::XMaxYMid, true),
+            RBScaleMode::XMinYMaxSlice => (Align::XMinYMax, true),
+            RBScaleMode::XMidYMaxSlice => (Align::XMidYMax, true),
+            RBScaleMode::XMaxYMaxSlice => (Align::XMaxYMax, true),
+        };
+        svgtypes::AspectRatio { defer: false, align, slice }
+    }
+}
+
+/// Renders an SVG with explicit `preserveAspectRatio` control.
+///
+/// The result is what the SVG would look like embedded as
+/// `<svg width="{width}" height="{height}" preserveAspectRatio="{fit}">`:
+/// its `viewBox` (or, without one, its `width × height` box) is placed in
+/// the output according to `fit`, replacing the document's own
+/// `preserveAspectRatio`. A small declared size doesn't matter; only the
+/// `viewBox` proportions do. Content outside the `viewBox` can show in the
+/// transparent bands of the meet modes, as in a browser.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `width` - Output width in pixels (must be > 0)
+/// * `height` - Output height in pixels (must be > 0)
+/// * `fit` - How the SVG is scaled and aligned in the output, as an
+///   `RBScaleMode` value
+///
+/// # Returns
+/// * `RBImage` of exactly `width × height` pixels
+/// * If an error occurs, returns an image with null pointer and zero dimensions
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_exact(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    width: u32,
+    height: u32,
+    fit: u32,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        let Some(fit) = RBScaleMode::from_raw(fit) else {
+            set_err(format!("invalid args: unknown scale mode {fit}"));
+            return RBImage::empty();
+        };
+
+        if svg_ptr.is_null() || svg_len == 0 || width == 0 || height == 0 {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        }
+        let svg_bytes = unsafe { slice::from_raw_parts(svg_ptr, svg_len) };
+
+        let result = parse_svg(svg_bytes, &default_options()).and_then(|tree| {
+            // Where the document's own sizing put the viewBox, in tree
+            // units, and the proportions to fit
+            let size = tree.size();
+            let (placed, src_w, src_h) = match root_viewbox(svg_bytes) {
+                Some((vb, aspect)) => (aspect_rect(aspect, vb.w, vb.h, size.width() as f64, size.height() as f64), vb.w, vb.h),
+                None => ([0.0, 0.0, size.width() as f64, size.height() as f64], size.width() as f64, size.height() as f64),
+            };
+            let [x, y, w, h] = aspect_rect(fit.aspect_ratio(), src_w, src_h, width as f64, height as f64);
+            let ts = checked_scale((w / placed[2]) as f32, (h / placed[3]) as f32)?
+                .pre_translate(-placed[0] as f32, -placed[1] as f32)
+                .post_translate(x as f32, y as f32);
+            render_tree_with_transform(&tree, width, height, ts)
+        });
+
+        match result {
+            Ok(pixmap) => RBImage::from_pixmap(pixmap),
+            Err(e) => {
+                set_err(e);
+                RBImage::empty()
+            }
+        }
+    })
+}
+
+/// How `rb_render_svg_to_rgba_with_id_list()` interprets its id list.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum RBVisibilityMode {
+    /// Render only the listed elements (and their descendants)
+    Whitelist = 0,
+    /// Render everything except the listed elements
+    Blacklist = 1,
+}
+
+impl RBVisibilityMode {
+    /// The mode with the given numeric value, as passed in from C.
+    fn from_raw(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(RBVisibilityMode::Whitelist),
+            1 => Some(RBVisibilityMode::Blacklist),
+            _ => None,
+        }
+    }
+}
+
+/// Builds the style sheet that implements an id-based visibility filter.
+fn visibility_style_sheet(ids: &[&str], mode: RBVisibilityMode) -> Result<String, String> {
+    if let Some(bad) = ids.iter().find(|id| id.contains(['"', '\\'])) {
+        return Err(format!("unsupported element id '{bad}'"));
+    }
+    let selectors = |suffix: &str| -> String {
+        ids.iter().map(|id| format!(r#"[id="{id}"]{suffix}"#)).collect::<Vec<_>>().join(", ")
+    };
+
+    Ok(match mode {
+        // `visibility` is inherited but can be turned back on by descendants,
+        // unlike `display`, so the listed subtrees stay visible
+        RBVisibilityMode::Whitelist if ids.is_empty() => "* { visibility: hidden }".into(),
+        RBVisibilityMode::Whitelist => format!(
+            "* {{ visibility: hidden }} {}, {} {{ visibility: visible }}",
+            selectors(""),
+            selectors(" *")
+        ),
+        RBVisibilityMode::Blacklist if ids.is_empty() => String::new(),
+        RBVisibilityMode::Blacklist => format!("{} {{ display: none }}", selectors("")),
+    })
+}
+
+/// Renders an SVG with only some of its elements shown, selected by `id`.
+///
+/// This enables layer-style exports (hiding guides, labels, annotations)
+/// from a single source file. The filter is applied as an injected style
+/// sheet (see `rb_options_set_style_sheet()`): blacklisted elements get
+/// `display: none`; in whitelist mode every element gets
+/// `visibility: hidden` except the listed ones and their descendants. The
+/// SVG's own `style` attributes, and its style rules using `#id` selectors,
+/// take precedence over the filter.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `width` - Desired output width in pixels (must be > 0)
+/// * `height` - Desired output height in pixels (must be > 0)
+/// * `ids` - Array of `id_count` null-terminated element ids (may be null
+///   when `id_count` is 0); ids must not contain `"` or `\`
+/// * `id_count` - Number of ids
+/// * `mode` - Whether the ids are the only elements shown or the ones hidden,
+///   as an `RBVisibilityMode` value
+///
+/// # Returns
+/// * `RBImage` struct containing the rendered pixel data
+/// * If an error occurs, returns an image with null pointer and zero dimensions
+///
+/// # Safety
+/// `svg_ptr` must be valid for `svg_len` bytes and `ids` for `id_count`
+/// valid null-terminated strings. The returned image must be freed with
+/// `rb_free_image()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_rgba_with_id_list(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    width: u32,
+    height: u32,
+    ids: *const *const c_char,
+    id_count: usize,
+    mode: u32,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        let Some(mode) = RBVisibilityMode::from_raw(mode) else {
+            set_err(format!("invalid args: unknown visibility mode {mode}"));
+            return RBImage::empty();
+        };
+
+        if ids.is_null() && id_count > 0 {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        }
+        let ids = if id_count == 0 { &[][..] } else { unsafe { slice::from_raw_parts(ids, id_count) } };
+        if ids.iter().any(|id| id.is_null()) {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        }
+        let ids: Vec<_> = ids.iter().map(|&id| unsafe { CStr::from_ptr(id) }.to_string_lossy()).collect();
+        let ids: Vec<&str> = ids.iter().map(|id| &**id).collect();
+
+        let result = visibility_style_sheet(&ids, mode).and_then(|css| {
+            let opts = RBOptions { style_sheet: Some(css), ..default_options() };
+            unsafe { render_svg_raw(svg_ptr, svg_len, width, height, &opts) }
+        });
+        match result {
+            Ok(pixmap) => RBImage::from_pixmap(pixmap),
+            Err(e) => {
+                set_err(e);
+                RBImage::empty()
+            }
+        }
+    })
+}
+
+/// Renders one page (artboard) of a multi-page SVG, or one symbol or `<use>`
+/// instance of an icon sheet.
+///
+/// Multi-artboard documents usually keep each page in its own top-level
+/// `<g id="...">`. Only the element with id `page_id` and its descendants
+/// are drawn, using the same filter as `rb_render_svg_to_rgba_with_id_list()`
+/// in whitelist mode, and the page's bounding box (including stroke) is
+/// scaled uniformly to fit the output and centered in it, so pages of any
+/// size or position export the same way. Other pages are not drawn, even
+/// where they overlap this one; the output outside the page is transparent.
+///
+/// Icon libraries define `<symbol>`s and draw them with `<use>`:
+/// * A `<symbol>` id renders the symbol whether or not the document uses
+///   it, instantiated as by `<use width="100%" height="100%">`: its own
+///   `viewBox` and `preserveAspectRatio` fit it into the output
+///   (letterboxing by default), and a symbol without a `viewBox` is drawn
+///   at 1 user unit per pixel from the top-left corner.
+/// * A `<use>` id renders that one instance, with its own position, size
+///   and overrides, fitted like a page; other instances of the same symbol
+///   are not drawn. Transforms and inherited properties of the `<use>`'s
+///   ancestors do not apply.
+///
+/// In both cases styles and definitions anywhere in the document apply.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `page_id` - Null-terminated `id` of the page, `<symbol>` or `<use>`
+///   element (must not be null; must not contain `"` or `\`)
+/// * `width` - Output width in pixels (must be > 0)
+/// * `height` - Output height in pixels (must be > 0)
+///
+/// # Returns
+/// * `RBImage` of exactly `width × height` pixels
+/// * If an error occurs (including an unknown id or an empty page), returns
+///   an image with null pointer and zero dimensions
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba()`; `page_id` must be a
+/// valid null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_page(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    page_id: *const c_char,
+    width: u32,
+    height: u32,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        if svg_ptr.is_null() || svg_len == 0 || page_id.is_null() || width == 0 || height == 0 {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        }
+        let Ok(id) = unsafe { CStr::from_ptr(page_id) }.to_str() else {
+            set_err("invalid args: page id is not valid UTF-8".into());
+            return RBImage::empty();
+        };
+
+        let svg_bytes = unsafe { slice::from_raw_parts(svg_ptr, svg_len) };
+        let result = reference_document(svg_bytes, id, width, height).and_then(|reference| match reference {
+            Some(Reference::Symbol(doc)) => render_svg_bytes(doc.as_bytes(), width, height, &default_options()),
+            Some(Reference::Use(doc)) => {
+                let tree = parse_svg(doc.as_bytes(), &default_options())?;
+                let bbox = tree.root().abs_stroke_bounding_box();
+                render_fitted(&tree, bbox, id, width, height)
+            }
+            None => {
+                let css = visibility_style_sheet(&[id], RBVisibilityMode::Whitelist)?;
+                let opts = RBOptions { style_sheet: Some(css), ..default_options() };
+                let tree = parse_svg(svg_bytes, &opts)?;
+                let node = tree.node_by_id(id).ok_or_else(|| format!("element '{id}' not found"))?;
+                render_fitted(&tree, node.abs_stroke_bounding_box(), id, width, height)
+            }
+        });
+
+        match result {
+            Ok(pixmap) => RBImage::from_pixmap(pixmap),
+            Err(e) => {
+                set_err(e);
+                RBImage::empty()
+            }
+        }
+    })
+}
+
+/// Renders a tree with `bbox` scaled uniformly to fit a `width × height`
+/// output and centered in it.
+fn render_fitted(tree: &ParsedSvg, bbox: usvg::Rect, id: &str, width: u32, height: u32) -> Result<Pixmap, String> {
+    if !(bbox.width() > 0.0 && bbox.height() > 0.0) {
+        return Err(format!("element '{id}' has an empty bounding box"));
+    }
+    let (bw, bh) = (bbox.width() as f64, bbox.height() as f64);
+    let [x, y, w, _] = aspect_rect(svgtypes::AspectRatio::default(), bw, bh, width as f64, height as f64);
+    let s = (w / bw) as f32;
+    let ts = checked_scale(s, s)?
+        .pre_translate(-bbox.x(), -bbox.y())
+        .post_translate(x as f32, y as f32);
+    render_tree_with_transform(tree, width, height, ts)
+}
+
+/// A document rewritten by `reference_document()` to draw one element
+/// that a page filter cannot isolate.
+enum Reference {
+    /// Draws a `<symbol>` sized to the output viewport
+    Symbol(String),
+    /// Draws one `<use>` instance, still to be fitted to the output
+    Use(String),
+}
+
+/// Builds a document that shows only the `<symbol>` or `<use>` with id
+/// `id`, or returns `None` when `id` names some other element.
+///
+/// The content a `<use>` clones is not a descendant of it, so hiding
+/// everything else with a style sheet would hide that content too, and a
+/// `<symbol>` draws nothing at all until it is used. Instead, the original
+/// content moves into `<defs>`, so the definitions, styles and the element
+/// itself stay available while nothing else is drawn, and a new `<use>`
+/// draws the element: sized to the `width × height` viewport for a symbol,
+/// or as-is for a `<use>`, which then draws its own instance.
+fn reference_document(svg_bytes: &[u8], id: &str, width: u32, height: u32) -> Result<Option<Reference>, String> {
+    let decompressed;
+    let svg_bytes = if svg_bytes.starts_with(&[0x1f, 0x8b]) {
+        decompressed = usvg::decompress_svgz(svg_bytes).map_err(|e| format!("parse error: {e}"))?;
+        &decompressed[..]
+    } else {
+        svg_bytes
+    };
+    let text = std::str::from_utf8(svg_bytes).map_err(|_| "SVG is not valid UTF-8")?;
+    let doc = usvg::roxmltree::Document::parse(text).map_err(|e| format!("parse error: {e}"))?;
+    let root = doc.root_element();
+
+    let is_symbol = match doc.descendants().find(|n| n.attribute("id") == Some(id)) {
+        Some(n) if n.has_tag_name("symbol") => true,
+        Some(n) if n.has_tag_name("use") => false,
+        _ => return Ok(None),
+    };
+
+    // Keep the root's namespaces and presentation attributes, but not its sizing
+    let mut out = String::from("<svg");
+    for ns in root.namespaces().filter(|ns| ns.name() != Some("xml")) {
+        match ns.name() {
+            Some(prefix) => out.push_str(&format!(" xmlns:{prefix}=\"{}\"", xml_escape(ns.uri()))),
+            None => out.push_str(&format!(" xmlns=\"{}\"", xml_escape(ns.uri()))),
+        }
+    }
+    const SIZING: &[&str] = &["width", "height", "viewBox", "preserveAspectRatio", "x", "y"];
+    for attr in root.attributes().filter(|a| a.namespace().is_some() || !SIZING.contains(&a.name())) {
+        out.push(' ');
+        out.push_str(&text[attr.range()]);
+    }
+    out.push_str(&format!(" width=\"{width}\" height=\"{height}\"><defs>"));
+    if let (Some(first), Some(last)) = (root.first_child(), root.last_child()) {
+        out.push_str(&text[first.range().start..last.range().end]);
+    }
+    let href = xml_escape(id);
+    Ok(Some(if is_symbol {
+        out.push_str(&format!("</defs><use href=\"#{href}\" width=\"{width}\" height=\"{height}\"/></svg>"));
+        Reference::Symbol(out)
+    } else {
+        out.push_str(&format!("</defs><use href=\"#{href}\"/></svg>"));
+        Reference::Use(out)
+    }))
+}
+
+/// Escapes text for use in XML character data and attribute values.
+fn xml_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            // Control characters are not allowed in XML 1.0
+            c if c.is_control() => out.push(' '),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Renders a placeholder image that shows an error message.
+///
+/// The image has a light red grid background and a red border, with the
+/// message in red text wrapped to the image width. Text needs a
+/// `sans-serif` font in the font database; without one only the grid and
+/// border are drawn, which still marks the image as broken.
+///
+/// # Arguments
+/// * `error_msg` - Null-terminated message to show, or null to use the
+///   current thread's last error (as from `rb_last_error_copy()`)
+/// * `width` - Output width in pixels (must be > 0)
+/// * `height` - Output height in pixels (must be > 0)
+///
+/// # Returns
+/// * The placeholder image
+/// * If an error occurs, returns an image with null pointer and zero dimensions
+///
+/// # Safety
+/// `error_msg` must be null or a valid null-terminated string. The returned
+/// image must be freed with `rb_free_image()`.
+///
+/// # Example
+/// ```c
+/// RBImage img = rb_render_svg_to_rgba(svg_data, svg_len, 100, 100);
+/// if (!img.ptr) img = rb_render_svg_to_rgba_error_image(NULL, 100, 100);
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_rgba_error_image(error_msg: *const c_char, width: u32, height: u32) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        // Read the pending error before clearing it
+        let msg = if error_msg.is_null() {
+            LAST_ERR.with(|e| e.borrow().clone()).unwrap_or_else(|| "unknown error".into())
+        } else {
+            unsafe { CStr::from_ptr(error_msg) }.to_string_lossy().into_owned()
+        };
+        clear_err();
+
+        if width == 0 || height == 0 {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        }
+
+        let (w, h) = (width as f32, height as f32);
+        let short = w.min(h);
+        let border = (short / 32.0).max(1.0);
+        let cell = (short / 8.0).max(4.0);
+        let font_size = (short / 12.0).max(8.0);
+        let pad = border * 2.0 + font_size * 0.25;
+
+        // Greedy word wrap with an average glyph width of about half an em
+        let max_chars = (((w - 2.0 * pad) / (font_size * 0.55)) as usize).max(1);
+        let mut lines: Vec<String> = Vec::new();
+        for word in msg.split_whitespace() {
+            match lines.last_mut() {
+                Some(line) if line.chars().count() + 1 + word.chars().count() <= max_chars => {
+                    line.push(' ');
+                    line.push_str(word);
+                }
+                _ => lines.push(word.to_string()),
+            }
+        }
+        let tspans: String = lines
+            .iter()
+            .map(|line| format!(r#"<tspan x="{pad}" dy="{dy}">{text}</tspan>"#, dy = font_size * 1.2, text = xml_escape(line)))
+            .collect();
+
+        let svg = format!(
+            concat!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}">"#,
+                r#"<defs><pattern id="grid" width="{cell}" height="{cell}" patternUnits="userSpaceOnUse">"#,
+                r##"<path d="M {cell} 0 L 0 0 0 {cell}" fill="none" stroke="#f2b8b8" stroke-width="1"/>"##,
+                "</pattern></defs>",
+                r##"<rect width="{w}" height="{h}" fill="#fff0f0"/>"##,
+                r#"<rect width="{w}" height="{h}" fill="url(#grid)"/>"#,
+                r##"<rect x="{half}" y="{half}" width="{iw}" height="{ih}" fill="none" stroke="#d00000" stroke-width="{border}"/>"##,
+                r##"<text y="{pad}" font-family="sans-serif" font-size="{font_size}" fill="#900000">{tspans}</text>"##,
+                "</svg>"
+            ),
+            w = w,
+            h = h,
+            cell = cell,
+            half = border / 2.0,
+            iw = w - border,
+            ih = h - border,
+            border = border,
+            pad = pad,
+            font_size = font_size,
+            tspans = tspans,
+        );
+
+        match render_svg_bytes(svg.as_bytes(), width, height, &RBOptions::default()) {
+            Ok(pixmap) => RBImage::from_pixmap(pixmap),
+            Err(e) => {
+                set_err(e);
+                RBImage::empty()
+            }
+        }
+    })
+}
+
+/// Replaces every pixel with its nearest palette entry.
+///
+/// Distances are Euclidean over straight (unpremultiplied) RGBA so that
+/// transparent pixels pick an alpha-0 entry; with an opaque image and palette
+/// that is plain RGB distance. With `dither`, the
+/// quantization error is diffused to unvisited neighbours (Floyd–Steinberg).
+fn quantize_to_palette(pixmap: &mut Pixmap, palette: &[[u8; 4]], dither: bool) {
+    let (width, height) = (pixmap.width() as usize, pixmap.height() as usize);
+    let mut work: Vec<[f32; 4]> = demultiplied_rgba(pixmap)
+        .chunks_exact(4)
+        .map(|p| [p[0] as f32, p[1] as f32, p[2] as f32, p[3] as f32])
+        .collect();
+
+    for i in 0..work.len() {
+        let px = work[i].map(|c| c.clamp(0.0, 255.0));
+        let dist = |e: &[u8; 4]| px.iter().zip(e).map(|(a, &b)| (a - b as f32).powi(2)).sum::<f32>();
+        let entry = *palette.iter().min_by(|a, b| dist(a).total_cmp(&dist(b))).unwrap();
+
+        let color = tiny_skia::ColorU8::from_rgba(entry[0], entry[1], entry[2], entry[3]);
+        pixmap.pixels_mut()[i] = color.premultiply();
+
+        if dither {
+            let err: [f32; 4] = std::array::from_fn(|c| px[c] - entry[c] as f32);
+            let (x, y) = (i % width, i / width);
+            let mut spread = |dx: isize, dy: usize, weight: f32| {
+                let nx = x.wrapping_add_signed(dx);
+                if nx < width && y + dy < height {
+                    let n = (y + dy) * width + nx;
+                    for c in 0..4 {
+                        work[n][c] += err[c] * weight;
+                    }
+                }
+            };
+            spread(1, 0, 7.0 / 16.0);
+            spread(-1, 1, 3.0 / 16.0);
+            spread(0, 1, 5.0 / 16.0);
+            spread(1, 1, 1.0 / 16.0);
+        }
+    }
+}
+
+/// Renders an SVG file and maps the result onto a fixed color palette.
+///
+/// Each pixel becomes the palette entry nearest to it. Distance is Euclidean
+/// over straight RGBA rather than RGB alone: transparent pixels have straight
+/// RGB 0,0,0, so an RGB-only distance would map them to opaque black (or any
+/// dark entry) instead of the palette's alpha-0 entry that GIF's transparent
+/// index expects. For opaque pixels and an opaque palette the alpha term is
+/// zero and this is exactly RGB distance. With `dither`, Floyd–Steinberg
+/// error diffusion trades banding for noise in gradients.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `width` - Desired output width in pixels (must be > 0)
+/// * `height` - Desired output height in pixels (must be > 0)
+/// * `palette` - `palette_size` straight RGBA quadruples (must not be null)
+/// * `palette_size` - Number of palette entries (must be > 0)
+/// * `dither` - `true` for Floyd–Steinberg error diffusion
+///
+/// # Returns
+/// * `RBImage` containing only (premultiplied) palette colors
+/// * If an error occurs, returns an image with null pointer and zero dimensions
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba()`; `palette` must be valid
+/// for `palette_size * 4` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_rgba_palette(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    width: u32,
+    height: u32,
+    palette: *const u8,
+    palette_size: usize,
+    dither: bool,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        if palette.is_null() || palette_size == 0 {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        }
+        let Some(palette_len) = palette_size.checked_mul(4) else {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        };
+        let palette: Vec<[u8; 4]> = unsafe { slice::from_raw_parts(palette, palette_len) }
+            .chunks_exact(4)
+            .map(|e| [e[0], e[1], e[2], e[3]])
+            .collect();
+
+        let opts = default_options();
+        match unsafe { render_svg_raw(svg_ptr, svg_len, width, height, &opts) } {
+            Ok(mut pixmap) => {
+                quantize_to_palette(&mut pixmap, &palette, dither);
+                RBImage::from_pixmap(pixmap)
+            }
+            Err(e) => {
+                set_err(e);
+                RBImage::empty()
+            }
+        }
+    })
+}
+
+/// Renders an SVG to floating-point coverage, for pipelines that need more
+/// than 8 bits of anti-aliasing precision.
+///
+/// The SVG is rendered at 4× the requested size in each direction and every
+/// 4×4 block of samples is averaged, giving 16 coverage samples per output
+/// pixel. The result is an `RBPixelFormat::Rgba32F` image: four
+/// native-endian `f32` per pixel (premultiplied R, G, B, A, each in
+/// [0, 1]) and `len == width * height * 16`. `ptr` comes from the system
+/// allocator and is aligned for `float` on supported platforms. The
+/// supersampled render needs 16× the memory of a normal one.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `width` - Desired output width in pixels (must be > 0)
+/// * `height` - Desired output height in pixels (must be > 0)
+///
+/// # Returns
+/// * `RBImage` containing the coverage values
+/// * If an error occurs, returns an image with null pointer and zero dimensions
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_coverage(svg_ptr: *const u8, svg_len: usize, width: u32, height: u32) -> RBImage {
+    const FACTOR: usize = 4;
+
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        let (Some(big_w), Some(big_h)) = (width.checked_mul(FACTOR as u32), height.checked_mul(FACTOR as u32)) else {
+            set_err("invalid args: output too large to supersample".into());
+            return RBImage::empty();
+        };
+
+        let opts = default_options();
+        let pixmap = match unsafe { render_svg_raw(svg_ptr, svg_len, big_w, big_h, &opts) } {
+            Ok(pixmap) => pixmap,
+            Err(e) => {
+                set_err(e);
+                return RBImage::empty();
+            }
+        };
+
+        let (w, h, big_w) = (width as usize, height as usize, big_w as usize);
+        let samples = pixmap.data();
+        let scale = 1.0 / (FACTOR * FACTOR * 255) as f32;
+        let mut out = Vec::with_capacity(w * h * 16);
+        for y in 0..h {
+            for x in 0..w {
+                let mut sum = [0u32; 4];
+                for sy in y * FACTOR..(y + 1) * FACTOR {
+                    let row = &samples[(sy * big_w + x * FACTOR) * 4..][..FACTOR * 4];
+                    for px in row.chunks_exact(4) {
+                        for c in 0..4 {
+                            sum[c] += px[c] as u32;
+                        }
+                    }
+                }
+                for channel in sum {
+                    out.extend_from_slice(&(channel as f32 * scale).to_ne_bytes());
+                }
+            }
+        }
+        RBImage::from_vec(out, width, height).with_format(RBPixelFormat::Rgba32F)
+    })
+}
+
+/// Color sub-pixel layout of the LCD panel `rb_render_svg_to_rgba_subpixel()`
+/// targets.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RBSubpixelOrder {
+    /// Horizontal stripes, red on the left
+    Rgb = 0,
+    /// Horizontal stripes, blue on the left
+    Bgr = 1,
+    /// Vertical stripes, red on top
+    Vrgb = 2,
+    /// Vertical stripes, blue on top
+    Vbgr = 3,
+}
+
+impl RBSubpixelOrder {
+    /// The order with the given numeric value, as passed in from C.
+    fn from_raw(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(RBSubpixelOrder::Rgb),
+            1 => Some(RBSubpixelOrder::Bgr),
+            2 => Some(RBSubpixelOrder::Vrgb),
+            3 => Some(RBSubpixelOrder::Vbgr),
+            _ => None,
+        }
+    }
+}
+
+/// Renders an SVG with LCD sub-pixel anti-aliasing.
+///
+/// The SVG is rendered at 3× resolution along the stripe direction (width
+/// for `Rgb`/`Bgr`, height for `Vrgb`/`Vbgr`), every sample is smoothed with
+/// FreeType's default 5-tap LCD filter (weights 8, 77, 86, 77, 8 / 256) to
+/// limit color fringes, and each output channel takes the sample under its
+/// own sub-pixel. Edges therefore get three times the positioning precision
+/// across the stripes, which mostly benefits small text but applies to
+/// every shape.
+///
+/// The output is premultiplied RGBA whose channels carry separate coverage;
+/// alpha is the largest of the three. It only looks right on a panel with
+/// the given layout, at 1:1 scale, composited over an opaque background;
+/// most accurate when the SVG itself is opaque. The supersampled render
+/// needs 3× the memory of a normal one.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `width` - Desired output width in pixels (must be > 0)
+/// * `height` - Desired output height in pixels (must be > 0)
+/// * `subpixel_order` - The panel's sub-pixel layout, as an `RBSubpixelOrder`
+///   value
+///
+/// # Returns
+/// * `RBImage` struct containing the rendered pixel data
+/// * If an error occurs, returns an image with null pointer and zero dimensions
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_rgba_subpixel(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    width: u32,
+    height: u32,
+    subpixel_order: u32,
+) -> RBImage {
+    /// FreeType's `FT_LCD_FILTER_DEFAULT` weights, summing to 256
+    const FILTER: [u32; 5] = [8, 77, 86, 77, 8];
+
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        let Some(subpixel_order) = RBSubpixelOrder::from_raw(subpixel_order) else {
+            set_err(format!("invalid args: unknown subpixel order {subpixel_order}"));
+            return RBImage::empty();
+        };
+
+        let vertical = matches!(subpixel_order, RBSubpixelOrder::Vrgb | RBSubpixelOrder::Vbgr);
+        let (big_w, big_h) = if vertical { (Some(width), height.checked_mul(3)) } else { (width.checked_mul(3), Some(height)) };
+        let (Some(big_w), Some(big_h)) = (big_w, big_h) else {
+            set_err("invalid args: output too large to supersample".into());
+            return RBImage::empty();
+        };
+
+        let pixmap = match unsafe { render_svg_raw(svg_ptr, svg_len, big_w, big_h, &default_options()) } {
+            Ok(pixmap) => pixmap,
+            Err(e) => {
+                set_err(e);
+                return RBImage::empty();
+            }
+        };
+
+        let (w, h, big_w) = (width as usize, height as usize, big_w as usize);
+        let samples = pixmap.data();
+        // Filtered value of one channel at sample (sx, sy), stepping along the
+        // stripes; samples past the border repeat the edge
+        let filtered = |sx: usize, sy: usize, c: usize| -> u32 {
+            let sum: u32 = FILTER
+                .iter()
+                .enumerate()
+                .map(|(t, k)| {
+                    let along = |v: usize, len: usize| (v + t).saturating_sub(2).min(len - 1);
+                    let (x, y) = if vertical { (sx, along(sy, big_h as usize)) } else { (along(sx, big_w), sy) };
+                    k * samples[(y * big_w + x) * 4 + c] as u32
+                })
+                .sum();
+            (sum + 128) / 256
+        };
+
+        let reversed = matches!(subpixel_order, RBSubpixelOrder::Bgr | RBSubpixelOrder::Vbgr);
+        let mut out = Vec::with_capacity(w * h * 4);
+        for y in 0..h {
+            for x in 0..w {
+                let mut px = [0u8; 4];
+                for c in 0..3 {
+                    let sub = if reversed { 2 - c } else { c };
+                    let (sx, sy) = if vertical { (x, y * 3 + sub) } else { (x * 3 + sub, y) };
+                    let alpha = filtered(sx, sy, 3);
+                    px[c] = filtered(sx, sy, c).min(alpha) as u8;
+                    px[3] = px[3].max(alpha as u8);
+                }
+                out.extend_from_slice(&px);
+            }
+        }
+        RBImage::from_vec(out, width, height)
+    })
+}
+
+/// Frees memory allocated for an RBImage.
+/// 
+/// This function must be called to free the memory allocated by `rb_render_svg_to_rgba()`.
+/// Failing to call this function will result in a memory leak.
+/// 
+/// # Arguments
+/// * `img` - The RBImage structure to free
+/// 
+/// # Safety
+/// This function is safe to call multiple times on the same image (idempotent).
+/// After calling this function, the image structure should not be used again.
+/// 
+/// # Example
+/// ```c
+/// RBImage img = rb_render_svg_to_rgba(svg_data, svg_len, 100, 100);
+/// // Use the image...
+/// rb_free_image(img);
+/// ```
+#[no_mangle]
+pub extern "C" fn rb_free_image(img: RBImage) {
+    ffi_guard((), || {
+        // Only free if we have valid data
+        if !img.ptr.is_null() && img.len > 0 {
+            forget_logical_size(img.ptr as usize);
+            // Reconstruct the Vec to properly deallocate the memory
+            // This is safe because we know the memory was allocated by Vec::from_raw_parts
+            unsafe { 
+                drop(Vec::from_raw_parts(img.ptr, img.len, img.len)) 
+            };
+        }
+    })
+}
+
+/// Gets the number of bytes between the starts of consecutive rows.
+///
+/// Rows are tightly packed, so this is always `width * bytes per pixel`
+/// (`width * 4` for RGBA); it is provided for APIs such as GPU uploads
+/// that take an explicit row pitch.
+///
+/// # Arguments
+/// * `img` - The image (null returns 0)
+///
+/// # Returns
+/// * The row stride in bytes
+/// * 0 for a null pointer or an empty image
+///
+/// # Safety
+/// `img` must be null or point to a valid `RBImage`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_stride(img: *const RBImage) -> usize {
+    ffi_guard(0, || {
+        unsafe { img.as_ref() }
+            .filter(|img| !img.ptr.is_null())
+            .and_then(|img| Some(img.width as usize * img.pixel_format().ok()?.bytes_per_pixel()))
+            .unwrap_or(0)
+    })
+}
+
+// ============================================================================
+// REGION RENDERING
+// ============================================================================
+//
+// Region renders produce the pixels a full render at the same output size
+// would have in that rectangle, without rasterizing the rest. Anti-aliased
+// edges can differ by a few levels from a full render because the rasterizer
+// works relative to the region's origin, and content whose look depends on
+// pixels outside the region (mostly filters such as blurs near the region's
+// edge) may differ more.
+
+/// Renders the `(x, y, w, h)` part of a `full_w × full_h` render.
+fn render_region(
+    tree: &ParsedSvg,
+    full_w: u32,
+    full_h: u32,
+    (x, y, w, h): (u32, u32, u32, u32),
+    opts: &RBOptions,
+) -> Result<Pixmap, String> {
+    let ts = fit_transform(tree, full_w, full_h, opts)?.post_translate(-(x as f32), -(y as f32));
+    render_tree_with_transform(tree, w, h, ts)
+}
+
+/// Renders one rectangle of an SVG as it would appear in a full render.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `width` - Width of the full (virtual) output in pixels (must be > 0)
+/// * `height` - Height of the full (virtual) output in pixels (must be > 0)
+/// * `x`, `y`, `w`, `h` - The region to render, in output pixels; must be
+///   non-empty and lie within `width × height`
+/// * `opts` - Options from `rb_options_new()` (null = the defaults from
+///   `rb_set_default_options()`)
+///
+/// # Returns
+/// * A `w × h` image
+/// * If an error occurs, returns an image with null pointer and zero dimensions
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba_with_options()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_region(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    width: u32,
+    height: u32,
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+    opts: *const RBOptions,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        let in_bounds = x.checked_add(w).is_some_and(|r| r <= width) && y.checked_add(h).is_some_and(|b| b <= height);
+        if svg_ptr.is_null() || svg_len == 0 || w == 0 || h == 0 || !in_bounds {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        }
+
+        let default_opts = default_options();
+        let opts = unsafe { opts.as_ref() }.unwrap_or(&default_opts);
+        let svg_bytes = unsafe { slice::from_raw_parts(svg_ptr, svg_len) };
+
+        match parse_svg(svg_bytes, opts).and_then(|tree| render_region(&tree, width, height, (x, y, w, h), opts)) {
+            Ok(pixmap) => RBImage::from_render(pixmap, opts),
+            Err(e) => {
+                set_err(e);
+                RBImage::empty()
+            }
+        }
+    })
+}
+
+/// Renders the area around one element, as a browser screenshot of that
+/// element would show it.
+///
+/// The element's bounding box (including stroke) is scaled uniformly to fit
+/// the `out_w × out_h` output minus a margin of `padding` pixels on every
+/// side, and centered in it. When the box's aspect ratio differs from the
+/// output's, the spare room on two sides grows beyond the padding. Margins
+/// show whatever surrounds the box in the document: the whole document is
+/// rendered, so backgrounds and siblings that overlap the margins appear
+/// too, while everything outside is cut off.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `element_id` - Null-terminated `id` of the element (must not be null)
+/// * `padding` - Margin around the bounding box in output pixels (must be
+///   finite and less than half of each output dimension; negative values
+///   crop into the box)
+/// * `out_w` - Output width in pixels (must be > 0)
+/// * `out_h` - Output height in pixels (must be > 0)
+///
+/// # Returns
+/// * An `out_w × out_h` image
+/// * If an error occurs (including an unknown id or an empty bounding box),
+///   returns an image with null pointer and zero dimensions
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba()`; `element_id` must be a
+/// valid null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_rgba_clipped_to_element(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    element_id: *const c_char,
+    padding: f32,
+    out_w: u32,
+    out_h: u32,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        if svg_ptr.is_null() || svg_len == 0 || element_id.is_null() || !padding.is_finite() || out_w == 0 || out_h == 0 {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        }
+        let Ok(id) = unsafe { CStr::from_ptr(element_id) }.to_str() else {
+            set_err("invalid args: element id is not valid UTF-8".into());
+            return RBImage::empty();
+        };
+
+        let opts = default_options();
+        let svg_bytes = unsafe { slice::from_raw_parts(svg_ptr, svg_len) };
+        let result = parse_svg(svg_bytes, &opts).and_then(|tree| {
+            let node = tree.node_by_id(id).ok_or_else(|| format!("element '{id}' not found"))?;
+            let bbox = node.abs_stroke_bounding_box();
+            if bbox.width() <= 0.0 || bbox.height() <= 0.0 {
+                return Err(format!("element '{id}' has an empty bounding box"));
+            }
+            // The box fits into what the padding leaves of the output
+            let (inner_w, inner_h) = (out_w as f64 - 2.0 * padding as f64, out_h as f64 - 2.0 * padding as f64);
+            if inner_w <= 0.0 || inner_h <= 0.0 {
+                return Err(format!("invalid args: padding {padding} leaves no room in {out_w}x{out_h}"));
+            }
+            let (bw, bh) = (bbox.width() as f64, bbox.height() as f64);
+            let [x, y, w, _] = aspect_rect(svgtypes::AspectRatio::default(), bw, bh, inner_w, inner_h);
+            let s = (w / bw) as f32;
+            let ts = checked_scale(s, s)?
+                .pre_translate(-bbox.x(), -bbox.y())
+                .post_translate(padding + x as f32, padding + y as f32);
+            render_tree_with_transform(&tree, out_w, out_h, ts)
+        });
+
+        match result {
+            Ok(pixmap) => RBImage::from_pixmap(pixmap),
+            Err(e) => {
+                set_err(e);
+                RBImage::empty()
+            }
+        }
+    })
+}
+
+/// Re-renders a dirty rectangle of a previously rendered image in place.
+///
+/// Meant for interactive viewers where only a small area changes between
+/// frames: pass the updated SVG and the area that changed, and only that
+/// area is rasterized and written into `img`. The region's pixels are
+/// replaced (not blended), since the re-render already contains everything
+/// that is visible there.
+///
+/// The caller guarantees that nothing outside the dirty rectangle changed
+/// since `img` was rendered from the same SVG, size and options; the
+/// library does not verify this.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the updated SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `opts` - The options `img` was rendered with (null = the defaults from
+///   `rb_set_default_options()`)
+/// * `img` - The cached image to update (must not be null)
+/// * `x`, `y`, `w`, `h` - The dirty rectangle in pixels, clipped to the image
+///
+/// # Returns
+/// * `true` on success (a rectangle entirely outside the image is a no-op)
+/// * `false` on error; `img` is left untouched (error is set)
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba_with_options()`; `img` must be
+/// a valid RGBA image from this library.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_dirty_rect(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    opts: *const RBOptions,
+    img: *mut RBImage,
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+
+        let Some(img) = (unsafe { img.as_mut() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        if svg_ptr.is_null() || svg_len == 0 {
+            set_err("invalid args".into());
+            return false;
+        }
+        if let Err(e) = check_rgba(img) {
+            set_err(e);
+            return false;
+        }
+
+        // Clip the dirty rectangle to the image
+        let (x1, y1) = (x.saturating_add(w).min(img.width), y.saturating_add(h).min(img.height));
+        if x >= x1 || y >= y1 {
+            return true;
+        }
+        let (w, h) = (x1 - x, y1 - y);
+
+        let default_opts = default_options();
+        let opts = unsafe { opts.as_ref() }.unwrap_or(&default_opts);
+        let svg_bytes = unsafe { slice::from_raw_parts(svg_ptr, svg_len) };
+
+        let region = match parse_svg(svg_bytes, opts).and_then(|tree| render_region(&tree, img.width, img.height, (x, y, w, h), opts)) {
+            Ok(p) => p,
+            Err(e) => {
+                set_err(e);
+                return false;
+            }
+        };
+
+        // Copy the region row by row into the cached buffer
+        let dst = unsafe { slice::from_raw_parts_mut(img.ptr, img.len) };
+        let stride = img.width as usize * 4;
+        let row_len = w as usize * 4;
+        for (row, src) in region.data().chunks_exact(row_len).enumerate() {
+            let start = (y as usize + row) * stride + x as usize * 4;
+            dst[start..start + row_len].copy_from_slice(src);
+        }
+        true
+    })
+}
+
+/// Renders an SVG straight into a memory-mapped file.
+///
+/// For outputs too large to hold in RAM. The file at `path` is created (or
+/// truncated), sized to `width * height * 4` bytes and mapped; the image is
+/// then rendered in full-width bands of rows directly into the mapping, so
+/// resident memory stays bounded by the OS page cache rather than by the
+/// image size. The SVG is parsed once.
+///
+/// As with `rb_render_svg_to_rgba_with_options()`, the requested size is
+/// first clamped to the options' size limits (see
+/// `rb_options_set_size_limits()`); `width` and `height` in the file layout
+/// below mean the clamped size.
+///
+/// File layout: no header, rows top to bottom, each row `width * 4` bytes of
+/// premultiplied RGBA (the same bytes `rb_render_svg_to_rgba()` returns).
+///
+/// Supported wherever the `memmap2` crate is (Unix and Windows). Band
+/// boundaries follow the REGION RENDERING caveats above.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `width` - Output width in pixels (must be > 0)
+/// * `height` - Output height in pixels (must be > 0)
+/// * `path` - Null-terminated, UTF-8 path of the output file
+/// * `opts` - Rendering options, or null for the defaults from
+///   `rb_set_default_options()`
+///
+/// # Returns
+/// * `true` on success
+/// * `false` on error, including when the file size would overflow the
+///   platform's file offset or address space (error is set)
+///
+/// # Safety
+/// `svg_ptr` must be valid for `svg_len` bytes, `path` must be a valid
+/// null-terminated string and `opts` must be null or a valid pointer from
+/// `rb_options_new()`. The file must not be modified by anyone else while
+/// rendering.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_mmap(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    width: u32,
+    height: u32,
+    path: *const c_char,
+    opts: *const RBOptions,
+) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+
+        if svg_ptr.is_null() || svg_len == 0 || width == 0 || height == 0 || path.is_null() {
+            set_err("invalid args".into());
+            return false;
+        }
+        let Ok(path) = unsafe { CStr::from_ptr(path) }.to_str() else {
+            set_err("path is not valid UTF-8".into());
+            return false;
+        };
+        let default_opts = default_options();
+        let opts = unsafe { opts.as_ref() }.unwrap_or(&default_opts);
+        let svg_bytes = unsafe { slice::from_raw_parts(svg_ptr, svg_len) };
+
+        match render_to_mmap(svg_bytes, width, height, path, opts) {
+            Ok(()) => true,
+            Err(e) => {
+                set_err(e);
+                false
+            }
+        }
+    })
+}
+
+/// Implementation of `rb_render_svg_to_mmap()`.
+fn render_to_mmap(svg_bytes: &[u8], width: u32, height: u32, path: &str, opts: &RBOptions) -> Result<(), String> {
+    /// Upper bound on pixels rendered per band
+    const BAND_PIXELS: usize = 16 * 1024 * 1024;
+
+    let (width, height) = opts.clamp_size(width, height);
+    let stride = width as u64 * 4;
+    let file_len = stride
+        .checked_mul(height as u64)
+        .filter(|&n| i64::try_from(n).is_ok() && usize::try_from(n).is_ok())
+        .ok_or("output too large for a file offset")?;
+
+    let tree = parse_svg(svg_bytes, opts)?;
+    let ts = fit_transform(&tree, width, height, opts)?;
+
+    let io_err = |e: std::io::Error| format!("io error: {e}");
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .map_err(io_err)?;
+    file.set_len(file_len).map_err(io_err)?;
+    let mut map = unsafe { memmap2::MmapMut::map_mut(&file) }.map_err(io_err)?;
+
+    let band_rows = (BAND_PIXELS / width as usize).clamp(1, height as usize);
+    for (band, bytes) in map.chunks_mut(band_rows * stride as usize).enumerate() {
+        let rows = (bytes.len() / stride as usize) as u32;
+        let mut canvas = tiny_skia::PixmapMut::from_bytes(bytes, width, rows).ok_or("invalid band size")?;
+        let y = (band * band_rows) as f32;
+        render_into(&tree, ts.post_translate(0.0, -y), &mut canvas)?;
+    }
+
+    map.flush().map_err(io_err)
+}
+
+/// Callback receiving one finished row of an incremental render; return
+/// `false` to cancel the rest of the render.
+pub type RBRowCallback = Option<extern "C" fn(row: u32, data: *const u8, stride: usize, userdata: *mut c_void) -> bool>;
+
+/// Renders an SVG progressively, handing each row to a callback as soon as
+/// its band is done.
+///
+/// The image is rendered in full-width bands of 32 rows, top to bottom, and
+/// after each band its rows are passed to `row_cb` in order. Each call gets
+/// the row index, the row's `width * 4` bytes of premultiplied RGBA (valid
+/// only during the call) and the stride, which equals the row length. If the
+/// callback returns `false`, no further rows are rendered or delivered, the
+/// error is set to "cancelled" (`RBErrorCode::Cancelled`) and the function
+/// returns `false`. Band boundaries follow the REGION RENDERING caveats
+/// above. The SVG is parsed once.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `width` - Output width in pixels (must be > 0)
+/// * `height` - Output height in pixels (must be > 0)
+/// * `row_cb` - Called once per row, on the calling thread (must not be null)
+/// * `userdata` - Opaque pointer forwarded to the callback unchanged
+///
+/// # Returns
+/// * `true` if every row was rendered and delivered
+/// * `false` on error or cancellation (error is set)
+///
+/// # Safety
+/// `svg_ptr` must be valid for `svg_len` bytes. The callback must not unwind
+/// and must not keep `data` past its return.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_rgba_incremental(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    width: u32,
+    height: u32,
+    row_cb: RBRowCallback,
+    userdata: *mut c_void,
+) -> bool {
+    /// Rows rendered per band
+    const BAND_ROWS: u32 = 32;
+
+    ffi_guard(false, || {
+        clear_err();
+
+        let Some(row_cb) = row_cb else {
+            set_err("invalid args".into());
+            return false;
+        };
+        if svg_ptr.is_null() || svg_len == 0 || width == 0 || height == 0 {
+            set_err("invalid args".into());
+            return false;
+        }
+        let svg_bytes = unsafe { slice::from_raw_parts(svg_ptr, svg_len) };
+
+        let opts = default_options();
+        let rendered = parse_svg(svg_bytes, &opts).and_then(|tree| {
+            let ts = fit_transform(&tree, width, height, &opts)?;
+            let stride = width as usize * 4;
+            for top in (0..height).step_by(BAND_ROWS as usize) {
+                let rows = BAND_ROWS.min(height - top);
+                let band = render_tree_with_transform(&tree, width, rows, ts.post_translate(0.0, -(top as f32)))?;
+                for (i, row) in band.data().chunks_exact(stride).enumerate() {
+                    if !row_cb(top + i as u32, row.as_ptr(), stride, userdata) {
+                        return Err("cancelled by row callback".into());
+                    }
+                }
+            }
+            Ok(())
+        });
+
+        match rendered {
+            Ok(()) => true,
+            Err(e) => {
+                set_err(e);
+                false
+            }
+        }
+    })
+}
+
+// ============================================================================
+// PARSED TREES
+// ============================================================================
+//
+// An `RBTree` keeps a parsed SVG so it can be rendered many times without
+// re-parsing. The tree remembers the options it was parsed with; size,
+// override and clip settings are fixed at parse time.
+//
+// Style sheets are resolved by usvg while parsing, so rendering with a
+// different style sheet (`rb_tree_render_with_style_sheet()`) always
+// re-parses the stored source; only renders of the base tree skip parsing.
+// For templated SVGs the saving therefore comes from keeping the base tree
+// and cloning it (`rb_tree_clone()` copies the tree without parsing), not
+// from partial re-parses.
+//
+// usvg trees are immutable once built, so the recoloring functions
+// (`rb_tree_set_fill()`, `rb_tree_set_stroke()`) also re-parse, once per
+// call; the renders that follow use the updated tree without parsing.
+//
+// Rendering never mutates a tree: every render allocates its own pixmap and
+// errors are thread-local, so any number of threads may call
+// `rb_tree_render()`, `rb_tree_render_with_style_sheet()` and
+// `rb_tree_clone()` on one handle at the same time. The functions taking a
+// `*mut RBTree` (`rb_tree_set_fill()`, `rb_tree_set_stroke()`,
+// `rb_tree_free()`) need exclusive access: no other call may use the handle
+// while they run.
+
+/// A parsed SVG document. Opaque to C callers.
+#[derive(Clone)]
+pub struct RBTree {
+    /// The original SVG bytes, kept for re-parsing with style overrides
+    source: Vec<u8>,
+    /// The options the tree was parsed with
+    opts: RBOptions,
+    /// Paint overrides set with `rb_tree_set_fill()`/`rb_tree_set_stroke()`,
+    /// as (element id, CSS property, straight RGBA color)
+    paint_overrides: Vec<(String, &'static str, [u8; 4])>,
+    /// The parsed tree
+    parsed: ParsedSvg,
+}
+
+// Concurrent renders of one handle rely on this; fail the build if a
+// dependency update ever makes the tree thread-bound.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<RBTree>();
+};
+
+impl RBTree {
+    /// The options to re-parse the source with: the tree's own, with the
+    /// paint overrides and then `extra` appended to the style sheet.
+    fn parse_options(&self, extra: &str) -> RBOptions {
+        use std::fmt::Write;
+
+        let mut opts = self.opts.clone();
+        let mut css = opts.style_sheet.take().unwrap_or_default();
+        for (id, property, rgba) in &self.paint_overrides {
+            let [r, g, b, a] = *rgba;
+            let _ = write!(
+                css,
+                "\n[id=\"{id}\"] {{ {property}: rgb({r}, {g}, {b}); {property}-opacity: {} }}",
+                a as f32 / 255.0
+            );
+        }
+        css.push('\n');
+        css.push_str(extra);
+        opts.style_sheet = Some(css);
+        opts
+    }
+}
+
+/// Parses an SVG once for repeated rendering.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `opts` - Options to parse and render with, or null for the defaults from
+///   `rb_set_default_options()`; they are copied, so `opts` may be freed afterwards
+///
+/// # Returns
+/// * A tree that must be released with `rb_tree_free()`
+/// * Null if parsing failed (error is set)
+///
+/// # Safety
+/// `svg_ptr` must be valid for `svg_len` bytes; `opts` must be null or a
+/// valid pointer from `rb_options_new()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_tree_parse(svg_ptr: *const u8, svg_len: usize, opts: *const RBOptions) -> *mut RBTree {
+    ffi_guard(std::ptr::null_mut(), || {
+        clear_err();
+
+        if svg_ptr.is_null() || svg_len == 0 {
+            set_err("invalid args".into());
+            return std::ptr::null_mut();
+        }
+        let source = unsafe { slice::from_raw_parts(svg_ptr, svg_len) }.to_vec();
+        let opts = unsafe { opts.as_ref() }.cloned().unwrap_or_else(default_options);
+
+        match parse_svg(&source, &opts) {
+            Ok(parsed) => Box::into_raw(Box::new(RBTree { source, opts, paint_overrides: Vec::new(), parsed })),
+            Err(e) => {
+                set_err(e);
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Frees a tree created by `rb_tree_parse()` or `rb_tree_clone()`.
+///
+/// # Arguments
+/// * `tree` - The tree to free (null is ignored)
+///
+/// # Safety
+/// `tree` must come from this library and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rb_tree_free(tree: *mut RBTree) {
+    ffi_guard((), || {
+        if !tree.is_null() {
+            drop(unsafe { Box::from_raw(tree) });
+        }
+    })
+}
+
+/// Copies a parsed tree without re-parsing it.
+///
+/// # Arguments
+/// * `tree` - The tree to copy (must not be null)
+///
+/// # Returns
+/// * A new tree that must be released with `rb_tree_free()`
+/// * Null if `tree` is null (error is set)
+///
+/// # Safety
+/// `tree` must be a valid pointer from `rb_tree_parse()` or `rb_tree_clone()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_tree_clone(tree: *const RBTree) -> *mut RBTree {
+    ffi_guard(std::ptr::null_mut(), || {
+        clear_err();
+        let Some(tree) = (unsafe { tree.as_ref() }) else {
+            set_err("invalid args".into());
+            return std::ptr::null_mut();
+        };
+        Box::into_raw(Box::new(tree.clone()))
+    })
+}
+
+/// Renders a parsed tree to RGBA pixel data.
+///
+/// Several threads may render the same tree at once; see the section notes
+/// above.
+///
+/// # Arguments
+/// * `tree` - The tree to render (must not be null)
+/// * `width` - Desired output width in pixels (must be > 0)
+/// * `height` - Desired output height in pixels (must be > 0)
+///
+/// # Returns
+/// * `RBImage` struct containing the rendered pixel data
+/// * If an error occurs, returns an image with null pointer and zero dimensions
+///
+/// # Safety
+/// `tree` must be a valid pointer from `rb_tree_parse()` or `rb_tree_clone()`.
+/// The returned image must be freed with `rb_free_image()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_tree_render(tree: *const RBTree, width: u32, height: u32) -> RBImage {
+    unsafe { rb_tree_render_with_style_sheet(tree, std::ptr::null(), width, height) }
+}
+
+/// Renders a parsed tree with an additional style sheet.
+///
+/// The style sheet is applied after the one from the tree's options (so it
+/// wins on equal specificity) and only for this render. This re-parses the
+/// tree's source; a null or empty `css` renders the stored tree directly.
+///
+/// # Arguments
+/// * `tree` - The tree to render (must not be null)
+/// * `css` - Null-terminated CSS text, or null for none
+/// * `width` - Desired output width in pixels (must be > 0)
+/// * `height` - Desired output height in pixels (must be > 0)
+///
+/// # Returns
+/// * `RBImage` struct containing the rendered pixel data
+/// * If an error occurs, returns an image with null pointer and zero dimensions
+///
+/// # Safety
+/// `tree` must be a valid pointer from `rb_tree_parse()` or `rb_tree_clone()`;
+/// `css` must be null or a valid null-terminated string. The returned image
+/// must be freed with `rb_free_image()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_tree_render_with_style_sheet(
+    tree: *const RBTree,
+    css: *const c_char,
+    width: u32,
+    height: u32,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        let Some(tree) = (unsafe { tree.as_ref() }) else {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        };
+        if width == 0 || height == 0 {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        }
+        let css = (!css.is_null()).then(|| unsafe { CStr::from_ptr(css) }.to_string_lossy());
+
+        let result = match css.as_deref().filter(|css| !css.is_empty()) {
+            None => render_tree(&tree.parsed, width, height, &tree.opts),
+            Some(css) => {
+                let opts = tree.parse_options(css);
+                parse_svg(&tree.source, &opts).and_then(|parsed| render_tree(&parsed, width, height, &opts))
+            }
+        };
+
+        match result {
+            Ok(pixmap) => RBImage::from_render(pixmap, &tree.opts),
+            Err(e) => {
+                set_err(e);
+                RBImage::empty()
+            }
+        }
+    })
+}
+
+/// Sets the fill color of the element with the given `id`.
+///
+/// The color applies to the element and is inherited by its children, like
+/// a presentation attribute; a shape with `fill="none"` gets the new fill.
+/// The SVG's own `style` attributes and `#id` rules in its style sheets take
+/// precedence. Setting a color again for the same element replaces it.
+///
+/// # Arguments
+/// * `tree` - The tree to modify (must not be null)
+/// * `id` - Null-terminated element id; must not contain `"` or `\`
+/// * `r`, `g`, `b`, `a` - The color (straight alpha)
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if the id doesn't name a rendered shape, group or text
+///   element, or on any other error (error is set, the tree is unchanged)
+///
+/// # Safety
+/// `tree` must be a valid pointer from `rb_tree_parse()` or `rb_tree_clone()`
+/// and `id` a valid null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rb_tree_set_fill(tree: *mut RBTree, id: *const c_char, r: u8, g: u8, b: u8, a: u8) -> bool {
+    unsafe { tree_set_paint(tree, id, "fill", [r, g, b, a]) }
+}
+
+/// Sets the stroke color of the element with the given `id`.
+///
+/// Works like `rb_tree_set_fill()`. Only the color changes; an element
+/// without a stroke gets one with the SVG default width of 1.
+///
+/// # Arguments
+/// * `tree` - The tree to modify (must not be null)
+/// * `id` - Null-terminated element id; must not contain `"` or `\`
+/// * `r`, `g`, `b`, `a` - The color (straight alpha)
+///
+/// # Returns
+/// * `true` on success
+/// * `false` on error (error is set, the tree is unchanged)
+///
+/// # Safety
+/// Same requirements as `rb_tree_set_fill()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_tree_set_stroke(tree: *mut RBTree, id: *const c_char, r: u8, g: u8, b: u8, a: u8) -> bool {
+    unsafe { tree_set_paint(tree, id, "stroke", [r, g, b, a]) }
+}
+
+/// Shared FFI body of `rb_tree_set_fill()` and `rb_tree_set_stroke()`.
+///
+/// # Safety
+/// Same requirements as `rb_tree_set_fill()`.
+unsafe fn tree_set_paint(tree: *mut RBTree, id: *const c_char, property: &'static str, rgba: [u8; 4]) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+
+        let (Some(tree), false) = (unsafe { tree.as_mut() }, id.is_null()) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        let id = unsafe { CStr::from_ptr(id) }.to_string_lossy().into_owned();
+        if id.contains(['"', '\\']) {
+            set_err(format!("unsupported element id '{id}'"));
+            return false;
+        }
+        match tree.parsed.node_by_id(&id) {
+            Some(usvg::Node::Group(_) | usvg::Node::Path(_) | usvg::Node::Text(_)) => {}
+            Some(usvg::Node::Image(_)) => {
+                set_err(format!("element '{id}' has no {property}"));
+                return false;
+            }
+            None => {
+                set_err(format!("element '{id}' not found"));
+                return false;
+            }
+        }
+
+        let mut overrides = tree.paint_overrides.clone();
+        overrides.retain(|(i, p, _)| !(*i == id && *p == property));
+        overrides.push((id, property, rgba));
+        let previous = std::mem::replace(&mut tree.paint_overrides, overrides);
+
+        match parse_svg(&tree.source, &tree.parse_options("")) {
+            Ok(parsed) => {
+                tree.parsed = parsed;
+                true
+            }
+            Err(e) => {
+                tree.paint_overrides = previous;
+                set_err(e);
+                false
+            }
+        }
+    })
+}
+
+// ============================================================================
+// BATCH RENDERING
+// ============================================================================
+
+/// Callback type invoked as batch items finish, with the number finished so
+/// far, the total and the caller's userdata.
+pub type RBProgressCallback = Option<extern "C" fn(done: usize, total: usize, userdata: *mut c_void)>;
+
+/// Reports finished batch items to an `RBProgressCallback`, one call at a time.
+struct Progress {
+    cb: RBProgressCallback,
+    userdata: usize,
+    total: usize,
+    /// Items finished so far; held locked while the callback runs
+    done: std::sync::Mutex<usize>,
+}
+
+impl Progress {
+    fn new(cb: RBProgressCallback, userdata: *mut c_void, total: usize) -> Self {
+        Progress { cb, userdata: userdata as usize, total, done: std::sync::Mutex::new(0) }
+    }
+
+    /// Counts one finished item and notifies the callback.
+    fn item_done(&self) {
+        let Some(cb) = self.cb else { return };
+        let mut done = self.done.lock().unwrap_or_else(|e| e.into_inner());
+        *done += 1;
+        cb(*done, self.total, self.userdata as *mut c_void);
+    }
+}
+
+/// Renders `tree` at each of `sizes` in parallel on the current rayon pool.
+fn render_sizes(tree: &ParsedSvg, sizes: &[RBSize], opts: &RBOptions, progress: &Progress) -> Result<Vec<Pixmap>, String> {
+    sizes
+        .par_iter()
+        .enumerate()
+        .map(|(i, s)| {
+            let pixmap = render_tree(tree, s.width, s.height, opts).map_err(|e| format!("size {i}: {e}"))?;
+            progress.item_done();
+            Ok(pixmap)
+        })
+        .collect()
+}
+
+/// Renders one SVG at several sizes in parallel.
+///
+/// The SVG is parsed once and each size is rendered on the library's thread
+/// pool (see `rb_set_thread_count()`).
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `sizes` - Array of `count` output sizes (each dimension must be > 0)
+/// * `count` - Number of entries in `sizes` and `out_images`
+/// * `out_images` - Caller-provided array of `count` images to fill
+///
+/// # Returns
+/// * `true` on success; `out_images[i]` holds the render for `sizes[i]`
+/// * `false` on failure; no images are written (error is set)
+///
+/// # Safety
+/// `sizes` and `out_images` must each point to `count` valid elements.
+/// Every returned image must be freed with `rb_free_image()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_rgba_batch(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    sizes: *const RBSize,
+    count: usize,
+    out_images: *mut RBImage,
+) -> bool {
+    rb_render_svg_to_rgba_batch_with_progress(svg_ptr, svg_len, sizes, count, out_images, None, std::ptr::null_mut())
+}
+
+/// Renders one SVG at several sizes in parallel like
+/// `rb_render_svg_to_rgba_batch()`, reporting progress as sizes finish.
+///
+/// `progress_cb` is called once per size that finishes rendering, in
+/// completion order, with `done` counting up from 1 to `count`. It runs on
+/// the thread pool's worker threads, not the calling thread, but calls are
+/// serialized: no two overlap, so a callback that only touches its own
+/// state needs no locking, though anything it shares with other threads
+/// (such as a UI) does. The render waits while the callback runs, so keep
+/// it short—post the update to your UI thread rather than drawing in it.
+/// If a size fails, sizes already in flight may still be reported before the
+/// call returns `false`.
+///
+/// # Arguments
+/// * Same as `rb_render_svg_to_rgba_batch()`, plus:
+/// * `progress_cb` - Callback receiving the progress (null = none)
+/// * `userdata` - Opaque pointer forwarded to the callback unchanged
+///
+/// # Returns
+/// * Same as `rb_render_svg_to_rgba_batch()`
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba_batch()`; `userdata` must
+/// be usable from other threads.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_rgba_batch_with_progress(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    sizes: *const RBSize,
+    count: usize,
+    out_images: *mut RBImage,
+    progress_cb: RBProgressCallback,
+    userdata: *mut c_void,
+) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+
+        if svg_ptr.is_null() || svg_len == 0 || sizes.is_null() || out_images.is_null() || count == 0 {
+            set_err("invalid args".into());
+            return false;
+        }
+
+        let svg_bytes = unsafe { slice::from_raw_parts(svg_ptr, svg_len) };
+        let sizes = unsafe { slice::from_raw_parts(sizes, count) };
+        if sizes.iter().any(|s| s.width == 0 || s.height == 0) {
+            set_err("invalid args".into());
+            return false;
+        }
+
+        let opts = default_options();
+        let tree = match parse_svg(svg_bytes, &opts) {
+            Ok(t) => t,
+            Err(e) => {
+                set_err(e);
+                return false;
+            }
+        };
+
+        let progress = Progress::new(progress_cb, userdata, count);
+        let results = thread_pool().install(|| render_sizes(&tree, sizes, &opts, &progress));
+
+        // All-or-nothing: nothing is handed to the caller if any size failed
+        let pixmaps = match results {
+            Ok(p) => p,
+            Err(e) => {
+                set_err(e);
+                return false;
+            }
+        };
+
+        let out = unsafe { slice::from_raw_parts_mut(out_images, count) };
+        for (slot, pixmap) in out.iter_mut().zip(pixmaps) {
+            *slot = RBImage::from_pixmap(pixmap);
+        }
+        true
+    })
+}
+
+/// Renders one SVG at several square sizes and writes each as a PNG file.
+///
+/// The SVG is parsed once; sizes are then rendered and written one at a
+/// time, so only one image is in memory at once. Size `n` is written to
+/// `{dir}/{name_prefix}-{n}.png`, replacing an existing file. A size that
+/// fails to render or write doesn't stop the others: its error is recorded,
+/// prefixed with the file name, and the remaining sizes are still written.
+/// When several fail, their errors are joined with `"; "`.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `sizes` - Array of `count` edge lengths in pixels (each must be > 0)
+/// * `count` - Number of entries in `sizes`
+/// * `dir` - Null-terminated, UTF-8 path of an existing directory
+/// * `name_prefix` - Null-terminated, UTF-8 file name prefix, e.g. `"icon"`
+///
+/// # Returns
+/// * The number of files written; less than `count` if any size failed
+///   (error is set)
+/// * -1 on invalid arguments or if the SVG fails to parse (error is set)
+///
+/// # Safety
+/// `svg_ptr` must be valid for `svg_len` bytes, `sizes` must point to
+/// `count` valid elements, and `dir` and `name_prefix` must be valid
+/// null-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_png_set(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    sizes: *const u32,
+    count: usize,
+    dir: *const c_char,
+    name_prefix: *const c_char,
+) -> i32 {
+    rb_render_svg_to_png_set_with_progress(svg_ptr, svg_len, sizes, count, dir, name_prefix, None, std::ptr::null_mut())
+}
+
+/// Writes one PNG per size like `rb_render_svg_to_png_set()`, reporting
+/// progress as files finish.
+///
+/// Sizes are processed in order on the calling thread, and `progress_cb`
+/// is called after each one, whether it was written or failed, with `done`
+/// counting up from 1 to `count`.
+///
+/// # Arguments
+/// * Same as `rb_render_svg_to_png_set()`, plus:
+/// * `progress_cb` - Callback receiving the progress (null = none)
+/// * `userdata` - Opaque pointer forwarded to the callback unchanged
+///
+/// # Returns
+/// * Same as `rb_render_svg_to_png_set()`
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_png_set()`.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn rb_render_svg_to_png_set_with_progress(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    sizes: *const u32,
+    count: usize,
+    dir: *const c_char,
+    name_prefix: *const c_char,
+    progress_cb: RBProgressCallback,
+    userdata: *mut c_void,
+) -> i32 {
+    ffi_guard(-1, || {
+        clear_err();
+
+        if svg_ptr.is_null() || svg_len == 0 || sizes.is_null() || count == 0 || dir.is_null() || name_prefix.is_null() {
+            set_err("invalid args".into());
+            return -1;
+        }
+        let sizes = unsafe { slice::from_raw_parts(sizes, count) };
+        if sizes.contains(&0) {
+            set_err("invalid args".into());
+            return -1;
+        }
+        let (Ok(dir), Ok(prefix)) = (unsafe { CStr::from_ptr(dir) }.to_str(), unsafe { CStr::from_ptr(name_prefix) }.to_str()) else {
+            set_err("path is not valid UTF-8".into());
+            return -1;
+        };
+
+        let svg_bytes = unsafe { slice::from_raw_parts(svg_ptr, svg_len) };
+        let opts = default_options();
+        let tree = match parse_svg(svg_bytes, &opts) {
+            Ok(t) => t,
+            Err(e) => {
+                set_err(e);
+                return -1;
+            }
+        };
+
+        let progress = Progress::new(progress_cb, userdata, count);
+        let mut written = 0;
+        let mut errors = Vec::new();
+        for &size in sizes {
+            let name = format!("{prefix}-{size}.png");
+            let result = render_tree(&tree, size, size, &opts)
+                .and_then(|pixmap| pixmap.encode_png().map_err(|e| format!("png encode error: {e}")))
+                .and_then(|data| std::fs::write(std::path::Path::new(dir).join(&name), data).map_err(|e| format!("io error: {e}")));
+            match result {
+                Ok(()) => written += 1,
+                Err(e) => errors.push(format!("{name}: {e}")),
+            }
+            progress.item_done();
+        }
+        if !errors.is_empty() {
+            set_err(errors.join("; "));
+        }
+        written
+    })
+}
+
+// ============================================================================
+// COMPOSITING
+// ============================================================================
+
+/// Converts a tightly packed, straight-alpha pixel buffer into a pixmap.
+fn raw_pixels_to_pixmap(data: &[u8], format: RBPixelFormat, width: u32, height: u32) -> Result<Pixmap, String> {
+    let bpp = format.bytes_per_pixel();
+    let needed = (width as usize).checked_mul(height as usize).and_then(|n| n.checked_mul(bpp));
+    if needed.is_none_or(|n| data.len() < n) {
+        return Err("pixel buffer too small".into());
+    }
+
+    let mut pixmap = Pixmap::new(width, height).ok_or("alloc pixmap failed")?;
+    for (dst, src) in pixmap.pixels_mut().iter_mut().zip(data.chunks_exact(bpp)) {
+        let (r, g, b, a) = match format {
+            RBPixelFormat::Rgba8 => (src[0], src[1], src[2], src[3]),
+            RBPixelFormat::Bgra8 => (src[2], src[1], src[0], src[3]),
+            RBPixelFormat::Rgb8 => (src[0], src[1], src[2], 255),
+            RBPixelFormat::Gray8 => (src[0], src[0], src[0], 255),
+            RBPixelFormat::Rgba32F => {
+                let [r, g, b, a] = std::array::from_fn(|c| {
+                    let v = f32::from_ne_bytes([src[c * 4], src[c * 4 + 1], src[c * 4 + 2], src[c * 4 + 3]]);
+                    (v.clamp(0.0, 1.0) * 255.0).round() as u8
+                });
+                (r, g, b, a)
+            }
+        };
+        *dst = tiny_skia::ColorU8::from_rgba(r, g, b, a).premultiply();
+    }
+    Ok(pixmap)
+}
+
+/// Draws `src` onto `dst` at `(x, y)` with the given blend mode and opacity.
+fn composite(
+    dst: &mut tiny_skia::PixmapMut,
+    src: &Pixmap,
+    x: i32,
+    y: i32,
+    blend_mode: tiny_skia::BlendMode,
+    opacity: f32,
+) {
+    // tiny-skia does not clip sprites that hang off the top/left edge, so
+    // crop the source to the visible part first
+    let (x0, y0) = (x.max(0), y.max(0));
+    let x1 = (x as i64 + src.width() as i64).min(dst.width() as i64);
+    let y1 = (y as i64 + src.height() as i64).min(dst.height() as i64);
+    if x0 as i64 >= x1 || y0 as i64 >= y1 {
+        return;
+    }
+    let visible = tiny_skia::IntRect::from_xywh(x0 - x, y0 - y, (x1 - x0 as i64) as u32, (y1 - y0 as i64) as u32);
+    let Some(src) = visible.and_then(|r| src.clone_rect(r)) else {
+        return;
+    };
+
+    let paint = tiny_skia::PixmapPaint {
+        opacity,
+        blend_mode,
+        quality: tiny_skia::FilterQuality::Nearest,
+    };
+    dst.draw_pixmap(x0, y0, src.as_ref(), &paint, Transform::identity(), None);
+}
+
+/// Renders an SVG over a raster background image.
+///
+/// The background is scaled to `width × height` with bilinear filtering, the
+/// SVG is rendered into its own transparent layer, and the layer is composited
+/// over the background (Porter-Duff source-over). This is the common "SVG
+/// overlay on a photo" case.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `bg_ptr` - Background pixels, tightly packed, straight alpha (must not be null)
+/// * `bg_len` - Length of the background buffer in bytes
+/// * `bg_format` - Layout of the background pixels, as an `RBPixelFormat`
+///   value
+/// * `bg_w` - Background width in pixels (must be > 0)
+/// * `bg_h` - Background height in pixels (must be > 0)
+/// * `width` - Desired output width in pixels (must be > 0)
+/// * `height` - Desired output height in pixels (must be > 0)
+///
+/// # Returns
+/// * The composited image
+/// * If an error occurs, returns an image with null pointer and zero dimensions
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba()`; `bg_ptr` must be valid for
+/// `bg_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_rgba_with_background_image(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    bg_ptr: *const u8,
+    bg_len: usize,
+    bg_format: u32,
+    bg_w: u32,
+    bg_h: u32,
+    width: u32,
+    height: u32,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        let Some(bg_format) = RBPixelFormat::from_raw(bg_format) else {
+            set_err(format!("invalid args: unknown pixel format {bg_format}"));
+            return RBImage::empty();
+        };
+        if bg_ptr.is_null() || bg_w == 0 || bg_h == 0 {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        }
+        let bg_bytes = unsafe { slice::from_raw_parts(bg_ptr, bg_len) };
+
+        let result = raw_pixels_to_pixmap(bg_bytes, bg_format, bg_w, bg_h).and_then(|background| {
+            let layer = unsafe { render_svg_raw(svg_ptr, svg_len, width, height, &default_options()) }?;
+
+            // Scale the background onto the output canvas
+            let mut out = Pixmap::new(width, height).ok_or("alloc pixmap failed")?;
+            let paint = tiny_skia::PixmapPaint {
+                quality: tiny_skia::FilterQuality::Bilinear,
+                ..Default::default()
+            };
+            let ts = Transform::from_scale(width as f32 / bg_w as f32, height as f32 / bg_h as f32);
+            out.draw_pixmap(0, 0, background.as_ref(), &paint, ts, None);
+
+            composite(&mut out.as_mut(), &layer, 0, 0, tiny_skia::BlendMode::SourceOver, 1.0);
+            Ok(out)
+        });
+
+        match result {
+            Ok(pixmap) => RBImage::from_pixmap(pixmap),
+            Err(e) => {
+                set_err(e);
+                RBImage::empty()
+            }
+        }
+    })
+}
+
+/// Renders an SVG with a drop shadow behind it.
+///
+/// The SVG is rendered at `width × height`, and its alpha channel, offset by
+/// `(shadow_dx, shadow_dy)` and blurred with `rb_image_gaussian_blur()`'s
+/// kernel, is filled with the shadow color and composited underneath it
+/// (source-over). The output grows on each side as far as the shadow
+/// reaches, i.e. the offset plus the blur radius `ceil(3 * shadow_blur)`,
+/// so nothing is clipped; the SVG's own pixels start at
+/// `(max(0, ceil(r - dx)), max(0, ceil(r - dy)))` for a radius `r`.
+/// Fractional offsets are resampled bilinearly.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `width` - Width of the SVG itself in pixels (must be > 0)
+/// * `height` - Height of the SVG itself in pixels (must be > 0)
+/// * `shadow_dx` - Horizontal shadow offset in pixels; positive is right
+/// * `shadow_dy` - Vertical shadow offset in pixels; positive is down
+/// * `shadow_blur` - Blur standard deviation in pixels (must be >= 0;
+///   0 gives a hard shadow)
+/// * `shadow_r`, `shadow_g`, `shadow_b` - Shadow color
+/// * `shadow_a` - Shadow opacity (straight alpha); scales the silhouette's alpha
+///
+/// # Returns
+/// * The image containing the SVG and its shadow
+/// * If an error occurs, returns an image with null pointer and zero dimensions
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba()`.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn rb_render_svg_to_rgba_with_shadow(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    width: u32,
+    height: u32,
+    shadow_dx: f32,
+    shadow_dy: f32,
+    shadow_blur: f32,
+    shadow_r: u8,
+    shadow_g: u8,
+    shadow_b: u8,
+    shadow_a: u8,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        if !shadow_dx.is_finite() || !shadow_dy.is_finite() || !shadow_blur.is_finite() || shadow_blur < 0.0 {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        }
+
+        let result = unsafe { render_svg_raw(svg_ptr, svg_len, width, height, &default_options()) }.and_then(|svg| {
+            let radius = (3.0 * shadow_blur).ceil();
+            let pad = |offset: f32| ((radius - offset).max(0.0).ceil(), (radius + offset).max(0.0).ceil());
+            let ((left, right), (top, bottom)) = (pad(shadow_dx), pad(shadow_dy));
+            let out_w = width as f32 + left + right;
+            let out_h = height as f32 + top + bottom;
+            if out_w > i32::MAX as f32 || out_h > i32::MAX as f32 {
+                return Err("alloc pixmap failed".to_string());
+            }
+            let mut out = Pixmap::new(out_w as u32, out_h as u32).ok_or("alloc pixmap failed")?;
+
+            // Offset silhouette, then blurred and filled with the shadow color
+            let paint = tiny_skia::PixmapPaint {
+                quality: tiny_skia::FilterQuality::Bilinear,
+                ..Default::default()
+            };
+            let ts = Transform::from_translate(left + shadow_dx, top + shadow_dy);
+            out.draw_pixmap(0, 0, svg.as_ref(), &paint, ts, None);
+            if shadow_blur > 0.0 {
+                gaussian_blur(&mut out, shadow_blur);
+            }
+            for px in out.data_mut().chunks_exact_mut(4) {
+                let alpha = (px[3] as u32 * shadow_a as u32 + 127) / 255;
+                for (dst, c) in px.iter_mut().zip([shadow_r, shadow_g, shadow_b]) {
+                    *dst = ((c as u32 * alpha + 127) / 255) as u8;
+                }
+                px[3] = alpha as u8;
+            }
+            composite(&mut out.as_mut(), &svg, left as i32, top as i32, tiny_skia::BlendMode::SourceOver, 1.0);
+            Ok(out)
+        });
+
+        match result {
+            Ok(pixmap) => RBImage::from_pixmap(pixmap),
+            Err(e) => {
+                set_err(e);
+                RBImage::empty()
+            }
+        }
+    })
+}
+
+/// Renders an SVG and stamps a PNG watermark onto it.
+///
+/// The watermark is drawn at its own pixel size with its top-left corner at
+/// `(wm_x, wm_y)` (offsets may be negative; it is clipped to the output),
+/// using source-over compositing with its alpha scaled by `wm_opacity`.
+/// Everything happens on pixels; the SVG is not modified. To watermark with
+/// an SVG, render it first and encode it as PNG.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `width` - Desired output width in pixels (must be > 0)
+/// * `height` - Desired output height in pixels (must be > 0)
+/// * `wm_ptr` - Pointer to the watermark, a PNG file in memory (must not be null)
+/// * `wm_len` - Length of the watermark data in bytes
+/// * `wm_x` - Horizontal position of the watermark in output pixels
+/// * `wm_y` - Vertical position of the watermark in output pixels
+/// * `wm_opacity` - Watermark opacity, clamped to 0.0..=1.0
+///
+/// # Returns
+/// * The composited image
+/// * If an error occurs, returns an image with null pointer and zero dimensions
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba()`; `wm_ptr` must be valid for
+/// `wm_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_rgba_with_watermark(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    width: u32,
+    height: u32,
+    wm_ptr: *const u8,
+    wm_len: usize,
+    wm_x: i32,
+    wm_y: i32,
+    wm_opacity: f32,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        if wm_ptr.is_null() || wm_len == 0 || wm_opacity.is_nan() {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        }
+        let wm_bytes = unsafe { slice::from_raw_parts(wm_ptr, wm_len) };
+
+        let result = Pixmap::decode_png(wm_bytes)
+            .map_err(|e| format!("watermark decode error: {e}"))
+            .and_then(|watermark| {
+                let mut out = unsafe { render_svg_raw(svg_ptr, svg_len, width, height, &default_options()) }?;
+                let opacity = wm_opacity.clamp(0.0, 1.0);
+                composite(&mut out.as_mut(), &watermark, wm_x, wm_y, tiny_skia::BlendMode::SourceOver, opacity);
+                Ok(out)
+            });
+
+        match result {
+            Ok(pixmap) => RBImage::from_pixmap(pixmap),
+            Err(e) => {
+                set_err(e);
+                RBImage::empty()
+            }
+        }
+    })
+}
+
+/// Renders an SVG through a second SVG used as a cookie cutter.
+///
+/// Both documents are rendered at `width × height`, each stretched to fill
+/// it. The clip render's colors are ignored; its alpha scales the main
+/// render's pixels, so the main SVG shows where the clip SVG is opaque,
+/// partially where it is translucent (including its anti-aliased edges), and
+/// nowhere else. Neither source is modified.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the main SVG data (must not be null)
+/// * `svg_len` - Length of the main SVG data in bytes
+/// * `clip_svg_ptr` - Pointer to the clip SVG data (must not be null)
+/// * `clip_svg_len` - Length of the clip SVG data in bytes
+/// * `width` - Desired output width in pixels (must be > 0)
+/// * `height` - Desired output height in pixels (must be > 0)
+///
+/// # Returns
+/// * `RBImage` containing the masked render
+/// * If an error occurs in either SVG, returns an image with null pointer
+///   and zero dimensions
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba()`, for both SVGs.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_rgba_with_clip_path(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    clip_svg_ptr: *const u8,
+    clip_svg_len: usize,
+    width: u32,
+    height: u32,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        let opts = default_options();
+        let result = unsafe { render_svg_raw(clip_svg_ptr, clip_svg_len, width, height, &opts) }.and_then(|clip| {
+            let mut pixmap = unsafe { render_svg_raw(svg_ptr, svg_len, width, height, &opts) }?;
+            pixmap.apply_mask(&tiny_skia::Mask::from_pixmap(clip.as_ref(), tiny_skia::MaskType::Alpha));
+            Ok(pixmap)
+        });
+
+        match result {
+            Ok(pixmap) => RBImage::from_pixmap(pixmap),
+            Err(e) => {
+                set_err(e);
+                RBImage::empty()
+            }
+        }
+    })
+}
+
+/// Blend modes accepted by `rb_image_blend()`.
+///
+/// The first four are Porter-Duff operators; the rest are the separable
+/// blend modes from the W3C Compositing spec, composited source-over.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum RBBlendMode {
+    /// Source over destination
+    SrcOver = 0,
+    /// Source where destination is opaque
+    SrcIn = 1,
+    /// Source where destination is transparent
+    SrcOut = 2,
+    /// Destination over source
+    DstOver = 3,
+    /// Multiply color channels
+    Multiply = 4,
+    /// Inverse of multiplying the inverses
+    Screen = 5,
+    /// Multiply or screen, depending on the destination
+    Overlay = 6,
+    /// Darker of source and destination
+    Darken = 7,
+    /// Lighter of source and destination
+    Lighten = 8,
+}
+
+impl RBBlendMode {
+    /// The mode with the given numeric value, as passed in from C.
+    fn from_raw(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(RBBlendMode::SrcOver),
+            1 => Some(RBBlendMode::SrcIn),
+            2 => Some(RBBlendMode::SrcOut),
+            3 => Some(RBBlendMode::DstOver),
+            4 => Some(RBBlendMode::Multiply),
+            5 => Some(RBBlendMode::Screen),
+            6 => Some(RBBlendMode::Overlay),
+            7 => Some(RBBlendMode::Darken),
+            8 => Some(RBBlendMode::Lighten),
+            _ => None,
+        }
+    }
+}
+
+impl From<RBBlendMode> for tiny_skia::BlendMode {
+    fn from(mode: RBBlendMode) -> Self {
+        match mode {
+            RBBlendMode::SrcOver => tiny_skia::BlendMode::SourceOver,
+            RBBlendMode::SrcIn => tiny_skia::BlendMode::SourceIn,
+            RBBlendMode::SrcOut => tiny_skia::BlendMode::SourceOut,
+            RBBlendMode::DstOver => tiny_skia::BlendMode::DestinationOver,
+            RBBlendMode::Multiply => tiny_skia::BlendMode::Multiply,
+            RBBlendMode::Screen => tiny_skia::BlendMode::Screen,
+            RBBlendMode::Overlay => tiny_skia::BlendMode::Overlay,
+            RBBlendMode::Darken => tiny_skia::BlendMode::Darken,
+            RBBlendMode::Lighten => tiny_skia::BlendMode::Lighten,
+        }
+    }
+}
+
+/// Blends `src` into `dst` in place.
+///
+/// `src` is placed with its top-left corner at `(src_x, src_y)` in `dst`
+/// (offsets may be negative) and clipped to `dst`. Only pixels covered by
+/// `src` are touched, including for `SrcIn` and `SrcOut`.
+///
+/// # Arguments
+/// * `dst` - The destination image, modified in place (must not be null)
+/// * `src` - The image to blend in (must not be null)
+/// * `mode` - The blend mode, as an `RBBlendMode` value
+/// * `src_x` - Horizontal offset of `src` in `dst`
+/// * `src_y` - Vertical offset of `src` in `dst`
+///
+/// # Returns
+/// * true on success
+/// * false if an error occurred (`dst` is left unchanged)
+///
+/// # Safety
+/// `dst` and `src` must point to `RBImage`s returned by this library. They
+/// may be the same image.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_blend(
+    dst: *mut RBImage,
+    src: *const RBImage,
+    mode: u32,
+    src_x: i32,
+    src_y: i32,
+) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+
+        let Some(mode) = RBBlendMode::from_raw(mode) else {
+            set_err(format!("invalid args: unknown blend mode {mode}"));
+            return false;
+        };
+
+        // Copy the source first so `dst == src` needs no special casing
+        let Some(src) = (unsafe { src.as_ref() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        let src = match unsafe { image_to_pixmap(src) } {
+            Ok(p) => p,
+            Err(e) => {
+                set_err(e);
+                return false;
+            }
+        };
+
+        let Some(dst) = (unsafe { dst.as_mut() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        if let Err(e) = check_rgba(dst) {
+            set_err(e);
+            return false;
+        }
+        let bytes = unsafe { slice::from_raw_parts_mut(dst.ptr, dst.len) };
+        let Some(mut canvas) = tiny_skia::PixmapMut::from_bytes(bytes, dst.width, dst.height) else {
+            set_err("invalid image".into());
+            return false;
+        };
+
+        composite(&mut canvas, &src, src_x, src_y, mode.into(), 1.0);
+        true
+    })
+}
+
+/// Creates an opaque two-color checkerboard, the usual backdrop for showing
+/// transparency.
+///
+/// Blend a render over it with `rb_image_blend()` and `RBBlendMode::SrcOver`
+/// to display it the way image editors do. Photoshop's default grid uses
+/// white (255, 255, 255) and light gray (204, 204, 204) with 8 pixel
+/// squares. The square at the top-left corner gets the first color. Renders
+/// can get the same backing directly with `rb_options_set_checkerboard()`.
+///
+/// # Arguments
+/// * `width` - Image width in pixels (must be > 0)
+/// * `height` - Image height in pixels (must be > 0)
+/// * `cell_size` - Edge length of one square in pixels (must be > 0)
+/// * `color1_r`, `color1_g`, `color1_b` - RGB of the top-left square
+/// * `color2_r`, `color2_g`, `color2_b` - RGB of the squares next to it
+///
+/// # Returns
+/// * An opaque RGBA image
+/// * On error, an image with null pointer and zero dimensions (error is set)
+///
+/// # Safety
+/// The returned image must be freed with `rb_free_image()`.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "C" fn rb_create_checkerboard_image(
+    width: u32,
+    height: u32,
+    cell_size: u32,
+    color1_r: u8,
+    color1_g: u8,
+    color1_b: u8,
+    color2_r: u8,
+    color2_g: u8,
+    color2_b: u8,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        if width == 0 || height == 0 || cell_size == 0 {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        }
+        let Some(mut pixmap) = Pixmap::new(width, height) else {
+            set_err("alloc pixmap failed".into());
+            return RBImage::empty();
+        };
+        let colors = [[color1_r, color1_g, color1_b], [color2_r, color2_g, color2_b]];
+        Checkerboard { square_size: cell_size, colors }.fill_behind(&mut pixmap.as_mut(), (0.0, 0.0));
+        RBImage::from_pixmap(pixmap)
+    })
+}
+
+// ============================================================================
+// ENCODED OUTPUT
+// ============================================================================
+//
+// Functions in this section return encoded files (TIFF, ...) as a plain byte
+// buffer plus length. Buffers are released with `rb_free_buffer()`. The
+// `rb_image_write_*` functions write simple formats straight to a file.
+//
+// Each format gets the alpha convention it defines, independent of the
+// premultiplied RGBA that `RBImage` holds:
+// - PNG and TIFF: straight (unassociated) alpha; pixels are un-premultiplied
+//   before encoding
+// - PPM/PGM: no alpha; pixels are written composited over black
+// - SVG outlines: alpha only, thresholded into inside and outside
+
+/// Hands ownership of an encoded buffer to the caller.
+///
+/// # Safety
+/// `out_len` must be valid for a write.
+unsafe fn buffer_into_raw(data: Vec<u8>, out_len: *mut usize) -> *mut u8 {
+    let mut data = data.into_boxed_slice();
+    unsafe { *out_len = data.len() };
+    let ptr = data.as_mut_ptr();
+    std::mem::forget(data);
+    ptr
+}
+
+/// Copies a premultiplied pixmap into straight (unassociated) RGBA bytes,
+/// as expected by most file formats.
+fn demultiplied_rgba(pixmap: &Pixmap) -> Vec<u8> {
+    pixmap
+        .pixels()
+        .iter()
+        .flat_map(|p| {
+            let c = p.demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+        .collect()
+}
+
+/// Frees a buffer returned by one of the encoded-output functions.
+///
+/// # Arguments
+/// * `ptr` - The buffer (null is ignored)
+/// * `len` - The length reported when the buffer was returned
+///
+/// # Safety
+/// `ptr`/`len` must come from this library and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rb_free_buffer(ptr: *mut u8, len: usize) {
+    ffi_guard((), || {
+        if !ptr.is_null() && len > 0 {
+            drop(unsafe { Vec::from_raw_parts(ptr, len, len) });
+        }
+    })
+}
+
+/// Compression applied to TIFF output.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum RBTiffCompression {
+    /// Uncompressed strips
+    None = 0,
+    /// LZW with horizontal prediction
+    Lzw = 1,
+    /// Deflate (zlib) with horizontal prediction
+    Deflate = 2,
+}
+
+impl RBTiffCompression {
+    /// The compression with the given numeric value, as passed in from C.
+    fn from_raw(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(RBTiffCompression::None),
+            1 => Some(RBTiffCompression::Lzw),
+            2 => Some(RBTiffCompression::Deflate),
+            _ => None,
+        }
+    }
+}
+
+/// Encodes a pixmap as an RGBA TIFF with unassociated (straight) alpha.
+fn encode_tiff(pixmap: &Pixmap, compression: RBTiffCompression) -> Result<Vec<u8>, String> {
+    use tiff::encoder::{colortype, Compression, DeflateLevel, Predictor, TiffEncoder};
+    use tiff::tags::{ExtraSamples, Tag};
+
+    let (compression, predictor) = match compression {
+        RBTiffCompression::None => (Compression::Uncompressed, Predictor::None),
+        RBTiffCompression::Lzw => (Compression::Lzw, Predictor::Horizontal),
+        RBTiffCompression::Deflate => (Compression::Deflate(DeflateLevel::Balanced), Predictor::Horizontal),
+    };
+
+    let rgba = demultiplied_rgba(pixmap);
+    let mut out = std::io::Cursor::new(Vec::new());
+    let result = (|| {
+        let mut encoder = TiffEncoder::new(&mut out)?
+            .with_compression(compression)
+            .with_predictor(predictor);
+        let mut image = encoder.new_image::<colortype::RGBA8>(pixmap.width(), pixmap.height())?;
+        image.encoder().write_tag(Tag::ExtraSamples, ExtraSamples::UnassociatedAlpha.to_u16())?;
+        image.write_data(&rgba)
+    })();
+    result.map_err(|e| format!("tiff encode error: {e}"))?;
+    Ok(out.into_inner())
+}
+
+/// Renders an SVG file to an RGBA TIFF in memory.
+///
+/// The TIFF stores 8-bit RGBA with straight (unassociated) alpha, so
+/// transparency is preserved for archival use.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `width` - Desired output width in pixels (must be > 0)
+/// * `height` - Desired output height in pixels (must be > 0)
+/// * `compression` - Strip compression to apply, as an `RBTiffCompression`
+///   value
+/// * `out_len` - Receives the length of the returned buffer (must not be null)
+///
+/// # Returns
+/// * Pointer to the TIFF bytes, to be freed with `rb_free_buffer()`
+/// * Null on error (error is set, `out_len` is set to 0)
+///
+/// # Safety
+/// The caller must ensure `svg_ptr` points to valid SVG data for `svg_len`
+/// bytes and that `out_len` is valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_tiff(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    width: u32,
+    height: u32,
+    compression: u32,
+    out_len: *mut usize,
+) -> *mut u8 {
+    ffi_guard(std::ptr::null_mut(), || {
+        clear_err();
+
+        if out_len.is_null() {
+            set_err("invalid args".into());
+            return std::ptr::null_mut();
+        }
+        unsafe { *out_len = 0 };
+
+        let Some(compression) = RBTiffCompression::from_raw(compression) else {
+            set_err(format!("invalid args: unknown TIFF compression {compression}"));
+            return std::ptr::null_mut();
+        };
+
+        let encoded = unsafe { render_svg_raw(svg_ptr, svg_len, width, height, &default_options()) }
+            .and_then(|pixmap| encode_tiff(&pixmap, compression));
+        match encoded {
+            Ok(data) => unsafe { buffer_into_raw(data, out_len) },
+            Err(e) => {
+                set_err(e);
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Writes an image as a binary Netpbm file: P6 (RGB) or P5 (grayscale).
+///
+/// # Safety
+/// `img.ptr` must be null or valid for `img.len` bytes.
+unsafe fn write_netpbm(img: &RBImage, path: &str, gray: bool) -> Result<(), String> {
+    use std::io::Write;
+
+    let (w, h) = (img.width as usize, img.height as usize);
+    // Premultiplied channels are the image composited over black
+    let pixels = unsafe { rgba_pixels(img) }?.chunks_exact(4);
+    let (magic, body): (&str, Vec<u8>) = if gray {
+        let luma = |p: &[u8]| ((p[0] as u32 * 299 + p[1] as u32 * 587 + p[2] as u32 * 114) / 1000) as u8;
+        ("P5", pixels.map(luma).collect())
+    } else {
+        ("P6", pixels.flat_map(|p| [p[0], p[1], p[2]]).collect())
+    };
+
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path).map_err(|e| format!("io error: {e}"))?);
+    write!(file, "{magic}\n{w} {h}\n255\n")
+        .and_then(|_| file.write_all(&body))
+        .and_then(|_| file.flush())
+        .map_err(|e| format!("io error: {e}"))
+}
+
+/// Writes an image to a binary PPM (P6) file.
+///
+/// PPM has no alpha channel: pixels are written as if composited over black.
+///
+/// # Arguments
+/// * `img` - The image to write (must not be null)
+/// * `path` - Null-terminated, UTF-8 file path; an existing file is replaced
+///
+/// # Returns
+/// * `true` on success
+/// * `false` on error (error is set)
+///
+/// # Safety
+/// `img` must point to an `RBImage` returned by this library; `path` must be
+/// a valid null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_write_ppm(img: *const RBImage, path: *const c_char) -> bool {
+    unsafe { image_write_netpbm(img, path, false) }
+}
+
+/// Writes an image to a binary PGM (P5) grayscale file.
+///
+/// Gray levels are Rec. 601 luma of the image composited over black.
+///
+/// # Arguments
+/// * `img` - The image to write (must not be null)
+/// * `path` - Null-terminated, UTF-8 file path; an existing file is replaced
+///
+/// # Returns
+/// * `true` on success
+/// * `false` on error (error is set)
+///
+/// # Safety
+/// `img` must point to an `RBImage` returned by this library; `path` must be
+/// a valid null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_write_pgm(img: *const RBImage, path: *const c_char) -> bool {
+    unsafe { image_write_netpbm(img, path, true) }
+}
+
+/// Shared FFI body of `rb_image_write_ppm()` and `rb_image_write_pgm()`.
+///
+/// # Safety
+/// Same requirements as `rb_image_write_ppm()`.
+unsafe fn image_write_netpbm(img: *const RBImage, path: *const c_char, gray: bool) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+
+        let (Some(img), false) = (unsafe { img.as_ref() }, path.is_null()) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        let Ok(path) = unsafe { CStr::from_ptr(path) }.to_str() else {
+            set_err("path is not valid UTF-8".into());
+            return false;
+        };
+
+        match unsafe { write_netpbm(img, path, gray) } {
+            Ok(()) => true,
+            Err(e) => {
+                set_err(e);
+                false
+            }
+        }
+    })
+}
+
+/// Renders an SVG file to a PNG in memory.
+///
+/// PNG defines straight alpha, so the premultiplied render is
+/// un-premultiplied before encoding; a 50% transparent red pixel is stored
+/// as `(255, 0, 0, 128)`.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `width` - Desired output width in pixels (must be > 0)
+/// * `height` - Desired output height in pixels (must be > 0)
+/// * `out_len` - Receives the length of the returned buffer (must not be null)
+///
+/// # Returns
+/// * Pointer to the PNG bytes, to be freed with `rb_free_buffer()`
+/// * Null on error (error is set, `out_len` is set to 0)
+///
+/// # Safety
+/// The caller must ensure `svg_ptr` points to valid SVG data for `svg_len`
+/// bytes and that `out_len` is valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_png(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    width: u32,
+    height: u32,
+    out_len: *mut usize,
+) -> *mut u8 {
+    ffi_guard(std::ptr::null_mut(), || {
+        clear_err();
+
+        if out_len.is_null() {
+            set_err("invalid args".into());
+            return std::ptr::null_mut();
+        }
+        unsafe { *out_len = 0 };
+
+        // `encode_png` un-premultiplies as part of encoding
+        let encoded = unsafe { render_svg_raw(svg_ptr, svg_len, width, height, &default_options()) }
+            .and_then(|pixmap| pixmap.encode_png().map_err(|e| format!("png encode error: {e}")));
+        match encoded {
+            Ok(data) => unsafe { buffer_into_raw(data, out_len) },
+            Err(e) => {
+                set_err(e);
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Renders an SVG and writes it as a color-managed PNG.
+///
+/// The SVG is rendered in sRGB, as SVG colors are defined. With
+/// `icc_profile_path` null, pixels are written unchanged and an sRGB
+/// profile (Little CMS's built-in IEC 61966-2.1 equivalent) is embedded in
+/// the PNG's `iCCP` chunk. With a profile, the RGB channels are converted
+/// from sRGB to that profile's color space with the perceptual rendering
+/// intent and the profile itself is embedded, so color-managed viewers and
+/// print pipelines reproduce the original colors. Only RGB output profiles
+/// can be used, since PNG stores RGB or gray. Alpha is straight, as in
+/// `rb_render_svg_to_png()`, and is not converted.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `width` - Desired output width in pixels (must be > 0)
+/// * `height` - Desired output height in pixels (must be > 0)
+/// * `icc_profile_path` - Null-terminated, UTF-8 path of an ICC profile, or
+///   null for sRGB
+/// * `output_path` - Null-terminated, UTF-8 path of the PNG to write; an
+///   existing file is replaced
+///
+/// # Returns
+/// * `true` on success
+/// * `false` on error, e.g. an unreadable or non-RGB profile (error is set)
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba()`; both paths must be null
+/// or valid null-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_png_with_icc(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    width: u32,
+    height: u32,
+    icc_profile_path: *const c_char,
+    output_path: *const c_char,
+) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+
+        if output_path.is_null() {
+            set_err("invalid args".into());
+            return false;
+        }
+        let path = |p: *const c_char| unsafe { CStr::from_ptr(p) }.to_str().map_err(|_| "path is not valid UTF-8".to_string());
+        let result = path(output_path).and_then(|output_path| {
+            let icc_profile_path = if icc_profile_path.is_null() { None } else { Some(path(icc_profile_path)?) };
+            let pixmap = unsafe { render_svg_raw(svg_ptr, svg_len, width, height, &default_options()) }?;
+            write_png_with_icc(&pixmap, icc_profile_path, output_path)
+        });
+
+        match result {
+            Ok(()) => true,
+            Err(e) => {
+                set_err(e);
+                false
+            }
+        }
+    })
+}
+
+/// Implementation of `rb_render_svg_to_png_with_icc()` after rendering.
+fn write_png_with_icc(pixmap: &Pixmap, icc_profile_path: Option<&str>, output_path: &str) -> Result<(), String> {
+    let srgb = lcms2::Profile::new_srgb();
+    let mut rgb: Vec<[u8; 3]> = Vec::with_capacity(pixmap.pixels().len());
+    let mut alpha: Vec<u8> = Vec::with_capacity(pixmap.pixels().len());
+    for px in pixmap.pixels() {
+        let c = px.demultiply();
+        rgb.push([c.red(), c.green(), c.blue()]);
+        alpha.push(c.alpha());
+    }
+
+    let icc = match icc_profile_path {
+        None => srgb.icc().map_err(|e| format!("icc error: {e}"))?,
+        Some(path) => {
+            let data = std::fs::read(path).map_err(|e| format!("io error: {e}"))?;
+            let profile = lcms2::Profile::new_icc(&data).map_err(|e| format!("icc error: {e}"))?;
+            if profile.color_space() != lcms2::ColorSpaceSignature::RgbData {
+                return Err(format!("icc error: unsupported profile color space {:?}", profile.color_space()));
+            }
+            let transform = lcms2::Transform::<[u8; 3], [u8; 3]>::new(
+                &srgb,
+                lcms2::PixelFormat::RGB_8,
+                &profile,
+                lcms2::PixelFormat::RGB_8,
+                lcms2::Intent::Perceptual,
+            )
+            .map_err(|e| format!("icc error: {e}"))?;
+            transform.transform_in_place(&mut rgb);
+            data
+        }
+    };
+
+    let data: Vec<u8> = rgb.iter().zip(&alpha).flat_map(|(&[r, g, b], &a)| [r, g, b, a]).collect();
+    let mut info = png::Info::with_size(pixmap.width(), pixmap.height());
+    info.color_type = png::ColorType::Rgba;
+    info.bit_depth = png::BitDepth::Eight;
+    info.icc_profile = Some(icc.into());
+
+    let file = std::io::BufWriter::new(std::fs::File::create(output_path).map_err(|e| format!("io error: {e}"))?);
+    let encoder = png::Encoder::with_info(file, info).map_err(|e| format!("png encode error: {e}"))?;
+    let mut writer = encoder.write_header().map_err(|e| format!("png encode error: {e}"))?;
+    writer.write_image_data(&data).map_err(|e| format!("png encode error: {e}"))?;
+    writer.finish().map_err(|e| format!("png encode error: {e}"))
+}
+
+/// Encodes an image of any `RBPixelFormat` as PNG.
+///
+/// # Safety
+/// `img.ptr` must be null or valid for `img.len` bytes.
+unsafe fn encode_image_png(img: &RBImage) -> Result<Vec<u8>, String> {
+    let format = img.pixel_format()?;
+    let pixels = unsafe { image_bytes(img) };
+    let expected = (img.width as usize)
+        .checked_mul(img.height as usize)
+        .and_then(|n| n.checked_mul(format.bytes_per_pixel()));
+    if pixels.is_empty() || expected != Some(pixels.len()) {
+        return Err("invalid image".into());
+    }
+
+    let demultiply = |r: u8, g: u8, b: u8, a: u8| {
+        let c = tiny_skia::PremultipliedColorU8::from_rgba(r, g, b, a)
+            .map(|c| c.demultiply())
+            .unwrap_or(tiny_skia::ColorU8::from_rgba(0, 0, 0, 0));
+        [c.red(), c.green(), c.blue(), c.alpha()]
+    };
+    let (color, depth, data): (png::ColorType, png::BitDepth, Vec<u8>) = match format {
+        RBPixelFormat::Rgba8 => (
+            png::ColorType::Rgba,
+            png::BitDepth::Eight,
+            pixels.chunks_exact(4).flat_map(|p| demultiply(p[0], p[1], p[2], p[3])).collect(),
+        ),
+        RBPixelFormat::Bgra8 => (
+            png::ColorType::Rgba,
+            png::BitDepth::Eight,
+            pixels.chunks_exact(4).flat_map(|p| demultiply(p[2], p[1], p[0], p[3])).collect(),
+        ),
+        RBPixelFormat::Rgb8 => (png::ColorType::Rgb, png::BitDepth::Eight, pixels.to_vec()),
+        RBPixelFormat::Gray8 => (png::ColorType::Grayscale, png::BitDepth::Eight, pixels.to_vec()),
+        RBPixelFormat::Rgba32F => {
+            // 16 bits per channel keeps most of the float precision
+            let data = pixels
+                .chunks_exact(16)
+                .flat_map(|p| {
+                    let [r, g, b, a]: [f32; 4] =
+                        std::array::from_fn(|c| f32::from_ne_bytes([p[c * 4], p[c * 4 + 1], p[c * 4 + 2], p[c * 4 + 3]]));
+                    let a = a.clamp(0.0, 1.0);
+                    let straight = |v: f32| if a > 0.0 { (v / a).clamp(0.0, 1.0) } else { 0.0 };
+                    [straight(r), straight(g), straight(b), a]
+                        .map(|v| ((v * 65535.0).round() as u16).to_be_bytes())
+                        .into_iter()
+                        .flatten()
+                })
+                .collect();
+            (png::ColorType::Rgba, png::BitDepth::Sixteen, data)
+        }
+    };
+
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, img.width, img.height);
+    encoder.set_color(color);
+    encoder.set_depth(depth);
+    let mut writer = encoder.write_header().map_err(|e| format!("png encode error: {e}"))?;
+    writer.write_image_data(&data).map_err(|e| format!("png encode error: {e}"))?;
+    writer.finish().map_err(|e| format!("png encode error: {e}"))?;
+    Ok(out)
+}
+
+/// Encodes an existing image as PNG, e.g. after editing its pixels.
+///
+/// The image's `format` field decides the PNG layout: RGBA and BGRA images
+/// (premultiplied, as this library produces them) are un-premultiplied into
+/// 8-bit RGBA, `RBPixelFormat::Rgb8` becomes 8-bit RGB,
+/// `RBPixelFormat::Gray8` 8-bit grayscale and `RBPixelFormat::Rgba32F`
+/// 16-bit RGBA. Caller-built images must set `format` to an `RBPixelFormat`
+/// value (anything else is an "invalid args" error) and a matching `len`.
+///
+/// # Arguments
+/// * `img` - The image to encode (must not be null)
+/// * `out_len` - Receives the length of the returned buffer (must not be null)
+///
+/// # Returns
+/// * Pointer to the PNG bytes, to be freed with `rb_free_buffer()`
+/// * Null on error (error is set, `out_len` is set to 0)
+///
+/// # Safety
+/// `img` must point to an `RBImage` whose `ptr` is valid for `len` bytes,
+/// and `out_len` must be valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_to_png(img: *const RBImage, out_len: *mut usize) -> *mut u8 {
+    ffi_guard(std::ptr::null_mut(), || {
+        clear_err();
+
+        if out_len.is_null() {
+            set_err("invalid args".into());
+            return std::ptr::null_mut();
+        }
+        unsafe { *out_len = 0 };
+        let Some(img) = (unsafe { img.as_ref() }) else {
+            set_err("invalid args".into());
+            return std::ptr::null_mut();
+        };
+
+        match unsafe { encode_image_png(img) } {
+            Ok(data) => unsafe { buffer_into_raw(data, out_len) },
+            Err(e) => {
+                set_err(e);
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Traces the boundaries of a binary mask with marching squares.
+///
+/// Samples sit at pixel centers and the mask is padded with one ring of
+/// outside samples, so every contour is closed. Points are in doubled pixel
+/// coordinates (every crossing lies on a half-pixel). Saddle cells keep
+/// diagonal inside samples apart.
+fn trace_contours(inside: &[bool], width: usize, height: usize) -> Vec<Vec<(i64, i64)>> {
+    let sample = |x: i64, y: i64| -> bool {
+        x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height && inside[y as usize * width + x as usize]
+    };
+
+    // Every crossing point ends exactly two segments, so contours can be
+    // followed through an adjacency map without orienting the segments
+    let mut segments: Vec<[(i64, i64); 2]> = Vec::new();
+    for y in -1..height as i64 {
+        for x in -1..width as i64 {
+            let (tl, tr, br, bl) = (sample(x, y), sample(x + 1, y), sample(x + 1, y + 1), sample(x, y + 1));
+            let top = (2 * x + 2, 2 * y + 1);
+            let right = (2 * x + 3, 2 * y + 2);
+            let bottom = (2 * x + 2, 2 * y + 3);
+            let left = (2 * x + 1, 2 * y + 2);
+            match (tl != tr, tr != br, br != bl, bl != tl) {
+                (false, false, false, false) => {}
+                (true, true, true, true) if tl => segments.extend([[top, left], [right, bottom]]),
+                (true, true, true, true) => segments.extend([[top, right], [left, bottom]]),
+                crossings => {
+                    let ends: Vec<_> = [(crossings.0, top), (crossings.1, right), (crossings.2, bottom), (crossings.3, left)]
+                        .into_iter()
+                        .filter_map(|(crosses, p)| crosses.then_some(p))
+                        .collect();
+                    segments.push([ends[0], ends[1]]);
+                }
+            }
+        }
+    }
+
+    let mut by_point: std::collections::HashMap<(i64, i64), Vec<usize>> = std::collections::HashMap::new();
+    for (i, seg) in segments.iter().enumerate() {
+        for p in seg {
+            by_point.entry(*p).or_default().push(i);
+        }
+    }
+
+    let mut used = vec![false; segments.len()];
+    let mut contours = Vec::new();
+    for start in 0..segments.len() {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+        let [first, mut point] = segments[start];
+        let mut contour = vec![first];
+        while point != first {
+            contour.push(point);
+            let Some(&next) = by_point[&point].iter().find(|&&s| !used[s]) else { break };
+            used[next] = true;
+            let [a, b] = segments[next];
+            point = if a == point { b } else { a };
+        }
+        contours.push(contour);
+    }
+    contours
+}
+
+/// Simplifies an open polyline with Ramer–Douglas–Peucker, keeping both ends.
+fn simplify_polyline(points: &[(f64, f64)], epsilon: f64, out: &mut Vec<(f64, f64)>) {
+    let (Some(&a), Some(&b)) = (points.first(), points.last()) else { return };
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len = dx.hypot(dy);
+    let distance = |p: &(f64, f64)| {
+        if len == 0.0 {
+            (p.0 - a.0).hypot(p.1 - a.1)
+        } else {
+            ((p.0 - a.0) * dy - (p.1 - a.1) * dx).abs() / len
+        }
+    };
+    let farthest = points
+        .iter()
+        .enumerate()
+        .skip(1)
+        .take(points.len().saturating_sub(2))
+        .map(|(i, p)| (i, distance(p)))
+        .max_by(|x, y| x.1.total_cmp(&y.1));
+    match farthest {
+        Some((i, d)) if d > epsilon => {
+            simplify_polyline(&points[..=i], epsilon, out);
+            out.pop();
+            simplify_polyline(&points[i..], epsilon, out);
+        }
+        _ => {
+            out.push(a);
+            if points.len() > 1 {
+                out.push(b);
+            }
+        }
+    }
+}
+
+/// Renders an SVG and traces its silhouette back into an SVG path.
+///
+/// The pipeline is: render at `width × height`, mark pixels whose alpha is
+/// above `threshold` as inside, extract the boundaries of that mask with
+/// marching squares (samples at pixel centers), simplify each boundary with
+/// Ramer–Douglas–Peucker at a tolerance of 0.5 pixels, and write the result
+/// as one `<path fill-rule="evenodd">` so holes stay holes. Colors are
+/// discarded; the output is the shape only, as cutters and routers expect.
+///
+/// The result is a standalone SVG of `width × height` user units, one unit
+/// per output pixel, with no path if nothing is above the threshold. Like
+/// any raster trace it is only as precise as the render: render larger for
+/// finer outlines.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `width` - Render width in pixels (must be > 0)
+/// * `height` - Render height in pixels (must be > 0)
+/// * `threshold` - Pixels with alpha at or below this count as outside
+/// * `out_svg` - Receives the outline SVG, to be freed with `rb_free_buffer()`
+/// * `out_svg_len` - Receives the length of the outline SVG in bytes
+///
+/// # Returns
+/// * `true` on success
+/// * `false` on error (error is set, outputs are null/0)
+///
+/// # Safety
+/// `svg_ptr` must be valid for `svg_len` bytes and both output pointers must
+/// be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rb_render_svg_to_svg_outline(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    width: u32,
+    height: u32,
+    threshold: u8,
+    out_svg: *mut *mut u8,
+    out_svg_len: *mut usize,
+) -> bool {
+    /// Largest distance, in pixels, simplification may move the outline
+    const TOLERANCE: f64 = 0.5;
+
+    ffi_guard(false, || {
+        clear_err();
+
+        if out_svg.is_null() || out_svg_len.is_null() {
+            set_err("invalid args".into());
+            return false;
+        }
+        unsafe {
+            *out_svg = std::ptr::null_mut();
+            *out_svg_len = 0;
+        }
+        let pixmap = match unsafe { render_svg_raw(svg_ptr, svg_len, width, height, &default_options()) } {
+            Ok(pixmap) => pixmap,
+            Err(e) => {
+                set_err(e);
+                return false;
+            }
+        };
+
+        let inside: Vec<bool> = pixmap.data().chunks_exact(4).map(|px| px[3] > threshold).collect();
+        let mut d = String::new();
+        for contour in trace_contours(&inside, width as usize, height as usize) {
+            // Split the closed loop at its start and the point farthest from
+            // it, so both halves simplify as open polylines
+            let mut points: Vec<(f64, f64)> = contour.iter().map(|&(x, y)| (x as f64 / 2.0, y as f64 / 2.0)).collect();
+            let start = points[0];
+            let far = (0..points.len())
+                .max_by(|&i, &j| {
+                    let dist = |k: usize| (points[k].0 - start.0).hypot(points[k].1 - start.1);
+                    dist(i).total_cmp(&dist(j))
+                })
+                .unwrap_or(0);
+            points.push(start);
+            let mut simplified = Vec::new();
+            simplify_polyline(&points[..=far], TOLERANCE, &mut simplified);
+            simplified.pop();
+            simplify_polyline(&points[far..], TOLERANCE, &mut simplified);
+            simplified.pop();
+            if simplified.len() < 3 {
+                continue;
+            }
+
+            for (i, (x, y)) in simplified.iter().enumerate() {
+                d.push_str(if i == 0 { "M" } else { "L" });
+                d.push_str(&format!("{x} {y}"));
+            }
+            d.push('Z');
+        }
+
+        let mut svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+        );
+        if !d.is_empty() {
+            svg.push_str(&format!(r#"<path fill-rule="evenodd" d="{d}"/>"#));
+        }
+        svg.push_str("</svg>\n");
+        unsafe { *out_svg = buffer_into_raw(svg.into_bytes(), out_svg_len) };
+        true
+    })
+}
+
+// ============================================================================
+// IMAGE OPERATIONS
+// ============================================================================
+
+/// Borrows the pixel bytes of an image; empty for a null image.
+///
+/// # Safety
+/// `img.ptr` must be null or valid for `img.len` bytes.
+unsafe fn image_bytes(img: &RBImage) -> &[u8] {
+    if img.ptr.is_null() || img.len == 0 {
+        &[]
+    } else {
+        unsafe { slice::from_raw_parts(img.ptr, img.len) }
+    }
+}
+
+/// Checks that `img` is an `RBPixelFormat::Rgba8` image whose `len` matches
+/// its size, as operations that read pixels as premultiplied RGBA need.
+///
+/// Images in other formats, e.g. from `rb_image_convert()`, are rejected as
+/// "invalid args" instead of having their channels misread.
+fn check_rgba(img: &RBImage) -> Result<(), String> {
+    let format = img.pixel_format()?;
+    if format != RBPixelFormat::Rgba8 {
+        return Err(format!("invalid args: expected an Rgba8 image, got {format:?}"));
+    }
+    let expected = (img.width as usize).checked_mul(img.height as usize).and_then(|n| n.checked_mul(4));
+    if img.ptr.is_null() || img.len == 0 || expected != Some(img.len) {
+        return Err("invalid image".into());
+    }
+    Ok(())
+}
+
+/// Borrows the pixel bytes of an image that passes `check_rgba()`.
+///
+/// # Safety
+/// `img.ptr` must be null or valid for `img.len` bytes.
+unsafe fn rgba_pixels(img: &RBImage) -> Result<&[u8], String> {
+    check_rgba(img)?;
+    Ok(unsafe { image_bytes(img) })
+}
+
+/// Copies an RGBA image into a new pixmap.
+///
+/// Pixel data is taken as-is, i.e. as premultiplied RGBA like every image
+/// this library returns.
+///
+/// # Safety
+/// `img.ptr` must be null or valid for `img.len` bytes.
+unsafe fn image_to_pixmap(img: &RBImage) -> Result<Pixmap, String> {
+    let data = unsafe { rgba_pixels(img) }?.to_vec();
+    let size = tiny_skia::IntSize::from_wh(img.width, img.height).ok_or("invalid image")?;
+    Pixmap::from_vec(data, size).ok_or_else(|| "invalid image".into())
+}
+
+/// 64-bit FNV-1a over a sequence of byte chunks.
+fn fnv1a64<'a>(chunks: impl IntoIterator<Item = &'a [u8]>) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    let mut hash = OFFSET;
+    for chunk in chunks {
+        for &b in chunk {
+            hash ^= b as u64;
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+    hash
+}
+
+/// Computes a stable content hash of an image, suitable as a cache key.
+///
+/// The hash is 64-bit FNV-1a over the width and height (little-endian `u32`s)
+/// followed by the pixel bytes. It is not cryptographic, but it is identical
+/// across runs, platforms and library builds for identical images.
+///
+/// # Arguments
+/// * `img` - The image to hash (must not be null)
+///
+/// # Returns
+/// * The hash value
+/// * 0 if `img` is null (error is set)
+///
+/// # Safety
+/// `img` must point to an `RBImage` returned by this library (or one whose
+/// `ptr` is valid for `len` bytes).
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_hash(img: *const RBImage) -> u64 {
+    ffi_guard(0, || {
+        clear_err();
+        let Some(img) = (unsafe { img.as_ref() }) else {
+            set_err("invalid args".into());
+            return 0;
+        };
+        let pixels = unsafe { image_bytes(img) };
+        fnv1a64([&img.width.to_le_bytes()[..], &img.height.to_le_bytes()[..], pixels])
+    })
+}
+
+/// Checksum algorithm for `rb_image_compute_checksum()`.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RBChecksumAlgorithm {
+    /// CRC-32 (IEEE 802.3, as in zlib, PNG and `crc32` tools), in the low 32 bits
+    Crc32 = 0,
+    /// 64-bit FNV-1a
+    Fnv1a = 1,
+    /// XXH64 with seed 0; the fastest of the three
+    Xxh64 = 2,
+}
+
+impl RBChecksumAlgorithm {
+    /// The algorithm with the given numeric value, as passed in from C.
+    fn from_raw(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(RBChecksumAlgorithm::Crc32),
+            1 => Some(RBChecksumAlgorithm::Fnv1a),
+            2 => Some(RBChecksumAlgorithm::Xxh64),
+            _ => None,
+        }
+    }
+}
+
+/// Computes a checksum of an image's pixel buffer.
+///
+/// Only the `len` bytes at `ptr` are hashed, so the checksum matches what
+/// external tools compute over the same raw buffer; unlike `rb_image_hash()`
+/// the dimensions are not included. Identical renders produce identical
+/// checksums on every run and platform. None of the algorithms are
+/// cryptographic.
+///
+/// # Arguments
+/// * `img` - The image to checksum (must not be null)
+/// * `algorithm` - The checksum algorithm, as an `RBChecksumAlgorithm` value
+/// * `out` - Receives the checksum (must not be null)
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if `img` or `out` is null (error is set)
+///
+/// # Safety
+/// `img` must point to an `RBImage` whose `ptr` is valid for `len` bytes,
+/// and `out` must be valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_compute_checksum(img: *const RBImage, algorithm: u32, out: *mut u64) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+
+        let Some(algorithm) = RBChecksumAlgorithm::from_raw(algorithm) else {
+            set_err(format!("invalid args: unknown checksum algorithm {algorithm}"));
+            return false;
+        };
+
+        let Some(img) = (unsafe { img.as_ref() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        if out.is_null() {
+            set_err("invalid args".into());
+            return false;
+        }
+        let pixels = unsafe { image_bytes(img) };
+        let checksum = match algorithm {
+            RBChecksumAlgorithm::Crc32 => crc32fast::hash(pixels) as u64,
+            RBChecksumAlgorithm::Fnv1a => fnv1a64([pixels]),
+            RBChecksumAlgorithm::Xxh64 => xxhash_rust::xxh64::xxh64(pixels, 0),
+        };
+        unsafe { *out = checksum };
+        true
+    })
+}
+
+/// Computes a 64-bit perceptual hash (pHash) of an image.
+///
+/// Unlike `rb_image_hash()`, similar-looking images get similar hashes:
+/// compare two with the Hamming distance `popcount(a ^ b)`. Distances up to
+/// about 10 usually mean the same picture with small changes (a slight
+/// recolor, resampling, compression); unrelated images average around 32.
+///
+/// The image is composited over white, converted to Rec. 601 luma and
+/// box-averaged down to 32×32. A 2D DCT-II is taken, and the 8×8 lowest
+/// frequencies after the DC term (rows and columns 1–8) are compared
+/// against their median: bit `8 * v + u` (counting from the least
+/// significant bit) is set where coefficient `(u + 1, v + 1)` is above it.
+/// The hash ignores the image's size and aspect ratio.
+///
+/// # Arguments
+/// * `img` - The image to hash (must not be null)
+///
+/// # Returns
+/// * The perceptual hash
+/// * 0 if `img` is null or invalid (error is set)
+///
+/// # Safety
+/// `img` must point to an `RBImage` returned by this library (or one whose
+/// `ptr` is valid for `len` bytes).
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_phash(img: *const RBImage) -> u64 {
+    /// Side of the downsampled image the DCT runs on
+    const N: usize = 32;
+
+    ffi_guard(0, || {
+        clear_err();
+
+        let Some(img) = (unsafe { img.as_ref() }) else {
+            set_err("invalid args".into());
+            return 0;
+        };
+        let (w, h) = (img.width as usize, img.height as usize);
+        let pixels = match unsafe { rgba_pixels(img) } {
+            Ok(pixels) => pixels,
+            Err(e) => {
+                set_err(e);
+                return 0;
+            }
+        };
+
+        // Premultiplied over white: c + 255 - a
+        let luma = |p: &[u8]| {
+            let [r, g, b] = [0, 1, 2].map(|c| p[c] as f64 + 255.0 - p[3] as f64);
+            (r * 299.0 + g * 587.0 + b * 114.0) / 1000.0
+        };
+        // Each cell averages at least one source pixel, so tiny images upsample
+        let span = |i: usize, len: usize| (i * len / N, ((i + 1) * len / N).max(i * len / N + 1));
+        let mut small = [[0.0f64; N]; N];
+        for (ty, row) in small.iter_mut().enumerate() {
+            let (y0, y1) = span(ty, h);
+            for (tx, cell) in row.iter_mut().enumerate() {
+                let (x0, x1) = span(tx, w);
+                let sum: f64 = (y0..y1)
+                    .flat_map(|y| (x0..x1).map(move |x| (y * w + x) * 4))
+                    .map(|i| luma(&pixels[i..i + 4]))
+                    .sum();
+                *cell = sum / ((y1 - y0) * (x1 - x0)) as f64;
+            }
+        }
+
+        // Only the 9 lowest frequencies are needed in each direction
+        let cos: Vec<[f64; N]> = (0..9)
+            .map(|u| std::array::from_fn(|x| ((2 * x + 1) as f64 * u as f64 * std::f64::consts::PI / (2 * N) as f64).cos()))
+            .collect();
+        let rows: Vec<[f64; 9]> =
+            small.iter().map(|row| std::array::from_fn(|u| (0..N).map(|x| row[x] * cos[u][x]).sum())).collect();
+        let mut coeffs = [0.0f64; 64];
+        for v in 1..9 {
+            for u in 1..9 {
+                coeffs[8 * (v - 1) + (u - 1)] = (0..N).map(|y| rows[y][u] * cos[v][y]).sum();
+            }
+        }
+
+        let mut sorted = coeffs;
+        sorted.sort_by(f64::total_cmp);
+        let median = (sorted[31] + sorted[32]) / 2.0;
+        coeffs.iter().enumerate().filter(|(_, &c)| c > median).fold(0u64, |hash, (i, _)| hash | 1 << i)
+    })
+}
+
+/// Counts the distinct RGBA colors in an image.
+///
+/// Colors are compared as stored, i.e. premultiplied. The scan stops as soon
+/// as more than `cap` colors have been seen, so asking "does this fit in a
+/// 256-entry palette?" stays cheap on photographic content.
+///
+/// # Arguments
+/// * `img` - The image to scan (must not be null)
+/// * `cap` - Maximum number of colors to count
+///
+/// # Returns
+/// * The number of distinct colors, at most `cap`
+/// * `cap + 1` if the image has more than `cap` colors
+/// * 0 if `img` is null (error is set)
+///
+/// # Safety
+/// `img` must point to an `RBImage` returned by this library (or one whose
+/// `ptr` is valid for `len` bytes).
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_color_count(img: *const RBImage, cap: u32) -> u32 {
+    ffi_guard(0, || {
+        clear_err();
+        let Some(img) = (unsafe { img.as_ref() }) else {
+            set_err("invalid args".into());
+            return 0;
+        };
+
+        let pixels = match unsafe { rgba_pixels(img) } {
+            Ok(pixels) => pixels,
+            Err(e) => {
+                set_err(e);
+                return 0;
+            }
+        };
+        let mut seen = std::collections::HashSet::new();
+        for px in pixels.chunks_exact(4) {
+            seen.insert(u32::from_ne_bytes([px[0], px[1], px[2], px[3]]));
+            if seen.len() > cap as usize {
+                return cap.saturating_add(1);
+            }
+        }
+        seen.len() as u32
+    })
+}
+
+/// Median-cut quantization of RGBA pixels.
+///
+/// Returns the palette and, for every pixel, the index of its entry.
+/// Deterministic for a given input, so a size query and the real call agree.
+fn median_cut(pixels: &[u8], max_colors: usize) -> (Vec<[u8; 4]>, Vec<u8>) {
+    let mut counts = std::collections::HashMap::new();
+    for px in pixels.chunks_exact(4) {
+        *counts.entry([px[0], px[1], px[2], px[3]]).or_insert(0u64) += 1;
+    }
+    let mut colors: Vec<([u8; 4], u64)> = counts.into_iter().collect();
+    colors.sort_unstable();
+
+    // Widest channel of a box, and its range
+    let widest = |colors: &[([u8; 4], u64)]| {
+        (0..4)
+            .map(|c| {
+                let (lo, hi) = colors.iter().fold((255, 0), |(lo, hi), (px, _)| (px[c].min(lo), px[c].max(hi)));
+                (hi - lo, c)
+            })
+            .max()
+            .unwrap_or((0, 0))
+    };
+
+    let mut boxes = vec![colors];
+    while boxes.len() < max_colors {
+        // Split the box with the widest spread until there are enough of them
+        let Some((i, channel)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .map(|(i, b)| (widest(b), i))
+            .max()
+            .map(|((_, channel), i)| (i, channel))
+        else {
+            break;
+        };
+        let b = &mut boxes[i];
+        b.sort_unstable_by_key(|(px, _)| px[channel]);
+        let total: u64 = b.iter().map(|(_, n)| n).sum();
+        let mut seen = 0;
+        let median = b.iter().position(|(_, n)| {
+            seen += n;
+            seen * 2 >= total
+        });
+        let at = median.unwrap_or(0).clamp(1, b.len() - 1);
+        let upper = b.split_off(at);
+        boxes.push(upper);
+    }
+
+    let palette: Vec<[u8; 4]> = boxes
+        .iter()
+        .map(|b| {
+            let total: u64 = b.iter().map(|(_, n)| n).sum();
+            std::array::from_fn(|c| {
+                let sum: u64 = b.iter().map(|(px, n)| px[c] as u64 * n).sum();
+                ((sum + total / 2) / total) as u8
+            })
+        })
+        .collect();
+
+    let index: std::collections::HashMap<[u8; 4], u8> = boxes
+        .iter()
+        .enumerate()
+        .flat_map(|(i, b)| b.iter().map(move |(px, _)| (*px, i as u8)))
+        .collect();
+    let indices = pixels.chunks_exact(4).map(|px| index[&[px[0], px[1], px[2], px[3]]]).collect();
+    (palette, indices)
+}
+
+/// Converts an image to a palette of at most `max_colors` entries plus one
+/// palette index per pixel, using median-cut quantization.
+///
+/// Palette entries are RGBA quadruples in the image's own alpha convention
+/// (premultiplied for images from this library; call
+/// `rb_image_unpremultiply()` first for a straight palette). Images with at
+/// most `max_colors` distinct colors are reproduced exactly.
+///
+/// There is no dithering: every pixel takes the average color of its
+/// median-cut box. For a dithered result, render with
+/// `rb_render_svg_to_rgba_palette(..., dither = true)` using the palette from
+/// a first pass, then quantize that image again; its colors all come from
+/// the palette, so the second pass maps them exactly.
+///
+/// To size the buffers, call once with `out_palette` and `out_indices` null:
+/// only `*out_palette_len` is written. Alternatively, pass a palette buffer
+/// of `max_colors * 4` bytes, which is always enough. `out_indices` needs
+/// `width * height` bytes.
+///
+/// # Arguments
+/// * `img` - The image to quantize (must not be null)
+/// * `max_colors` - Palette size limit, 1 to 256
+/// * `out_palette` - Receives the palette, 4 bytes per entry (null = size query)
+/// * `out_palette_len` - Receives the palette length in bytes (must not be null)
+/// * `out_indices` - Receives one palette index per pixel, rows top to bottom
+///   (null = size query)
+///
+/// # Returns
+/// * `true` on success
+/// * `false` on invalid arguments or an invalid image (error is set)
+///
+/// # Safety
+/// `img` must point to an `RBImage` returned by this library; `out_palette`
+/// must be null or valid for the number of bytes a size query reports;
+/// `out_indices` must be null or valid for `width * height` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rb_quantize(
+    img: *const RBImage,
+    max_colors: u32,
+    out_palette: *mut u8,
+    out_palette_len: *mut usize,
+    out_indices: *mut u8,
+) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+
+        let Some(img) = (unsafe { img.as_ref() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        if out_palette_len.is_null() || !(1..=256).contains(&max_colors) || out_palette.is_null() != out_indices.is_null() {
+            set_err("invalid args".into());
+            return false;
+        }
+        let pixels = match unsafe { rgba_pixels(img) } {
+            Ok(pixels) => pixels,
+            Err(e) => {
+                set_err(e);
+                return false;
+            }
+        };
+
+        let (palette, indices) = median_cut(pixels, max_colors as usize);
+        unsafe { *out_palette_len = palette.len() * 4 };
+        if !out_palette.is_null() {
+            unsafe {
+                std::ptr::copy_nonoverlapping(palette.as_flattened().as_ptr(), out_palette, palette.len() * 4);
+                std::ptr::copy_nonoverlapping(indices.as_ptr(), out_indices, indices.len());
+            }
+        }
+        true
+    })
+}
+
+/// Like `rb_quantize()`, but returns the palette indices as an image.
+///
+/// The palette and indices are exactly `rb_quantize()`'s. `out_indexed`
+/// receives an `RBPixelFormat::Gray8` image of the source's dimensions whose
+/// bytes are palette indices, ready to hand to a GIF or indexed-PNG encoder.
+///
+/// # Arguments
+/// * `src` - The image to quantize (must not be null)
+/// * `max_colors` - Palette size limit, 1 to 256
+/// * `out_palette` - Receives the palette, 4 bytes per entry; must hold
+///   `max_colors * 4` bytes (must not be null)
+/// * `out_palette_len` - Receives the palette length in bytes, as from
+///   `rb_quantize()` (must not be null)
+/// * `out_indexed` - Receives the index image (must not be null)
+///
+/// # Returns
+/// * `true` on success
+/// * `false` on invalid arguments or an invalid image (error is set, the
+///   outputs are left unchanged)
+///
+/// # Safety
+/// `src` must point to an `RBImage` returned by this library; the output
+/// pointers must be valid for writes. The index image must be freed with
+/// `rb_free_image()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_quantize_to_n_colors(
+    src: *const RBImage,
+    max_colors: u32,
+    out_palette: *mut u8,
+    out_palette_len: *mut usize,
+    out_indexed: *mut RBImage,
+) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+
+        let Some(src) = (unsafe { src.as_ref() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        if out_palette.is_null() || out_indexed.is_null() {
+            set_err("invalid args".into());
+            return false;
+        }
+        // Validate before sizing the index buffer from the image's dimensions
+        if let Err(e) = check_rgba(src) {
+            set_err(e);
+            return false;
+        }
+
+        let mut indices = vec![0; src.width as usize * src.height as usize];
+        if !unsafe { rb_quantize(src, max_colors, out_palette, out_palette_len, indices.as_mut_ptr()) } {
+            return false;
+        }
+        unsafe { out_indexed.write(RBImage::from_vec(indices, src.width, src.height).with_format(RBPixelFormat::Gray8)) };
+        true
+    })
+}
+
+/// Applies one SVG `<filter>` to the image's pixels.
+///
+/// The filter is applied by resvg's own filter engine, to an `<image>` the
+/// size of `img`, so filter regions and `objectBoundingBox` units refer to
+/// the whole image. Only the `<filter>` element itself is used: primitives
+/// that reference other parts of `filter_svg` (e.g. `feImage` pointing at an
+/// element) will not resolve.
+///
+/// # Arguments
+/// * `img` - The source image (must not be null)
+/// * `filter_svg` - An SVG document containing the filter definition
+/// * `filter_svg_len` - Length of `filter_svg` in bytes
+/// * `filter_id` - Null-terminated `id` of the `<filter>` to apply
+///
+/// # Returns
+/// * A new image of the same dimensions
+/// * If an error occurs, returns an image with null pointer and zero dimensions
+///
+/// # Safety
+/// All pointers must be valid for their stated lengths. The returned image
+/// must be freed with `rb_free_image()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_apply_svg_filter(
+    img: *const RBImage,
+    filter_svg: *const u8,
+    filter_svg_len: usize,
+    filter_id: *const c_char,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        let (Some(img), false, false) = (unsafe { img.as_ref() }, filter_svg.is_null(), filter_id.is_null()) else {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        };
+        let filter_svg = unsafe { slice::from_raw_parts(filter_svg, filter_svg_len) };
+        let filter_id = unsafe { CStr::from_ptr(filter_id) }.to_string_lossy();
+
+        match unsafe { apply_svg_filter(img, filter_svg, &filter_id) } {
+            Ok(pixmap) => RBImage::from_pixmap(pixmap),
+            Err(e) => {
+                set_err(e);
+                RBImage::empty()
+            }
+        }
+    })
+}
+
+/// Implementation of `rb_image_apply_svg_filter()`.
+///
+/// # Safety
+/// `img.ptr` must be null or valid for `img.len` bytes.
+unsafe fn apply_svg_filter(img: &RBImage, filter_svg: &[u8], filter_id: &str) -> Result<Pixmap, String> {
+    /// Placeholder href resolved to the source image
+    const SOURCE_HREF: &str = "rb-source-image";
+
+    let source = unsafe { image_to_pixmap(img) }?;
+    let png = source.encode_png().map_err(|e| format!("encode error: {e}"))?;
+
+    let text = std::str::from_utf8(filter_svg).map_err(|_| "filter SVG is not valid UTF-8")?;
+    let doc = usvg::roxmltree::Document::parse(text).map_err(|e| format!("parse error: {e}"))?;
+    let filter = doc
+        .descendants()
+        .find(|n| n.has_tag_name("filter") && n.attribute("id") == Some(filter_id))
+        .ok_or_else(|| format!("filter '{filter_id}' not found"))?;
+
+    // Wrap the filter around an image element covering the whole canvas
+    let (w, h) = (img.width, img.height);
+    let filter_xml = &doc.input_text()[filter.range()];
+    let document = format!(
+        concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{w}" height="{h}">"#,
+            r#"<defs>{filter_xml}</defs>"#,
+            r#"<image width="{w}" height="{h}" image-rendering="optimizeSpeed" href="{href}" filter="url(#{id})"/>"#,
+            "</svg>"
+        ),
+        w = w,
+        h = h,
+        filter_xml = filter_xml,
+        href = SOURCE_HREF,
+        id = xml_escape(filter_id),
+    );
+
+    let png = Arc::new(png);
+    let mut opt = usvg::Options::default();
+    opt.image_href_resolver.resolve_string = Box::new(move |href, _| {
+        (href == SOURCE_HREF).then(|| usvg::ImageKind::PNG(png.clone()))
+    });
+    let tree = Tree::from_data(document.as_bytes(), &opt).map_err(|e| format!("parse error: {e}"))?;
+    render_tree(&tree.into(), w, h, &RBOptions::default())
+}
+
+/// Applies a Gaussian blur with standard deviation `sigma` pixels.
+///
+/// The blur runs as two separable 1D passes (horizontal, then vertical) with
+/// a kernel radius of `ceil(3 * sigma)`. Pixels outside the image count as
+/// transparent, so edges fade out the way a drop shadow should. Blurring is
+/// done on the premultiplied data, which keeps colors from bleeding out of
+/// transparent areas.
+///
+/// # Arguments
+/// * `src` - The source image (must not be null)
+/// * `sigma` - Standard deviation in pixels (must be finite and >= 0);
+///   0 returns an exact copy
+///
+/// # Returns
+/// * A new image of the same dimensions
+/// * If an error occurs, returns an image with null pointer and zero dimensions
+///
+/// # Safety
+/// `src` must point to an `RBImage` returned by this library. The returned
+/// image must be freed with `rb_free_image()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_gaussian_blur(src: *const RBImage, sigma: f32) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        let Some(src) = (unsafe { src.as_ref() }) else {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        };
+        if !sigma.is_finite() || sigma < 0.0 {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        }
+
+        match unsafe { image_to_pixmap(src) } {
+            Ok(mut pixmap) => {
+                if sigma > 0.0 {
+                    gaussian_blur(&mut pixmap, sigma);
+                }
+                RBImage::from_pixmap(pixmap)
+            }
+            Err(e) => {
+                set_err(e);
+                RBImage::empty()
+            }
+        }
+    })
+}
+
+/// Sharpens an image with an unsharp mask.
+///
+/// The image is blurred with `rb_image_gaussian_blur()`'s kernel, and every
+/// color channel moves away from its blurred value by `amount` times the
+/// difference: `out = orig + amount * (orig - blurred)`. Channels that
+/// differ from the blur by less than `threshold` are left alone, so flat
+/// areas and faint noise aren't amplified. The comparison uses straight
+/// (unpremultiplied) colors, weighting neighbours by their alpha, so
+/// transparent surroundings and the image border don't create halos. Alpha
+/// is not changed.
+///
+/// # Arguments
+/// * `src` - The source image (must not be null)
+/// * `sigma` - Blur standard deviation in pixels (must be finite and >= 0);
+///   typical values are 0.5–2
+/// * `amount` - Strength (must be finite and >= 0); 1.0 doubles the local
+///   contrast, 0 or a `sigma` of 0 returns an exact copy
+/// * `threshold` - Smallest per-channel difference (0–255) that is sharpened
+///
+/// # Returns
+/// * A new image of the same dimensions
+/// * If an error occurs, returns an image with null pointer and zero dimensions
+///
+/// # Safety
+/// `src` must point to an `RBImage` returned by this library. The returned
+/// image must be freed with `rb_free_image()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_unsharp_mask(src: *const RBImage, sigma: f32, amount: f32, threshold: u8) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        let Some(src) = (unsafe { src.as_ref() }) else {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        };
+        if !sigma.is_finite() || sigma < 0.0 || !amount.is_finite() || amount < 0.0 {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        }
+
+        let mut pixmap = match unsafe { image_to_pixmap(src) } {
+            Ok(p) => p,
+            Err(e) => {
+                set_err(e);
+                return RBImage::empty();
+            }
+        };
+        if sigma == 0.0 || amount == 0.0 {
+            return RBImage::from_pixmap(pixmap);
+        }
+
+        let mut blurred = pixmap.clone();
+        gaussian_blur(&mut blurred, sigma);
+        for (px, bl) in pixmap.data_mut().chunks_exact_mut(4).zip(blurred.data().chunks_exact(4)) {
+            if px[3] == 0 || bl[3] == 0 {
+                continue;
+            }
+            let (a, blur_a) = (px[3] as f32, bl[3] as f32);
+            for c in 0..3 {
+                let orig = px[c] as f32 * 255.0 / a;
+                let diff = orig - bl[c] as f32 * 255.0 / blur_a;
+                if diff.abs() >= threshold as f32 {
+                    let sharpened = (orig + amount * diff).clamp(0.0, 255.0);
+                    px[c] = (sharpened * a / 255.0).round().min(a) as u8;
+                }
+            }
+        }
+        RBImage::from_pixmap(pixmap)
+    })
+}
+
+/// Resizes an image with nearest-neighbor sampling.
+///
+/// Every output pixel copies exactly one source pixel, chosen with integer
+/// index math, so edges stay hard and no new colors are introduced. Integer
+/// scale factors give pixel-perfect magnification, which suits pixel art and
+/// debugging views.
+///
+/// # Arguments
+/// * `src` - The source image (must not be null)
+/// * `new_w` - Output width in pixels (must be > 0)
+/// * `new_h` - Output height in pixels (must be > 0)
+///
+/// # Returns
+/// * A new `new_w × new_h` image
+/// * If an error occurs, returns an image with null pointer and zero dimensions
+///
+/// # Safety
+/// `src` must point to an `RBImage` returned by this library. The returned
+/// image must be freed with `rb_free_image()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_resize_nearest(src: *const RBImage, new_w: u32, new_h: u32) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        let Some(src) = (unsafe { src.as_ref() }) else {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        };
+        if new_w == 0 || new_h == 0 {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        }
+        let (src_w, src_h) = (src.width as usize, src.height as usize);
+        if let Err(e) = check_rgba(src) {
+            set_err(e);
+            return RBImage::empty();
+        }
+        let Some(len) = (new_w as usize).checked_mul(new_h as usize).and_then(|n| n.checked_mul(4)) else {
+            set_err("image too large".into());
+            return RBImage::empty();
+        };
+
+        let pixels = unsafe { image_bytes(src) };
+        let (new_w, new_h) = (new_w as usize, new_h as usize);
+        let columns: Vec<usize> = (0..new_w).map(|x| x * src_w / new_w * 4).collect();
+
+        let mut out = Vec::with_capacity(len);
+        for y in 0..new_h {
+            let row = &pixels[y * src_h / new_h * src_w * 4..][..src_w * 4];
+            for &offset in &columns {
+                out.extend_from_slice(&row[offset..offset + 4]);
+            }
+        }
+        RBImage::from_vec(out, new_w as u32, new_h as u32)
+    })
+}
+
+/// Resamples an image through an affine transform.
+///
+/// The matrix `[a, b, c, d, e, f_val]` uses the layout
+/// `rb_render_svg_to_rgba_with_matrix_out()` writes, mapping source pixel
+/// coordinates to output coordinates as `x' = a*x + c*y + e`,
+/// `y' = b*x + d*y + f_val`, so rotations, skews, flips and scales all work;
+/// a perspective projection is not affine and can't be expressed. Each
+/// output pixel is mapped back through the inverse transform and sampled
+/// bilinearly, which leaves no holes. Output pixels that map outside the
+/// source get the background color; along the source's edges the two are
+/// blended by coverage.
+///
+/// # Arguments
+/// * `src` - The source image (must not be null)
+/// * `a`, `b`, `c`, `d`, `e`, `f_val` - The transform; must be finite and
+///   invertible
+/// * `out_w` - Output width in pixels (must be > 0)
+/// * `out_h` - Output height in pixels (must be > 0)
+/// * `bg_r`, `bg_g`, `bg_b`, `bg_a` - Color outside the source (straight alpha)
+///
+/// # Returns
+/// * A new `out_w × out_h` image
+/// * If an error occurs, returns an image with null pointer and zero dimensions
+///
+/// # Safety
+/// `src` must point to an `RBImage` returned by this library. The returned
+/// image must be freed with `rb_free_image()`.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn rb_image_affine_transform(
+    src: *const RBImage,
+    a: f32,
+    b: f32,
+    c: f32,
+    d: f32,
+    e: f32,
+    f_val: f32,
+    out_w: u32,
+    out_h: u32,
+    bg_r: u8,
+    bg_g: u8,
+    bg_b: u8,
+    bg_a: u8,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        let Some(src) = (unsafe { src.as_ref() }) else {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        };
+        let ts = Transform::from_row(a, b, c, d, e, f_val);
+        // tiny-skia "inverts" a zero scale to an infinite one instead of failing
+        if out_w == 0 || out_h == 0 || !ts.is_finite() || !ts.invert().is_some_and(|inv| inv.is_finite()) {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        }
+        let source = match unsafe { image_to_pixmap(src) } {
+            Ok(p) => p,
+            Err(e) => {
+                set_err(e);
+                return RBImage::empty();
+            }
+        };
+        let Some(mut out) = Pixmap::new(out_w, out_h) else {
+            set_err("alloc pixmap failed".into());
+            return RBImage::empty();
+        };
+
+        out.fill(tiny_skia::Color::from_rgba8(bg_r, bg_g, bg_b, bg_a));
+        let paint = tiny_skia::PixmapPaint {
+            opacity: 1.0,
+            // Replace rather than composite, so translucent source pixels stay as they are
+            blend_mode: tiny_skia::BlendMode::Source,
+            quality: tiny_skia::FilterQuality::Bilinear,
+        };
+        out.draw_pixmap(0, 0, source.as_ref(), &paint, ts, None);
+        RBImage::from_pixmap(out)
+    })
+}
+
+/// Converts a height map into a tangent-space normal map.
+///
+/// Heights are the bytes of an `RBPixelFormat::Gray8` plane (as from
+/// `rb_image_split_channels()`) or the red channel of an RGBA image, with 0
+/// the lowest and 255 the highest point. Gradients come from a 3×3 Sobel
+/// operator with edge pixels repeated at the borders. Each normal
+/// `normalize(-dh/dx * strength, dh/dy * strength, 1)` is packed as
+/// `[nx*127+128, ny*127+128, nz*127+128, 255]`, with green pointing up the
+/// image (the OpenGL convention; invert green for DirectX).
+///
+/// # Arguments
+/// * `height_map` - The height map (must not be null)
+/// * `strength` - Gradient multiplier; larger values give steeper normals
+///   (must be finite, 0 gives a flat map)
+///
+/// # Returns
+/// * A new opaque RGBA image of the same dimensions
+/// * If an error occurs, returns an image with null pointer and zero dimensions
+///
+/// # Safety
+/// `height_map` must point to an `RBImage` returned by this library. The
+/// returned image must be freed with `rb_free_image()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_pack_normals(height_map: *const RBImage, strength: f32) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        let Some(src) = (unsafe { height_map.as_ref() }) else {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        };
+        if !strength.is_finite() {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        }
+        let (w, h) = (src.width as usize, src.height as usize);
+        let pixels = unsafe { image_bytes(src) };
+        let stride = match src.pixel_format() {
+            Ok(RBPixelFormat::Gray8) if !pixels.is_empty() && pixels.len() == w * h => 1,
+            Ok(RBPixelFormat::Gray8) => {
+                set_err("invalid image".into());
+                return RBImage::empty();
+            }
+            _ => match check_rgba(src) {
+                Ok(()) => 4,
+                Err(e) => {
+                    set_err(e);
+                    return RBImage::empty();
+                }
+            },
+        };
+
+        let height = |x: isize, y: isize| {
+            let (x, y) = (x.clamp(0, w as isize - 1) as usize, y.clamp(0, h as isize - 1) as usize);
+            pixels[(y * w + x) * stride] as f32 / 255.0
+        };
+        let mut out = Vec::with_capacity(w * h * 4);
+        for y in 0..h as isize {
+            for x in 0..w as isize {
+                let gx = (height(x + 1, y - 1) + 2.0 * height(x + 1, y) + height(x + 1, y + 1))
+                    - (height(x - 1, y - 1) + 2.0 * height(x - 1, y) + height(x - 1, y + 1));
+                let gy = (height(x - 1, y + 1) + 2.0 * height(x, y + 1) + height(x + 1, y + 1))
+                    - (height(x - 1, y - 1) + 2.0 * height(x, y - 1) + height(x + 1, y - 1));
+                // The Sobel weights sum to 8 per side
+                let (nx, ny, nz) = (-gx / 8.0 * strength, gy / 8.0 * strength, 1.0);
+                let len = (nx * nx + ny * ny + nz * nz).sqrt();
+                let pack = |n: f32| (n / len * 127.0 + 128.0).round().clamp(0.0, 255.0) as u8;
+                out.extend_from_slice(&[pack(nx), pack(ny), pack(nz), 255]);
+            }
+        }
+        RBImage::from_vec(out, src.width, src.height)
+    })
+}
+
+/// Edge operators for `rb_image_edge_detect()`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum RBEdgeMode {
+    /// 3×3 Sobel gradient magnitude
+    Sobel = 0,
+    /// 3×3 Prewitt gradient magnitude
+    Prewitt = 1,
+    /// 5×5 Laplacian of Gaussian (σ ≈ 1.4), absolute response
+    LaplacianOfGaussian = 2,
+}
+
+impl RBEdgeMode {
+    /// The mode with the given numeric value, as passed in from C.
+    fn from_raw(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(RBEdgeMode::Sobel),
+            1 => Some(RBEdgeMode::Prewitt),
+            2 => Some(RBEdgeMode::LaplacianOfGaussian),
+            _ => None,
+        }
+    }
+}
+
+/// Correlates a `w × h` plane with a square kernel, repeating edge pixels
+/// past the borders.
+fn convolve_clamped(plane: &[f32], w: usize, h: usize, kernel: &[f32]) -> Vec<f32> {
+    let size = (kernel.len() as f64).sqrt() as isize;
+    let r = size / 2;
+    let mut out = Vec::with_capacity(w * h);
+    for y in 0..h as isize {
+        for x in 0..w as isize {
+            let mut sum = 0.0;
+            for ky in 0..size {
+                let sy = (y + ky - r).clamp(0, h as isize - 1) as usize;
+                for kx in 0..size {
+                    let sx = (x + kx - r).clamp(0, w as isize - 1) as usize;
+                    sum += plane[sy * w + sx] * kernel[(ky * size + kx) as usize];
+                }
+            }
+            out.push(sum);
+        }
+    }
+    out
+}
+
+/// Finds edges in an image, e.g. to get SVG shape outlines for tracing.
+///
+/// The operator runs on two grayscale planes, luminance (Rec. 601 weights,
+/// composited over black) and alpha, and each pixel keeps the stronger of
+/// the two responses, so a shape's outline is found whether it stands out
+/// by color or only against transparency. Edge pixels are repeated past the
+/// image borders. Responses are scaled so the strongest edge in the image
+/// is 255; an image without edges comes out all 0.
+///
+/// # Arguments
+/// * `src` - The source image (must not be null)
+/// * `mode` - The edge operator, as an `RBEdgeMode` value
+///
+/// # Returns
+/// * A new opaque image of the same dimensions with the edge strength in
+///   R, 0 in G and B, and 255 in A
+/// * If an error occurs, returns an image with null pointer and zero dimensions
+///
+/// # Safety
+/// `src` must point to an `RBImage` returned by this library. The returned
+/// image must be freed with `rb_free_image()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_edge_detect(src: *const RBImage, mode: u32) -> RBImage {
+    const SOBEL: [[f32; 9]; 2] = [
+        [-1.0, 0.0, 1.0, -2.0, 0.0, 2.0, -1.0, 0.0, 1.0],
+        [-1.0, -2.0, -1.0, 0.0, 0.0, 0.0, 1.0, 2.0, 1.0],
+    ];
+    const PREWITT: [[f32; 9]; 2] = [
+        [-1.0, 0.0, 1.0, -1.0, 0.0, 1.0, -1.0, 0.0, 1.0],
+        [-1.0, -1.0, -1.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0],
+    ];
+    #[rustfmt::skip]
+    const LOG: [f32; 25] = [
+        0.0, 0.0, -1.0, 0.0, 0.0,
+        0.0, -1.0, -2.0, -1.0, 0.0,
+        -1.0, -2.0, 16.0, -2.0, -1.0,
+        0.0, -1.0, -2.0, -1.0, 0.0,
+        0.0, 0.0, -1.0, 0.0, 0.0,
+    ];
+
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        let Some(mode) = RBEdgeMode::from_raw(mode) else {
+            set_err(format!("invalid args: unknown edge mode {mode}"));
+            return RBImage::empty();
+        };
+
+        let Some(src) = (unsafe { src.as_ref() }) else {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        };
+        let (w, h) = (src.width as usize, src.height as usize);
+        let pixels = match unsafe { rgba_pixels(src) } {
+            Ok(pixels) => pixels,
+            Err(e) => {
+                set_err(e);
+                return RBImage::empty();
+            }
+        };
+
+        let luma: Vec<f32> = pixels
+            .chunks_exact(4)
+            .map(|p| (p[0] as f32 * 0.299 + p[1] as f32 * 0.587 + p[2] as f32 * 0.114) / 255.0)
+            .collect();
+        let alpha: Vec<f32> = pixels.chunks_exact(4).map(|p| p[3] as f32 / 255.0).collect();
+
+        let response = |plane: &[f32]| -> Vec<f32> {
+            let gradient = |[kx, ky]: [[f32; 9]; 2]| {
+                let (gx, gy) = (convolve_clamped(plane, w, h, &kx), convolve_clamped(plane, w, h, &ky));
+                gx.iter().zip(&gy).map(|(x, y)| x.hypot(*y)).collect()
+            };
+            match mode {
+                RBEdgeMode::Sobel => gradient(SOBEL),
+                RBEdgeMode::Prewitt => gradient(PREWITT),
+                RBEdgeMode::LaplacianOfGaussian => convolve_clamped(plane, w, h, &LOG).iter().map(|v| v.abs()).collect(),
+            }
+        };
+        let strength: Vec<f32> = response(&luma).iter().zip(response(&alpha)).map(|(l, a)| l.max(a)).collect();
+
+        let max = strength.iter().copied().fold(0.0, f32::max);
+        let scale = if max > 0.0 { 255.0 / max } else { 0.0 };
+        let out = strength.iter().flat_map(|&s| [(s * scale).round() as u8, 0, 0, 255]).collect();
+        RBImage::from_vec(out, src.width, src.height)
+    })
+}
+
+/// What `rb_image_threshold()` compares against its threshold.
+///
+/// The color modes look at the image composited over white, so transparent
+/// areas count as white.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum RBThresholdMode {
+    /// Black where luminance is below the threshold
+    Luminance = 0,
+    /// Black where alpha is at or above the threshold
+    Alpha = 1,
+    /// Black where every one of R, G and B is below the threshold
+    AnyChannel = 2,
+}
+
+impl RBThresholdMode {
+    /// The mode with the given numeric value, as passed in from C.
+    fn from_raw(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(RBThresholdMode::Luminance),
+            1 => Some(RBThresholdMode::Alpha),
+            2 => Some(RBThresholdMode::AnyChannel),
+            _ => None,
+        }
+    }
+}
+
+/// Reduces an image to two values: opaque black and "off".
+///
+/// Useful for silhouettes, binary masks and stencil buffers. Luminance uses
+/// the Rec. 601 weights; `threshold == 128` with
+/// `RBThresholdMode::Luminance` is the usual "ink vs. paper" split, while
+/// `RBThresholdMode::Alpha` gives the shape's silhouette regardless of
+/// color.
+///
+/// # Arguments
+/// * `src` - The source image (must not be null)
+/// * `threshold` - Comparison value, see `RBThresholdMode`
+/// * `mode` - What to compare, as an `RBThresholdMode` value
+/// * `white_background` - `true` to make "off" pixels opaque white instead
+///   of transparent
+///
+/// # Returns
+/// * A new image of the same dimensions
+/// * If an error occurs, returns an image with null pointer and zero dimensions
+///
+/// # Safety
+/// `src` must point to an `RBImage` returned by this library. The returned
+/// image must be freed with `rb_free_image()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_threshold(
+    src: *const RBImage,
+    threshold: u8,
+    mode: u32,
+    white_background: bool,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        let Some(mode) = RBThresholdMode::from_raw(mode) else {
+            set_err(format!("invalid args: unknown threshold mode {mode}"));
+            return RBImage::empty();
+        };
+
+        let Some(src) = (unsafe { src.as_ref() }) else {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        };
+        let pixels = match unsafe { rgba_pixels(src) } {
+            Ok(pixels) => pixels,
+            Err(e) => {
+                set_err(e);
+                return RBImage::empty();
+            }
+        };
+
+        let off = if white_background { [255; 4] } else { [0; 4] };
+        let out = pixels
+            .chunks_exact(4)
+            .flat_map(|px| {
+                // Premultiplied color over white
+                let rgb = [0, 1, 2].map(|c| px[c] as u32 + 255 - px[3] as u32);
+                let black = match mode {
+                    RBThresholdMode::Luminance => (rgb[0] * 299 + rgb[1] * 587 + rgb[2] * 114) / 1000 < threshold as u32,
+                    RBThresholdMode::Alpha => px[3] >= threshold,
+                    RBThresholdMode::AnyChannel => rgb.iter().all(|&c| c < threshold as u32),
+                };
+                if black { [0, 0, 0, 255] } else { off }
+            })
+            .collect();
+        RBImage::from_vec(out, src.width, src.height)
+    })
+}
+
+/// Draws an image as ASCII art, for eyeballing renders in a terminal.
+///
+/// The image is composited over black and split into `cols` columns of
+/// cells; rows are chosen so the picture keeps its aspect ratio with
+/// character cells about twice as tall as wide. Each cell's average
+/// brightness picks one of ` .:;+xX$&#@`, darkest first.
+///
+/// # Arguments
+/// * `img` - The image to draw (must not be null)
+/// * `cols` - Number of characters per line (must be > 0; capped at the
+///   image width)
+///
+/// # Returns
+/// * A null-terminated string with `\n` after every line, to be freed with
+///   `rb_free_cstring()`
+/// * Null if an error occurred (error is set)
+///
+/// # Safety
+/// `img` must point to an `RBImage` returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_to_ascii_art(img: *const RBImage, cols: u32) -> *mut c_char {
+    const RAMP: &[u8] = b" .:;+xX$&#@";
+
+    ffi_guard(std::ptr::null_mut(), || {
+        clear_err();
+
+        let Some(img) = (unsafe { img.as_ref() }) else {
+            set_err("invalid args".into());
+            return std::ptr::null_mut();
+        };
+        let (w, h) = (img.width as usize, img.height as usize);
+        if cols == 0 {
+            set_err("invalid args".into());
+            return std::ptr::null_mut();
+        }
+        let pixels = match unsafe { rgba_pixels(img) } {
+            Ok(pixels) => pixels,
+            Err(e) => {
+                set_err(e);
+                return std::ptr::null_mut();
+            }
+        };
+
+        let cols = (cols as usize).min(w);
+        let rows = ((h * cols) as f64 / w as f64 / 2.0).round().max(1.0) as usize;
+
+        let mut out = String::with_capacity((cols + 1) * rows);
+        for row in 0..rows {
+            let (y0, y1) = (row * h / rows, ((row + 1) * h / rows).max(row * h / rows + 1));
+            for col in 0..cols {
+                let (x0, x1) = (col * w / cols, ((col + 1) * w / cols).max(col * w / cols + 1));
+                let mut sum = 0u64;
+                for y in y0..y1 {
+                    for px in pixels[(y * w + x0) * 4..(y * w + x1) * 4].chunks_exact(4) {
+                        // Premultiplied values are already composited over black
+                        sum += (px[0] as u64 * 299 + px[1] as u64 * 587 + px[2] as u64 * 114) / 1000;
+                    }
+                }
+                let brightness = sum / ((x1 - x0) * (y1 - y0)) as u64;
+                out.push(RAMP[brightness as usize * (RAMP.len() - 1) / 255] as char);
+            }
+            out.push('\n');
+        }
+
+        CString::new(out).map(CString::into_raw).unwrap_or(std::ptr::null_mut())
+    })
+}
+
+/// Formats an image's first pixels as hex, for inspecting pixel data while
+/// debugging.
+///
+/// Each pixel is written as its stored bytes in lowercase hex, e.g.
+/// `ff000080` for premultiplied RGBA (R, G, B, A); other formats write their
+/// own bytes per pixel (`rrggbb` for RGB, `gg` for gray). Pixels are
+/// separated by spaces, 16 to a line, in row-major order, and every line
+/// ends with `\n`. Lines don't follow image rows.
+///
+/// # Arguments
+/// * `img` - The image to dump (must not be null)
+/// * `max_pixels` - Number of pixels to dump, capped at the image size
+///   (0 = all)
+///
+/// # Returns
+/// * A null-terminated string, to be freed with `rb_free_cstring()`
+/// * Null on error (error is set)
+///
+/// # Safety
+/// `img` must point to an `RBImage` returned by this library (or one whose
+/// `format` and `len` match its pixels).
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_to_hex_dump(img: *const RBImage, max_pixels: u32) -> *mut c_char {
+    /// Pixels per line
+    const PER_LINE: usize = 16;
+
+    ffi_guard(std::ptr::null_mut(), || {
+        use std::fmt::Write;
+
+        clear_err();
+
+        let Some(img) = (unsafe { img.as_ref() }) else {
+            set_err("invalid args".into());
+            return std::ptr::null_mut();
+        };
+        let bpp = match img.pixel_format() {
+            Ok(format) => format.bytes_per_pixel(),
+            Err(e) => {
+                set_err(e);
+                return std::ptr::null_mut();
+            }
+        };
+        let pixels = unsafe { image_bytes(img) };
+        let expected = (img.width as usize).checked_mul(img.height as usize).and_then(|n| n.checked_mul(bpp));
+        if pixels.is_empty() || expected != Some(pixels.len()) {
+            set_err("invalid image".into());
+            return std::ptr::null_mut();
+        }
+
+        let count = match max_pixels {
+            0 => pixels.len() / bpp,
+            n => (n as usize).min(pixels.len() / bpp),
+        };
+        let mut out = String::with_capacity(count * (bpp * 2 + 1));
+        for (i, px) in pixels.chunks_exact(bpp).take(count).enumerate() {
+            for b in px {
+                let _ = write!(out, "{b:02x}");
+            }
+            out.push(if (i + 1) % PER_LINE == 0 || i + 1 == count { '\n' } else { ' ' });
+        }
+
+        CString::new(out).map(CString::into_raw).unwrap_or(std::ptr::null_mut())
+    })
+}
+
+/// Converts an image's color channels from sRGB encoding to linear light, in place.
+///
+/// Each pixel is un-premultiplied, the sRGB transfer function is inverted
+/// per channel (IEC 61966-2-1), and the result is premultiplied again, so
+/// the output is still premultiplied RGBA, just linear. Alpha is unchanged.
+/// Note that 8 bits are too few for linear light: dark tones lose precision,
+/// so convert back only when necessary.
+///
+/// # Arguments
+/// * `img` - The image to convert (must not be null)
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if `img` is null or invalid (error is set)
+///
+/// # Safety
+/// `img` must point to an `RBImage` returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_srgb_to_linear(img: *mut RBImage) -> bool {
+    unsafe {
+        map_straight_channels(img, |c| {
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        })
+    }
+}
+
+/// Converts an image's color channels from linear light to sRGB encoding, in place.
+///
+/// The inverse of `rb_image_srgb_to_linear()`.
+///
+/// # Arguments
+/// * `img` - The image to convert (must not be null)
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if `img` is null or invalid (error is set)
+///
+/// # Safety
+/// `img` must point to an `RBImage` returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_linear_to_srgb(img: *mut RBImage) -> bool {
+    unsafe {
+        map_straight_channels(img, |c| {
+            if c <= 0.003_130_8 {
+                c * 12.92
+            } else {
+                1.055 * c.powf(1.0 / 2.4) - 0.055
+            }
+        })
+    }
+}
+
+/// Converts an image from premultiplied to straight alpha, in place.
+///
+/// Images from this library are premultiplied; use this before handing
+/// pixels to code that expects straight alpha (most encoders and image
+/// editors). Color information in nearly transparent pixels is coarse, so
+/// a round trip through `rb_image_premultiply()` is not lossless for them.
+/// Other `rb_image_*` functions expect premultiplied input.
+///
+/// # Arguments
+/// * `img` - The image to convert (must not be null)
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if `img` is null or invalid (error is set)
+///
+/// # Safety
+/// `img` must point to an `RBImage` returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_unpremultiply(img: *mut RBImage) -> bool {
+    unsafe {
+        map_pixels_in_place(img, |px| {
+            let c = tiny_skia::PremultipliedColorU8::from_rgba(px[0], px[1], px[2], px[3])
+                .map(|c| c.demultiply())
+                .unwrap_or(tiny_skia::ColorU8::from_rgba(0, 0, 0, 0));
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+    }
+}
+
+/// Converts an image from straight to premultiplied alpha, in place.
+///
+/// The inverse of `rb_image_unpremultiply()`.
+///
+/// # Arguments
+/// * `img` - The image to convert (must not be null)
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if `img` is null or invalid (error is set)
+///
+/// # Safety
+/// `img` must point to an `RBImage` returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_premultiply(img: *mut RBImage) -> bool {
+    unsafe {
+        map_pixels_in_place(img, |px| {
+            let c = tiny_skia::ColorU8::from_rgba(px[0], px[1], px[2], px[3]).premultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+    }
+}
+
+/// Splits an image into one single-channel plane per channel.
+///
+/// Each plane is an `RBPixelFormat::Gray8` image with one byte per pixel
+/// (`len == width * height`), rows top to bottom. Bytes are copied as stored, so the color planes of a
+/// premultiplied image are premultiplied too; call
+/// `rb_image_unpremultiply()` first to get straight color values.
+/// `rb_image_merge_channels()` recombines planes into an RGBA image.
+///
+/// # Arguments
+/// * `src` - The image to split (must not be null)
+/// * `out_r` - Receives the red plane (null = skip)
+/// * `out_g` - Receives the green plane (null = skip)
+/// * `out_b` - Receives the blue plane (null = skip)
+/// * `out_a` - Receives the alpha plane (null = skip)
+///
+/// # Returns
+/// * `true` on success; free every requested plane with `rb_free_image()`
+/// * `false` if `src` is null or invalid (error is set, requested planes are empty)
+///
+/// # Safety
+/// `src` must point to an `RBImage` returned by this library; each non-null
+/// output pointer must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_split_channels(
+    src: *const RBImage,
+    out_r: *mut RBImage,
+    out_g: *mut RBImage,
+    out_b: *mut RBImage,
+    out_a: *mut RBImage,
+) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+        let outs = [out_r, out_g, out_b, out_a];
+        for out in outs.into_iter().filter(|out| !out.is_null()) {
+            unsafe { out.write(RBImage::empty()) };
+        }
+
+        let Some(src) = (unsafe { src.as_ref() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        let pixels = match unsafe { rgba_pixels(src) } {
+            Ok(pixels) => pixels,
+            Err(e) => {
+                set_err(e);
+                return false;
+            }
+        };
+
+        for (channel, out) in outs.into_iter().enumerate().filter(|(_, out)| !out.is_null()) {
+            let plane: Vec<u8> = pixels.chunks_exact(4).map(|px| px[channel]).collect();
+            unsafe { out.write(RBImage::from_vec(plane, src.width, src.height).with_format(RBPixelFormat::Gray8)) };
+        }
+        true
+    })
+}
+
+/// Combines up to four single-channel planes into one RGBA image.
+///
+/// The inverse of `rb_image_split_channels()`. Bytes are combined as given:
+/// planes split from a premultiplied image recombine into the same image,
+/// while straight color planes give a straight image that should go through
+/// `rb_image_premultiply()` before other `rb_image_*` functions use it. Each
+/// plane contributes its first `width * height` bytes, rows top to bottom.
+///
+/// # Arguments
+/// * `r` - Red plane (null = all 0)
+/// * `g` - Green plane (null = all 0)
+/// * `b` - Blue plane (null = all 0)
+/// * `a` - Alpha plane (null = all 255)
+/// * `width` - Output width in pixels (must be > 0)
+/// * `height` - Output height in pixels (must be > 0)
+///
+/// # Returns
+/// * A new `width × height` RGBA image
+/// * If an error occurs (zero size, or a plane shorter than `width * height`),
+///   returns an image with null pointer and zero dimensions
+///
+/// # Safety
+/// Each non-null input must point to an `RBImage` whose `ptr` is valid for
+/// `len` bytes. The returned image must be freed with `rb_free_image()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_merge_channels(
+    r: *const RBImage,
+    g: *const RBImage,
+    b: *const RBImage,
+    a: *const RBImage,
+    width: u32,
+    height: u32,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        let Some(pixels) = (width as usize).checked_mul(height as usize).filter(|&n| n > 0) else {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        };
+
+        let mut planes: [Option<&[u8]>; 4] = [None; 4];
+        for (plane, img) in planes.iter_mut().zip([r, g, b, a]) {
+            let Some(img) = (unsafe { img.as_ref() }) else { continue };
+            let bytes = unsafe { image_bytes(img) };
+            if bytes.len() < pixels {
+                set_err(format!("invalid image: plane has {} bytes, need {pixels}", bytes.len()));
+                return RBImage::empty();
+            }
+            *plane = Some(&bytes[..pixels]);
+        }
+
+        let mut data = Vec::with_capacity(pixels * 4);
+        for i in 0..pixels {
+            for (channel, plane) in planes.iter().enumerate() {
+                data.push(plane.map_or(if channel == 3 { 255 } else { 0 }, |p| p[i]));
+            }
+        }
+        RBImage::from_vec(data, width, height)
+    })
+}
+
+/// Converts an image to another pixel format.
+///
+/// Every pair of `RBPixelFormat`s is supported, converting to the same format
+/// copies the image, and RGBA and BGRA stay premultiplied:
+/// - `Rgba8` ↔ `Bgra8` swaps red and blue
+/// - To `Rgb8` or `Gray8` composites the image over the opaque background,
+///   exactly like `rb_options_set_output_format()`; gray uses the Rec. 601
+///   luma `(299 R + 587 G + 114 B) / 1000`
+/// - From `Rgb8` or `Gray8` adds opaque alpha (gray is copied to R, G and B)
+/// - To `Rgba32F` maps each byte `v` to `v / 255.0`; from it, values are
+///   clamped to [0, 1], scaled by 255 and rounded, so 8-bit values survive
+///   a round trip unchanged
+///
+/// # Arguments
+/// * `src` - The image to convert; its `format` and `len` must match (must
+///   not be null; not modified)
+/// * `target_format` - The numeric value of the `RBPixelFormat` to return
+/// * `bg_r`, `bg_g`, `bg_b` - RGB that transparency is flattened onto; only
+///   used when converting to `Rgb8` or `Gray8`
+///
+/// # Returns
+/// * A new image in `target_format` with the same dimensions
+/// * If an error occurs (including an unknown `target_format`), returns an
+///   image with null pointer and zero dimensions
+///
+/// # Safety
+/// `src` must point to an `RBImage` whose `ptr` is valid for `len` bytes.
+/// The returned image must be freed with `rb_free_image()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_convert(
+    src: *const RBImage,
+    target_format: u32,
+    bg_r: u8,
+    bg_g: u8,
+    bg_b: u8,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        let Some(target_format) = RBPixelFormat::from_raw(target_format) else {
+            set_err(format!("invalid args: unknown pixel format {target_format}"));
+            return RBImage::empty();
+        };
+
+        let Some(src) = (unsafe { src.as_ref() }) else {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        };
+        let src_format = match src.pixel_format() {
+            Ok(format) => format,
+            Err(e) => {
+                set_err(e);
+                return RBImage::empty();
+            }
+        };
+        let pixels = unsafe { image_bytes(src) };
+        let bpp = src_format.bytes_per_pixel();
+        let expected = (src.width as usize).checked_mul(src.height as usize).and_then(|n| n.checked_mul(bpp));
+        if pixels.is_empty() || expected != Some(pixels.len()) {
+            set_err("invalid image".into());
+            return RBImage::empty();
+        }
+
+        // Decode to premultiplied RGBA on a 0-255 scale
+        let decode = |p: &[u8]| -> [f32; 4] {
+            match src_format {
+                RBPixelFormat::Rgba8 => [p[0], p[1], p[2], p[3]].map(f32::from),
+                RBPixelFormat::Bgra8 => [p[2], p[1], p[0], p[3]].map(f32::from),
+                RBPixelFormat::Rgb8 => [p[0], p[1], p[2], 255].map(f32::from),
+                RBPixelFormat::Gray8 => [p[0], p[0], p[0], 255].map(f32::from),
+                RBPixelFormat::Rgba32F => std::array::from_fn(|c| {
+                    let v = f32::from_ne_bytes([p[c * 4], p[c * 4 + 1], p[c * 4 + 2], p[c * 4 + 3]]);
+                    v.clamp(0.0, 1.0) * 255.0
+                }),
+            }
+        };
+        let bg = [bg_r, bg_g, bg_b];
+        let to_u8 = |v: f32| v.round().clamp(0.0, 255.0) as u8;
+        let flatten = |px: [f32; 4]| -> [u8; 3] {
+            let inv = 255 - to_u8(px[3]) as u32;
+            // Premultiplied source-over onto the opaque background
+            std::array::from_fn(|c| (to_u8(px[c]) as u32 + (bg[c] as u32 * inv + 127) / 255).min(255) as u8)
+        };
+
+        let mut out = Vec::with_capacity(pixels.len() / bpp * target_format.bytes_per_pixel());
+        for px in pixels.chunks_exact(bpp).map(decode) {
+            let a = to_u8(px[3]);
+            let color = |c: usize| to_u8(px[c]).min(a);
+            match target_format {
+                RBPixelFormat::Rgba8 => out.extend_from_slice(&[color(0), color(1), color(2), a]),
+                RBPixelFormat::Bgra8 => out.extend_from_slice(&[color(2), color(1), color(0), a]),
+                RBPixelFormat::Rgb8 => out.extend_from_slice(&flatten(px)),
+                RBPixelFormat::Gray8 => {
+                    let [r, g, b] = flatten(px).map(u32::from);
+                    out.push(((r * 299 + g * 587 + b * 114) / 1000) as u8);
+                }
+                RBPixelFormat::Rgba32F => {
+                    for v in px {
+                        out.extend_from_slice(&(v / 255.0).to_ne_bytes());
+                    }
+                }
+            }
+        }
+        RBImage::from_vec(out, src.width, src.height).with_format(target_format)
+    })
+}
+
+/// Converts an image into a caller-provided buffer laid out for `glTexImage2D()`.
+///
+/// Supported `gl_format` values, with the channels written per pixel:
+/// - `GL_RGBA` (`0x1908`): R, G, B, A
+/// - `GL_BGRA` (`0x80E1`): B, G, R, A
+/// - `GL_RGB` (`0x1907`): R, G, B
+/// - `GL_BGR` (`0x80E0`): B, G, R
+/// - `GL_ALPHA` (`0x1906`): A
+///
+/// Supported `gl_type` values, for every format:
+/// - `GL_UNSIGNED_BYTE` (`0x1401`): one byte per channel, unchanged
+/// - `GL_UNSIGNED_SHORT` (`0x1403`): native-endian `u16`, `v * 257`
+/// - `GL_FLOAT` (`0x1406`): native-endian `f32`, `v / 255.0`
+///
+/// Colors stay premultiplied, matching the usual
+/// `glBlendFunc(GL_ONE, GL_ONE_MINUS_SRC_ALPHA)`; `GL_RGB` and `GL_BGR`
+/// drop alpha, which leaves the image composited over black. Rows are
+/// tightly packed top to bottom, so the first row lands at texture
+/// coordinate t = 0. Rows of 3-channel byte data are only 4-byte aligned
+/// when the width is a multiple of 4; upload other widths with
+/// `glPixelStorei(GL_UNPACK_ALIGNMENT, 1)`.
+///
+/// # Arguments
+/// * `img` - The image to convert, in `Rgba8` format (must not be null; not modified)
+/// * `gl_format` - OpenGL pixel format enum value, from the list above
+/// * `gl_type` - OpenGL pixel type enum value, from the list above
+/// * `out_ptr` - Destination buffer (must not be null)
+/// * `out_len` - Size of `out_ptr` in bytes; at least
+///   `width * height * channels * bytes per channel`
+///
+/// # Returns
+/// * `true` on success; exactly the required number of bytes were written
+/// * `false` on an unsupported combination, a short buffer or an invalid
+///   image (error is set, `out_ptr` is untouched)
+///
+/// # Safety
+/// `img` must point to an `RBImage` whose `ptr` is valid for `len` bytes,
+/// and `out_ptr` must be valid for writes of `out_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_to_opengl_texture_data(
+    img: *const RBImage,
+    gl_format: u32,
+    gl_type: u32,
+    out_ptr: *mut u8,
+    out_len: usize,
+) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+
+        let Some(img) = (unsafe { img.as_ref() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        if out_ptr.is_null() {
+            set_err("invalid args".into());
+            return false;
+        }
+        let pixels = match unsafe { rgba_pixels(img) } {
+            Ok(pixels) => pixels,
+            Err(e) => {
+                set_err(e);
+                return false;
+            }
+        };
+
+        // Source channel indices written for each pixel
+        let channels: &[usize] = match gl_format {
+            0x1908 => &[0, 1, 2, 3],
+            0x80E1 => &[2, 1, 0, 3],
+            0x1907 => &[0, 1, 2],
+            0x80E0 => &[2, 1, 0],
+            0x1906 => &[3],
+            _ => {
+                set_err(format!("invalid args: unsupported GL format 0x{gl_format:04X}"));
+                return false;
+            }
+        };
+        let channel_size = match gl_type {
+            0x1401 => 1,
+            0x1403 => 2,
+            0x1406 => 4,
+            _ => {
+                set_err(format!("invalid args: unsupported GL type 0x{gl_type:04X}"));
+                return false;
+            }
+        };
+        let needed = pixels.len() / 4 * channels.len() * channel_size;
+        if out_len < needed {
+            set_err(format!("invalid args: buffer too small ({out_len} bytes, {needed} needed)"));
+            return false;
+        }
+
+        let out = unsafe { slice::from_raw_parts_mut(out_ptr, needed) };
+        let values = pixels.chunks_exact(4).flat_map(|px| channels.iter().map(move |&c| px[c]));
+        for (dst, v) in out.chunks_exact_mut(channel_size).zip(values) {
+            match channel_size {
+                1 => dst[0] = v,
+                2 => dst.copy_from_slice(&(v as u16 * 257).to_ne_bytes()),
+                _ => dst.copy_from_slice(&(v as f32 / 255.0).to_ne_bytes()),
+            }
+        }
+        true
+    })
+}
+
+/// Replaces every RGBA pixel of `img` with `f(pixel)`.
+///
+/// # Safety
+/// `img` must be null or point to an `RBImage` returned by this library.
+unsafe fn map_pixels_in_place(img: *mut RBImage, f: impl Fn(&[u8]) -> [u8; 4]) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+
+        let Some(img) = (unsafe { img.as_mut() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        if let Err(e) = check_rgba(img) {
+            set_err(e);
+            return false;
+        }
+
+        let pixels = unsafe { slice::from_raw_parts_mut(img.ptr, img.len) };
+        for px in pixels.chunks_exact_mut(4) {
+            let mapped = f(px);
+            px.copy_from_slice(&mapped);
+        }
+        true
+    })
+}
+
+/// Applies `f` to the straight (un-premultiplied) color channels of every
+/// pixel, in the 0..=1 range, then premultiplies again.
+///
+/// # Safety
+/// `img` must be null or point to an `RBImage` returned by this library.
+unsafe fn map_straight_channels(img: *mut RBImage, f: impl Fn(f32) -> f32) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+
+        let Some(img) = (unsafe { img.as_mut() }) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        if let Err(e) = check_rgba(img) {
+            set_err(e);
+            return false;
+        }
+
+        let pixels = unsafe { slice::from_raw_parts_mut(img.ptr, img.len) };
+        for px in pixels.chunks_exact_mut(4) {
+            if px[3] == 0 {
+                continue;
+            }
+            let a = px[3] as f32 / 255.0;
+            for c in &mut px[..3] {
+                let straight = (*c as f32 / 255.0 / a).min(1.0);
+                *c = (f(straight).clamp(0.0, 1.0) * a * 255.0).round() as u8;
+            }
+        }
+        true
+    })
+}
+
+/// Finds the tight bounds `(x0, y0, x1, y1)` (exclusive end) of the pixels
+/// whose alpha is above `threshold`, or `None` if there are none.
+fn alpha_bounds(pixels: &[u8], width: usize, height: usize, threshold: u8) -> Option<(usize, usize, usize, usize)> {
+    let mut bounds: Option<(usize, usize, usize, usize)> = None;
+    for (y, row) in pixels.chunks_exact(width * 4).take(height).enumerate() {
+        let Some(first) = row.chunks_exact(4).position(|px| px[3] > threshold) else { continue };
+        let last = row.chunks_exact(4).rposition(|px| px[3] > threshold).unwrap_or(first);
+        bounds = Some(match bounds {
+            None => (first, y, last + 1, y + 1),
+            Some((x0, y0, x1, _)) => (x0.min(first), y0, x1.max(last + 1), y + 1),
+        });
+    }
+    bounds
+}
+
+/// Copies the `(x0, y0, x1, y1)` rectangle out of an RGBA buffer.
+fn crop_pixels(pixels: &[u8], width: usize, (x0, y0, x1, y1): (usize, usize, usize, usize)) -> Vec<u8> {
+    let mut out = Vec::with_capacity((x1 - x0) * (y1 - y0) * 4);
+    for row in pixels.chunks_exact(width * 4).skip(y0).take(y1 - y0) {
+        out.extend_from_slice(&row[x0 * 4..x1 * 4]);
+    }
+    out
+}
+
+/// Crops an image to the bounding box of its non-transparent pixels.
+///
+/// # Arguments
+/// * `img` - The image to crop (must not be null; not modified)
+/// * `alpha_threshold` - Pixels with alpha at or below this count as empty
+///   (0 keeps every pixel that isn't fully transparent)
+///
+/// # Returns
+/// * A new image covering exactly the pixels with alpha above the threshold
+/// * If the image has no such pixels, or on any other error, an image with
+///   null pointer and zero dimensions (error is set)
+///
+/// # Safety
+/// `img` must point to an `RBImage` returned by this library. The returned
+/// image must be freed with `rb_free_image()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_autocrop(img: *const RBImage, alpha_threshold: u8) -> RBImage {
+    unsafe { rb_autocrop_sides(img, alpha_threshold, true, true, true, true) }
+}
+
+/// Like `rb_autocrop()`, but trims only the chosen edges.
+///
+/// Edges that aren't trimmed stay where they are, so e.g. trimming only the
+/// top and bottom keeps the content's horizontal position and the full width.
+///
+/// # Arguments
+/// * `img` - The image to crop (must not be null; not modified)
+/// * `alpha_threshold` - Pixels with alpha at or below this count as empty
+/// * `top` / `bottom` / `left` / `right` - Which edges to trim
+///
+/// # Returns
+/// * A new image with the selected transparent borders removed
+/// * If the image has no pixels above the threshold, or on any other error,
+///   an image with null pointer and zero dimensions (error is set)
+///
+/// # Safety
+/// `img` must point to an `RBImage` returned by this library. The returned
+/// image must be freed with `rb_free_image()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_autocrop_sides(
+    img: *const RBImage,
+    alpha_threshold: u8,
+    top: bool,
+    bottom: bool,
+    left: bool,
+    right: bool,
+) -> RBImage {
+    ffi_guard(RBImage::empty(), || {
+        clear_err();
+
+        let Some(img) = (unsafe { img.as_ref() }) else {
+            set_err("invalid args".into());
+            return RBImage::empty();
+        };
+        let (w, h) = (img.width as usize, img.height as usize);
+        let pixels = match unsafe { rgba_pixels(img) } {
+            Ok(pixels) => pixels,
+            Err(e) => {
+                set_err(e);
+                return RBImage::empty();
+            }
+        };
+
+        match alpha_bounds(pixels, w, h, alpha_threshold) {
+            Some((x0, y0, x1, y1)) => {
+                let rect @ (x0, y0, x1, y1) = (
+                    if left { x0 } else { 0 },
+                    if top { y0 } else { 0 },
+                    if right { x1 } else { w },
+                    if bottom { y1 } else { h },
+                );
+                RBImage::from_vec(crop_pixels(pixels, w, rect), (x1 - x0) as u32, (y1 - y0) as u32)
+            }
+            None => {
+                set_err("image is fully transparent".into());
+                RBImage::empty()
+            }
+        }
+    })
+}
+
+/// Normalized 1D Gaussian kernel with radius `ceil(3 * sigma)`.
+fn gaussian_kernel(sigma: f32) -> Vec<f32> {
+    let radius = (3.0 * sigma).ceil() as usize;
+    let weights: Vec<f32> = (0..=2 * radius)
+        .map(|i| {
+            let d = i as f32 - radius as f32;
+            (-(d * d) / (2.0 * sigma * sigma)).exp()
+        })
+        .collect();
+    let sum: f32 = weights.iter().sum();
+    weights.into_iter().map(|k| k / sum).collect()
+}
+
+/// Computes the Structural Similarity Index (SSIM) of two images.
+///
+/// SSIM follows Wang et al. (2004): local statistics use an 11×11 Gaussian
+/// window with σ = 1.5, and the constants are `K1 = 0.01`, `K2 = 0.03` for
+/// 8-bit data. The index is computed separately for R, G, B and A (as
+/// stored, i.e. premultiplied) and the four mean scores are averaged. Near
+/// the borders the window is restricted to the image.
+///
+/// 1.0 means identical; scores below about 0.95 usually mean visible
+/// differences.
+///
+/// # Arguments
+/// * `a` - The first image (must not be null)
+/// * `b` - The second image, same dimensions as `a` (must not be null)
+/// * `out_score` - Receives the score (must not be null)
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if the images are invalid or differ in size (error is set)
+///
+/// # Safety
+/// `a` and `b` must point to `RBImage`s returned by this library;
+/// `out_score` must be valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn rb_image_compare_ssim(a: *const RBImage, b: *const RBImage, out_score: *mut f64) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+
+        let (Some(a), Some(b), false) = (unsafe { a.as_ref() }, unsafe { b.as_ref() }, out_score.is_null()) else {
+            set_err("invalid args".into());
+            return false;
+        };
+        let (w, h) = (a.width as usize, a.height as usize);
+        if (a.width, a.height) != (b.width, b.height) {
+            set_err("image dimensions differ".into());
+            return false;
+        }
+        if let Err(e) = check_rgba(a).and_then(|()| check_rgba(b)) {
+            set_err(e);
+            return false;
+        }
+
+        let (pa, pb) = unsafe { (image_bytes(a), image_bytes(b)) };
+        let score = (0..4).map(|c| ssim_channel(pa, pb, c, w, h)).sum::<f64>() / 4.0;
+        unsafe { *out_score = score };
+        true
+    })
+}
+
+/// Mean SSIM of channel `c` of two equally sized RGBA buffers.
+fn ssim_channel(a: &[u8], b: &[u8], c: usize, w: usize, h: usize) -> f64 {
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+    let kernel = gaussian_kernel(1.5);
+    let radius = kernel.len() / 2;
+
+    // Gaussian-weighted local mean, renormalized where the window is clipped
+    let blur = |plane: &[f64]| -> Vec<f64> {
+        let pass = |input: &[f64], len: usize, count: usize, stride: usize, step: usize| {
+            let mut output = vec![0.0f64; input.len()];
+            for row in 0..count {
+                let base = row * step;
+                for i in 0..len {
+                    let (lo, hi) = (i.saturating_sub(radius), (i + radius).min(len - 1));
+                    let (mut acc, mut weight) = (0.0, 0.0);
+                    for j in lo..=hi {
+                        let k = kernel[j + radius - i] as f64;
+                        acc += input[base + j * stride] * k;
+                        weight += k;
+                    }
+                    output[base + i * stride] = acc / weight;
+                }
+            }
+            output
+        };
+        pass(&pass(plane, w, h, 1, w), h, w, w, 1)
+    };
+
+    let x: Vec<f64> = a.chunks_exact(4).map(|p| p[c] as f64).collect();
+    let y: Vec<f64> = b.chunks_exact(4).map(|p| p[c] as f64).collect();
+    let product = |p: &[f64], q: &[f64]| -> Vec<f64> { p.iter().zip(q).map(|(p, q)| p * q).collect() };
+
+    let (mu_x, mu_y) = (blur(&x), blur(&y));
+    let (xx, yy, xy) = (blur(&product(&x, &x)), blur(&product(&y, &y)), blur(&product(&x, &y)));
+
+    let total: f64 = (0..w * h)
+        .map(|i| {
+            let (mx, my) = (mu_x[i], mu_y[i]);
+            let var_x = xx[i] - mx * mx;
+            let var_y = yy[i] - my * my;
+            let cov = xy[i] - mx * my;
+            ((2.0 * mx * my + C1) * (2.0 * cov + C2)) / ((mx * mx + my * my + C1) * (var_x + var_y + C2))
+        })
+        .sum();
+    total / (w * h) as f64
+}
+
+/// Blurs premultiplied RGBA pixels in place. `sigma` must be > 0.
+fn gaussian_blur(pixmap: &mut Pixmap, sigma: f32) {
+    let (w, h) = (pixmap.width() as usize, pixmap.height() as usize);
+    let kernel = gaussian_kernel(sigma);
+    let radius = kernel.len() / 2;
+
+    // One 1D pass along rows of `len` pixels; `stride` steps between pixels of
+    // a row and `step` between rows.
+    let pass = |input: &[[f32; 4]], len: usize, count: usize, stride: usize, step: usize| {
+        let mut output = vec![[0.0f32; 4]; input.len()];
+        for row in 0..count {
+            let base = row * step;
+            for i in 0..len {
+                let mut acc = [0.0f32; 4];
+                let lo = i.saturating_sub(radius);
+                let hi = (i + radius).min(len - 1);
+                for j in lo..=hi {
+                    let k = kernel[j + radius - i];
+                    let px = &input[base + j * stride];
+                    for c in 0..4 {
+                        acc[c] += px[c] * k;
+                    }
+                }
+                output[base + i * stride] = acc;
+            }
+        }
+        output
+    };
+
+    let pixels: Vec<[f32; 4]> = pixmap
+        .data()
+        .chunks_exact(4)
+        .map(|p| [p[0] as f32, p[1] as f32, p[2] as f32, p[3] as f32])
+        .collect();
+    let horizontal = pass(&pixels, w, h, 1, w);
+    let blurred = pass(&horizontal, h, w, w, 1);
+
+    for (dst, px) in pixmap.data_mut().chunks_exact_mut(4).zip(blurred) {
+        let a = px[3].round().clamp(0.0, 255.0);
+        // Rounding must not push a color channel above alpha
+        for c in 0..3 {
+            dst[c] = px[c].round().clamp(0.0, a) as u8;
+        }
+        dst[3] = a as u8;
+    }
+}
+
+// ============================================================================
+// SVG INTROSPECTION
+// ============================================================================
+//
+// Queries that inspect an SVG without rendering it.
+
+/// Collects every family named by `font-family`, in document order and
+/// without duplicates.
+///
+/// Looks at `font-family` presentation attributes, inline `style`
+/// attributes and `<style>` sheets. Generic families are reported by their
+/// CSS keyword (e.g. `sans-serif`).
+fn collect_font_families(svg_bytes: &[u8]) -> Result<Vec<String>, String> {
+    let text = std::str::from_utf8(svg_bytes).map_err(|_| "SVG is not valid UTF-8")?;
+    let doc = usvg::roxmltree::Document::parse(text).map_err(|e| format!("parse error: {e}"))?;
+
+    let mut values: Vec<String> = Vec::new();
+    for node in doc.descendants().filter(|n| n.is_element()) {
+        if let Some(v) = node.attribute("font-family") {
+            values.push(v.to_string());
+        }
+        if let Some(style) = node.attribute("style") {
+            values.extend(
+                simplecss::DeclarationTokenizer::from(style)
+                    .filter(|d| d.name == "font-family")
+                    .map(|d| d.value.to_string()),
+            );
+        }
+        if node.has_tag_name("style") {
+            let css: String = node.children().filter_map(|c| c.text()).collect();
+            let sheet = simplecss::StyleSheet::parse(&css);
+            values.extend(
+                sheet
+                    .rules
+                    .iter()
+                    .flat_map(|r| r.declarations.iter())
+                    .filter(|d| d.name == "font-family")
+                    .map(|d| d.value.to_string()),
+            );
+        }
+    }
+
+    let mut families: Vec<String> = Vec::new();
+    for family in values.iter().filter_map(|v| svgtypes::parse_font_families(v).ok()).flatten() {
+        let name = match family {
+            svgtypes::FontFamily::Named(name) => name,
+            generic => generic.to_string(),
+        };
+        if !families.contains(&name) {
+            families.push(name);
+        }
+    }
+    Ok(families)
+}
+
+/// Lists the unique font families referenced by an SVG.
+///
+/// Use this to check that required fonts are available (and load missing
+/// ones) before rendering. Families come from `font-family` attributes,
+/// inline `style` attributes and `<style>` sheets, in document order;
+/// generic families are reported as `serif`, `sans-serif`, `monospace`,
+/// `cursive` or `fantasy`. The `font` shorthand is not inspected.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `out_families` - Receives a null-terminated array of `out_count` strings
+/// * `out_count` - Receives the number of families
+///
+/// # Returns
+/// * `true` on success (the array may be empty)
+/// * `false` on error (error is set, outputs are null/0)
+///
+/// # Safety
+/// The caller must ensure `svg_ptr` points to valid SVG data for `svg_len`
+/// bytes and that both output pointers are valid for writes. Free the array
+/// with `rb_free_string_array()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_query_svg_used_fonts(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    out_families: *mut *mut *mut c_char,
+    out_count: *mut usize,
+) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+
+        if svg_ptr.is_null() || svg_len == 0 || out_families.is_null() || out_count.is_null() {
+            set_err("invalid args".into());
+            return false;
+        }
+        unsafe {
+            *out_families = std::ptr::null_mut();
+            *out_count = 0;
+        }
+
+        let svg_bytes = unsafe { slice::from_raw_parts(svg_ptr, svg_len) };
+        match collect_font_families(svg_bytes) {
+            Ok(families) => {
+                unsafe { string_array_into_raw(families, out_families, out_count) };
+                true
+            }
+            Err(e) => {
+                set_err(e);
+                false
+            }
+        }
+    })
+}
+
+/// Node counts of a parsed tree, for `rb_query_svg_element_count()`.
+#[derive(Default)]
+struct NodeCounts {
+    paths: usize,
+    texts: usize,
+    images: usize,
+    groups: usize,
+}
+
+impl NodeCounts {
+    /// Adds up the nodes below `group`, not counting `group` itself.
+    fn add_children(&mut self, group: &usvg::Group) {
+        for node in group.children() {
+            match node {
+                usvg::Node::Group(child) => {
+                    self.groups += 1;
+                    self.add_children(child);
+                }
+                usvg::Node::Path(_) => self.paths += 1,
+                usvg::Node::Text(_) => self.texts += 1,
+                usvg::Node::Image(_) => self.images += 1,
+            }
+        }
+    }
+}
+
+/// Counts the nodes of an SVG's render tree by type, as a cheap complexity
+/// estimate before rendering.
+///
+/// The SVG is parsed exactly as for rendering, and the counts describe the
+/// resulting usvg tree rather than the source markup: basic shapes and
+/// `<line>`/`<polygon>` etc. count as paths, `<use>` references count once
+/// per instance (each adding a group), hidden and empty elements are
+/// already dropped (as is text when no loaded font can render it), and
+/// usvg may add groups of its own (e.g. for filters). Content of clip
+/// paths, masks and patterns is not counted.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `out_total` - Receives the number of nodes of all types (null = skip)
+/// * `out_paths` - Receives the number of paths (null = skip)
+/// * `out_texts` - Receives the number of text nodes (null = skip)
+/// * `out_images` - Receives the number of raster and nested-SVG images (null = skip)
+/// * `out_groups` - Receives the number of groups, excluding the root (null = skip)
+///
+/// # Returns
+/// * `true` on success
+/// * `false` on error (error is set, outputs are untouched)
+///
+/// # Safety
+/// `svg_ptr` must be valid for `svg_len` bytes; each non-null output pointer
+/// must be valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn rb_query_svg_element_count(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    out_total: *mut usize,
+    out_paths: *mut usize,
+    out_texts: *mut usize,
+    out_images: *mut usize,
+    out_groups: *mut usize,
+) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+
+        if svg_ptr.is_null() || svg_len == 0 {
+            set_err("invalid args".into());
+            return false;
+        }
+        let svg_bytes = unsafe { slice::from_raw_parts(svg_ptr, svg_len) };
+
+        match parse_svg(svg_bytes, &default_options()) {
+            Ok(tree) => {
+                let mut counts = NodeCounts::default();
+                counts.add_children(tree.root());
+                let total = counts.paths + counts.texts + counts.images + counts.groups;
+                let outputs = [
+                    (out_total, total),
+                    (out_paths, counts.paths),
+                    (out_texts, counts.texts),
+                    (out_images, counts.images),
+                    (out_groups, counts.groups),
+                ];
+                for (out, value) in outputs {
+                    if !out.is_null() {
+                        unsafe { *out = value };
+                    }
+                }
+                true
+            }
+            Err(e) => {
+                set_err(e);
+                false
+            }
+        }
+    })
+}
+
+/// Lists the distinct element names used in an SVG's source markup.
+///
+/// Unlike `rb_query_svg_element_count()` this looks at the document as
+/// written, before usvg resolves it, so it reports e.g. `circle`, `use` or
+/// `feTurbulence` by name. Names are local names without namespace
+/// prefixes, unique and sorted; elements from foreign namespaces (such as
+/// Inkscape's `sodipodi:namedview`) are included by local name.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `out_types` - Receives a null-terminated array of `out_count` strings
+/// * `out_count` - Receives the number of names
+///
+/// # Returns
+/// * `true` on success
+/// * `false` on error (error is set, outputs are null/0)
+///
+/// # Safety
+/// `svg_ptr` must be valid for `svg_len` bytes and both output pointers
+/// valid for writes. Free the array with `rb_free_string_array()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_query_svg_node_types(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    out_types: *mut *mut *mut c_char,
+    out_count: *mut usize,
+) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+
+        if svg_ptr.is_null() || svg_len == 0 || out_types.is_null() || out_count.is_null() {
+            set_err("invalid args".into());
+            return false;
+        }
+        unsafe {
+            *out_types = std::ptr::null_mut();
+            *out_count = 0;
+        }
+
+        let svg_bytes = unsafe { slice::from_raw_parts(svg_ptr, svg_len) };
+        let result = std::str::from_utf8(svg_bytes)
+            .map_err(|_| "SVG is not valid UTF-8".to_string())
+            .and_then(|text| usvg::roxmltree::Document::parse(text).map_err(|e| format!("parse error: {e}")))
+            .map(|doc| {
+                doc.descendants()
+                    .filter(|n| n.is_element())
+                    .map(|n| n.tag_name().name().to_string())
+                    .collect::<std::collections::BTreeSet<_>>()
+            });
+
+        match result {
+            Ok(names) => {
+                unsafe { string_array_into_raw(names.into_iter().collect(), out_types, out_count) };
+                true
+            }
+            Err(e) => {
+                set_err(e);
+                false
+            }
+        }
+    })
+}
+
+/// A construct that resvg parses around but does not render faithfully.
+struct UnsupportedFeature {
+    /// What was found, e.g. `<foreignObject>` or `in="BackgroundImage"`
+    what: String,
+    /// `id` of the element carrying it (may be empty)
+    id: String,
+    /// 1-based line number in the source document
+    line: u32,
+}
+
+impl std::fmt::Display for UnsupportedFeature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at line {}", self.what, self.line)?;
+        if !self.id.is_empty() {
+            write!(f, " (id \"{}\")", self.id)?;
+        }
+        Ok(())
+    }
+}
+
+/// Lists the constructs in an SVG document that resvg cannot render faithfully.
+///
+/// See `rb_options_set_strict_features()` for what is detected.
+fn find_unsupported_features(svg_bytes: &[u8]) -> Result<Vec<UnsupportedFeature>, String> {
+    const ELEMENTS: &[&str] = &[
+        "animate", "animateColor", "animateMotion", "animateTransform", "set",
+        "foreignObject", "font", "font-face", "glyph", "missing-glyph", "script",
+    ];
+    const FILTER_INPUTS: &[&str] = &["BackgroundImage", "BackgroundAlpha", "FillPaint", "StrokePaint"];
+
+    let text = std::str::from_utf8(svg_bytes).map_err(|_| "SVG is not valid UTF-8")?;
+    let doc = usvg::roxmltree::Document::parse(text).map_err(|e| format!("parse error: {e}"))?;
+
+    let mut found = Vec::new();
+    for node in doc.descendants().filter(|n| n.is_element()) {
+        let mut report = |what: String| {
+            found.push(UnsupportedFeature {
+                what,
+                id: node.attribute("id").unwrap_or_default().to_string(),
+                line: doc.text_pos_at(node.range().start).row,
+            });
+        };
+
+        let name = node.tag_name().name();
+        if ELEMENTS.contains(&name) {
+            report(format!("<{name}>"));
+        }
+        for attr in ["in", "in2"] {
+            if let Some(input) = node.attribute(attr).filter(|v| FILTER_INPUTS.contains(v)) {
+                report(format!("{attr}=\"{input}\""));
+            }
+        }
+        if node.has_attribute("enable-background") {
+            report("enable-background".into());
+        }
+    }
+    Ok(found)
+}
+
+/// Callback receiving one construct found by `rb_feature_report()`.
+pub type RBFeatureCallback =
+    Option<extern "C" fn(what: *const c_char, id: *const c_char, line: u32, userdata: *mut c_void)>;
+
+/// Lists the constructs in an SVG that resvg cannot render faithfully.
+///
+/// This reports the same things `rb_options_set_strict_features()` rejects,
+/// one callback per occurrence in document order, so a compatibility report
+/// can point at each one. Detection is best-effort: it inspects the SVG
+/// document for known-problematic elements and attributes rather than
+/// rendering it, and other filters, blend modes and so on are supported and
+/// not reported. A return of 0 doesn't promise pixel-identical output to a
+/// browser.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `out_cb` - Called with what was found (e.g. `<foreignObject>` or
+///   `in="BackgroundImage"`), the carrying element's `id` (empty if none)
+///   and its 1-based line number; the strings are only valid during the
+///   call. May be null to just count.
+/// * `userdata` - Opaque pointer forwarded to the callback unchanged
+///
+/// # Returns
+/// * The number of constructs found (0 if none)
+/// * -1 on error (error is set)
+///
+/// # Safety
+/// The caller must ensure `svg_ptr` points to valid SVG data for `svg_len`
+/// bytes. The callback must not unwind.
+#[no_mangle]
+pub unsafe extern "C" fn rb_feature_report(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    out_cb: RBFeatureCallback,
+    userdata: *mut c_void,
+) -> i32 {
+    ffi_guard(-1, || {
+        clear_err();
+
+        if svg_ptr.is_null() || svg_len == 0 {
+            set_err("invalid args".into());
+            return -1;
+        }
+
+        let svg_bytes = unsafe { slice::from_raw_parts(svg_ptr, svg_len) };
+        let found = match find_unsupported_features(svg_bytes) {
+            Ok(found) => found,
+            Err(e) => {
+                set_err(e);
+                return -1;
+            }
+        };
+        if let Some(cb) = out_cb {
+            for feature in &found {
+                // Neither string can contain a nul: both come from parsed XML
+                let what = CString::new(feature.what.as_str()).unwrap_or_default();
+                let id = CString::new(feature.id.as_str()).unwrap_or_default();
+                cb(what.as_ptr(), id.as_ptr(), feature.line, userdata);
+            }
+        }
+        found.len().try_into().unwrap_or(i32::MAX)
+    })
+}
+
+/// Returns whether the SVG contains any `<text>` element with non-blank content.
+fn svg_has_text(svg_bytes: &[u8]) -> Result<bool, String> {
+    let text = std::str::from_utf8(svg_bytes).map_err(|_| "SVG is not valid UTF-8")?;
+    let doc = usvg::roxmltree::Document::parse(text).map_err(|e| format!("parse error: {e}"))?;
+    Ok(doc.descendants().filter(|n| n.has_tag_name("text")).any(|text_node| {
+        text_node
+            .descendants()
+            .filter(|n| n.is_text())
+            .any(|n| n.text().is_some_and(|t| !t.trim().is_empty()))
+    }))
+}
+
+/// Reports whether an SVG needs fonts to render.
+///
+/// Text nodes are dropped from the parsed tree when no font can be found for
+/// them, so the check works on the XML document instead: the SVG contains
+/// text if any `<text>` element (including its `<tspan>`/`<textPath>`
+/// children) has non-whitespace character data. Text inside `<defs>` or
+/// otherwise unrendered parts counts too, so the answer errs on the side of
+/// loading fonts.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+///
+/// # Returns
+/// * 1 if the SVG contains text
+/// * 0 if it is pure shapes/images
+/// * -1 on error (error is set)
+///
+/// # Safety
+/// The caller must ensure `svg_ptr` points to valid SVG data for `svg_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rb_svg_has_text(svg_ptr: *const u8, svg_len: usize) -> i32 {
+    ffi_guard(-1, || {
+        clear_err();
+
+        if svg_ptr.is_null() || svg_len == 0 {
+            set_err("invalid args".into());
+            return -1;
+        }
+
+        let svg_bytes = unsafe { slice::from_raw_parts(svg_ptr, svg_len) };
+        match svg_has_text(svg_bytes) {
+            Ok(has_text) => has_text as i32,
+            Err(e) => {
+                set_err(e);
+                -1
+            }
+        }
+    })
+}
+
+/// Sums the cost weights of a group's subtree for `rb_estimate_cost()`.
+fn node_cost(group: &usvg::Group) -> u64 {
+    const PATH: u64 = 1;
+    const TEXT: u64 = 4;
+    const IMAGE: u64 = 4;
+    const CLIP_OR_MASK: u64 = 2;
+    const FILTER_PRIMITIVE: u64 = 10;
+
+    let mut cost = 0u64;
+    if group.clip_path().is_some() {
+        cost += CLIP_OR_MASK;
+    }
+    if group.mask().is_some() {
+        cost += CLIP_OR_MASK;
+    }
+    for filter in group.filters() {
+        cost += FILTER_PRIMITIVE * filter.primitives().len() as u64;
+    }
+    for child in group.children() {
+        cost = cost.saturating_add(match child {
+            usvg::Node::Group(g) => node_cost(g),
+            usvg::Node::Path(_) => PATH,
+            usvg::Node::Text(_) => TEXT,
+            usvg::Node::Image(_) => IMAGE,
+        });
+    }
+    cost
+}
+
+/// Estimates the relative cost of rendering an SVG, without rendering it.
+///
+/// The SVG is parsed exactly as for rendering, then its tree is weighed:
+/// each path counts 1, each text or image node 4, each clip path or mask 2
+/// and each filter primitive 10. The score is
+/// `width * height * (1 + total weight) / 1000`, saturating at `u64::MAX`.
+/// Scores are comparable between SVGs but have no unit; filters dominate
+/// because they run per pixel over their whole region.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `width` - Planned output width in pixels (must be > 0)
+/// * `height` - Planned output height in pixels (must be > 0)
+/// * `out_cost` - Receives the score (must not be null)
+///
+/// # Returns
+/// * `true` on success
+/// * `false` on error (error is set)
+///
+/// # Safety
+/// `svg_ptr` must be valid for `svg_len` bytes and `out_cost` valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn rb_estimate_cost(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    width: u32,
+    height: u32,
+    out_cost: *mut u64,
+) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+
+        if svg_ptr.is_null() || svg_len == 0 || width == 0 || height == 0 || out_cost.is_null() {
+            set_err("invalid args".into());
+            return false;
+        }
+        let svg_bytes = unsafe { slice::from_raw_parts(svg_ptr, svg_len) };
+
+        match parse_svg(svg_bytes, &default_options()) {
+            Ok(tree) => {
+                let weight = 1u64.saturating_add(node_cost(tree.root()));
+                let pixels = width as u64 * height as u64;
+                unsafe { *out_cost = pixels.saturating_mul(weight) / 1000 };
+                true
+            }
+            Err(e) => {
+                set_err(e);
+                false
+            }
+        }
+    })
+}
+
+/// Quotes a string as a JSON string literal.
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Renders an SVG node by node and reports where the time goes.
+///
+/// Meant for finding the one element (usually a filter) that makes an SVG
+/// slow. Each top-level node is rendered on its own, at its final position
+/// in a `width × height` output, and timed. A root that holds nothing but a
+/// single plain group (no filter, mask, clip path or opacity) is looked
+/// through, so documents wrapped in one `<g>` still report their parts.
+///
+/// The timings are approximate: nodes are rendered onto an empty canvas
+/// rather than over each other, so blending costs differ from a real render,
+/// and timer resolution limits accuracy for cheap nodes. Profiling costs
+/// about one extra full render plus a canvas clear per node, which is why
+/// it is a separate call rather than something normal renders do.
+///
+/// The result is a JSON object:
+/// `{"total_ms": 12.5, "nodes": [{"index": 3, "id": "glow", "kind": "group", "ms": 11.9}, ...]}`.
+/// `nodes` is sorted slowest first; `index` is the node's position among
+/// its siblings, `id` is `""` for nodes without one (an anonymous wrapper
+/// group, as usvg creates for filtered elements, reports its only child's
+/// id) and `kind` is one of
+/// `group`, `path`, `image` or `text`.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `width` - Output width in pixels (must be > 0)
+/// * `height` - Output height in pixels (must be > 0)
+/// * `top_n` - Number of slowest nodes to report (0 = all)
+/// * `opts` - Options from `rb_options_new()` (null = the defaults from
+///   `rb_set_default_options()`)
+///
+/// # Returns
+/// * A null-terminated JSON string; free it with `rb_free_cstring()`
+/// * Null on error (error is set)
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba_with_options()`.
+#[no_mangle]
+pub unsafe extern "C" fn rb_profile_svg(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    width: u32,
+    height: u32,
+    top_n: usize,
+    opts: *const RBOptions,
+) -> *mut c_char {
+    ffi_guard(std::ptr::null_mut(), || {
+        clear_err();
+
+        if svg_ptr.is_null() || svg_len == 0 || width == 0 || height == 0 {
+            set_err("invalid args".into());
+            return std::ptr::null_mut();
+        }
+        let default_opts = default_options();
+        let opts = unsafe { opts.as_ref() }.unwrap_or(&default_opts);
+        let svg_bytes = unsafe { slice::from_raw_parts(svg_ptr, svg_len) };
+
+        match parse_svg(svg_bytes, opts).and_then(|tree| profile_nodes(&tree, width, height, top_n, opts)) {
+            Ok(json) => CString::new(json).map(CString::into_raw).unwrap_or(std::ptr::null_mut()),
+            Err(e) => {
+                set_err(e);
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Times each top-level node of `tree` for `rb_profile_svg()`.
+fn profile_nodes(tree: &ParsedSvg, width: u32, height: u32, top_n: usize, opts: &RBOptions) -> Result<String, String> {
+    let ts = fit_transform(tree, width, height, opts)?;
+    let mut pixmap = Pixmap::new(width, height).ok_or("alloc pixmap failed")?;
+
+    let mut parent = tree.root();
+    while let [usvg::Node::Group(group)] = parent.children() {
+        let plain = group.filters().is_empty()
+            && group.mask().is_none()
+            && group.clip_path().is_none()
+            && group.opacity() == usvg::Opacity::ONE;
+        if !plain {
+            break;
+        }
+        parent = group;
+    }
+
+    let base = ts.pre_concat(parent.abs_transform());
+    let mut timings = Vec::new();
+    for (index, node) in parent.children().iter().enumerate() {
+        pixmap.fill(tiny_skia::Color::TRANSPARENT);
+        let start = std::time::Instant::now();
+        if let Some(bbox) = node.abs_layer_bounding_box() {
+            // `render_node()` positions the node at its bbox origin; undo that
+            resvg::render_node(node, base.pre_translate(bbox.x(), bbox.y()), &mut pixmap.as_mut());
+        }
+        let ms = start.elapsed().as_secs_f64() * 1000.0;
+        let kind = match node {
+            usvg::Node::Group(_) => "group",
+            usvg::Node::Path(_) => "path",
+            usvg::Node::Image(_) => "image",
+            usvg::Node::Text(_) => "text",
+        };
+        // usvg wraps filtered elements in an anonymous group; name it after its content
+        let id = match node {
+            usvg::Node::Group(group) if group.id().is_empty() && group.children().len() == 1 => group.children()[0].id(),
+            _ => node.id(),
+        };
+        timings.push((index, id.to_owned(), kind, ms));
+    }
+
+    let total_ms: f64 = timings.iter().map(|t| t.3).sum();
+    timings.sort_by(|a, b| b.3.total_cmp(&a.3));
+    if top_n > 0 {
+        timings.truncate(top_n);
+    }
+    let nodes: Vec<String> = timings
+        .iter()
+        .map(|(index, id, kind, ms)| {
+            format!(r#"{{"index": {index}, "id": {}, "kind": "{kind}", "ms": {ms:.3}}}"#, json_string(id))
+        })
+        .collect();
+    Ok(format!(r#"{{"total_ms": {total_ms:.3}, "nodes": [{}]}}"#, nodes.join(", ")))
+}
+
+/// Times repeated renders of one SVG, for profiling the library in the
+/// caller's own environment.
+///
+/// Each iteration parses and renders the SVG as `rb_render_svg_to_rgba()`
+/// does, timed with a monotonic clock, and the image is dropped right
+/// away, so memory use stays at one render. Every render is also hashed and
+/// compared with the first; output that differs between iterations fails
+/// the call, which catches nondeterminism regressions.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes
+/// * `iterations` - Number of renders (must be > 0)
+/// * `width` - Output width in pixels (must be > 0)
+/// * `height` - Output height in pixels (must be > 0)
+/// * `out_min_us` - Receives the fastest render time in microseconds (must not be null)
+/// * `out_max_us` - Receives the slowest render time in microseconds (must not be null)
+/// * `out_avg_us` - Receives the mean render time in microseconds (must not be null)
+///
+/// # Returns
+/// * `true` on success; the outputs are written
+/// * `false` on error or if two renders differ (error is set, outputs are
+///   left unchanged)
+///
+/// # Safety
+/// Same requirements as `rb_render_svg_to_rgba()`; the output pointers must
+/// be valid for writes.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn rb_render_svg_stress_test(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    iterations: u32,
+    width: u32,
+    height: u32,
+    out_min_us: *mut u64,
+    out_max_us: *mut u64,
+    out_avg_us: *mut u64,
+) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+
+        if iterations == 0 || out_min_us.is_null() || out_max_us.is_null() || out_avg_us.is_null() {
+            set_err("invalid args".into());
+            return false;
+        }
+
+        let opts = default_options();
+        let (mut min, mut max, mut total) = (u64::MAX, 0u64, 0u128);
+        let mut first_hash = None;
+        for i in 0..iterations {
+            let start = std::time::Instant::now();
+            let pixmap = match unsafe { render_svg_raw(svg_ptr, svg_len, width, height, &opts) } {
+                Ok(p) => p,
+                Err(e) => {
+                    set_err(e);
+                    return false;
+                }
+            };
+            let us = start.elapsed().as_micros() as u64;
+            let hash = fnv1a64([pixmap.data()]);
+            drop(pixmap);
+
+            if *first_hash.get_or_insert(hash) != hash {
+                set_err(format!("render {i} differs from the first (hash {hash:016x})"));
+                return false;
+            }
+            min = min.min(us);
+            max = max.max(us);
+            total += us as u128;
+        }
+
+        unsafe {
+            *out_min_us = min;
+            *out_max_us = max;
+            *out_avg_us = (total / iterations as u128) as u64;
+        }
+        true
+    })
+}
+
+// ============================================================================
+// SVG REWRITING
+// ============================================================================
+//
+// Functions that return a modified copy of an SVG document. Edits are made
+// on the source text, so everything not touched (formatting, comments,
+// unknown elements) is preserved byte for byte. Results are returned as a
+// buffer freed with `rb_free_buffer()`.
+
+/// Applies non-overlapping `(byte range, replacement)` edits to `text`.
+fn apply_edits(text: &str, mut edits: Vec<(std::ops::Range<usize>, String)>) -> String {
+    edits.sort_by_key(|(range, _)| range.start);
+    let mut out = String::with_capacity(text.len());
+    let mut pos = 0;
+    for (range, replacement) in edits {
+        out.push_str(&text[pos..range.start]);
+        out.push_str(&replacement);
+        pos = range.end;
+    }
+    out.push_str(&text[pos..]);
+    out
+}
+
+/// Resolves a URL from the SVG to a file inside `dir`.
+///
+/// Returns `None` for data URIs, remote URLs, and paths that would escape
+/// `dir` or don't exist.
+fn local_file_in(dir: &std::path::Path, url: &str) -> Option<std::path::PathBuf> {
+    let url = url.trim();
+    let path = url.strip_prefix("file://").unwrap_or(url);
+    if path.is_empty() || path.starts_with('#') || (path.contains(':') && !url.starts_with("file://")) {
+        // Fragment, data URI or another scheme
+        return None;
+    }
+    let dir = dir.canonicalize().ok()?;
+    let file = dir.join(path).canonicalize().ok()?;
+    (file.starts_with(&dir) && file.is_file()).then_some(file)
+}
+
+/// Builds a `data:` URI for a file.
+fn file_data_uri(path: &std::path::Path, mime: &str) -> Result<String, String> {
+    let data = std::fs::read(path).map_err(|e| format!("io error: {}: {e}", path.display()))?;
+    Ok(format!("data:{mime};base64,{}", base64_encode(&data)))
+}
+
+/// Shared FFI body of the rewriting functions: validates the arguments,
+/// runs `rewrite` on the SVG text and hands the result to the caller.
+///
+/// # Safety
+/// `svg_ptr` must be valid for `svg_len` bytes, `dir` must be a valid
+/// null-terminated string and both output pointers must be valid for writes.
+unsafe fn rewrite_svg_ffi(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    dir: *const c_char,
+    out_svg: *mut *mut u8,
+    out_len: *mut usize,
+    rewrite: impl FnOnce(&str, &std::path::Path) -> Result<String, String>,
+) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+
+        if svg_ptr.is_null() || svg_len == 0 || dir.is_null() || out_svg.is_null() || out_len.is_null() {
+            set_err("invalid args".into());
+            return false;
+        }
+        unsafe {
+            *out_svg = std::ptr::null_mut();
+            *out_len = 0;
+        }
+        let Ok(dir) = unsafe { CStr::from_ptr(dir) }.to_str() else {
+            set_err("path is not valid UTF-8".into());
+            return false;
+        };
+        let Ok(text) = std::str::from_utf8(unsafe { slice::from_raw_parts(svg_ptr, svg_len) }) else {
+            set_err("SVG is not valid UTF-8".into());
+            return false;
+        };
+
+        match rewrite(text, std::path::Path::new(dir)) {
+            Ok(svg) => {
+                unsafe { *out_svg = buffer_into_raw(svg.into_bytes(), out_len) };
+                true
+            }
+            Err(e) => {
+                set_err(e);
+                false
+            }
+        }
+    })
+}
+
+/// Produces a copy of an SVG with its `@font-face` fonts inlined.
+///
+/// Every `url(...)` inside an `@font-face` rule of a `<style>` element that
+/// names a file inside `font_dir` is replaced with a base64 `data:` URI, so
+/// the SVG no longer depends on the font files. The MIME type follows the
+/// file extension (`font/woff2`, `font/woff`, `font/ttf`, `font/otf`).
+/// URLs that aren't local files, or files outside `font_dir`, are left as
+/// they are; missing files are reported through `rb_last_warning_copy()`.
+///
+/// Note that resvg itself ignores `@font-face`; this is for making SVGs
+/// self-contained for archival or for other renderers.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes (must be UTF-8)
+/// * `font_dir` - Null-terminated directory that relative URLs resolve against
+/// * `out_svg` - Receives the new SVG, to be freed with `rb_free_buffer()`
+/// * `out_len` - Receives the length of the new SVG in bytes
+///
+/// # Returns
+/// * `true` on success
+/// * `false` on error (error is set, outputs are null/0)
+///
+/// # Safety
+/// `svg_ptr` must be valid for `svg_len` bytes, `font_dir` must be a valid
+/// null-terminated string and both output pointers must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rb_svg_embed_fonts(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    font_dir: *const c_char,
+    out_svg: *mut *mut u8,
+    out_len: *mut usize,
+) -> bool {
+    unsafe { rewrite_svg_ffi(svg_ptr, svg_len, font_dir, out_svg, out_len, embed_fonts) }
+}
+
+/// Implementation of `rb_svg_embed_fonts()`.
+fn embed_fonts(text: &str, font_dir: &std::path::Path) -> Result<String, String> {
+    let doc = usvg::roxmltree::Document::parse(text).map_err(|e| format!("parse error: {e}"))?;
+
+    let mut edits = Vec::new();
+    for style in doc.descendants().filter(|n| n.has_tag_name("style")) {
+        let range = style.range();
+        let css = &text[range.clone()];
+        for rule in css.match_indices("@font-face").map(|(i, _)| i) {
+            let Some(body_len) = css[rule..].find('}') else { continue };
+            let body_start = range.start + rule;
+            for (url_range, url) in css_urls(&text[body_start..body_start + body_len]) {
+                let url_range = body_start + url_range.start..body_start + url_range.end;
+                let Some(file) = local_file_in(font_dir, &url) else {
+                    if !url.starts_with("data:") && !url.contains("://") {
+                        add_warning(format!("font '{url}' not found in font directory"));
+                    }
+                    continue;
+                };
+                let mime = match file.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+                    Some("woff2") => "font/woff2",
+                    Some("woff") => "font/woff",
+                    Some("ttf") => "font/ttf",
+                    Some("otf") => "font/otf",
+                    _ => "application/octet-stream",
+                };
+                edits.push((url_range, format!("\"{}\"", file_data_uri(&file, mime)?)));
+            }
+        }
+    }
+
+    Ok(apply_edits(text, edits))
+}
+
+/// Produces a copy of an SVG with its local raster images inlined.
+///
+/// The `href`/`xlink:href` of every `<image>` and `<feImage>` element that
+/// names a file inside `base_dir` is replaced with a base64 `data:` URI.
+/// The MIME type follows the file extension (PNG, JPEG, GIF, WebP and SVG);
+/// data URIs, remote URLs and element references are left as they are, and
+/// missing or unrecognized files are reported through
+/// `rb_last_warning_copy()`.
+///
+/// # Arguments
+/// * `svg_ptr` - Pointer to the SVG data (must not be null)
+/// * `svg_len` - Length of the SVG data in bytes (must be UTF-8)
+/// * `base_dir` - Null-terminated directory that relative hrefs resolve against
+/// * `out_svg` - Receives the new SVG, to be freed with `rb_free_buffer()`
+/// * `out_len` - Receives the length of the new SVG in bytes
+///
+/// # Returns
+/// * `true` on success
+/// * `false` on error (error is set, outputs are null/0)
+///
+/// # Safety
+/// `svg_ptr` must be valid for `svg_len` bytes, `base_dir` must be a valid
+/// null-terminated string and both output pointers must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rb_svg_inline_images(
+    svg_ptr: *const u8,
+    svg_len: usize,
+    base_dir: *const c_char,
+    out_svg: *mut *mut u8,
+    out_len: *mut usize,
+) -> bool {
+    unsafe { rewrite_svg_ffi(svg_ptr, svg_len, base_dir, out_svg, out_len, inline_images) }
+}
+
+/// Implementation of `rb_svg_inline_images()`.
+fn inline_images(text: &str, base_dir: &std::path::Path) -> Result<String, String> {
+    const XLINK_NS: &str = "http://www.w3.org/1999/xlink";
+
+    let doc = usvg::roxmltree::Document::parse(text).map_err(|e| format!("parse error: {e}"))?;
+
+    let mut edits = Vec::new();
+    let images = doc.descendants().filter(|n| n.has_tag_name("image") || n.has_tag_name("feImage"));
+    for attr in images.flat_map(|n| n.attributes()) {
+        if attr.name() != "href" || !matches!(attr.namespace(), None | Some(XLINK_NS)) {
+            continue;
+        }
+        let href = attr.value();
+        let Some(file) = local_file_in(base_dir, href) else {
+            if !href.starts_with("data:") && !href.contains("://") && !href.starts_with('#') {
+                add_warning(format!("image '{href}' not found in base directory"));
+            }
+            continue;
+        };
+        let mime = match file.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("png") => "image/png",
+            Some("jpg" | "jpeg") => "image/jpeg",
+            Some("gif") => "image/gif",
+            Some("webp") => "image/webp",
+            Some("svg") => "image/svg+xml",
+            _ => {
+                add_warning(format!("image '{href}' has an unrecognized type"));
+                continue;
+            }
+        };
+        edits.push((attr.range_value(), file_data_uri(&file, mime)?));
+    }
+
+    Ok(apply_edits(text, edits))
+}
+
+/// Finds the `url(...)` tokens in a CSS fragment.
+///
+/// Returns the byte range of each argument (including any quotes) and the
+/// unquoted URL.
+fn css_urls(css: &str) -> Vec<(std::ops::Range<usize>, String)> {
+    let mut urls = Vec::new();
+    let mut pos = 0;
+    while let Some(i) = css[pos..].find("url(") {
+        let start = pos + i + 4;
+        let Some(len) = css[start..].find(')') else { break };
+        let arg = &css[start..start + len];
+        let trimmed = arg.trim();
+        let offset = start + (arg.len() - arg.trim_start().len());
+        let url = trimmed
+            .strip_prefix(['"', '\''])
+            .and_then(|u| u.strip_suffix(['"', '\'']))
+            .unwrap_or(trimmed);
+        urls.push((offset..offset + trimmed.len(), url.to_string()));
+        pos = start + len + 1;
+    }
+    urls
+}
+
+// ============================================================================
+// UTILITIES
+// ============================================================================
+
+/// Parses an SVG/CSS color string into RGBA components.
+///
+/// Accepts everything SVG accepts for a color value, including:
+/// - `#rgb`, `#rrggbb`, `#rrggbbaa`
+/// - `rgb(r, g, b)`, `rgba(r, g, b, a)`
+/// - Named colors from the SVG color table (e.g. `cornflowerblue`)
+///
+/// # Arguments
+/// * `color_str` - Null-terminated color string (must not be null)
+/// * `out_r`, `out_g`, `out_b`, `out_a` - Destinations for the components (must not be null)
+///
+/// # Returns
+/// * `true` on success
+/// * `false` if the string isn't a valid color (error is set, outputs untouched)
+///
+/// # Safety
+/// `color_str` must be a valid null-terminated string and every output
+/// pointer must be valid for a single byte write.
+#[no_mangle]
+pub unsafe extern "C" fn rb_parse_color(
+    color_str: *const c_char,
+    out_r: *mut u8,
+    out_g: *mut u8,
+    out_b: *mut u8,
+    out_a: *mut u8,
+) -> bool {
+    ffi_guard(false, || {
+        clear_err();
+
+        if color_str.is_null() || out_r.is_null() || out_g.is_null() || out_b.is_null() || out_a.is_null() {
+            set_err("invalid args".into());
+            return false;
+        }
+
+        let text = match unsafe { CStr::from_ptr(color_str) }.to_str() {
+            Ok(t) => t.trim(),
+            Err(_) => {
+                set_err("color string is not valid UTF-8".into());
+                return false;
+            }
+        };
+
+        match text.parse::<svgtypes::Color>() {
+            Ok(c) => {
+                unsafe {
+                    *out_r = c.red;
+                    *out_g = c.green;
+                    *out_b = c.blue;
+                    *out_a = c.alpha;
+                }
+                true
+            }
+            Err(e) => {
+                set_err(format!("invalid color '{text}': {e}"));
+                false
+            }
+        }
+    })
+}
+
+/// Hands a list of strings to the caller as a null-terminated array.
+///
+/// # Safety
+/// Both output pointers must be valid for writes.
+unsafe fn string_array_into_raw(strings: Vec<String>, out_array: *mut *mut *mut c_char, out_count: *mut usize) {
+    let count = strings.len();
+    let mut array: Vec<*mut c_char> = strings
+        .into_iter()
+        .map(|s| CString::new(s.replace('\0', " ")).unwrap_or_default().into_raw())
+        .collect();
+    array.push(std::ptr::null_mut());
+    let mut array = array.into_boxed_slice();
+    unsafe {
+        *out_array = array.as_mut_ptr();
+        *out_count = count;
+    }
+    std::mem::forget(array);
+}
+
+/// Frees a string array returned by this library.
+///
+/// # Arguments
+/// * `array` - The array (null is ignored)
+/// * `count` - The count reported alongside the array
+///
+/// # Safety
+/// `array`/`count` must come from this library and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rb_free_string_array(array: *mut *mut c_char, count: usize) {
+    ffi_guard((), || {
+        if array.is_null() {
+            return;
+        }
+        // The array holds `count` strings plus the null terminator
+        let array = unsafe { Vec::from_raw_parts(array, count + 1, count + 1) };
+        for s in array.into_iter().take(count) {
+            drop(unsafe { CString::from_raw(s) });
+        }
+    })
+}
+
+/// Frees a string returned by this library.
+///
+/// # Arguments
+/// * `s` - The string (null is ignored)
+///
+/// # Safety
+/// `s` must come from this library (e.g. `rb_image_to_ascii_art()`) and must
+/// not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rb_free_cstring(s: *mut c_char) {
+    ffi_guard((), || {
+        if !s.is_null() {
+            drop(unsafe { CString::from_raw(s) });
+        }
+    })
+}
+
+/// Encodes bytes as standard base64 with padding.
+fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], chunk.get(1).copied().unwrap_or(0), chunk.get(2).copied().unwrap_or(0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serializes tests that touch process-wide state (default options,
+    /// font aliases, the thread pool), and tests that render without options
+    /// and so read the default options.
+    static GLOBALS: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    fn lock_globals() -> std::sync::MutexGuard<'static, ()> {
+        GLOBALS.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    const SQUARE: &[u8] = br##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10" viewBox="0 0 10 10"><rect x="2" y="2" width="6" height="6" fill="#f00"/></svg>"##;
+
+    /// Copies an image's bytes into a `Vec` and frees it.
+    fn take_bytes(img: RBImage) -> Vec<u8> {
+        let bytes = unsafe { image_bytes(&img) }.to_vec();
+        rb_free_image(img);
+        bytes
+    }
+
+    fn pixel(img: &RBImage, x: u32, y: u32) -> [u8; 4] {
+        let bpp = img.pixel_format().unwrap().bytes_per_pixel();
+        let i = (y * img.width + x) as usize * bpp;
+        let mut px = [0u8; 4];
+        px[..bpp].copy_from_slice(unsafe { &image_bytes(img)[i..i + bpp] });
+        px
+    }
+
+    fn last_error() -> Option<String> {
+        LAST_ERR.with(|e| e.borrow().clone())
+    }
+
+    fn last_warning() -> Option<String> {
+        LAST_WARN.with(|w| w.borrow().clone())
+    }
+
+    fn render(svg: &[u8], width: u32, height: u32, opts: &RBOptions) -> RBImage {
+        unsafe { rb_render_svg_to_rgba_with_options(svg.as_ptr(), svg.len(), width, height, opts) }
+    }
+
+    #[test]
+    fn single_thread_pool_matches_default_pool() {
+        let _g = lock_globals();
+        // Fails harmlessly if another test already built the pool at size 1
+        rb_set_thread_count(1);
+        assert_eq!(thread_pool().current_num_threads(), 1);
+
+        let sizes = [RBSize { width: 16, height: 16 }, RBSize { width: 40, height: 24 }, RBSize { width: 7, height: 31 }];
+        let mut out: Vec<RBImage> = (0..sizes.len()).map(|_| RBImage::empty()).collect();
+        assert!(unsafe { rb_render_svg_to_rgba_batch(SQUARE.as_ptr(), SQUARE.len(), sizes.as_ptr(), sizes.len(), out.as_mut_ptr()) });
+
+        let tree = parse_svg(SQUARE, &RBOptions::default()).unwrap();
+        let progress = Progress::new(None, std::ptr::null_mut(), sizes.len());
+        let pool = rayon::ThreadPoolBuilder::new().build().unwrap();
+        let expected = pool.install(|| render_sizes(&tree, &sizes, &RBOptions::default(), &progress)).unwrap();
+
+        for (img, want) in out.into_iter().zip(expected) {
+            assert_eq!(take_bytes(img), want.data());
+        }
+    }
+
+    #[test]
+    fn option_setters_clear_a_stale_error() {
+        type Setter = unsafe fn(*mut RBOptions) -> bool;
+        let setters: [(&str, Setter); 15] = [
+            ("assumed_size", |o| unsafe { rb_options_set_assumed_size(o, 20.0, 40.0) }),
+            ("size_override", |o| unsafe { rb_options_set_size_override(o, 200.0, 200.0) }),
+            ("embedded_images", |o| unsafe { rb_options_set_embedded_images(o, true, 1 << 20) }),
+            ("strict_features", |o| unsafe { rb_options_set_strict_features(o, true) }),
+            ("clip_to_viewbox", |o| unsafe { rb_options_set_clip_to_viewbox(o, true) }),
+            ("text_antialias", |o| unsafe { rb_options_set_text_antialias(o, false) }),
+            ("crisp_edges", |o| unsafe { rb_options_set_crisp_edges(o, true) }),
+            ("seed", |o| unsafe { rb_options_set_seed(o, 7) }),
+            ("default_clip_rule", |o| unsafe { rb_options_set_default_clip_rule(o, RBFillRule::EvenOdd as u32) }),
+            ("size_limits", |o| unsafe { rb_options_set_size_limits(o, 0, 0, 400, 400) }),
+            ("output_format", |o| unsafe { rb_options_set_output_format(o, RBPixelFormat::Rgb8 as u32, 255, 255, 255) }),
+            ("checkerboard", |o| unsafe { rb_options_set_checkerboard(o, true, 2, 255, 255, 255, 204, 204, 204) }),
+            ("base_path", |o| unsafe { rb_options_set_base_path(o, c"assets".as_ptr()) }),
+            ("style_sheet", |o| unsafe { rb_options_set_style_sheet(o, c"#a { fill: #00f }".as_ptr()) }),
+            ("languages", |o| unsafe { rb_options_set_languages(o, c"ja, en-US".as_ptr()) }),
+        ];
+        for (name, setter) in setters {
+            let mut opts = RBOptions::default();
+            set_err("stale".into());
+            assert!(unsafe { setter(&mut opts) }, "{name}");
+            assert_eq!(last_error(), None, "{name}");
+        }
+    }
+
+    #[test]
+    fn size_override_changes_fit_but_not_viewbox() {
+        // The viewBox's top-left quarter is red and maps onto the declared 100×100
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100" viewBox="0 0 10 10"><rect width="5" height="5" fill="#f00"/></svg>"##;
+
+        let plain = render(svg, 200, 200, &RBOptions::default());
+        assert_eq!(pixel(&plain, 75, 75), [255, 0, 0, 255]);
+        assert_eq!(pixel(&plain, 125, 125), [0, 0, 0, 0]);
+        take_bytes(plain);
+
+        let mut opts = RBOptions::default();
+        assert!(unsafe { rb_options_set_size_override(&mut opts, 200.0, 200.0) });
+
+        // Scaled by 200/200 instead of 200/100; the viewBox still spans the declared size
+        let overridden = render(svg, 200, 200, &opts);
+        assert_eq!(pixel(&overridden, 25, 25), [255, 0, 0, 255]);
+        assert_eq!(pixel(&overridden, 75, 75), [0, 0, 0, 0]);
+        take_bytes(overridden);
+    }
+
+    #[test]
+    fn image_hash_tracks_pixel_content() {
+        let a = render(SQUARE, 16, 16, &RBOptions::default());
+        let b = render(SQUARE, 16, 16, &RBOptions::default());
+        let hash_a = unsafe { rb_image_hash(&a) };
+        assert_ne!(hash_a, 0);
+        assert_eq!(hash_a, unsafe { rb_image_hash(&b) });
+
+        unsafe { *b.ptr.add(4 * 17) ^= 1 };
+        assert_ne!(hash_a, unsafe { rb_image_hash(&b) });
+        take_bytes(a);
+        take_bytes(b);
+    }
+
+    #[test]
+    fn tiff_output_decodes_to_the_rendered_size() {
+        let _g = lock_globals();
+        for compression in [RBTiffCompression::None, RBTiffCompression::Lzw, RBTiffCompression::Deflate] {
+            let mut len = 0;
+            let ptr = unsafe { rb_render_svg_to_tiff(SQUARE.as_ptr(), SQUARE.len(), 24, 12, compression as u32, &mut len) };
+            assert!(!ptr.is_null());
+            let data = unsafe { slice::from_raw_parts(ptr, len) }.to_vec();
+            unsafe { rb_free_buffer(ptr, len) };
+
+            let mut decoder = tiff::decoder::Decoder::new(std::io::Cursor::new(data)).unwrap();
+            assert_eq!(decoder.dimensions().unwrap(), (24, 12));
+            assert_eq!(decoder.colortype().unwrap(), tiff::ColorType::RGBA(8));
+            let tiff::decoder::DecodingResult::U8(pixels) = decoder.read_image().unwrap() else {
+                panic!("expected 8-bit samples");
+            };
+            assert_eq!(pixels.len(), 24 * 12 * 4);
+        }
+
+        let mut len = 1;
+        assert!(unsafe { rb_render_svg_to_tiff(SQUARE.as_ptr(), SQUARE.len(), 24, 12, 3, &mut len) }.is_null());
+        assert_eq!(len, 0);
+        assert_eq!(last_error().as_deref(), Some("invalid args: unknown TIFF compression 3"));
+    }
+
+    #[test]
+    fn svg_has_text_distinguishes_text_from_shapes() {
+        let has_text = |svg: &[u8]| unsafe { rb_svg_has_text(svg.as_ptr(), svg.len()) };
+        assert_eq!(has_text(SQUARE), 0);
+        assert_eq!(has_text(br#"<svg xmlns="http://www.w3.org/2000/svg"><text x="1" y="9">Hi</text></svg>"#), 1);
+        assert_eq!(has_text(br#"<svg xmlns="http://www.w3.org/2000/svg"><text><tspan>Hi</tspan></text></svg>"#), 1);
+        assert_eq!(has_text(br#"<svg xmlns="http://www.w3.org/2000/svg"><text>  </text></svg>"#), 0);
+        assert_eq!(has_text(b"<svg"), -1);
+    }
+
+    /// A 2×2 opaque green PNG file.
+    fn green_png() -> Vec<u8> {
+        let mut png_bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png_bytes, 2, 2);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header().unwrap().write_image_data(&[0, 255, 0, 255].repeat(4)).unwrap();
+        png_bytes
+    }
+
+    /// A `data:` URI holding `green_png()`.
+    fn green_png_uri() -> String {
+        format!("data:image/png;base64,{}", base64_encode(&green_png()))
+    }
+
+    /// A fresh, empty directory under the system temp dir.
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("resvg-bridge-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn oversized_data_uri_is_skipped_with_a_warning() {
+        let uri = green_png_uri();
+        let svg = format!(r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><image width="10" height="10" href="{uri}"/></svg>"#);
+
+        let mut opts = RBOptions::default();
+        assert!(unsafe { rb_options_set_embedded_images(&mut opts, true, 1 << 20) });
+        let img = render(svg.as_bytes(), 10, 10, &opts);
+        assert_eq!(pixel(&img, 5, 5), [0, 255, 0, 255]);
+        assert_eq!(last_warning(), None);
+        take_bytes(img);
+
+        assert!(unsafe { rb_options_set_embedded_images(&mut opts, true, 10) });
+        let img = render(svg.as_bytes(), 10, 10, &opts);
+        assert_eq!(pixel(&img, 5, 5), [0, 0, 0, 0]);
+        let warning = last_warning().unwrap();
+        assert!(warning.starts_with("skipped embedded") && warning.ends_with("(limit 10)"), "{warning}");
+        take_bytes(img);
+    }
+
+    #[test]
+    fn strict_features_rejects_background_filter_inputs() {
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><filter id="f"><feBlend in="SourceGraphic" in2="BackgroundImage"/></filter><rect width="10" height="10" filter="url(#f)"/></svg>"#;
+
+        let img = render(svg, 10, 10, &RBOptions::default());
+        assert!(!img.ptr.is_null());
+        take_bytes(img);
+
+        let mut opts = RBOptions::default();
+        assert!(unsafe { rb_options_set_strict_features(&mut opts, true) });
+        let img = render(svg, 10, 10, &opts);
+        assert!(img.ptr.is_null());
+        assert_eq!(last_error().as_deref(), Some("unsupported features: in2=\"BackgroundImage\" at line 1"));
+    }
+
+    #[test]
+    fn color_count_on_three_colors() {
+        let data = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255], [255, 0, 0, 255]].concat();
+        let img = RBImage::from_vec(data, 2, 2);
+        assert_eq!(unsafe { rb_image_color_count(&img, 256) }, 3);
+        assert_eq!(unsafe { rb_image_color_count(&img, 3) }, 3);
+        assert_eq!(unsafe { rb_image_color_count(&img, 2) }, 3);
+        assert_eq!(unsafe { rb_image_color_count(&img, 1) }, 2);
+        take_bytes(img);
+    }
+
+    #[test]
+    fn clip_to_viewbox_hides_overflow() {
+        // The viewBox maps onto x 5..15; the rect spills into the letterbox on both sides
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10" viewBox="0 0 10 10"><rect x="-5" width="20" height="10" fill="#f00"/></svg>"##;
+
+        let img = render(svg, 20, 10, &RBOptions::default());
+        assert_eq!(pixel(&img, 1, 5), [255, 0, 0, 255]);
+        take_bytes(img);
+
+        let mut opts = RBOptions::default();
+        assert!(unsafe { rb_options_set_clip_to_viewbox(&mut opts, true) });
+        let img = render(svg, 20, 10, &opts);
+        assert_eq!(pixel(&img, 1, 5), [0, 0, 0, 0]);
+        assert_eq!(pixel(&img, 18, 5), [0, 0, 0, 0]);
+        assert_eq!(pixel(&img, 10, 5), [255, 0, 0, 255]);
+        take_bytes(img);
+    }
+
+    #[test]
+    fn tree_reuse_with_style_override() {
+        let _g = lock_globals();
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect id="a" width="10" height="10" fill="#f00"/></svg>"##;
+        let mut opts = RBOptions::default();
+        assert!(unsafe { rb_options_set_style_sheet(&mut opts, c"#a { fill: #00f }".as_ptr()) });
+
+        let tree = unsafe { rb_tree_parse(svg.as_ptr(), svg.len(), &opts) };
+        assert!(!tree.is_null());
+        let render_with = |css: &CStr| {
+            let img = unsafe { rb_tree_render_with_style_sheet(tree, css.as_ptr(), 10, 10) };
+            let px = pixel(&img, 5, 5);
+            take_bytes(img);
+            px
+        };
+        assert_eq!(render_with(c""), [0, 0, 255, 255]);
+        assert_eq!(render_with(c"#a { fill: #0f0 }"), [0, 255, 0, 255]);
+        // The per-render style sheet leaves the stored tree untouched
+        assert_eq!(render_with(c""), [0, 0, 255, 255]);
+        unsafe { rb_tree_free(tree) };
+    }
+
+    #[test]
+    fn degenerate_scales_are_rejected() {
+        for (sx, sy) in [(0.0, 1.0), (1.0, f32::MIN_POSITIVE / 2.0), (f32::NAN, 1.0), (1.0, f32::INFINITY)] {
+            assert!(checked_scale(sx, sy).unwrap_err().starts_with("invalid scale"));
+        }
+        assert!(checked_scale(f32::MAX, 1.0).is_ok());
+
+        let zero = br#"<svg xmlns="http://www.w3.org/2000/svg" width="0" height="10"/>"#;
+        let img = render(zero, 10, 10, &RBOptions::default());
+        assert!(img.ptr.is_null());
+        assert_eq!(rb_last_error_code(), RBErrorCode::Parse);
+
+        // A near-zero declared size is clamped instead of producing a huge scale
+        let tiny = br##"<svg xmlns="http://www.w3.org/2000/svg" width="1e-39" height="1e-39" viewBox="0 0 10 10"><rect width="10" height="10" fill="#f00"/></svg>"##;
+        let img = render(tiny, 4, 4, &RBOptions::default());
+        assert!(!img.ptr.is_null());
+        take_bytes(img);
+
+        let img = render(SQUARE, u32::MAX, 1, &RBOptions::default());
+        assert!(img.ptr.is_null());
+        assert!(last_error().is_some());
+    }
+
+    #[test]
+    fn languages_select_switch_branch() {
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><switch><rect systemLanguage="ja" width="10" height="10" fill="#f00"/><rect width="10" height="10" fill="#00f"/></switch></svg>"##;
+
+        let img = render(svg, 10, 10, &RBOptions::default());
+        assert_eq!(pixel(&img, 5, 5), [0, 0, 255, 255]);
+        take_bytes(img);
+
+        let mut opts = RBOptions::default();
+        assert!(unsafe { rb_options_set_languages(&mut opts, c"ja, en-US".as_ptr()) });
+        assert_eq!(opts.languages.as_deref(), Some(["ja".to_string(), "en-US".to_string()].as_slice()));
+        let img = render(svg, 10, 10, &opts);
+        assert_eq!(pixel(&img, 5, 5), [255, 0, 0, 255]);
+        take_bytes(img);
+    }
+
+    #[test]
+    fn autocrop_removes_known_padding() {
+        // SQUARE at 10×10 leaves two transparent pixels on each side of the rect
+        let img = render(SQUARE, 10, 10, &RBOptions::default());
+        let cropped = unsafe { rb_autocrop(&img, 0) };
+        assert_eq!((cropped.width, cropped.height), (6, 6));
+        assert!(unsafe { image_bytes(&cropped) }.chunks_exact(4).all(|px| px == [255, 0, 0, 255]));
+        take_bytes(cropped);
+
+        let trimmed = unsafe { rb_autocrop_sides(&img, 0, true, false, true, false) };
+        assert_eq!((trimmed.width, trimmed.height), (8, 8));
+        assert_eq!(pixel(&trimmed, 0, 0), [255, 0, 0, 255]);
+        assert_eq!(pixel(&trimmed, 7, 7), [0, 0, 0, 0]);
+        take_bytes(trimmed);
+        take_bytes(img);
+
+        let empty = RBImage::from_vec(vec![0; 16], 2, 2);
+        assert!(unsafe { rb_autocrop(&empty, 0) }.ptr.is_null());
+        assert_eq!(last_error().as_deref(), Some("image is fully transparent"));
+        take_bytes(empty);
+    }
+
+    #[test]
+    fn mid_gray_srgb_linear_round_trip() {
+        let mut img = RBImage::from_vec([128, 128, 128, 255, 0, 0, 0, 0].to_vec(), 2, 1);
+        assert!(unsafe { rb_image_srgb_to_linear(&mut img) });
+        // sRGB 128 is 21.6% linear light
+        assert_eq!(pixel(&img, 0, 0), [55, 55, 55, 255]);
+        assert_eq!(pixel(&img, 1, 0), [0, 0, 0, 0]);
+        assert!(unsafe { rb_image_linear_to_srgb(&mut img) });
+        assert_eq!(pixel(&img, 0, 0), [128, 128, 128, 255]);
+        take_bytes(img);
+    }
+
+    /// Decodes an 8-bit RGBA PNG into `(width, height, pixels)`.
+    fn decode_png(data: &[u8]) -> (u32, u32, Vec<u8>) {
+        let mut reader = png::Decoder::new(data).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.color_type, info.bit_depth), (png::ColorType::Rgba, png::BitDepth::Eight));
+        pixels.truncate(info.buffer_size());
+        (info.width, info.height, pixels)
+    }
+
+    #[test]
+    fn semi_transparent_pixel_round_trips_through_png_as_straight_alpha() {
+        let _g = lock_globals();
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="4"><rect width="4" height="4" fill="#f00" fill-opacity="0.5"/></svg>"##;
+
+        let mut len = 0;
+        let ptr = unsafe { rb_render_svg_to_png(svg.as_ptr(), svg.len(), 4, 4, &mut len) };
+        assert!(!ptr.is_null());
+        let (w, h, pixels) = decode_png(unsafe { slice::from_raw_parts(ptr, len) });
+        unsafe { rb_free_buffer(ptr, len) };
+        assert_eq!((w, h), (4, 4));
+        assert_eq!(pixels[..4], [255, 0, 0, 128]);
+
+        // The raw render is premultiplied and converts to the same straight pixel
+        let mut img = render(svg, 4, 4, &RBOptions::default());
+        assert_eq!(pixel(&img, 0, 0), [128, 0, 0, 128]);
+        assert!(unsafe { rb_image_unpremultiply(&mut img) });
+        assert_eq!(pixel(&img, 0, 0), [255, 0, 0, 128]);
+        assert!(unsafe { rb_image_premultiply(&mut img) });
+        assert_eq!(pixel(&img, 0, 0), [128, 0, 0, 128]);
+        take_bytes(img);
+    }
+
+    #[test]
+    fn tree_set_fill_changes_the_pixel() {
+        let _g = lock_globals();
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect id="bg" width="10" height="10" fill="#f00"/></svg>"##;
+        let tree = unsafe { rb_tree_parse(svg.as_ptr(), svg.len(), std::ptr::null()) };
+        let center = || {
+            let img = unsafe { rb_tree_render(tree, 10, 10) };
+            let px = pixel(&img, 5, 5);
+            take_bytes(img);
+            px
+        };
+        assert_eq!(center(), [255, 0, 0, 255]);
+
+        assert!(unsafe { rb_tree_set_fill(tree, c"bg".as_ptr(), 0, 255, 0, 255) });
+        assert_eq!(center(), [0, 255, 0, 255]);
+
+        assert!(!unsafe { rb_tree_set_fill(tree, c"missing".as_ptr(), 0, 0, 255, 255) });
+        assert!(last_error().is_some());
+        assert_eq!(center(), [0, 255, 0, 255]);
+        unsafe { rb_tree_free(tree) };
+    }
+
+    #[test]
+    fn checkerboard_squares_alternate_behind_transparency() {
+        let mut opts = RBOptions::default();
+        assert!(unsafe { rb_options_set_checkerboard(&mut opts, true, 2, 255, 255, 255, 204, 204, 204) });
+
+        let img = render(SQUARE, 10, 10, &opts);
+        assert_eq!(pixel(&img, 0, 0), [255, 255, 255, 255]);
+        assert_eq!(pixel(&img, 2, 0), [204, 204, 204, 255]);
+        assert_eq!(pixel(&img, 0, 3), [204, 204, 204, 255]);
+        assert_eq!(pixel(&img, 9, 9), [255, 255, 255, 255]);
+        // Opaque content covers the pattern
+        assert_eq!(pixel(&img, 5, 5), [255, 0, 0, 255]);
+        take_bytes(img);
+
+        assert!(!unsafe { rb_options_set_checkerboard(&mut opts, true, 0, 0, 0, 0, 0, 0, 0) });
+        assert!(unsafe { rb_options_set_checkerboard(&mut opts, false, 0, 0, 0, 0, 0, 0, 0) });
+        assert!(opts.checkerboard.is_none());
+    }
+
+    #[test]
+    fn palette_maps_transparency_to_the_alpha_zero_entry() {
+        let _g = lock_globals();
+        let palette: [u8; 12] = [0, 0, 0, 255, 250, 10, 10, 255, 0, 0, 0, 0];
+        let img = unsafe { rb_render_svg_to_rgba_palette(SQUARE.as_ptr(), SQUARE.len(), 10, 10, palette.as_ptr(), 3, false) };
+        // By RGB alone the transparent pixel ties with, and picks, opaque black
+        assert_eq!(pixel(&img, 0, 0), [0, 0, 0, 0]);
+        assert_eq!(pixel(&img, 5, 5), [250, 10, 10, 255]);
+        take_bytes(img);
+    }
+
+    /// Options with the system fonts loaded, or `None` if the system has none.
+    fn system_font_options() -> Option<RBOptions> {
+        let mut opts = RBOptions::default();
+        (unsafe { rb_options_set_font_db_from_system(&mut opts) } > 0).then_some(opts)
+    }
+
+    #[test]
+    fn text_antialias_toggle_changes_glyph_edges() {
+        let Some(mut opts) = system_font_options() else { return };
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="16"><text x="1" y="12" font-family="DejaVu Sans" font-size="11">Rag</text></svg>"#;
+        let partial = |img: &RBImage| unsafe { image_bytes(img) }.chunks_exact(4).filter(|px| px[3] != 0 && px[3] != 255).count();
+
+        let smooth = render(svg, 40, 16, &opts);
+        assert!(partial(&smooth) > 0);
+        take_bytes(smooth);
+
+        assert!(unsafe { rb_options_set_text_antialias(&mut opts, false) });
+        let aliased = render(svg, 40, 16, &opts);
+        assert!(unsafe { image_bytes(&aliased) }.iter().skip(3).step_by(4).any(|&a| a == 255));
+        assert_eq!(partial(&aliased), 0);
+        take_bytes(aliased);
+    }
+
+    #[test]
+    fn quantized_gradient_fits_the_palette_limit() {
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="8"><linearGradient id="g"><stop offset="0" stop-color="#f00"/><stop offset="1" stop-color="#00f"/></linearGradient><rect width="64" height="8" fill="url(#g)"/></svg>"##;
+        let img = render(svg, 64, 8, &RBOptions::default());
+        assert!(unsafe { rb_image_color_count(&img, 16) } > 16);
+
+        let mut palette_len = 0;
+        assert!(unsafe { rb_quantize(&img, 16, std::ptr::null_mut(), &mut palette_len, std::ptr::null_mut()) });
+        assert!(palette_len > 0 && palette_len <= 16 * 4);
+
+        let mut palette = vec![0u8; palette_len];
+        let mut indices = vec![0u8; 64 * 8];
+        assert!(unsafe { rb_quantize(&img, 16, palette.as_mut_ptr(), &mut palette_len, indices.as_mut_ptr()) });
+        assert!(indices.iter().all(|&i| (i as usize) < palette_len / 4));
+        // The gradient's ends map to entries near pure red and pure blue
+        let entry = |i: u8| &palette[i as usize * 4..i as usize * 4 + 4];
+        assert!(entry(indices[0])[0] > 200 && entry(indices[63])[2] > 200);
+
+        // The image variant reports the same palette, with its length in bytes
+        let mut image_palette = vec![0u8; 16 * 4];
+        let mut image_palette_len = 0;
+        let mut indexed = RBImage::empty();
+        assert!(unsafe {
+            rb_image_quantize_to_n_colors(&img, 16, image_palette.as_mut_ptr(), &mut image_palette_len, &mut indexed)
+        });
+        assert_eq!(image_palette_len, palette_len);
+        assert_eq!(image_palette[..palette_len], palette[..]);
+        assert_eq!(indexed.pixel_format(), Ok(RBPixelFormat::Gray8));
+        assert_eq!(take_bytes(indexed), indices);
+        take_bytes(img);
+    }
+
+    #[test]
+    fn relative_image_href_resolves_against_base_path() {
+        let dir = temp_dir("base-path");
+        std::fs::create_dir(dir.join("img")).unwrap();
+        std::fs::write(dir.join("img/dot.png"), green_png()).unwrap();
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><image width="10" height="10" href="img/dot.png"/></svg>"#;
+
+        let img = render(svg, 10, 10, &RBOptions::default());
+        assert_eq!(pixel(&img, 5, 5), [0, 0, 0, 0]);
+        take_bytes(img);
+
+        let mut opts = RBOptions::default();
+        let path = CString::new(dir.to_str().unwrap()).unwrap();
+        assert!(unsafe { rb_options_set_base_path(&mut opts, path.as_ptr()) });
+        let img = render(svg, 10, 10, &opts);
+        assert_eq!(pixel(&img, 5, 5), [0, 255, 0, 255]);
+        take_bytes(img);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn clipped_to_element_padding_is_in_output_pixels() {
+        let _g = lock_globals();
+        // A 10×10-unit element on a blue background; the output scale is 4 px/unit
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100"><rect width="100" height="100" fill="#00f"/><rect id="box" x="20" y="30" width="10" height="10" fill="#f00"/></svg>"##;
+        let img = unsafe { rb_render_svg_to_rgba_clipped_to_element(svg.as_ptr(), svg.len(), c"box".as_ptr(), 5.0, 50, 50) };
+        assert_eq!((img.width, img.height), (50, 50));
+        for (x, want) in [(4, [0, 0, 255, 255]), (5, [255, 0, 0, 255]), (44, [255, 0, 0, 255]), (45, [0, 0, 255, 255])] {
+            assert_eq!(pixel(&img, x, 25), want, "x = {x}");
+            assert_eq!(pixel(&img, 25, x), want, "y = {x}");
+        }
+        take_bytes(img);
+
+        // A wider output keeps the box square and shows more of the background beside it
+        let img = unsafe { rb_render_svg_to_rgba_clipped_to_element(svg.as_ptr(), svg.len(), c"box".as_ptr(), 5.0, 90, 50) };
+        assert_eq!((img.width, img.height), (90, 50));
+        for (x, want) in [(24, [0, 0, 255, 255]), (25, [255, 0, 0, 255]), (64, [255, 0, 0, 255]), (65, [0, 0, 255, 255])] {
+            assert_eq!(pixel(&img, x, 25), want, "x = {x}");
+        }
+        assert_eq!(pixel(&img, 45, 4), [0, 0, 255, 255]);
+        assert_eq!(pixel(&img, 45, 5), [255, 0, 0, 255]);
+        take_bytes(img);
+
+        let img = unsafe { rb_render_svg_to_rgba_clipped_to_element(svg.as_ptr(), svg.len(), c"box".as_ptr(), 25.0, 50, 50) };
+        assert!(img.ptr.is_null());
+        assert_eq!(rb_last_error_code(), RBErrorCode::InvalidArgs);
+    }
+
+    #[test]
+    fn rgb_output_flattens_onto_the_background() {
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="2"><rect width="2" height="2" fill="#f00" fill-opacity="0.5"/></svg>"##;
+        let mut opts = RBOptions::default();
+        assert!(unsafe { rb_options_set_output_format(&mut opts, RBPixelFormat::Rgb8 as u32, 255, 255, 255) });
+
+        let img = render(svg, 4, 2, &opts);
+        assert_eq!(img.format, RBPixelFormat::Rgb8 as u32);
+        assert_eq!(img.len, 4 * 2 * 3);
+        assert_eq!(pixel(&img, 0, 0)[..3], [255, 127, 127]);
+        assert_eq!(pixel(&img, 3, 1)[..3], [255, 255, 255]);
+        take_bytes(img);
+    }
+
+    #[test]
+    fn one_tree_renders_identically_from_many_threads() {
+        let _g = lock_globals();
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="32" height="32"><radialGradient id="g"><stop offset="0" stop-color="#ff0"/><stop offset="1" stop-color="#08f" stop-opacity="0.3"/></radialGradient><circle cx="16" cy="16" r="14" fill="url(#g)" stroke="#000"/></svg>"##;
+        let tree = unsafe { rb_tree_parse(svg.as_ptr(), svg.len(), std::ptr::null()) };
+        assert!(!tree.is_null());
+        // Each thread renders its own size, so renders at different sizes overlap
+        let sizes: Vec<(u32, u32)> = (0..8).map(|i| (16 + 9 * i, 48 - 4 * i)).collect();
+        let expected: Vec<Vec<u8>> =
+            sizes.iter().map(|&(w, h)| take_bytes(unsafe { rb_tree_render(tree, w, h) })).collect();
+
+        // Raw pointers aren't Send; the tree itself is Send + Sync
+        let shared = tree as usize;
+        let outputs: Vec<Vec<Vec<u8>>> = std::thread::scope(|s| {
+            let handles: Vec<_> = sizes
+                .iter()
+                .map(|&(w, h)| {
+                    s.spawn(move || (0..4).map(|_| take_bytes(unsafe { rb_tree_render(shared as *const RBTree, w, h) })).collect())
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        for ((outputs, expected), (w, h)) in outputs.iter().zip(&expected).zip(&sizes) {
+            assert_eq!(expected.len(), (w * h * 4) as usize);
+            assert!(outputs.iter().all(|out| out == expected), "{w}x{h}");
+        }
+        unsafe { rb_tree_free(tree) };
+    }
+
+    #[test]
+    fn group_opacity_applies_to_the_flattened_group() {
+        // Browsers composite the group first, so the overlap shows no red through the blue
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="30" height="10"><g opacity="0.5"><rect width="20" height="10" fill="#f00"/><rect x="10" width="20" height="10" fill="#00f"/></g></svg>"##;
+        let img = render(svg, 30, 10, &RBOptions::default());
+        assert_eq!(pixel(&img, 5, 5), [128, 0, 0, 128]);
+        assert_eq!(pixel(&img, 15, 5), [0, 0, 128, 128]);
+        assert_eq!(pixel(&img, 25, 5), [0, 0, 128, 128]);
+        take_bytes(img);
+
+        // Per-element opacity blends the overlap instead
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="30" height="10"><rect width="20" height="10" fill="#f00" opacity="0.5"/><rect x="10" width="20" height="10" fill="#00f" opacity="0.5"/></svg>"##;
+        let img = render(svg, 30, 10, &RBOptions::default());
+        assert_eq!(pixel(&img, 15, 5), [64, 0, 128, 192]);
+        take_bytes(img);
+    }
+
+    #[test]
+    fn caller_built_image_encodes_to_png() {
+        let mut data = [[0, 0, 255, 255], [128, 0, 0, 128], [0, 0, 0, 0], [10, 20, 30, 255]].concat();
+        let img = RBImage { ptr: data.as_mut_ptr(), len: data.len(), width: 2, height: 2, format: RBPixelFormat::Rgba8 as u32 };
+
+        let mut len = 0;
+        let ptr = unsafe { rb_image_to_png(&img, &mut len) };
+        assert!(!ptr.is_null());
+        let (w, h, pixels) = decode_png(unsafe { slice::from_raw_parts(ptr, len) });
+        unsafe { rb_free_buffer(ptr, len) };
+        assert_eq!((w, h), (2, 2));
+        assert_eq!(pixels, [[0, 0, 255, 255], [255, 0, 0, 128], [0, 0, 0, 0], [10, 20, 30, 255]].concat());
+
+        let bad = RBImage { len: 15, ..img };
+        assert!(unsafe { rb_image_to_png(&bad, &mut len) }.is_null());
+        assert_eq!(len, 0);
+
+        let unknown = RBImage { format: 99, ..img };
+        assert!(unsafe { rb_image_to_png(&unknown, &mut len) }.is_null());
+        assert_eq!(last_error().as_deref(), Some("invalid args: unknown pixel format 99"));
+    }
+
+    extern "C" fn stop_after_row_40(row: u32, _data: *const u8, stride: usize, userdata: *mut c_void) -> bool {
+        let rows = unsafe { &mut *(userdata as *mut Vec<(u32, usize)>) };
+        rows.push((row, stride));
+        row < 40
+    }
+
+    #[test]
+    fn incremental_render_stops_when_the_row_callback_cancels() {
+        let _g = lock_globals();
+        let mut rows = Vec::<(u32, usize)>::new();
+        let ok = unsafe {
+            rb_render_svg_to_rgba_incremental(SQUARE.as_ptr(), SQUARE.len(), 20, 100, Some(stop_after_row_40), &mut rows as *mut _ as *mut c_void)
+        };
+        assert!(!ok);
+        assert_eq!(rb_last_error_code(), RBErrorCode::Cancelled);
+        assert_eq!(rows, (0..=40).map(|r| (r, 80)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn every_error_code_has_a_description() {
+        let describe = |code: i32| unsafe { CStr::from_ptr(rb_error_code_string(code)) }.to_str().unwrap();
+        let descriptions: Vec<&str> = (RBErrorCode::None as i32..=RBErrorCode::Busy as i32).map(describe).collect();
+        assert_eq!(descriptions.len(), 10);
+        for (code, text) in descriptions.iter().enumerate() {
+            assert!(!text.is_empty() && *text != "unrecognized error code", "code {code}");
+        }
+        let unique: std::collections::HashSet<_> = descriptions.iter().collect();
+        assert_eq!(unique.len(), descriptions.len());
+
+        assert_eq!(describe(RBErrorCode::Busy as i32 + 1), "unrecognized error code");
+        assert_eq!(describe(-1), "unrecognized error code");
+    }
+
+    #[test]
+    fn default_clip_rule_applies_to_fills_and_clip_paths() {
+        // Both subpaths wind the same way, so only even-odd leaves a hole
+        let ring = "M0 0H20V20H0Z M5 5H15V15H5Z";
+        let fill = format!(r##"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="20"><path d="{ring}" fill="#f00"/></svg>"##);
+        let clip = format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="20"><clipPath id="c"><path d="{ring}"/></clipPath><rect width="20" height="20" fill="#f00" clip-path="url(#c)"/></svg>"##
+        );
+        let mut opts = RBOptions::default();
+        for svg in [&fill, &clip] {
+            let img = render(svg.as_bytes(), 20, 20, &opts);
+            assert_eq!(pixel(&img, 10, 10), [255, 0, 0, 255]);
+            take_bytes(img);
+        }
+
+        assert!(unsafe { rb_options_set_default_clip_rule(&mut opts, RBFillRule::EvenOdd as u32) });
+        for svg in [&fill, &clip] {
+            let img = render(svg.as_bytes(), 20, 20, &opts);
+            assert_eq!(pixel(&img, 10, 10), [0, 0, 0, 0]);
+            assert_eq!(pixel(&img, 2, 2), [255, 0, 0, 255]);
+            take_bytes(img);
+        }
+
+        assert!(!unsafe { rb_options_set_default_clip_rule(&mut opts, 2) });
+        assert_eq!(last_error().as_deref(), Some("invalid args: unknown fill rule 2"));
+        assert!(opts.default_fill_rule == RBFillRule::EvenOdd);
+    }
+
+    #[test]
+    fn checkerboard_image_uses_the_given_colors() {
+        let img = rb_create_checkerboard_image(5, 3, 2, 255, 255, 255, 204, 204, 204);
+        assert_eq!((img.width, img.height), (5, 3));
+        let rows: Vec<Vec<u8>> = (0..3).map(|y| (0..5).map(|x| pixel(&img, x, y)[0]).collect()).collect();
+        assert_eq!(rows, [[255, 255, 204, 204, 255], [255, 255, 204, 204, 255], [204, 204, 255, 255, 204]]);
+        assert!(unsafe { image_bytes(&img) }.chunks_exact(4).all(|px| px[0] == px[1] && px[1] == px[2] && px[3] == 255));
+        take_bytes(img);
+
+        assert!(rb_create_checkerboard_image(5, 3, 0, 0, 0, 0, 0, 0, 0).ptr.is_null());
+    }
+
+    #[test]
+    fn size_limits_keep_the_aspect_ratio() {
+        let mut opts = RBOptions::default();
+        assert!(unsafe { rb_options_set_size_limits(&mut opts, 0, 0, 400, 400) });
+        assert_eq!(opts.clamp_size(1000, 500), (400, 200));
+        assert_eq!(opts.clamp_size(300, 900), (133, 400));
+        assert_eq!(opts.clamp_size(200, 100), (200, 100));
+
+        let img = render(SQUARE, 1000, 500, &opts);
+        assert_eq!((img.width, img.height), (400, 200));
+        take_bytes(img);
+
+        assert!(unsafe { rb_options_set_size_limits(&mut opts, 64, 64, 0, 0) });
+        assert_eq!(opts.clamp_size(16, 32), (64, 128));
+        assert!(!unsafe { rb_options_set_size_limits(&mut opts, 64, 64, 32, 0) });
+    }
+
+    extern "C" fn collect_feature(what: *const c_char, id: *const c_char, line: u32, userdata: *mut c_void) {
+        let found = unsafe { &mut *(userdata as *mut Vec<(String, String, u32)>) };
+        let text = |p| unsafe { CStr::from_ptr(p) }.to_string_lossy().into_owned();
+        found.push((text(what), text(id), line));
+    }
+
+    #[test]
+    fn feature_report_lists_unsupported_filter_inputs() {
+        let svg = b"<svg xmlns=\"http://www.w3.org/2000/svg\">\n<filter id=\"f\">\n<feComposite id=\"bg\" in=\"BackgroundAlpha\"/>\n<feGaussianBlur stdDeviation=\"2\"/>\n</filter>\n</svg>";
+        let mut found = Vec::<(String, String, u32)>::new();
+        let count = unsafe { rb_feature_report(svg.as_ptr(), svg.len(), Some(collect_feature), &mut found as *mut _ as *mut c_void) };
+        assert_eq!(count, 1);
+        assert_eq!(found, [("in=\"BackgroundAlpha\"".to_string(), "bg".to_string(), 3)]);
+
+        assert_eq!(unsafe { rb_feature_report(SQUARE.as_ptr(), SQUARE.len(), None, std::ptr::null_mut()) }, 0);
+    }
+
+    #[test]
+    fn image_convert_produces_exact_bytes() {
+        // Opaque red, half-transparent green (premultiplied) and transparent
+        let src = RBImage::from_vec([[255, 0, 0, 255], [0, 128, 0, 128], [0, 0, 0, 0]].concat(), 3, 1);
+        let convert = |format: RBPixelFormat, [r, g, b]: [u8; 3]| {
+            let img = unsafe { rb_image_convert(&src, format as u32, r, g, b) };
+            assert_eq!((img.width, img.height, img.format), (3, 1, format as u32));
+            take_bytes(img)
+        };
+
+        assert_eq!(convert(RBPixelFormat::Rgba8, [0; 3]), unsafe { image_bytes(&src) });
+        assert_eq!(convert(RBPixelFormat::Bgra8, [0; 3]), [0, 0, 255, 255, 0, 128, 0, 128, 0, 0, 0, 0]);
+        assert_eq!(convert(RBPixelFormat::Rgb8, [0, 0, 255]), [255, 0, 0, 0, 128, 127, 0, 0, 255]);
+        assert_eq!(convert(RBPixelFormat::Gray8, [255; 3]), [76, 202, 255]);
+
+        let floats: Vec<f32> = convert(RBPixelFormat::Rgba32F, [0; 3])
+            .chunks_exact(4)
+            .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        assert_eq!(floats[..4], [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(floats[5], 128.0 / 255.0);
+
+        // Gray8 back to RGBA adds opaque alpha
+        let gray = RBImage::from_vec(vec![0, 90, 255], 3, 1).with_format(RBPixelFormat::Gray8);
+        let rgba = unsafe { rb_image_convert(&gray, RBPixelFormat::Rgba8 as u32, 0, 0, 0) };
+        assert_eq!(take_bytes(rgba), [0, 0, 0, 255, 90, 90, 90, 255, 255, 255, 255, 255]);
+        take_bytes(gray);
+
+        assert!(unsafe { rb_image_convert(&src, 5, 0, 0, 0) }.ptr.is_null());
+        assert_eq!(last_error().as_deref(), Some("invalid args: unknown pixel format 5"));
+        take_bytes(src);
+
+        let mut opts = RBOptions::default();
+        assert!(!unsafe { rb_options_set_output_format(&mut opts, 99, 0, 0, 0) });
+        assert!(!unsafe { rb_options_set_output_format(&mut opts, RBPixelFormat::Gray8 as u32, 0, 0, 0) });
+        assert_eq!(opts.output_format, RBPixelFormat::Rgba8);
+    }
+
+    #[test]
+    fn longest_side_follows_orientation_and_default_limits() {
+        let _globals = lock_globals();
+        let landscape = br#"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="100"/>"#;
+        let portrait = br#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="200"/>"#;
+        let longest = |svg: &[u8], side: u32| {
+            let img = unsafe { rb_render_svg_longest(svg.as_ptr(), svg.len(), side) };
+            let size = (img.width, img.height);
+            take_bytes(img);
+            size
+        };
+        assert_eq!(longest(landscape, 50), (50, 25));
+        assert_eq!(longest(portrait, 50), (25, 50));
+
+        let mut limited = RBOptions::default();
+        assert!(unsafe { rb_options_set_size_limits(&mut limited, 0, 0, 40, 40) });
+        unsafe { rb_set_default_options(&limited) };
+        let clamped = (longest(landscape, 50), longest(portrait, 50), longest(landscape, 20));
+        unsafe { rb_set_default_options(std::ptr::null()) };
+        assert_eq!(clamped, ((40, 20), (20, 40), (20, 10)));
+    }
+
+    #[test]
+    fn affine_transform_flips_and_fills_the_background() {
+        let src = RBImage::from_vec([[255, 0, 0, 255], [0, 0, 255, 255]].concat(), 2, 1);
+        // Mirror horizontally into a 3-pixel-wide output; the last column is outside the source
+        let img = unsafe { rb_image_affine_transform(&src, -1.0, 0.0, 0.0, 1.0, 2.0, 0.0, 3, 1, 0, 255, 0, 255) };
+        assert_eq!(take_bytes(img), [[0, 0, 255, 255], [255, 0, 0, 255], [0, 255, 0, 255]].concat());
+
+        assert!(unsafe { rb_image_affine_transform(&src, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 3, 1, 0, 0, 0, 0) }.ptr.is_null());
+        take_bytes(src);
+    }
+
+    #[test]
+    fn logical_render_doubles_at_dpr_two() {
+        let _g = lock_globals();
+        let (mut logical_w, mut logical_h) = (0, 0);
+        let img = unsafe { rb_render_svg_logical(SQUARE.as_ptr(), SQUARE.len(), 24, 16, 2.0, &mut logical_w, &mut logical_h) };
+        assert_eq!((img.width, img.height), (48, 32));
+        assert_eq!((logical_w, logical_h), (24, 16));
+        // The rect spans x 2..8 of 10 units, i.e. physical x 9.6..38.4
+        assert_eq!(pixel(&img, 10, 16), [255, 0, 0, 255]);
+        assert_eq!(pixel(&img, 8, 16), [0, 0, 0, 0]);
+        take_bytes(img);
+
+        let img = unsafe { rb_render_svg_logical(SQUARE.as_ptr(), SQUARE.len(), 24, 16, 0.0, &mut logical_w, &mut logical_h) };
+        assert!(img.ptr.is_null());
+        assert_eq!((logical_w, logical_h), (0, 0));
+    }
+
+    #[test]
+    fn unsized_svg_falls_back_to_the_assumed_size() {
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg"><rect x="10" y="10" width="5" height="5" fill="#f00"/></svg>"##;
+        let tree = parse_svg(svg, &RBOptions::default()).unwrap();
+        assert_eq!((tree.size().width(), tree.size().height()), (100.0, 100.0));
+        assert_eq!(last_warning().as_deref(), Some("SVG has no width, height or viewBox; assuming 100x100"));
+
+        // At the fallback size the rect keeps its place instead of filling a 1×1 document
+        let img = render(svg, 100, 100, &RBOptions::default());
+        assert_eq!(pixel(&img, 12, 12), [255, 0, 0, 255]);
+        assert_eq!(pixel(&img, 50, 50), [0, 0, 0, 0]);
+        take_bytes(img);
+
+        let mut opts = RBOptions::default();
+        assert!(unsafe { rb_options_set_assumed_size(&mut opts, 20.0, 40.0) });
+        let tree = parse_svg(svg, &opts).unwrap();
+        assert_eq!((tree.size().width(), tree.size().height()), (20.0, 40.0));
+        assert!(!unsafe { rb_options_set_assumed_size(&mut opts, 0.0, 40.0) });
+
+        LAST_WARN.with(|w| *w.borrow_mut() = None);
+        parse_svg(SQUARE, &opts).unwrap();
+        assert_eq!(last_warning(), None);
+    }
+
+    #[test]
+    fn phash_is_close_for_a_slightly_recolored_image() {
+        let svg = |fill: &str| {
+            format!(r##"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="64"><rect width="64" height="64" fill="#fff"/><circle cx="24" cy="28" r="16" fill="{fill}"/><rect x="40" y="36" width="20" height="24" fill="{fill}"/></svg>"##)
+        };
+        let hash = |svg: String| {
+            let img = render(svg.as_bytes(), 64, 64, &RBOptions::default());
+            let h = unsafe { rb_image_phash(&img) };
+            take_bytes(img);
+            h
+        };
+        let original = hash(svg("#204080"));
+        let recolored = hash(svg("#244488"));
+        let different = hash(r##"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="64"><rect width="64" height="64" fill="#fff"/><rect x="4" y="4" width="28" height="56" fill="#204080"/></svg>"##.into());
+
+        assert!((original ^ recolored).count_ones() <= 4);
+        assert!((original ^ different).count_ones() > 10);
+    }
+
+    #[test]
+    fn aliased_font_family_renders_text() {
+        let _globals = lock_globals();
+        let Some(opts) = system_font_options() else { return };
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="16"><text x="1" y="12" font-family="Brand Sans" font-size="12">Hi</text></svg>"#;
+        let inked = |opts: &RBOptions| {
+            let img = render(svg, 40, 16, opts);
+            let n = unsafe { image_bytes(&img) }.chunks_exact(4).filter(|px| px[3] > 0).count();
+            take_bytes(img);
+            n
+        };
+        assert_eq!(inked(&opts), 0);
+
+        assert!(unsafe { rb_add_font_alias(c"BRAND sans".as_ptr(), c"DejaVu Sans".as_ptr()) });
+        let with_alias = inked(&opts);
+        assert!(unsafe { rb_add_font_alias(c"Brand Sans".as_ptr(), std::ptr::null()) });
+        assert!(with_alias > 0);
+        assert_eq!(inked(&opts), 0);
+    }
+
+    #[test]
+    fn png_set_writes_one_file_per_size() {
+        let _g = lock_globals();
+        let dir = temp_dir("png-set");
+        let path = CString::new(dir.to_str().unwrap()).unwrap();
+        let sizes = [16, 32, 48];
+        let written = unsafe { rb_render_svg_to_png_set(SQUARE.as_ptr(), SQUARE.len(), sizes.as_ptr(), sizes.len(), path.as_ptr(), c"icon".as_ptr()) };
+        assert_eq!(written, 3);
+        for size in sizes {
+            let (w, h, pixels) = decode_png(&std::fs::read(dir.join(format!("icon-{size}.png"))).unwrap());
+            assert_eq!((w, h), (size, size));
+            assert_eq!(pixels[(size as usize / 2 * size as usize + size as usize / 2) * 4..][..4], [255, 0, 0, 255]);
+        }
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 3);
+
+        let missing = CString::new(dir.join("missing").to_str().unwrap()).unwrap();
+        let written = unsafe { rb_render_svg_to_png_set(SQUARE.as_ptr(), SQUARE.len(), sizes.as_ptr(), 2, missing.as_ptr(), c"icon".as_ptr()) };
+        assert_eq!(written, 0);
+        let err = last_error().unwrap();
+        assert!(err.starts_with("icon-16.png: io error") && err.contains("; icon-32.png: io error"), "{err}");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    extern "C" fn record_progress(done: usize, total: usize, userdata: *mut c_void) {
+        let calls = unsafe { &*(userdata as *const std::sync::Mutex<Vec<(usize, usize)>>) };
+        calls.lock().unwrap().push((done, total));
+    }
+
+    #[test]
+    fn progress_callback_runs_once_per_item() {
+        let _globals = lock_globals();
+        rb_set_thread_count(1);
+        let sizes = [RBSize { width: 8, height: 8 }, RBSize { width: 16, height: 4 }, RBSize { width: 4, height: 16 }, RBSize { width: 12, height: 12 }];
+        let mut out: Vec<RBImage> = (0..sizes.len()).map(|_| RBImage::empty()).collect();
+        let calls = std::sync::Mutex::new(Vec::<(usize, usize)>::new());
+        let userdata = &calls as *const _ as *mut c_void;
+        assert!(unsafe {
+            rb_render_svg_to_rgba_batch_with_progress(SQUARE.as_ptr(), SQUARE.len(), sizes.as_ptr(), sizes.len(), out.as_mut_ptr(), Some(record_progress), userdata)
+        });
+        out.into_iter().for_each(|img| drop(take_bytes(img)));
+        assert_eq!(*calls.lock().unwrap(), [(1, 4), (2, 4), (3, 4), (4, 4)]);
+
+        let dir = temp_dir("png-set-progress");
+        let path = CString::new(dir.to_str().unwrap()).unwrap();
+        calls.lock().unwrap().clear();
+        let edges = [8, 16];
+        let written = unsafe {
+            rb_render_svg_to_png_set_with_progress(SQUARE.as_ptr(), SQUARE.len(), edges.as_ptr(), 2, path.as_ptr(), c"p".as_ptr(), Some(record_progress), userdata)
+        };
+        assert_eq!(written, 2);
+        assert_eq!(*calls.lock().unwrap(), [(1, 2), (2, 2)]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rows_are_tightly_packed() {
+        for (w, h) in [(1, 1), (7, 3), (33, 17)] {
+            let img = render(SQUARE, w, h, &RBOptions::default());
+            assert_eq!(img.len, w as usize * h as usize * 4);
+            assert_eq!(unsafe { rb_image_stride(&img) }, w as usize * 4);
+            assert_eq!(img.len, h as usize * unsafe { rb_image_stride(&img) });
+            take_bytes(img);
+        }
+        let gray = RBImage::from_vec(vec![0; 15], 5, 3).with_format(RBPixelFormat::Gray8);
+        assert_eq!(unsafe { rb_image_stride(&gray) }, 5);
+        take_bytes(gray);
+        assert_eq!(unsafe { rb_image_stride(std::ptr::null()) }, 0);
+    }
+
+    #[test]
+    fn drop_shadow_extends_the_canvas_under_the_svg() {
+        let _g = lock_globals();
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="#f00"/></svg>"##;
+        let shadow = |dx: f32, dy: f32, blur: f32, a: u8| unsafe {
+            rb_render_svg_to_rgba_with_shadow(svg.as_ptr(), svg.len(), 10, 10, dx, dy, blur, 0, 0, 255, a)
+        };
+
+        let img = shadow(4.0, 4.0, 0.0, 255);
+        assert_eq!((img.width, img.height), (14, 14));
+        assert_eq!(pixel(&img, 2, 2), [255, 0, 0, 255]);
+        assert_eq!(pixel(&img, 12, 12), [0, 0, 255, 255]);
+        assert_eq!(pixel(&img, 12, 2), [0, 0, 0, 0]);
+        take_bytes(img);
+
+        // A negative offset pads the top-left instead; shadow alpha scales its opacity
+        let img = shadow(-2.0, 0.0, 0.0, 128);
+        assert_eq!((img.width, img.height), (12, 10));
+        assert_eq!(pixel(&img, 0, 5), [0, 0, 128, 128]);
+        assert_eq!(pixel(&img, 2, 5), [255, 0, 0, 255]);
+        take_bytes(img);
+
+        // Blurring grows the canvas by the kernel radius of ceil(3 * sigma)
+        let img = shadow(4.0, 4.0, 1.0, 255);
+        assert_eq!((img.width, img.height), (17, 17));
+        let edge = pixel(&img, 14, 8)[3];
+        assert!(edge > 0 && edge < 255, "{edge}");
+        assert_eq!(pixel(&img, 2, 2), [255, 0, 0, 255]);
+        take_bytes(img);
+    }
+
+    #[test]
+    fn render_page_isolates_and_fits_one_page() {
+        let _g = lock_globals();
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="10">
+            <g id="page1"><rect width="10" height="10" fill="#f00"/></g>
+            <g id="page2"><rect x="5" width="35" height="10" fill="#00f" opacity="0.5"/><rect x="20" width="20" height="10" fill="#00f"/></g>
+        </svg>"##;
+        let page = |id: &CStr| unsafe { rb_render_page(svg.as_ptr(), svg.len(), id.as_ptr(), 20, 20) };
+
+        // page1 is square, so it fills the output; page2's overlap is not drawn
+        let img = page(c"page1");
+        assert_eq!((img.width, img.height), (20, 20));
+        let bytes = take_bytes(img);
+        assert!(bytes.chunks_exact(4).all(|px| px == [255, 0, 0, 255]));
+
+        // page2 is 35x10, letterboxed into the middle rows with no red from page1
+        let img = page(c"page2");
+        assert_eq!(pixel(&img, 19, 10), [0, 0, 255, 255]);
+        assert_eq!(pixel(&img, 0, 10), [0, 0, 128, 128]);
+        assert_eq!(pixel(&img, 10, 0), [0, 0, 0, 0]);
+        let bytes = take_bytes(img);
+        assert!(bytes.chunks_exact(4).all(|px| px[0] == 0));
+
+        let img = page(c"page3");
+        assert!(img.ptr.is_null());
+        assert_eq!(last_error().as_deref(), Some("element 'page3' not found"));
+    }
+
+    #[test]
+    fn default_options_apply_to_renders_without_options() {
+        let _g = lock_globals();
+        let blue = [0, 0, 255, 255];
+        let mut opts = RBOptions::default();
+        assert!(unsafe { rb_options_set_style_sheet(&mut opts, c"rect { fill: #00f }".as_ptr()) });
+        set_err("stale".into());
+        unsafe { rb_set_default_options(&opts) };
+        assert_eq!(last_error(), None);
+
+        let img = unsafe { rb_render_svg_to_rgba(SQUARE.as_ptr(), SQUARE.len(), 10, 10) };
+        assert_eq!(pixel(&img, 5, 5), blue);
+        take_bytes(img);
+
+        let mut len = 0;
+        let ptr = unsafe { rb_render_svg_to_png(SQUARE.as_ptr(), SQUARE.len(), 10, 10, &mut len) };
+        let (_, _, pixels) = decode_png(unsafe { slice::from_raw_parts(ptr, len) });
+        unsafe { rb_free_buffer(ptr, len) };
+        assert_eq!(pixels[(5 * 10 + 5) * 4..][..4], blue);
+
+        let tree = unsafe { rb_tree_parse(SQUARE.as_ptr(), SQUARE.len(), std::ptr::null()) };
+        let img = unsafe { rb_tree_render(tree, 10, 10) };
+        unsafe { rb_tree_free(tree) };
+        assert_eq!(pixel(&img, 5, 5), blue);
+        take_bytes(img);
+
+        // Options objects are unaffected, and null restores the built-in defaults
+        let img = render(SQUARE, 10, 10, &RBOptions::default());
+        assert_eq!(pixel(&img, 5, 5), [255, 0, 0, 255]);
+        take_bytes(img);
+        unsafe { rb_set_default_options(std::ptr::null()) };
+        let img = unsafe { rb_render_svg_to_rgba(SQUARE.as_ptr(), SQUARE.len(), 10, 10) };
+        assert_eq!(pixel(&img, 5, 5), [255, 0, 0, 255]);
+        take_bytes(img);
+    }
+
+    #[test]
+    fn crisp_edges_draw_a_1px_line_as_one_solid_row() {
+        // A 1px stroke centered off the pixel grid covers rows 5 and 6 by 75% and 25%
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="32" height="32"><line x1="2" y1="5.75" x2="30" y2="5.75" stroke="#000" stroke-width="1"/></svg>"##;
+        let row_alphas = |bytes: &[u8]| -> Vec<Vec<u8>> {
+            bytes.chunks_exact(32 * 4).map(|row| row[2 * 4..30 * 4].chunks_exact(4).map(|px| px[3]).collect()).collect()
+        };
+        let smooth = row_alphas(&take_bytes(render(svg, 32, 32, &RBOptions::default())));
+        assert!(smooth[5].iter().chain(&smooth[6]).all(|&a| a != 0 && a != 255));
+
+        let mut opts = RBOptions::default();
+        assert!(unsafe { rb_options_set_crisp_edges(&mut opts, true) });
+        let bytes = take_bytes(render(svg, 32, 32, &opts));
+        let crisp = row_alphas(&bytes);
+        let opaque: Vec<_> = (0..32).filter(|&y| crisp[y].iter().all(|&a| a == 255)).collect();
+        assert_eq!(opaque, [5]);
+        // Nothing else is drawn, not even faintly
+        let drawn = bytes.chunks_exact(4).filter(|px| px[3] != 0).count();
+        assert_eq!(drawn, 28);
+    }
+
+    #[test]
+    fn logical_size_is_remembered_until_the_image_is_freed() {
+        let _g = lock_globals();
+        // 3x5 points at 0.5 is 2x3 pixels, which no scale division can map back
+        let img = unsafe { rb_render_svg_to_rgba_retina(SQUARE.as_ptr(), SQUARE.len(), 3, 5, 0.5) };
+        assert_eq!((img.width, img.height), (2, 3));
+        set_err("stale".into());
+        assert_eq!(unsafe { (rb_image_logical_width(&img), rb_image_logical_height(&img)) }, (3, 5));
+        assert_eq!(last_error(), None);
+        let key = img.ptr as usize;
+        rb_free_image(img);
+        assert!(!LOGICAL_SIZES.read().unwrap().as_ref().is_some_and(|sizes| sizes.contains_key(&key)));
+
+        // Other images report their physical size
+        let img = render(SQUARE, 12, 9, &RBOptions::default());
+        assert_eq!(unsafe { (rb_image_logical_width(&img), rb_image_logical_height(&img)) }, (12, 9));
+        take_bytes(img);
+
+        assert_eq!(unsafe { rb_image_logical_width(std::ptr::null()) }, 0);
+        assert_eq!(last_error().as_deref(), Some("invalid args"));
+    }
+
+    #[test]
+    fn dithered_palette_render_is_identical_across_calls_and_seeds() {
+        let _g = lock_globals();
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="32" height="8"><linearGradient id="g"><stop offset="0" stop-color="#000"/><stop offset="1" stop-color="#fff"/></linearGradient><rect width="32" height="8" fill="url(#g)"/></svg>"##;
+        let palette = [0, 0, 0, 255, 255, 255, 255, 255];
+        let dithered = |seed: u64| {
+            let mut opts = RBOptions::default();
+            assert!(unsafe { rb_options_set_seed(&mut opts, seed) });
+            unsafe { rb_set_default_options(&opts) };
+            let img = unsafe { rb_render_svg_to_rgba_palette(svg.as_ptr(), svg.len(), 32, 8, palette.as_ptr(), 2, true) };
+            take_bytes(img)
+        };
+
+        let first = dithered(7);
+        // Dithering a gradient against black and white mixes both
+        assert!(first.chunks_exact(4).any(|px| px[0] == 0) && first.chunks_exact(4).any(|px| px[0] == 255));
+        assert_eq!(dithered(7), first);
+        // The seed is a no-op, so another seed gives the same bytes
+        assert_eq!(dithered(8), first);
+        unsafe { rb_set_default_options(std::ptr::null()) };
+    }
+
+    #[test]
+    fn concurrent_timeouts_leave_at_most_the_capped_number_of_workers() {
+        let _g = lock_globals();
+        // Turbulence at this size keeps each worker busy long after its caller gave up
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><filter id="n"><feTurbulence baseFrequency="0.05" numOctaves="8"/></filter><rect width="10" height="10" filter="url(#n)"/></svg>"##;
+        let barrier = std::sync::Barrier::new(12);
+        let errors: Vec<String> = std::thread::scope(|scope| {
+            let calls: Vec<_> = (0..12)
+                .map(|_| {
+                    scope.spawn(|| {
+                        barrier.wait();
+                        let img = unsafe { rb_render_svg_timeout(svg.as_ptr(), svg.len(), 400, 400, 1) };
+                        assert!(img.ptr.is_null());
+                        assert!(RENDER_WORKERS.load(Ordering::Acquire) <= MAX_RENDER_WORKERS);
+                        last_error().unwrap()
+                    })
+                })
+                .collect();
+            calls.into_iter().map(|c| c.join().unwrap()).collect()
+        });
+
+        assert!(errors.iter().all(|e| e.starts_with("timeout: ")), "{errors:?}");
+        let refused = errors.iter().filter(|e| e.contains("too many")).count();
+        assert!(refused >= 12 - MAX_RENDER_WORKERS, "{errors:?}");
+
+        // Abandoned workers release their slots once they finish
+        let start = std::time::Instant::now();
+        while RENDER_WORKERS.load(Ordering::Acquire) > 0 {
+            assert!(start.elapsed() < std::time::Duration::from_secs(60));
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn background_image_format_is_decoded_from_its_raw_value() {
+        let _g = lock_globals();
+        let gray = [200u8; 4];
+        let composite = |format: u32| unsafe {
+            rb_render_svg_to_rgba_with_background_image(SQUARE.as_ptr(), SQUARE.len(), gray.as_ptr(), gray.len(), format, 2, 2, 10, 10)
+        };
+
+        let img = composite(RBPixelFormat::Gray8 as u32);
+        assert_eq!(pixel(&img, 0, 0), [200, 200, 200, 255]);
+        assert_eq!(pixel(&img, 5, 5), [255, 0, 0, 255]);
+        take_bytes(img);
+
+        let img = composite(7);
+        assert!(img.ptr.is_null());
+        assert_eq!(last_error().as_deref(), Some("invalid args: unknown pixel format 7"));
+    }
+
+    #[test]
+    fn blend_mode_is_decoded_from_its_raw_value() {
+        let mut dst = RBImage::from_vec([0, 0, 255, 255].repeat(4), 2, 2);
+        let src = RBImage::from_vec(vec![255, 0, 0, 255], 1, 1);
+        assert!(unsafe { rb_image_blend(&mut dst, &src, RBBlendMode::SrcOver as u32, 1, 0) });
+        assert_eq!(pixel(&dst, 1, 0), [255, 0, 0, 255]);
+        assert_eq!(pixel(&dst, 0, 0), [0, 0, 255, 255]);
+
+        assert!(!unsafe { rb_image_blend(&mut dst, &src, 42, 0, 0) });
+        assert_eq!(last_error().as_deref(), Some("invalid args: unknown blend mode 42"));
+        assert_eq!(pixel(&dst, 0, 0), [0, 0, 255, 255]);
+        take_bytes(dst);
+        take_bytes(src);
+    }
+
+    #[test]
+    fn id_list_shows_or_hides_the_listed_elements() {
+        let _g = lock_globals();
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10"><rect id="a" width="10" height="10" fill="#f00"/><rect id="b" x="10" width="10" height="10" fill="#00f"/></svg>"##;
+        let ids = [c"a".as_ptr()];
+        let render_ids = |mode: u32| unsafe { rb_render_svg_to_rgba_with_id_list(svg.as_ptr(), svg.len(), 20, 10, ids.as_ptr(), 1, mode) };
+
+        let img = render_ids(RBVisibilityMode::Whitelist as u32);
+        assert_eq!((pixel(&img, 5, 5), pixel(&img, 15, 5)), ([255, 0, 0, 255], [0, 0, 0, 0]));
+        take_bytes(img);
+
+        let img = render_ids(RBVisibilityMode::Blacklist as u32);
+        assert_eq!((pixel(&img, 5, 5), pixel(&img, 15, 5)), ([0, 0, 0, 0], [0, 0, 255, 255]));
+        take_bytes(img);
+
+        assert!(render_ids(2).ptr.is_null());
+        assert_eq!(last_error().as_deref(), Some("invalid args: unknown visibility mode 2"));
+    }
+
+    #[test]
+    fn threshold_mode_is_decoded_from_its_raw_value() {
+        // Red has luminance 76, below 128, and is the only pixel with alpha 255
+        let src = RBImage::from_vec([[255, 0, 0, 255], [0, 0, 0, 0]].concat(), 2, 1);
+        let out = unsafe { rb_image_threshold(&src, 128, RBThresholdMode::Luminance as u32, false) };
+        assert_eq!(take_bytes(out), [[0, 0, 0, 255], [0, 0, 0, 0]].concat());
+        let out = unsafe { rb_image_threshold(&src, 255, RBThresholdMode::Alpha as u32, true) };
+        assert_eq!(take_bytes(out), [[0, 0, 0, 255], [255; 4]].concat());
+
+        assert!(unsafe { rb_image_threshold(&src, 128, 3, false) }.ptr.is_null());
+        assert_eq!(last_error().as_deref(), Some("invalid args: unknown threshold mode 3"));
+        take_bytes(src);
+    }
+
+    #[test]
+    fn edge_mode_is_decoded_from_its_raw_value() {
+        let src = render(SQUARE, 10, 10, &RBOptions::default());
+        let edges = unsafe { rb_image_edge_detect(&src, RBEdgeMode::Sobel as u32) };
+        // Flat regions have no gradient; the square's border does
+        assert_eq!(pixel(&edges, 0, 0), [0, 0, 0, 255]);
+        assert!(pixel(&edges, 2, 5)[0] > 0);
+        take_bytes(edges);
+
+        assert!(unsafe { rb_image_edge_detect(&src, 3) }.ptr.is_null());
+        assert_eq!(last_error().as_deref(), Some("invalid args: unknown edge mode 3"));
+        take_bytes(src);
+    }
+
+    #[test]
+    fn subpixel_order_is_decoded_from_its_raw_value() {
+        let _g = lock_globals();
+        for order in [RBSubpixelOrder::Rgb, RBSubpixelOrder::Vbgr] {
+            let img = unsafe { rb_render_svg_to_rgba_subpixel(SQUARE.as_ptr(), SQUARE.len(), 10, 10, order as u32) };
+            assert_eq!((img.width, img.height), (10, 10));
+            assert_eq!(pixel(&img, 5, 5), [255, 0, 0, 255]);
+            take_bytes(img);
+        }
+
+        assert!(unsafe { rb_render_svg_to_rgba_subpixel(SQUARE.as_ptr(), SQUARE.len(), 10, 10, 4) }.ptr.is_null());
+        assert_eq!(last_error().as_deref(), Some("invalid args: unknown subpixel order 4"));
+    }
+
+    #[test]
+    fn exact_render_aligns_by_the_raw_scale_mode() {
+        let _g = lock_globals();
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10"><rect width="20" height="10" fill="#f00"/></svg>"##;
+        let exact = |fit: u32| unsafe { rb_render_svg_exact(svg.as_ptr(), svg.len(), 10, 10, fit) };
+
+        let img = exact(RBScaleMode::XMinYMin as u32);
+        assert_eq!((pixel(&img, 5, 2), pixel(&img, 5, 7)), ([255, 0, 0, 255], [0, 0, 0, 0]));
+        take_bytes(img);
+        let img = exact(RBScaleMode::XMinYMax as u32);
+        assert_eq!((pixel(&img, 5, 2), pixel(&img, 5, 7)), ([0, 0, 0, 0], [255, 0, 0, 255]));
+        take_bytes(img);
+
+        assert!(exact(19).ptr.is_null());
+        assert_eq!(last_error().as_deref(), Some("invalid args: unknown scale mode 19"));
+    }
+
+    #[test]
+    fn checksum_algorithm_is_decoded_from_its_raw_value() {
+        let img = RBImage::from_vec(b"123456789".to_vec(), 9, 1).with_format(RBPixelFormat::Gray8);
+        let mut sum = 0;
+        assert!(unsafe { rb_image_compute_checksum(&img, RBChecksumAlgorithm::Crc32 as u32, &mut sum) });
+        // The standard CRC-32 check value
+        assert_eq!(sum, 0xCBF4_3926);
+
+        assert!(!unsafe { rb_image_compute_checksum(&img, 3, &mut sum) });
+        assert_eq!(last_error().as_deref(), Some("invalid args: unknown checksum algorithm 3"));
+        take_bytes(img);
+    }
+
+    #[test]
+    fn rgba_operations_reject_converted_images() {
+        let src = render(SQUARE, 10, 10, &RBOptions::default());
+        let bgra = unsafe { rb_image_convert(&src, RBPixelFormat::Bgra8 as u32, 0, 0, 0) };
+        let expected = Some("invalid args: expected an Rgba8 image, got Bgra8");
+
+        assert!(unsafe { rb_image_gaussian_blur(&bgra, 1.0) }.ptr.is_null());
+        assert_eq!(last_error().as_deref(), expected);
+        assert!(unsafe { rb_image_threshold(&bgra, 128, RBThresholdMode::Alpha as u32, false) }.ptr.is_null());
+        assert_eq!(last_error().as_deref(), expected);
+        let mut dst = render(SQUARE, 10, 10, &RBOptions::default());
+        assert!(!unsafe { rb_image_blend(&mut dst, &bgra, RBBlendMode::SrcOver as u32, 0, 0) });
+        assert_eq!(last_error().as_deref(), expected);
+        let mut score = 0.0;
+        assert!(!unsafe { rb_image_compare_ssim(&src, &bgra, &mut score) });
+        assert_eq!(last_error().as_deref(), expected);
+
+        // Converting back makes it usable again
+        let rgba = unsafe { rb_image_convert(&bgra, RBPixelFormat::Rgba8 as u32, 0, 0, 0) };
+        assert!(unsafe { rb_image_compare_ssim(&src, &rgba, &mut score) });
+        assert_eq!(score, 1.0);
+        for img in [src, bgra, dst, rgba] {
+            take_bytes(img);
+        }
+    }
+
+    #[test]
+    fn context_render_keeps_warnings_in_the_context() {
+        let _g = lock_globals();
+        let unsized_svg = br##"<svg xmlns="http://www.w3.org/2000/svg"><rect width="5" height="5"/></svg>"##;
+        let ctx = rb_context_new();
+        clear_err();
+        set_err("outer".into());
+        add_warning("outer warning".into());
+
+        for _ in 0..3 {
+            let img = unsafe { rb_render_svg_to_rgba_ctx(ctx, unsized_svg.as_ptr(), unsized_svg.len(), 10, 10) };
+            assert!(!img.ptr.is_null());
+            take_bytes(img);
+            let warning = unsafe { CStr::from_ptr(rb_context_last_warning(ctx)) };
+            assert_eq!(warning.to_str().unwrap(), "SVG has no width, height or viewBox; assuming 100x100");
+        }
+        // The thread's state is untouched, and doesn't grow with each call
+        assert_eq!(last_error().as_deref(), Some("outer"));
+        assert_eq!(last_warning().as_deref(), Some("outer warning"));
+
+        let img = unsafe { rb_render_svg_to_rgba_ctx(ctx, SQUARE.as_ptr(), SQUARE.len(), 10, 10) };
+        take_bytes(img);
+        assert!(unsafe { rb_context_last_warning(ctx) }.is_null());
+        unsafe { rb_context_free(ctx) };
+
+        let img = unsafe { rb_render_svg_to_rgba_ctx(std::ptr::null_mut(), SQUARE.as_ptr(), SQUARE.len(), 10, 10) };
+        assert!(img.ptr.is_null());
+        assert_eq!(last_error().as_deref(), Some("invalid args: null context"));
+    }
+
+    #[test]
+    fn render_page_draws_symbols_and_single_use_instances() {
+        let _g = lock_globals();
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="20">
+            <defs>
+                <symbol id="dot" viewBox="0 0 10 10"><rect width="10" height="10" fill="#f00"/></symbol>
+                <symbol id="bar" viewBox="0 0 20 10"><rect width="20" height="10" fill="#00f"/></symbol>
+                <symbol id="unused" viewBox="0 0 10 10"><rect width="10" height="10" fill="#0f0"/></symbol>
+            </defs>
+            <use id="first" href="#dot" width="20" height="20"/>
+            <use id="second" href="#bar" x="10" y="5" width="30" height="15"/>
+        </svg>"##;
+        let page = |id: &CStr| unsafe { rb_render_page(svg.as_ptr(), svg.len(), id.as_ptr(), 20, 20) };
+
+        // A symbol is instantiated into the output by its own viewBox, used or not
+        let bytes = take_bytes(page(c"unused"));
+        assert!(bytes.chunks_exact(4).all(|px| px == [0, 255, 0, 255]));
+        let img = page(c"bar");
+        assert_eq!(pixel(&img, 10, 2), [0, 0, 0, 0]);
+        assert_eq!(pixel(&img, 10, 10), [0, 0, 255, 255]);
+        take_bytes(img);
+
+        // A <use> draws only its own instance, even where another overlaps it
+        let bytes = take_bytes(page(c"first"));
+        assert!(bytes.chunks_exact(4).all(|px| px == [255, 0, 0, 255]));
+        let img = page(c"second");
+        assert_eq!(pixel(&img, 10, 10), [0, 0, 255, 255]);
+        let bytes = take_bytes(img);
+        assert!(bytes.chunks_exact(4).all(|px| px[0] == 0));
+    }
+
+    #[test]
+    fn autocrop_sides_trims_uneven_top_and_bottom_margins() {
+        // 5×10 with content at x 1..2 and y 1..3: margins of 1 above, 6 below,
+        // 1 left and 3 right
+        let mut bytes = vec![0; 5 * 10 * 4];
+        for y in 1..3 {
+            bytes[(y * 5 + 1) * 4..(y * 5 + 2) * 4].copy_from_slice(&[255, 0, 0, 255]);
+        }
+        let img = RBImage::from_vec(bytes, 5, 10);
+
+        let trimmed = unsafe { rb_autocrop_sides(&img, 0, true, true, false, false) };
+        assert_eq!((trimmed.width, trimmed.height), (5, 2));
+        for y in 0..2 {
+            assert_eq!(pixel(&trimmed, 0, y), [0, 0, 0, 0]);
+            assert_eq!(pixel(&trimmed, 1, y), [255, 0, 0, 255]);
+            assert_eq!(pixel(&trimmed, 2, y), [0, 0, 0, 0]);
+        }
+        take_bytes(trimmed);
+        take_bytes(img);
+    }
+
+    #[test]
+    fn panic_handler_chains_to_the_host_hook_and_only_reports_library_panics() {
+        let _g = lock_globals();
+        static HOST_CALLS: AtomicUsize = AtomicUsize::new(0);
+        static REPORTS: AtomicUsize = AtomicUsize::new(0);
+        extern "C" fn on_panic(msg: *const c_char, userdata: *mut c_void) {
+            assert!(unsafe { CStr::from_ptr(msg) }.to_str().unwrap().starts_with("boom at "));
+            assert_eq!(userdata as usize, 7);
+            REPORTS.fetch_add(1, Ordering::SeqCst);
+        }
+        let test_thread = std::thread::current().id();
+        let original = panic::take_hook();
+        panic::set_hook(Box::new(move |_| {
+            if std::thread::current().id() == test_thread {
+                HOST_CALLS.fetch_add(1, Ordering::SeqCst);
+            }
+        }));
+        let counts = || (HOST_CALLS.load(Ordering::SeqCst), REPORTS.load(Ordering::SeqCst));
+
+        rb_set_panic_handler(Some(on_panic), 7 as *mut c_void);
+        ffi_guard((), || panic!("boom"));
+        assert_eq!(last_error().as_deref(), Some("panic: boom"));
+        assert_eq!(counts(), (1, 1));
+        // The host's own panics reach its hook but not the callback
+        let _ = panic::catch_unwind(|| panic!("host"));
+        assert_eq!(counts(), (2, 1));
+
+        // Resetting puts the host's hook back
+        rb_set_panic_handler(None, std::ptr::null_mut());
+        ffi_guard((), || panic!("boom"));
+        assert_eq!(counts(), (3, 1));
+
+        panic::set_hook(original);
+    }
+
+    #[test]
+    fn svg_filter_ids_are_escaped_into_the_wrapper_document() {
+        let filters = br##"<svg xmlns="http://www.w3.org/2000/svg"><filter id="fill&amp;mark" x="0" y="0" width="1" height="1"><feFlood flood-color="#00f"/></filter></svg>"##;
+        let src = RBImage::from_vec([255, 0, 0, 255].repeat(4), 2, 2);
+
+        let img = unsafe { rb_image_apply_svg_filter(&src, filters.as_ptr(), filters.len(), c"fill&mark".as_ptr()) };
+        assert_eq!(last_error(), None);
+        assert!(take_bytes(img).chunks_exact(4).all(|px| px == [0, 0, 255, 255]));
+
+        let img = unsafe { rb_image_apply_svg_filter(&src, filters.as_ptr(), filters.len(), c"fill".as_ptr()) };
+        assert!(img.ptr.is_null());
+        assert_eq!(last_error().as_deref(), Some("filter 'fill' not found"));
+        take_bytes(src);
+    }
+
+    #[test]
+    fn font_preload_reports_a_running_load_as_busy() {
+        let _g = lock_globals();
+        // Stand in for a load that is still running
+        assert!(!FONT_PRELOAD_RUNNING.swap(true, Ordering::AcqRel));
+        assert!(!rb_preload_fonts_async(None, std::ptr::null_mut()));
+        assert_eq!(rb_last_error_code(), RBErrorCode::Busy);
+        assert_eq!(last_error().as_deref(), Some("busy: a font preload is already running"));
+        FONT_PRELOAD_RUNNING.store(false, Ordering::Release);
+    }
+
+    #[test]
+    fn mmap_render_applies_the_size_limits() {
+        let mut opts = RBOptions::default();
+        assert!(unsafe { rb_options_set_size_limits(&mut opts, 0, 0, 8, 8) });
+        let path = temp_dir("mmap").join("out.rgba");
+        let c_path = CString::new(path.to_str().unwrap()).unwrap();
+
+        assert!(unsafe { rb_render_svg_to_mmap(SQUARE.as_ptr(), SQUARE.len(), 16, 16, c_path.as_ptr(), &opts) });
+        let buffered = render(SQUARE, 16, 16, &opts);
+        assert_eq!((buffered.width, buffered.height), (8, 8));
+        assert_eq!(std::fs::read(&path).unwrap(), take_bytes(buffered));
     }
 }